@@ -1,8 +0,0 @@
-// use pest::Parser;
-// use pest::iterators::Pairs;
-// use satysfi_peg_parser::grammar::{common::Grammar, literal::Literal};
-
-fn main() {
-    // let hoge = Literal::parse("#``12.5pt``#");
-    // dbg!(hoge);
-}
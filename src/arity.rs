@@ -0,0 +1,497 @@
+//! Command arity checking.
+//!
+//! `sig` の `val \cmd : [inline-text; int list] inline-cmd` や `direct +cmd : [...] block-cmd`
+//! という宣言をもとに、コマンドの使用箇所の引数を調べる。
+//!
+//! - インラインコマンドや数式コマンドなど、宣言と同じ種類のコマンドとして使われているか。
+//! - 引数の数と順序が宣言と合っているか。
+//! - `{...}` や `<...>` の引数が、`inline-text` や `block-text` と宣言された位置にあるか。
+//!   `(...)` の引数は式の型を求めないと分からないため、
+//!   リストやレコードのリテラルでなければどの位置にあってもよいとする。
+//! - `?:` や `?*` の引数が、`t?` と宣言された位置にあるか。
+//!
+//! 型推論（[`crate::types`]）と異なり、コマンドの定義の中身や組み込みの関数が分からなくても使える。
+
+#[cfg(test)]
+mod tests;
+
+use crate::diagnostic::Diagnostic;
+use crate::grammar::common::{Ranged, Span};
+use crate::grammar::{
+    CommandArg, HorizontalToken, MathCmdArg, MathUnary, Program, SigStmt, Statement, TypeExpr,
+    TypeListUnit, TypeProd, TypeUnary, Unary, ValName, VerticalElement,
+};
+use crate::resolve::{resolve, Resolution};
+use crate::types::CommandKind;
+use crate::visit::{self, Visitor};
+use crate::workspace::Workspace;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// 引数の宣言された型から分かる、引数の書き方。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// `inline-text`。`{...}` で書ける。
+    InlineText,
+    /// `block-text`。`<...>` で書ける。
+    BlockText,
+    /// `math`。数式コマンドでは `{...}` で書ける。
+    Math,
+    /// その他の型。`(...)` で書く。
+    Expr,
+}
+
+/// コマンドの引数の宣言。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Param {
+    /// 引数の書き方。
+    pub kind: ArgKind,
+    /// `t?` のように省略できるかどうか。
+    pub optional: bool,
+}
+
+/// コマンドの宣言。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSignature {
+    /// コマンドの種類。
+    pub kind: CommandKind,
+    /// 引数の宣言。
+    pub params: Vec<Param>,
+}
+
+/// コマンドの種類と、使用箇所で書く名前から宣言への対応。
+pub type Signatures = BTreeMap<(CommandKind, String), CommandSignature>;
+
+/// program の `sig` で宣言されたコマンド。
+///
+/// `val` で宣言したものは `\M.cmd` の形で、`direct` で宣言したものはさらに `\cmd` の形でも引ける。
+pub fn signatures(program: &Program) -> Signatures {
+    let mut signatures = Signatures::new();
+    if let Some(preamble) = &program.preamble {
+        collect(&preamble.body.0, &mut signatures);
+    }
+    signatures
+}
+
+/// program のコマンドの使用箇所の引数を、宣言と照らし合わせる。
+///
+/// program 中の宣言に加えて、external にある宣言も使う。
+/// program 中の `let-inline` などで定義され、`sig` で宣言されていないコマンドは調べない。
+/// 見つかった問題は位置の順に返す。
+pub fn check_commands(program: &Program, external: &Signatures) -> Vec<Diagnostic> {
+    let mut checker = ArityChecker {
+        resolution: resolve(program),
+        local: signatures(program),
+        external,
+        diagnostics: vec![],
+    };
+    checker.visit_program(program);
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|d| d.span);
+    diagnostics
+}
+
+/// workspace 中のファイル path のコマンドの使用箇所を、読み込むファイルの宣言も使って調べる。
+pub fn check_workspace(workspace: &Workspace, path: &Path) -> Vec<Diagnostic> {
+    let program = match workspace.program(path) {
+        Some(program) => program,
+        None => return vec![],
+    };
+    // 推移的に読み込まれるファイルの宣言を集める
+    let mut external = Signatures::new();
+    let mut visited = vec![path.to_owned()];
+    let mut next = 0;
+    while next < visited.len() {
+        let imports: Vec<_> = workspace
+            .imports(&visited[next])
+            .iter()
+            .filter_map(|import| import.path.clone())
+            .collect();
+        for import in imports {
+            if visited.contains(&import) {
+                continue;
+            }
+            if let Some(package) = workspace.program(&import) {
+                external.extend(signatures(package));
+            }
+            visited.push(import);
+        }
+        next += 1;
+    }
+    check_commands(program, &external)
+}
+
+/// stmts 中のモジュールの `sig` からコマンドの宣言を集める。
+fn collect(stmts: &[Ranged<Statement>], signatures: &mut Signatures) {
+    for stmt in stmts {
+        let (module, signature, body) = match &stmt.body {
+            Statement::Module {
+                name,
+                signature,
+                body,
+            } => (&name.body, signature, body),
+            _ => continue,
+        };
+        for sig in signature.iter().flat_map(|signature| &signature.body.0) {
+            let (name, ty, direct) = match &sig.body {
+                SigStmt::Val { name, ty, .. } => match &name.body {
+                    ValName::InlineCmd(name) | ValName::BlockCmd(name) => {
+                        (name.as_str(), ty, false)
+                    }
+                    ValName::Var(_) | ValName::BinOperator(_) => continue,
+                },
+                SigStmt::Direct { name, ty, .. } => (name.body.as_str(), ty, true),
+                SigStmt::Type { .. } => continue,
+            };
+            let signature = match command_signature(&ty.body) {
+                Some(signature) => signature,
+                None => continue,
+            };
+            let (prefix, bare) = name.split_at(1);
+            let qualified = format!("{}{}.{}", prefix, module, bare);
+            if direct {
+                signatures.insert((signature.kind, name.to_owned()), signature.clone());
+            }
+            signatures.insert((signature.kind, qualified), signature);
+        }
+        collect(&body.body.0, signatures);
+    }
+}
+
+/// `[t1; t2?] inline-cmd` などの型から、コマンドの宣言を作る。コマンドの型でなければ None。
+fn command_signature(ty: &TypeExpr) -> Option<CommandSignature> {
+    if !ty.optional_args.is_empty() || !ty.args.is_empty() {
+        return None;
+    }
+    let (kind, list) = match single_unary(&ty.ret.body)? {
+        TypeUnary::InlineCmd(list) => (CommandKind::Inline, list),
+        TypeUnary::BlockCmd(list) => (CommandKind::Block, list),
+        TypeUnary::MathCmd(list) => (CommandKind::Math, list),
+        _ => return None,
+    };
+    let params = list
+        .body
+        .0
+        .iter()
+        .map(|unit| match &unit.body {
+            TypeListUnit::Optional(prod) => Param {
+                kind: arg_kind(single_unary(prod)),
+                optional: true,
+            },
+            TypeListUnit::Mandatory(ty) => {
+                let function = !ty.optional_args.is_empty() || !ty.args.is_empty();
+                let unary = if function {
+                    None
+                } else {
+                    single_unary(&ty.ret.body)
+                };
+                Param {
+                    kind: arg_kind(unary),
+                    optional: false,
+                }
+            }
+        })
+        .collect();
+    Some(CommandSignature { kind, params })
+}
+
+/// 組でない型の中身。`(t)` の括弧は外す。
+fn single_unary(prod: &TypeProd) -> Option<&TypeUnary> {
+    match prod.0.as_slice() {
+        [unary] => match &unary.body {
+            TypeUnary::Paren(ty) if ty.body.optional_args.is_empty() && ty.body.args.is_empty() => {
+                single_unary(&ty.body.ret.body)
+            }
+            unary => Some(unary),
+        },
+        _ => None,
+    }
+}
+
+/// 引数の型から、引数の書き方を決める。
+fn arg_kind(ty: Option<&TypeUnary>) -> ArgKind {
+    match ty {
+        Some(TypeUnary::Name {
+            modname: None,
+            name,
+        }) => match name.body.as_str() {
+            "inline-text" => ArgKind::InlineText,
+            "block-text" => ArgKind::BlockText,
+            "math" => ArgKind::Math,
+            _ => ArgKind::Expr,
+        },
+        _ => ArgKind::Expr,
+    }
+}
+
+/// 使用箇所での引数の書き方。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Form {
+    /// `{...}`。数式コマンドでは `!{...}`。
+    Horizontal,
+    /// `<...>`。数式コマンドでは `!<...>`。
+    Vertical,
+    /// 数式コマンドの `{...}`。
+    Math,
+    /// `(...)`
+    Expr,
+    /// `[...]`
+    List,
+    /// `(| ... |)`
+    Record,
+    /// `?*`
+    Omitted,
+}
+
+impl Form {
+    /// 式の引数の書き方。
+    fn of_unary(unary: &Unary) -> Form {
+        match unary {
+            Unary::List(_) => Form::List,
+            Unary::Record(_) => Form::Record,
+            _ => Form::Expr,
+        }
+    }
+
+    /// kind の引数の位置に書けるかどうか。
+    fn accepts(self, kind: ArgKind) -> bool {
+        match self {
+            Form::Horizontal => kind == ArgKind::InlineText,
+            Form::Vertical => kind == ArgKind::BlockText,
+            Form::Math => kind == ArgKind::Math,
+            Form::List | Form::Record => kind == ArgKind::Expr,
+            Form::Expr | Form::Omitted => true,
+        }
+    }
+
+    /// メッセージに使う書き方。math は数式コマンドの引数かどうか。
+    fn describe(self, math: bool) -> &'static str {
+        match (self, math) {
+            (Form::Horizontal, false) => "`{...}`",
+            (Form::Horizontal, true) => "`!{...}`",
+            (Form::Vertical, false) => "`<...>`",
+            (Form::Vertical, true) => "`!<...>`",
+            (Form::Math, _) => "`{...}`",
+            (Form::Expr, false) => "`(...)`",
+            (Form::Expr, true) => "`!(...)`",
+            (Form::List, false) => "`[...]`",
+            (Form::List, true) => "`![...]`",
+            (Form::Record, false) => "`(| ... |)`",
+            (Form::Record, true) => "`!(| ... |)`",
+            (Form::Omitted, _) => "`?*`",
+        }
+    }
+}
+
+impl ArgKind {
+    /// この種類の引数の、典型的な書き方。
+    fn form(self, command: CommandKind) -> Form {
+        match (self, command) {
+            (ArgKind::InlineText, _) => Form::Horizontal,
+            (ArgKind::BlockText, _) => Form::Vertical,
+            (ArgKind::Math, CommandKind::Math) => Form::Math,
+            (ArgKind::Math, _) | (ArgKind::Expr, _) => Form::Expr,
+        }
+    }
+}
+
+/// 使用箇所での引数。
+struct Given {
+    /// 引数の範囲。
+    span: Span,
+    /// 引数の書き方。
+    form: Form,
+    /// `?:` や `?*` の引数かどうか。
+    optional: bool,
+}
+
+/// コマンドの使用箇所を調べる Visitor。
+struct ArityChecker<'a> {
+    /// 名前解決の結果。`sig` で宣言されたコマンドかどうかを調べるのに使う。
+    resolution: Resolution,
+    /// プログラム中の宣言。
+    local: Signatures,
+    /// 読み込むファイルなど、プログラムの外の宣言。
+    external: &'a Signatures,
+    /// 見つかった問題。
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ArityChecker<'_> {
+    /// 使用箇所 name が指すコマンドの宣言。
+    ///
+    /// kind の宣言がなく、同じ名前で別の種類のコマンドが宣言されていればその宣言を返す。
+    fn signature(&self, kind: CommandKind, name: &Ranged<String>) -> Option<&CommandSignature> {
+        let reference = self.resolution.references.get(&name.span());
+        let (key, local_only) = match reference.and_then(|reference| reference.binding) {
+            // ファイル中で定義されたコマンドは、それを囲むモジュールの宣言を使う
+            Some(id) => {
+                let binding = &self.resolution.bindings[id];
+                let module = &self.resolution.bindings[binding.module?];
+                let (prefix, bare) = binding.name.split_at(1);
+                (format!("{}{}.{}", prefix, module.name, bare), true)
+            }
+            None => (name.body.clone(), false),
+        };
+        let get = |kind: CommandKind| {
+            let key = (kind, key.clone());
+            let local = self.local.get(&key);
+            if local_only {
+                local
+            } else {
+                local.or_else(|| self.external.get(&key))
+            }
+        };
+        get(kind).or_else(|| {
+            [CommandKind::Inline, CommandKind::Block, CommandKind::Math]
+                .iter()
+                .copied()
+                .filter(|&other| other != kind)
+                .find_map(get)
+        })
+    }
+
+    /// コマンド name の使用箇所の引数 args を調べる。
+    ///
+    /// 宣言と異なる種類のコマンドとして使われている場合は、そのことだけを報告する。
+    fn check(&mut self, kind: CommandKind, name: &Ranged<String>, args: Vec<Given>) {
+        let signature = match self.signature(kind, name) {
+            Some(signature) => signature,
+            None => return,
+        };
+        if signature.kind != kind {
+            let message = format!(
+                "`{}` is declared as {}, but used as {}",
+                name.body,
+                describe_kind(signature.kind),
+                describe_kind(kind)
+            );
+            self.diagnostics
+                .push(Diagnostic::error(name.span(), message));
+            return;
+        }
+        let params = signature.params.clone();
+        let math = kind == CommandKind::Math;
+        let mut next = 0;
+        for arg in args {
+            if !arg.optional {
+                while params.get(next).is_some_and(|param| param.optional) {
+                    next += 1;
+                }
+            }
+            let param = match params.get(next) {
+                Some(param) if param.optional == arg.optional => param,
+                _ if arg.optional => {
+                    let message = format!("unexpected optional argument for `{}`", name.body);
+                    self.diagnostics.push(Diagnostic::error(arg.span, message));
+                    continue;
+                }
+                _ => {
+                    let message = format!(
+                        "too many arguments for `{}`: it takes {}",
+                        name.body,
+                        params.iter().filter(|param| !param.optional).count()
+                    );
+                    self.diagnostics.push(Diagnostic::error(arg.span, message));
+                    continue;
+                }
+            };
+            if !arg.form.accepts(param.kind) {
+                let message = format!(
+                    "`{}` expects {} here, but {} is given",
+                    name.body,
+                    param.kind.form(kind).describe(math),
+                    arg.form.describe(math)
+                );
+                self.diagnostics.push(Diagnostic::error(arg.span, message));
+            }
+            next += 1;
+        }
+        let missing: Vec<_> = params[next.min(params.len())..]
+            .iter()
+            .filter(|param| !param.optional)
+            .map(|param| param.kind.form(kind).describe(math))
+            .collect();
+        if !missing.is_empty() {
+            let message = format!(
+                "`{}` expects {} more argument(s): {}",
+                name.body,
+                missing.len(),
+                missing.join(", ")
+            );
+            self.diagnostics
+                .push(Diagnostic::error(name.span(), message));
+        }
+    }
+}
+
+/// メッセージに使うコマンドの種類。
+fn describe_kind(kind: CommandKind) -> &'static str {
+    match kind {
+        CommandKind::Inline => "an inline command",
+        CommandKind::Block => "a block command",
+        CommandKind::Math => "a math command",
+    }
+}
+
+/// インラインコマンドやブロックコマンドの引数の書き方。
+fn command_args(args: &[Ranged<CommandArg>]) -> Vec<Given> {
+    args.iter()
+        .map(|arg| {
+            let (form, optional) = match &arg.body {
+                CommandArg::Expr(unary) => (Form::of_unary(unary), false),
+                CommandArg::Optional(unary) => (Form::of_unary(&unary.body), true),
+                CommandArg::OptionalOmitted => (Form::Omitted, true),
+                CommandArg::Vertical(_) => (Form::Vertical, false),
+                CommandArg::Horizontal(_) => (Form::Horizontal, false),
+            };
+            Given {
+                span: arg.span(),
+                form,
+                optional,
+            }
+        })
+        .collect()
+}
+
+/// 数式コマンドの引数の書き方。
+fn math_arg_form(arg: &MathCmdArg) -> Form {
+    match arg {
+        MathCmdArg::Math(_) => Form::Math,
+        MathCmdArg::Horizontal(_) => Form::Horizontal,
+        MathCmdArg::Vertical(_) => Form::Vertical,
+        MathCmdArg::Expr(unary) => Form::of_unary(unary),
+        MathCmdArg::Optional(inner) => math_arg_form(&inner.body),
+    }
+}
+
+impl Visitor for ArityChecker<'_> {
+    fn visit_horizontal_token(&mut self, node: &HorizontalToken, span: Span) {
+        if let HorizontalToken::InlineCmd { name, args } = node {
+            self.check(CommandKind::Inline, name, command_args(args));
+        }
+        visit::walk_horizontal_token(self, node, span);
+    }
+
+    fn visit_vertical_element(&mut self, node: &VerticalElement, span: Span) {
+        if let VerticalElement::BlockCmd { name, args } = node {
+            self.check(CommandKind::Block, name, command_args(args));
+        }
+        visit::walk_vertical_element(self, node, span);
+    }
+
+    fn visit_math_unary(&mut self, node: &MathUnary, span: Span) {
+        if let MathUnary::Cmd { name, args } = node {
+            let args = args
+                .iter()
+                .map(|arg| Given {
+                    span: arg.span(),
+                    form: math_arg_form(&arg.body),
+                    optional: matches!(arg.body, MathCmdArg::Optional(_)),
+                })
+                .collect();
+            self.check(CommandKind::Math, name, args);
+        }
+        visit::walk_math_unary(self, node, span);
+    }
+}
@@ -0,0 +1,272 @@
+use super::*;
+use crate::grammar::common::Grammar;
+use crate::package::PackageResolver;
+use std::fs;
+use std::path::PathBuf;
+
+/// text 中の宣言だけを使って調べた、誤りの位置とメッセージ。
+fn errors(text: &str) -> Vec<(usize, usize, String)> {
+    errors_with(text, &Signatures::new())
+}
+
+/// text を external の宣言も使って調べた、誤りの位置とメッセージ。
+fn errors_with(text: &str, external: &Signatures) -> Vec<(usize, usize, String)> {
+    check_commands(&Program::parse(text).unwrap(), external)
+        .into_iter()
+        .map(|d| (d.span.start.row, d.span.start.col, d.message))
+        .collect()
+}
+
+const SIG: &str = "\
+module M : sig
+  val \\emph : [inline-text] inline-cmd
+  direct \\link : [string; inline-text] inline-cmd
+  direct +sec : [int?; inline-text; block-text] block-cmd
+  val \\frac : [math; math] math-cmd
+end = struct
+end
+";
+
+#[test]
+fn signatures_of_sig() {
+    let program = Program::parse(&format!("{}in {}", SIG, "'<>")).unwrap();
+    let signatures = signatures(&program);
+    let names: Vec<_> = signatures.keys().map(|(_, name)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["\\M.emph", "\\M.link", "\\link", "+M.sec", "+sec", "\\M.frac"]
+    );
+    let sec = &signatures[&(CommandKind::Block, "+sec".to_owned())];
+    assert_eq!(
+        sec.params,
+        vec![
+            Param {
+                kind: ArgKind::Expr,
+                optional: true
+            },
+            Param {
+                kind: ArgKind::InlineText,
+                optional: false
+            },
+            Param {
+                kind: ArgKind::BlockText,
+                optional: false
+            },
+        ]
+    );
+}
+
+#[test]
+fn arity() {
+    let text = format!("{}in {}", SIG, "'<+sec{a}<>{b} +sec{a}>");
+    assert_eq!(
+        errors(&text),
+        vec![
+            (
+                8,
+                15,
+                "too many arguments for `+sec`: it takes 2".to_owned()
+            ),
+            (
+                8,
+                19,
+                "`+sec` expects 1 more argument(s): `<...>`".to_owned()
+            ),
+        ]
+    );
+    let text = format!("{}in {}", SIG, "{\\link; \\M.emph{a}}");
+    assert_eq!(
+        errors(&text),
+        vec![(
+            8,
+            5,
+            "`\\link` expects 2 more argument(s): `(...)`, `{...}`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn kinds() {
+    let text = format!(
+        "{}{}",
+        SIG, "in {\\link{a}{b} \\link(`u`)[1]; \\link(`u`){b}}"
+    );
+    assert_eq!(
+        errors(&text),
+        vec![
+            (
+                8,
+                10,
+                "`\\link` expects `(...)` here, but `{...}` is given".to_owned()
+            ),
+            (
+                8,
+                27,
+                "`\\link` expects `{...}` here, but `[...]` is given".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn optional_arguments() {
+    let text = format!(
+        "{}{}",
+        SIG, "in '<+sec?:(1){a}<> +sec?*{a}<> +sec{a}<> +M.sec?:(1)?:(2){a}<>>"
+    );
+    assert_eq!(
+        errors(&text),
+        vec![(
+            8,
+            54,
+            "unexpected optional argument for `+M.sec`".to_owned()
+        )]
+    );
+    let text = format!("{}in {}", SIG, "{\\M.emph?:(1){a}}");
+    assert_eq!(
+        errors(&text),
+        vec![(
+            8,
+            12,
+            "unexpected optional argument for `\\M.emph`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn math_commands() {
+    let text = format!("{}in {}", SIG, "${\\M.frac{x}!{y} + \\M.frac!(1)}");
+    assert_eq!(
+        errors(&text),
+        vec![
+            (
+                8,
+                16,
+                "`\\M.frac` expects `{...}` here, but `!{...}` is given".to_owned()
+            ),
+            (
+                8,
+                23,
+                "`\\M.frac` expects 1 more argument(s): `{...}`".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn local_definitions() {
+    // sig で宣言されていない定義は調べない
+    let text = format!(
+        "{}{}",
+        SIG, "let-inline ctx \\link it = read-inline ctx it\nin {\\link{a}}"
+    );
+    assert_eq!(errors(&text), vec![]);
+
+    // モジュールの中での使用箇所は、そのモジュールの宣言を使う
+    let text = "module N : sig
+  val \\b : [inline-text] inline-cmd
+end = struct
+  let-inline ctx \\b it = read-inline ctx it
+  let-inline ctx \\c it = read-inline ctx {\\b[1];}
+end
+in {}";
+    assert_eq!(
+        errors(text),
+        vec![(
+            5,
+            45,
+            "`\\b` expects `{...}` here, but `[...]` is given".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn command_kind_mismatch() {
+    // 種類の誤りだけを報告し、引数の数は調べない
+    let text = format!(
+        "{}in {}",
+        SIG, "'<+p{\\M.frac{a}} +p{${\\link}} +p{\\M.emph{a}{b}}>"
+    );
+    assert_eq!(
+        errors(&text),
+        vec![
+            (
+                8,
+                9,
+                "`\\M.frac` is declared as a math command, but used as an inline command"
+                    .to_owned()
+            ),
+            (
+                8,
+                26,
+                "`\\link` is declared as an inline command, but used as a math command".to_owned()
+            ),
+            (
+                8,
+                47,
+                "too many arguments for `\\M.emph`: it takes 1".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn external_signatures() {
+    let external = signatures(&Program::parse(&format!("{}in {}", SIG, "'<>")).unwrap());
+    assert_eq!(
+        errors_with("{\\link{a}}", &external),
+        vec![
+            (
+                1,
+                2,
+                "`\\link` expects 1 more argument(s): `{...}`".to_owned()
+            ),
+            (
+                1,
+                7,
+                "`\\link` expects `(...)` here, but `{...}` is given".to_owned()
+            ),
+        ]
+    );
+    assert_eq!(errors_with("{\\unknown{a}{b}}", &external), vec![]);
+}
+
+/// テストごとの一時ディレクトリに files を書き出し、そのディレクトリを返す。
+fn setup(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "satysfi-peg-parser-arity-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, text) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+    dir.canonicalize().unwrap()
+}
+
+#[test]
+fn workspace() {
+    let dir = setup(
+        "workspace",
+        &[
+            ("packages/base.satyh", SIG),
+            ("packages/wrap.satyh", "@require: base\nlet x = 1\n"),
+        ],
+    );
+    let doc = dir.join("doc.saty");
+    let mut workspace = Workspace::new(PackageResolver::new(vec![dir.join("packages")]));
+    workspace.update(&doc, "@require: wrap\n'<+sec{a}>".to_owned());
+    let messages: Vec<_> = check_workspace(&workspace, &doc)
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(
+        messages,
+        vec!["`+sec` expects 1 more argument(s): `<...>`".to_owned()]
+    );
+    assert_eq!(workspace.diagnostics()[doc.as_path()].len(), 1);
+    assert!(check_workspace(&workspace, &dir.join("none.saty")).is_empty());
+}
@@ -0,0 +1,104 @@
+//! Formatter for SATySFi source files.
+//!
+//! ```text
+//! satysfi-fmt [--check] [--width <N>] [--reflow-text] [--keep-lists] [FILE...]
+//! ```
+//!
+//! ファイルを指定しない場合は標準入力を整形して標準出力に書き出す。
+//! ファイルを指定した場合はそのファイルを書き換える。
+//! `--check` を付けると書き換えは行わず、整形されていないファイルがあれば終了コード 1 で終了する。
+
+use anyhow::{anyhow, Context, Result};
+use satysfi_peg_parser::formatter::{format, FormatOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process;
+
+/// コマンドライン引数。
+struct Args {
+    /// 整形済みかどうかの確認のみ行う。
+    check: bool,
+    /// 整形の設定。
+    options: FormatOptions,
+    /// 整形するファイル。空の場合は標準入力を用いる。
+    files: Vec<PathBuf>,
+}
+
+/// コマンドライン引数を読む。
+fn parse_args() -> Result<Args> {
+    let mut args = Args {
+        check: false,
+        options: FormatOptions::default(),
+        files: vec![],
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--check" => args.check = true,
+            "--reflow-text" => args.options.reflow_text = true,
+            "--keep-lists" => args.options.normalize_lists = false,
+            "--width" => {
+                let width = iter
+                    .next()
+                    .ok_or_else(|| anyhow!("--width requires a value"))?;
+                args.options.width = width
+                    .parse()
+                    .with_context(|| format!("invalid width: {}", width))?;
+            }
+            "-h" | "--help" => {
+                println!(
+                    "usage: satysfi-fmt [--check] [--width <N>] [--reflow-text] [--keep-lists] [FILE...]"
+                );
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(anyhow!("unknown option: {}", arg)),
+            _ => args.files.push(PathBuf::from(arg)),
+        }
+    }
+    Ok(args)
+}
+
+/// 整形を行い、すべて整形済みであれば真を返す。
+fn run(args: &Args) -> Result<bool> {
+    if args.files.is_empty() {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        let formatted = format(&text, &args.options).map_err(|e| anyhow!("<stdin>\n{}", e))?;
+        if args.check {
+            return Ok(formatted == text);
+        }
+        std::io::stdout().write_all(formatted.as_bytes())?;
+        return Ok(true);
+    }
+
+    let mut all_formatted = true;
+    for file in &args.files {
+        let text = std::fs::read_to_string(file)
+            .with_context(|| format!("cannot read {}", file.display()))?;
+        let formatted =
+            format(&text, &args.options).map_err(|e| anyhow!("{}\n{}", file.display(), e))?;
+        if formatted == text {
+            continue;
+        }
+        all_formatted = false;
+        if args.check {
+            println!("{}", file.display());
+        } else {
+            std::fs::write(file, formatted)
+                .with_context(|| format!("cannot write {}", file.display()))?;
+        }
+    }
+    Ok(all_formatted || !args.check)
+}
+
+fn main() {
+    let result = parse_args().and_then(|args| run(&args));
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            process::exit(2);
+        }
+    }
+}
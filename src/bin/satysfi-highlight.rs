@@ -0,0 +1,94 @@
+//! HTML syntax highlighter for SATySFi source files.
+//!
+//! ```text
+//! satysfi-highlight [--standalone] [FILE]
+//! ```
+//!
+//! ファイルを指定しない場合は標準入力を読む。
+//! 既定では `<pre class="satysfi"><code>...</code></pre>` のみを出力する。
+//! `--standalone` を付けると、スタイルシートを含む HTML 文書全体を出力する。
+//! パースできないコードはそのまま（エスケープして）出力する。
+
+use anyhow::{anyhow, Context, Result};
+use satysfi_peg_parser::highlight::to_html;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process;
+
+/// `--standalone` で埋め込むスタイルシート。
+const STYLE: &str = "\
+.satysfi { background: #fafafa; padding: 0.5em; }
+.satysfi .inline-cmd, .satysfi .block-cmd { color: #a626a4; }
+.satysfi .math-cmd { color: #986801; }
+.satysfi .module, .satysfi .type { color: #c18401; }
+.satysfi .variant { color: #0184bc; }
+.satysfi .type-param { color: #0184bc; font-style: italic; }
+.satysfi .field { color: #e45649; }
+.satysfi .number, .satysfi .bool, .satysfi .unit { color: #986801; }
+.satysfi .string, .satysfi .string-delimiter { color: #50a14f; }
+.satysfi .text { color: #383a42; }
+.satysfi .comment { color: #a0a1a7; font-style: italic; }
+.satysfi .definition, .satysfi .declaration { font-weight: bold; }
+";
+
+/// コマンドライン引数。
+struct Args {
+    /// HTML 文書全体を出力する。
+    standalone: bool,
+    /// 読み込むファイル。None の場合は標準入力を用いる。
+    file: Option<PathBuf>,
+}
+
+/// コマンドライン引数を読む。
+fn parse_args() -> Result<Args> {
+    let mut args = Args {
+        standalone: false,
+        file: None,
+    };
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--standalone" => args.standalone = true,
+            "-h" | "--help" => {
+                println!("usage: satysfi-highlight [--standalone] [FILE]");
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(anyhow!("unknown option: {}", arg)),
+            _ if args.file.is_some() => return Err(anyhow!("too many files: {}", arg)),
+            _ => args.file = Some(PathBuf::from(arg)),
+        }
+    }
+    Ok(args)
+}
+
+/// ファイルを読んで HTML を出力する。
+fn run(args: &Args) -> Result<()> {
+    let text = match &args.file {
+        Some(file) => std::fs::read_to_string(file)
+            .with_context(|| format!("cannot read {}", file.display()))?,
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    let code = format!(
+        "<pre class=\"satysfi\"><code>{}</code></pre>",
+        to_html(&text)
+    );
+    if args.standalone {
+        println!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<style>\n{}</style>\n</head>\n<body>\n{}\n</body>\n</html>",
+            STYLE, code
+        );
+    } else {
+        println!("{}", code);
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = parse_args().and_then(|args| run(&args)) {
+        eprintln!("error: {:#}", e);
+        process::exit(2);
+    }
+}
@@ -0,0 +1,273 @@
+//! Linter for SATySFi source files.
+//!
+//! ```text
+//! satysfi-lint [--config <FILE>] [--format human|json] [--fix] [FILE...]
+//! satysfi-lint --list-rules
+//! ```
+//!
+//! ファイルを指定しない場合は標準入力を調べる。
+//! `--fix` を指定すると、自動修正を適用してファイルを書き換え、修正後に残った報告を出力する。
+//! 標準入力を調べる場合は修正後のソースコードを標準出力に、報告を標準エラー出力に出力する。
+//! 修正するとパースできなくなる場合は、ファイルを書き換えない。
+//! 設定ファイルを指定しない場合は、カレントディレクトリの `satysfi-lint.toml` があればそれを使う。
+//! 報告やパースの失敗があれば終了コード 1 で、引数や設定の誤りなどでは終了コード 2 で終了する。
+//! `serde` feature が必要。
+//!
+//! - `--format human`: `FILE:LINE:COL: warning[RULE]: MESSAGE` の形式で出力する（既定）。
+//! - `--format json`: 報告の配列を JSON で出力する。
+
+use anyhow::{anyhow, Context, Result};
+use satysfi_peg_parser::diagnostic::{Diagnostic, Severity};
+use satysfi_peg_parser::lint::{Config, FixError, Linter, Report};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// 既定の設定ファイルの名前。
+const CONFIG_FILE: &str = "satysfi-lint.toml";
+
+/// 出力の形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// 人が読むための形式。
+    Human,
+    /// JSON
+    Json,
+}
+
+/// コマンドライン引数。
+struct Args {
+    /// 設定ファイル。
+    config: Option<PathBuf>,
+    /// 出力の形式。
+    format: Format,
+    /// 規則の一覧を出力する。
+    list_rules: bool,
+    /// 自動修正を適用する。
+    fix: bool,
+    /// 調べるファイル。空の場合は標準入力を用いる。
+    files: Vec<PathBuf>,
+}
+
+/// コマンドライン引数を読む。
+fn parse_args() -> Result<Args> {
+    let mut args = Args {
+        config: None,
+        format: Format::Human,
+        list_rules: false,
+        fix: false,
+        files: vec![],
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => {
+                let path = iter
+                    .next()
+                    .ok_or_else(|| anyhow!("--config requires a value"))?;
+                args.config = Some(PathBuf::from(path));
+            }
+            "--format" => {
+                let format = iter
+                    .next()
+                    .ok_or_else(|| anyhow!("--format requires a value"))?;
+                args.format = match format.as_str() {
+                    "human" => Format::Human,
+                    "json" => Format::Json,
+                    _ => return Err(anyhow!("unknown format: {}", format)),
+                };
+            }
+            "--list-rules" => args.list_rules = true,
+            "--fix" => args.fix = true,
+            "-h" | "--help" => {
+                println!(
+                    "usage: satysfi-lint [--config <FILE>] [--format human|json] [--fix] [FILE...]\n       satysfi-lint --list-rules"
+                );
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(anyhow!("unknown option: {}", arg)),
+            _ => args.files.push(PathBuf::from(arg)),
+        }
+    }
+    Ok(args)
+}
+
+/// 設定ファイルを読む。指定がなく、既定のファイルもなければ既定の設定を使う。
+fn load_config(path: Option<&Path>) -> Result<Config> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(CONFIG_FILE).is_file() => Path::new(CONFIG_FILE),
+        None => return Ok(Config::default()),
+    };
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    Config::parse(&text).with_context(|| format!("{}", path.display()))
+}
+
+/// 1 つのファイルを調べた結果。
+struct Checked {
+    /// ファイル名。
+    name: String,
+    /// 報告。パースに失敗した場合はその診断。
+    result: Result<Vec<Report>, Diagnostic>,
+}
+
+/// 重大度の表記。
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+/// 結果を人が読む形式で out に出力する。
+fn print_human(checked: &[Checked], out: &mut dyn Write) -> Result<()> {
+    for file in checked {
+        match &file.result {
+            Ok(reports) => {
+                for report in reports {
+                    let start = report.span.start;
+                    writeln!(
+                        out,
+                        "{}:{}:{}: {}[{}]: {}",
+                        file.name,
+                        start.row,
+                        start.col,
+                        severity_name(report.severity),
+                        report.rule,
+                        report.message
+                    )?;
+                }
+            }
+            Err(d) => writeln!(
+                out,
+                "{}:{}:{}: error: {}",
+                file.name, d.span.start.row, d.span.start.col, d.message
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// 結果を JSON で out に出力する。
+fn print_json(checked: &[Checked], out: &mut dyn Write) -> Result<()> {
+    let mut entries = vec![];
+    for file in checked {
+        match &file.result {
+            Ok(reports) => {
+                for report in reports {
+                    let mut entry = serde_json::to_value(report)?;
+                    entry["file"] = file.name.clone().into();
+                    entries.push(entry);
+                }
+            }
+            Err(d) => entries.push(serde_json::json!({
+                "file": file.name,
+                "rule": null,
+                "severity": "error",
+                "span": d.span,
+                "message": d.message,
+                "fix": [],
+            })),
+        }
+    }
+    writeln!(out, "{}", serde_json::to_string_pretty(&entries)?)?;
+    Ok(())
+}
+
+/// text に自動修正を適用し、修正後のソースコードと残った報告を返す。
+///
+/// 修正するとパースできなくなる場合は、その理由を診断として返す。
+fn fix(
+    linter: &Linter,
+    path: Option<&Path>,
+    text: &str,
+) -> Result<(String, Vec<Report>), Diagnostic> {
+    linter
+        .fix(path, text)
+        .map(|fixed| (fixed.text, fixed.remaining))
+        .map_err(|e| match &e {
+            FixError::Parse(d) => d.clone(),
+            FixError::Unparsable { diagnostic, .. } => {
+                Diagnostic::error(diagnostic.span, e.to_string())
+            }
+        })
+}
+
+/// lint を行い、報告やパースの失敗がなければ真を返す。
+fn run(args: &Args) -> Result<bool> {
+    let config = load_config(args.config.as_deref())?;
+    let linter = Linter::new(config)?;
+    if args.list_rules {
+        for rule in linter.rules() {
+            let severity = linter.severity(rule).map_or("off", severity_name);
+            println!("{} ({}): {}", rule.id(), severity, rule.description());
+        }
+        return Ok(true);
+    }
+
+    let mut checked = vec![];
+    let stdin = args.files.is_empty();
+    if stdin {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        let result = if args.fix {
+            fix(&linter, None, &text).map(|(text, reports)| {
+                print!("{}", text);
+                reports
+            })
+        } else {
+            linter.check(None, &text)
+        };
+        checked.push(Checked {
+            name: "<stdin>".to_owned(),
+            result,
+        });
+    }
+    for file in &args.files {
+        let text = std::fs::read_to_string(file)
+            .with_context(|| format!("cannot read {}", file.display()))?;
+        let result = if args.fix {
+            let result = fix(&linter, Some(file), &text);
+            if let Ok((fixed, _)) = &result {
+                if *fixed != text {
+                    std::fs::write(file, fixed)
+                        .with_context(|| format!("cannot write {}", file.display()))?;
+                }
+            }
+            result.map(|(_, reports)| reports)
+        } else {
+            linter.check(Some(file), &text)
+        };
+        checked.push(Checked {
+            name: file.display().to_string(),
+            result,
+        });
+    }
+
+    // 修正後のソースコードを標準出力に出す場合、報告は標準エラー出力に出す
+    let mut out: Box<dyn Write> = if stdin && args.fix {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+    match args.format {
+        Format::Human => print_human(&checked, &mut out)?,
+        Format::Json => print_json(&checked, &mut out)?,
+    }
+    Ok(checked
+        .iter()
+        .all(|file| matches!(&file.result, Ok(reports) if reports.is_empty())))
+}
+
+fn main() {
+    let result = parse_args().and_then(|args| run(&args));
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            process::exit(2);
+        }
+    }
+}
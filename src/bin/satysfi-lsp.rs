@@ -0,0 +1,564 @@
+//! Language server for SATySFi.
+//!
+//! ```text
+//! satysfi-lsp
+//! ```
+//!
+//! 標準入出力で Language Server Protocol を話す。`lsp` feature が必要。
+//! 次の機能を提供する。
+//!
+//! - ファイルを開いたときと変更したときの構文エラー、ステージの誤り、
+//!   `sig` での宣言と合わないコマンドの引数の通知
+//! - `let` / `let-inline` / `let-block` / `let-math` / `module` のアウトライン
+//! - `struct` / `sig` / `'< ... >` / `{ ... }` の折り畳み
+//! - 構文要素に沿った選択範囲の拡大
+//! - コマンド名や変数、リテラルなどの semantic tokens
+//! - 定義へのジャンプと参照の検索
+//! - 名前の変更
+
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentSymbolRequest, FoldingRangeRequest, GotoDefinition, References, Rename, Request as _,
+    SelectionRangeRequest, SemanticTokensFullRequest,
+};
+use lsp_types::{
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    FoldingRange, FoldingRangeKind, FoldingRangeParams, FoldingRangeProviderCapability,
+    GotoDefinitionParams, GotoDefinitionResponse, Position, PublishDiagnosticsParams, Range,
+    ReferenceParams, RenameParams, SelectionRange, SelectionRangeParams,
+    SelectionRangeProviderCapability, SemanticToken, SemanticTokenModifier, SemanticTokenType,
+    SemanticTokens, SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
+    SemanticTokensParams, SemanticTokensServerCapabilities, ServerCapabilities, SymbolKind,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+use satysfi_peg_parser::arity;
+use satysfi_peg_parser::diagnostic::{Diagnostic, Severity};
+use satysfi_peg_parser::grammar::common::{Grammar, Location, Span};
+use satysfi_peg_parser::grammar::Program;
+use satysfi_peg_parser::highlight::{self, TokenKind};
+use satysfi_peg_parser::outline::{self, FoldingKind, Symbol};
+use satysfi_peg_parser::rename::{self, RenameError};
+use satysfi_peg_parser::resolve;
+use satysfi_peg_parser::stage;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 開かれているファイル。
+struct Document {
+    /// ファイルの中身。
+    text: String,
+    /// パースの結果。失敗した場合は None。
+    program: Option<Program>,
+}
+
+impl Document {
+    /// ファイルの中身をパースする。
+    fn new(text: String) -> (Self, Vec<Diagnostic>) {
+        match Program::parse(&text) {
+            Ok(program) => {
+                let mut diagnostics = stage::check_stages(&program);
+                diagnostics.extend(arity::check_commands(&program, &Default::default()));
+                let doc = Document {
+                    text,
+                    program: Some(program),
+                };
+                (doc, diagnostics)
+            }
+            Err(e) => (
+                Document {
+                    text,
+                    program: None,
+                },
+                vec![Diagnostic::from(e)],
+            ),
+        }
+    }
+
+    /// 行の中身。
+    fn line(&self, row: usize) -> &str {
+        self.text.lines().nth(row - 1).unwrap_or("")
+    }
+
+    /// Location を LSP の位置に変換する。LSP の列は UTF-16 で数える。
+    fn position(&self, loc: Location) -> Position {
+        let character = self
+            .line(loc.row)
+            .chars()
+            .take(loc.col - 1)
+            .map(char::len_utf16)
+            .sum::<usize>();
+        Position::new(loc.row as u32 - 1, character as u32)
+    }
+
+    /// LSP の位置を Location に変換する。
+    fn location(&self, pos: Position) -> Location {
+        let row = pos.line as usize + 1;
+        let mut units = 0;
+        let col = self
+            .line(row)
+            .chars()
+            .take_while(|c| {
+                units += c.len_utf16();
+                units <= pos.character as usize
+            })
+            .count();
+        Location { row, col: col + 1 }
+    }
+
+    /// Span を LSP の範囲に変換する。
+    fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+}
+
+/// 言語サーバの状態。
+struct Server {
+    /// クライアントとの接続。
+    connection: Connection,
+    /// 開かれているファイル。
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    /// ファイルを更新し、構文エラーなどを通知する。
+    fn update(&mut self, uri: Url, text: String) -> Result<()> {
+        let (doc, diagnostics) = Document::new(text);
+        let diagnostics = diagnostics
+            .iter()
+            .map(|d| lsp_types::Diagnostic {
+                range: doc.range(d.span),
+                severity: Some(match d.severity {
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                }),
+                source: Some("satysfi".to_owned()),
+                message: d.message.clone(),
+                ..Default::default()
+            })
+            .collect();
+        self.documents.insert(uri.clone(), doc);
+        self.publish(uri, diagnostics)
+    }
+
+    /// 診断を通知する。
+    fn publish(&self, uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_owned(), params);
+        self.connection.sender.send(notification.into())?;
+        Ok(())
+    }
+
+    /// 通知を処理する。引数が正しくない通知は無視する。
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = match parse_params(notification.params) {
+                    Ok(params) => params,
+                    Err(_) => return Ok(()),
+                };
+                self.update(params.text_document.uri, params.text_document.text)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams = match parse_params(notification.params) {
+                    Ok(params) => params,
+                    Err(_) => return Ok(()),
+                };
+                // 全文を同期するため、最後の変更が新しい中身になる
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.update(params.text_document.uri, change.text)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = match parse_params(notification.params) {
+                    Ok(params) => params,
+                    Err(_) => return Ok(()),
+                };
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, vec![])?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// リクエストを処理する。
+    ///
+    /// 引数が正しくないリクエストには InvalidParams のエラーを返し、処理を続ける。
+    fn handle_request(&self, request: Request) -> Result<()> {
+        let id = request.id.clone();
+        let response = match self.dispatch(request) {
+            Ok(result) => Response::new_ok(id, result),
+            Err((code, message)) => Response::new_err(id, code as i32, message),
+        };
+        self.respond(response)
+    }
+
+    /// リクエストの結果を求める。失敗した場合はエラーの種類とメッセージを返す。
+    fn dispatch(&self, request: Request) -> RequestResult {
+        match request.method.as_str() {
+            DocumentSymbolRequest::METHOD => {
+                let params: DocumentSymbolParams = parse_params(request.params)?;
+                to_result(self.document_symbols(&params.text_document.uri))
+            }
+            FoldingRangeRequest::METHOD => {
+                let params: FoldingRangeParams = parse_params(request.params)?;
+                to_result(self.folding_ranges(&params.text_document.uri))
+            }
+            SelectionRangeRequest::METHOD => {
+                let params: SelectionRangeParams = parse_params(request.params)?;
+                to_result(self.selection_ranges(&params.text_document.uri, &params.positions))
+            }
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams = parse_params(request.params)?;
+                let params = params.text_document_position_params;
+                to_result(self.definition(&params.text_document.uri, params.position))
+            }
+            References::METHOD => {
+                let params: ReferenceParams = parse_params(request.params)?;
+                let position = params.text_document_position;
+                to_result(self.references(
+                    &position.text_document.uri,
+                    position.position,
+                    params.context.include_declaration,
+                ))
+            }
+            Rename::METHOD => {
+                let params: RenameParams = parse_params(request.params)?;
+                let position = params.text_document_position;
+                let edit = self
+                    .rename(
+                        &position.text_document.uri,
+                        position.position,
+                        &params.new_name,
+                    )
+                    .map_err(|e| (ErrorCode::RequestFailed, e.to_string()))?;
+                to_result(edit)
+            }
+            SemanticTokensFullRequest::METHOD => {
+                let params: SemanticTokensParams = parse_params(request.params)?;
+                to_result(self.semantic_tokens(&params.text_document.uri))
+            }
+            method => Err((
+                ErrorCode::MethodNotFound,
+                format!("unknown method: {}", method),
+            )),
+        }
+    }
+
+    /// レスポンスを送る。
+    fn respond(&self, response: Response) -> Result<()> {
+        self.connection.sender.send(response.into())?;
+        Ok(())
+    }
+
+    /// パースに成功しているファイル。
+    fn parsed(&self, uri: &Url) -> Option<(&Document, &Program)> {
+        let doc = self.documents.get(uri)?;
+        Some((doc, doc.program.as_ref()?))
+    }
+
+    /// アウトライン。
+    fn document_symbols(&self, uri: &Url) -> Option<DocumentSymbolResponse> {
+        /// Symbol を LSP の DocumentSymbol に変換する。
+        #[allow(deprecated)]
+        fn convert(doc: &Document, symbol: &Symbol) -> DocumentSymbol {
+            let (kind, detail) = match symbol.kind {
+                outline::SymbolKind::Let => (SymbolKind::VARIABLE, "let"),
+                outline::SymbolKind::LetInline => (SymbolKind::FUNCTION, "let-inline"),
+                outline::SymbolKind::LetBlock => (SymbolKind::FUNCTION, "let-block"),
+                outline::SymbolKind::LetMath => (SymbolKind::FUNCTION, "let-math"),
+                outline::SymbolKind::Module => (SymbolKind::MODULE, "module"),
+            };
+            DocumentSymbol {
+                name: symbol.name.clone(),
+                detail: Some(detail.to_owned()),
+                kind,
+                tags: None,
+                deprecated: None,
+                range: doc.range(symbol.span),
+                selection_range: doc.range(symbol.name_span),
+                children: Some(
+                    symbol
+                        .children
+                        .iter()
+                        .map(|child| convert(doc, child))
+                        .collect(),
+                ),
+            }
+        }
+
+        let (doc, program) = self.parsed(uri)?;
+        let symbols = outline::document_symbols(program)
+            .iter()
+            .map(|symbol| convert(doc, symbol))
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    /// 折り畳み範囲。
+    fn folding_ranges(&self, uri: &Url) -> Option<Vec<FoldingRange>> {
+        let (doc, program) = self.parsed(uri)?;
+        let ranges = outline::folding_ranges(program)
+            .into_iter()
+            .map(|(span, kind)| {
+                let range = doc.range(span);
+                FoldingRange {
+                    start_line: range.start.line,
+                    start_character: Some(range.start.character),
+                    end_line: range.end.line,
+                    end_character: Some(range.end.character),
+                    kind: Some(FoldingRangeKind::Region),
+                    collapsed_text: Some(
+                        match kind {
+                            FoldingKind::Struct => "struct ... end",
+                            FoldingKind::Sig => "sig ... end",
+                            FoldingKind::Vertical => "< ... >",
+                            FoldingKind::Horizontal => "{ ... }",
+                        }
+                        .to_owned(),
+                    ),
+                }
+            })
+            .collect();
+        Some(ranges)
+    }
+
+    /// 選択範囲。
+    fn selection_ranges(&self, uri: &Url, positions: &[Position]) -> Option<Vec<SelectionRange>> {
+        let (doc, program) = self.parsed(uri)?;
+        let ranges = positions
+            .iter()
+            .map(|&pos| {
+                let spans = outline::selection_ranges(program, doc.location(pos));
+                // 外側から順に親として繋いでいく
+                let mut range = SelectionRange {
+                    range: Range::new(pos, pos),
+                    parent: None,
+                };
+                for (i, span) in spans.iter().rev().enumerate() {
+                    let parent = if i == 0 { None } else { Some(Box::new(range)) };
+                    range = SelectionRange {
+                        range: doc.range(*span),
+                        parent,
+                    };
+                }
+                range
+            })
+            .collect();
+        Some(ranges)
+    }
+
+    /// 定義へのジャンプ。
+    fn definition(&self, uri: &Url, pos: Position) -> Option<GotoDefinitionResponse> {
+        let (doc, program) = self.parsed(uri)?;
+        let span = resolve::definition_at(program, doc.location(pos))?;
+        let location = lsp_types::Location::new(uri.clone(), doc.range(span));
+        Some(GotoDefinitionResponse::Scalar(location))
+    }
+
+    /// 参照の検索。
+    fn references(
+        &self,
+        uri: &Url,
+        pos: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<lsp_types::Location>> {
+        let (doc, program) = self.parsed(uri)?;
+        let loc = doc.location(pos);
+        let mut spans = resolve::references_of(program, loc);
+        if include_declaration {
+            spans.extend(resolve::definition_at(program, loc));
+            spans.sort();
+        }
+        let locations = spans
+            .into_iter()
+            .map(|span| lsp_types::Location::new(uri.clone(), doc.range(span)))
+            .collect();
+        Some(locations)
+    }
+
+    /// 名前の変更。
+    ///
+    /// パースできていないファイルでは何も変更しない。
+    fn rename(
+        &self,
+        uri: &Url,
+        pos: Position,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>, RenameError> {
+        let (doc, program) = match self.parsed(uri) {
+            Some(parsed) => parsed,
+            None => return Ok(None),
+        };
+        let edits = rename::rename(&doc.text, program, doc.location(pos), new_name)?
+            .into_iter()
+            .map(|edit| TextEdit::new(doc.range(edit.span), edit.new_text))
+            .collect();
+        let changes = std::iter::once((uri.clone(), edits)).collect();
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    /// semantic tokens。
+    ///
+    /// LSP のトークンは 1 行に収める必要があるため、複数行にわたる地の文は行ごとに分ける。
+    fn semantic_tokens(&self, uri: &Url) -> Option<SemanticTokens> {
+        let (doc, _) = self.parsed(uri)?;
+        let tokens = highlight::semantic_tokens(&doc.text).ok()?;
+        let mut data = vec![];
+        let mut prev = Position::new(0, 0);
+        for token in tokens {
+            let token_type = token_type(token.kind);
+            let (start, end) = (token.span.start, token.span.end);
+            for row in start.row..=end.row {
+                let first = if row == start.row { start.col } else { 1 };
+                let last = if row == end.row {
+                    end.col
+                } else {
+                    doc.line(row).chars().count() + 1
+                };
+                let from = doc.position(Location { row, col: first });
+                let to = doc.position(Location { row, col: last });
+                if from.character == to.character {
+                    continue;
+                }
+                let delta_start = if from.line == prev.line {
+                    from.character - prev.character
+                } else {
+                    from.character
+                };
+                data.push(SemanticToken {
+                    delta_line: from.line - prev.line,
+                    delta_start,
+                    length: to.character - from.character,
+                    token_type,
+                    token_modifiers_bitset: token.modifiers.bits(),
+                });
+                prev = from;
+            }
+        }
+        Some(SemanticTokens {
+            result_id: None,
+            data,
+        })
+    }
+
+    /// exit 通知が来るまでメッセージを処理する。
+    fn run(&mut self) -> Result<()> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// リクエストの結果。失敗した場合はエラーの種類とメッセージ。
+type RequestResult = std::result::Result<serde_json::Value, (ErrorCode, String)>;
+
+/// リクエストや通知の引数を読む。読めない場合は InvalidParams のエラーにする。
+fn parse_params<P: DeserializeOwned>(
+    params: serde_json::Value,
+) -> std::result::Result<P, (ErrorCode, String)> {
+    serde_json::from_value(params).map_err(|e| (ErrorCode::InvalidParams, e.to_string()))
+}
+
+/// 結果を JSON にする。
+fn to_result<T: Serialize>(result: T) -> RequestResult {
+    serde_json::to_value(result).map_err(|e| (ErrorCode::InternalError, e.to_string()))
+}
+
+/// semantic tokens の種類の一覧。[`token_type`] の返す添字に対応する。
+fn token_types() -> Vec<SemanticTokenType> {
+    vec![
+        SemanticTokenType::FUNCTION,
+        SemanticTokenType::MACRO,
+        SemanticTokenType::METHOD,
+        SemanticTokenType::VARIABLE,
+        SemanticTokenType::NAMESPACE,
+        SemanticTokenType::ENUM_MEMBER,
+        SemanticTokenType::TYPE_PARAMETER,
+        SemanticTokenType::TYPE,
+        SemanticTokenType::PROPERTY,
+        SemanticTokenType::NUMBER,
+        SemanticTokenType::KEYWORD,
+        SemanticTokenType::STRING,
+        SemanticTokenType::new("text"),
+        SemanticTokenType::COMMENT,
+    ]
+}
+
+/// トークンの種類を [`token_types`] の添字にする。
+fn token_type(kind: TokenKind) -> u32 {
+    match kind {
+        TokenKind::InlineCmd => 0,
+        TokenKind::BlockCmd => 1,
+        TokenKind::MathCmd => 2,
+        TokenKind::Variable => 3,
+        TokenKind::Module => 4,
+        TokenKind::Variant => 5,
+        TokenKind::TypeParam => 6,
+        TokenKind::Type => 7,
+        TokenKind::Field => 8,
+        TokenKind::Number => 9,
+        TokenKind::Bool | TokenKind::Unit => 10,
+        TokenKind::String | TokenKind::StringDelimiter => 11,
+        TokenKind::Text => 12,
+        TokenKind::Comment => 13,
+    }
+}
+
+/// サーバが提供する機能。
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_symbol_provider: Some(lsp_types::OneOf::Left(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+        definition_provider: Some(lsp_types::OneOf::Left(true)),
+        references_provider: Some(lsp_types::OneOf::Left(true)),
+        rename_provider: Some(lsp_types::OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: token_types(),
+                    // highlight::Modifiers のビットの順
+                    token_modifiers: vec![
+                        SemanticTokenModifier::DEFINITION,
+                        SemanticTokenModifier::DECLARATION,
+                    ],
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    }
+}
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.run()?;
+    drop(server);
+    io_threads.join()?;
+    Ok(())
+}
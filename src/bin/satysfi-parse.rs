@@ -0,0 +1,288 @@
+//! Parser driver for SATySFi source files.
+//!
+//! ```text
+//! satysfi-parse [--rule <RULE>] [--pairs | --ast | --sexp | --json] [FILE]
+//! satysfi-parse --list-rules
+//! ```
+//!
+//! ファイルを指定しない場合は標準入力をパースする。
+//! 既定では `program` 規則でパースし、成功すれば `ok` を出力する。
+//! 失敗した場合は `FILE:LINE:COL: error: MESSAGE` の形式で標準エラー出力に書き出し、終了コード 1 で終了する。
+//! 引数の誤りやファイルの読み込みに失敗した場合の終了コードは 2 とする。
+//!
+//! - `--pairs`: pest が生成した構文木をそのまま出力する。
+//! - `--ast`: 構文要素をデバッグ表示で出力する。
+//! - `--sexp`: 構文要素を範囲付きの S 式で出力する。
+//! - `--json`: 構文要素を JSON で出力する。`serde` feature が必要。
+
+use anyhow::{anyhow, Context, Result};
+use pest::Parser;
+use satysfi_peg_parser::diagnostic::Diagnostic;
+use satysfi_peg_parser::grammar::common::{Grammar, Location, Ranged, Span};
+use satysfi_peg_parser::grammar::*;
+use satysfi_peg_parser::parser::{skip_trivia, Pair, Rule, SatysfiParser};
+use satysfi_peg_parser::sexp::{to_sexp, ToSexp};
+use std::fmt::Debug;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process;
+
+/// 出力の形式。
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Output {
+    /// 成功したら `ok` とだけ出力する。
+    Ok,
+    /// pest の構文木。
+    Pairs,
+    /// 構文要素のデバッグ表示。
+    Ast,
+    /// 構文要素の S 式。
+    Sexp,
+    /// 構文要素の JSON。
+    Json,
+}
+
+/// コマンドライン引数。
+struct Args {
+    /// パースを始める規則。
+    rule: Rule,
+    /// 出力の形式。
+    output: Output,
+    /// パースするファイル。None の場合は標準入力を用いる。
+    file: Option<PathBuf>,
+}
+
+/// 規則の名前から規則を得る。
+fn find_rule(name: &str) -> Option<Rule> {
+    Rule::all_rules()
+        .iter()
+        .copied()
+        .find(|rule| format!("{:?}", rule) == name)
+}
+
+/// コマンドライン引数を読む。
+fn parse_args() -> Result<Args> {
+    let mut args = Args {
+        rule: Rule::program,
+        output: Output::Ok,
+        file: None,
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--pairs" => args.output = Output::Pairs,
+            "--ast" => args.output = Output::Ast,
+            "--sexp" => args.output = Output::Sexp,
+            "--json" => args.output = Output::Json,
+            "--rule" => {
+                let name = iter
+                    .next()
+                    .ok_or_else(|| anyhow!("--rule requires a value"))?;
+                args.rule = find_rule(&name).ok_or_else(|| anyhow!("unknown rule: {}", name))?;
+            }
+            "--list-rules" => {
+                for rule in Rule::all_rules() {
+                    println!("{:?}", rule);
+                }
+                process::exit(0);
+            }
+            "-h" | "--help" => {
+                println!(
+                    "usage: satysfi-parse [--rule <RULE>] [--pairs | --ast | --sexp | --json] [FILE]\n       satysfi-parse --list-rules"
+                );
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(anyhow!("unknown option: {}", arg)),
+            _ if args.file.is_some() => return Err(anyhow!("too many files: {}", arg)),
+            _ => args.file = Some(PathBuf::from(arg)),
+        }
+    }
+    Ok(args)
+}
+
+/// text を rule でパースする。末尾の空白とコメント以外が読み残された場合も失敗とする。
+fn parse(rule: Rule, text: &str) -> Result<Pair<'_>, Diagnostic> {
+    let pair = SatysfiParser::parse(rule, text)?.next().unwrap();
+    let end = pair.as_span().end();
+    let rest = skip_trivia(&text[end..]);
+    if !rest.is_empty() {
+        let loc = Location { row: 1, col: 1 }.advance(&text[..text.len() - rest.len()]);
+        let span = Span {
+            start: loc,
+            end: loc,
+        };
+        return Err(Diagnostic::error(
+            span,
+            format!("unexpected input after {:?}", rule),
+        ));
+    }
+    Ok(pair)
+}
+
+/// pest の構文木を字下げして出力する。
+fn print_pairs(pair: Pair<'_>, depth: usize) {
+    let span = pair.as_span();
+    let (start_row, start_col) = span.start_pos().line_col();
+    let (end_row, end_col) = span.end_pos().line_col();
+    let inner = pair.clone().into_inner();
+    print!(
+        "{}{:?} {}:{}-{}:{}",
+        "  ".repeat(depth),
+        pair.as_rule(),
+        start_row,
+        start_col,
+        end_row,
+        end_col
+    );
+    if inner.peek().is_none() {
+        print!(" {:?}", span.as_str());
+    }
+    println!();
+    for pair in inner {
+        print_pairs(pair, depth + 1);
+    }
+}
+
+/// 出力できる構文要素。
+#[cfg(feature = "serde")]
+trait Node: Grammar + Debug + ToSexp + serde::Serialize {}
+#[cfg(feature = "serde")]
+impl<T: Grammar + Debug + ToSexp + serde::Serialize> Node for T {}
+
+/// 出力できる構文要素。
+#[cfg(not(feature = "serde"))]
+trait Node: Grammar + Debug + ToSexp {}
+#[cfg(not(feature = "serde"))]
+impl<T: Grammar + Debug + ToSexp> Node for T {}
+
+/// pair を T として読み、構文要素を出力する。
+fn print_node<T: Node>(pair: Pair<'_>, output: Output) -> Result<()> {
+    let node = T::parse_pair_ranged(pair);
+    match output {
+        Output::Json => print_json(&node),
+        Output::Sexp => {
+            print!("{}", to_sexp(&node, true));
+            Ok(())
+        }
+        _ => {
+            println!("{:#?}", node);
+            Ok(())
+        }
+    }
+}
+
+/// 構文要素を JSON で出力する。
+#[cfg(feature = "serde")]
+fn print_json<T: Node>(node: &Ranged<T>) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(node)?);
+    Ok(())
+}
+
+/// 構文要素を JSON で出力する。
+#[cfg(not(feature = "serde"))]
+fn print_json<T: Node>(_node: &Ranged<T>) -> Result<()> {
+    Err(anyhow!("--json requires the `serde` feature"))
+}
+
+/// 規則に対応する構文要素の型を選び、構文要素を出力する。
+///
+/// [`Grammar::parse`] と同様に、rule でパースした結果の最初の pair を構文要素として読む。
+fn print_ast(rule: Rule, pair: Pair<'_>, output: Output) -> Result<()> {
+    macro_rules! dispatch {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                if rule == <$ty>::rule() {
+                    return print_node::<$ty>(pair, output);
+                }
+            )*
+        };
+    }
+    dispatch![
+        Program,
+        Stage,
+        Header,
+        Preamble,
+        Statement,
+        Argument,
+        Signature,
+        SigStmt,
+        Struct,
+        Constraint,
+        TypeExpr,
+        TypeProd,
+        TypeUnary,
+        TypeList,
+        TypeRecord,
+        TypeRecordUnit,
+        Pattern,
+        Expr,
+        MatchArm,
+        Unary,
+        Record,
+        RecordUnit,
+        List,
+        Tuple,
+        Variable,
+        CommandArg,
+        Vertical,
+        VerticalElement,
+        Horizontal,
+        HorizontalBullet,
+        HorizontalSingle,
+        HorizontalToken,
+        Math,
+        MathSingle,
+        MathToken,
+        MathGroup,
+        MathUnary,
+        MathCmdArg,
+        Literal,
+        Length,
+    ];
+    Err(anyhow!("no AST node for rule {:?}", rule))
+}
+
+/// パースを行い、成功すれば真を返す。
+fn run(args: &Args) -> Result<bool> {
+    let (name, text) = match &args.file {
+        Some(file) => {
+            let text = std::fs::read_to_string(file)
+                .with_context(|| format!("cannot read {}", file.display()))?;
+            (file.display().to_string(), text)
+        }
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            ("<stdin>".to_owned(), text)
+        }
+    };
+
+    let pair = match parse(args.rule, &text) {
+        Ok(pair) => pair,
+        Err(d) => {
+            eprintln!(
+                "{}:{}:{}: error: {}",
+                name, d.span.start.row, d.span.start.col, d.message
+            );
+            return Ok(false);
+        }
+    };
+    match args.output {
+        Output::Ok => println!("ok"),
+        Output::Pairs => print_pairs(pair, 0),
+        Output::Ast | Output::Sexp | Output::Json => print_ast(args.rule, pair, args.output)?,
+    }
+    Ok(true)
+}
+
+fn main() {
+    let result = parse_args().and_then(|args| run(&args));
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Diagnostics reported to the user.
+//!
+//! パースの失敗などを、ソースコード上の範囲とメッセージの組で表す。
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::{Location, Span};
+use crate::parser::Rule;
+use pest::error::{Error, ErrorVariant, LineColLocation};
+
+/// 診断の重大度。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// エラー。
+    Error,
+    /// 警告。
+    Warning,
+}
+
+/// 診断。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 問題のある範囲。位置のみが分かる場合は start と end が等しい。
+    pub span: Span,
+    /// 重大度。
+    pub severity: Severity,
+    /// メッセージ。
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// エラーの診断を作る。
+    pub fn error(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            span,
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error<Rule>> for Diagnostic {
+    fn from(e: Error<Rule>) -> Self {
+        let location = |(row, col)| Location { row, col };
+        let span = match e.line_col {
+            LineColLocation::Pos(pos) => Span {
+                start: location(pos),
+                end: location(pos),
+            },
+            LineColLocation::Span(start, end) => Span {
+                start: location(start),
+                end: location(end),
+            },
+        };
+        if let ErrorVariant::ParsingError { negatives, .. } = &e.variant {
+            let misplaced = negatives.iter().any(|rule| {
+                matches!(
+                    rule,
+                    Rule::misplaced_cmd_option | Rule::misplaced_math_cmd_option
+                )
+            });
+            if misplaced {
+                // `?:` や `?*` の 2 文字を指す
+                let end = Location {
+                    row: span.start.row,
+                    col: span.start.col + 2,
+                };
+                let message = "optional arguments (`?:` / `?*`) must precede mandatory arguments";
+                return Diagnostic::error(
+                    Span {
+                        start: span.start,
+                        end,
+                    },
+                    message,
+                );
+            }
+        }
+        Diagnostic::error(span, e.variant.message())
+    }
+}
@@ -0,0 +1,34 @@
+use super::*;
+use crate::grammar::common::Grammar;
+use crate::grammar::Program;
+
+/// text のパースの失敗を診断にしたときの、範囲とメッセージ。
+fn parse_error(text: &str) -> ((usize, usize), (usize, usize), String) {
+    let d = Diagnostic::from(Program::parse(text).unwrap_err());
+    (
+        (d.span.start.row, d.span.start.col),
+        (d.span.end.row, d.span.end.col),
+        d.message,
+    )
+}
+
+#[test]
+fn misplaced_options() {
+    let message = "optional arguments (`?:` / `?*`) must precede mandatory arguments";
+    assert_eq!(
+        parse_error("{\\cmd(x)?:(y){t}}"),
+        ((1, 9), (1, 11), message.to_owned())
+    );
+    assert_eq!(
+        parse_error("'<\n  +p(x)(y)?*;\n>"),
+        ((2, 11), (2, 13), message.to_owned())
+    );
+    assert_eq!(
+        parse_error("${\\frac{x}?:{y}}"),
+        ((1, 11), (1, 13), message.to_owned())
+    );
+    // それ以外の失敗は pest のメッセージのまま
+    let (start, end, message) = parse_error("let = 1");
+    assert_eq!(start, end);
+    assert!(message.starts_with("expected"), "{}", message);
+}
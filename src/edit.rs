@@ -0,0 +1,289 @@
+//! Text edits on source files.
+//!
+//! リネームや lint の自動修正などの変換結果を、ソースコード上の範囲と置き換える文字列の組で表す。
+//! 範囲は行と列で持つが、重なりの検出や適用は元のテキスト上のバイト位置に直して行う。
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::{Location, Span};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// ソースコードの一部の置き換え。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TextEdit {
+    /// 置き換える範囲。
+    pub span: Span,
+    /// 置き換え後の文字列。
+    pub new_text: String,
+}
+
+impl TextEdit {
+    /// span を new_text で置き換える編集を作る。
+    pub fn new(span: Span, new_text: impl Into<String>) -> Self {
+        TextEdit {
+            span,
+            new_text: new_text.into(),
+        }
+    }
+
+    /// text 上で置き換えるバイト位置の範囲。範囲が text の外にあれば None。
+    pub fn byte_range(&self, text: &str) -> Option<Range<usize>> {
+        let offsets = LineOffsets::new(text);
+        let start = offsets.checked_offset(text, self.span.start)?;
+        let end = offsets.checked_offset(text, self.span.end)?;
+        (start <= end).then_some(start..end)
+    }
+}
+
+/// 編集を適用できない理由。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// この範囲の編集が、テキストの外にある。
+    OutOfRange(Span),
+    /// この 2 つの範囲の編集が重なっている。同じ位置への挿入も重なりとみなす。
+    Overlap(Span, Span),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::OutOfRange(span) => write!(
+                f,
+                "edit at {}:{} is out of range",
+                span.start.row, span.start.col
+            ),
+            EditError::Overlap(a, b) => write!(
+                f,
+                "edits at {}:{} and {}:{} overlap",
+                a.start.row, a.start.col, b.start.row, b.start.col
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// 2 つのバイト位置の範囲が重なるかどうか。
+///
+/// 開始位置が同じ場合は、どちらを先に適用するか決まらないため重なりとみなす。
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start == b.start || (a.start < b.end && b.start < a.end)
+}
+
+/// edits をバイト位置に直し、開始位置の順に並べる。まったく同じ編集は 1 つにまとめる。
+///
+/// 範囲が text の外にある場合や、互いに重なる場合は失敗する。
+fn resolve<'a>(
+    text: &str,
+    edits: &'a [TextEdit],
+) -> Result<Vec<(Range<usize>, &'a TextEdit)>, EditError> {
+    let offsets = LineOffsets::new(text);
+    let mut resolved = edits
+        .iter()
+        .map(|edit| {
+            let start = offsets.checked_offset(text, edit.span.start);
+            let end = offsets.checked_offset(text, edit.span.end);
+            match (start, end) {
+                (Some(start), Some(end)) if start <= end => Ok((start..end, edit)),
+                _ => Err(EditError::OutOfRange(edit.span)),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    resolved.sort_by_key(|(range, _)| (range.start, range.end));
+    resolved.dedup_by(|(_, a), (_, b)| a == b);
+    for pair in resolved.windows(2) {
+        let ((a_range, a), (b_range, b)) = (&pair[0], &pair[1]);
+        if overlaps(a_range, b_range) {
+            return Err(EditError::Overlap(a.span, b.span));
+        }
+    }
+    Ok(resolved)
+}
+
+/// edits が text の範囲内にあり、互いに重ならないことを確かめる。
+pub fn check(text: &str, edits: &[TextEdit]) -> Result<(), EditError> {
+    resolve(text, edits).map(|_| ())
+}
+
+/// text に edits を適用する。
+///
+/// edits の順序は問わない。範囲が text の外にある場合や、互いに重なる場合は失敗する。
+pub fn try_apply(text: &str, edits: &[TextEdit]) -> Result<String, EditError> {
+    let resolved = resolve(text, edits)?;
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (range, edit) in resolved {
+        result.push_str(&text[pos..range.start]);
+        result.push_str(&edit.new_text);
+        pos = range.end;
+    }
+    result.push_str(&text[pos..]);
+    Ok(result)
+}
+
+/// text に edits を適用する。
+///
+/// # Panics
+///
+/// [`try_apply`] が失敗する場合。
+pub fn apply(text: &str, edits: &[TextEdit]) -> String {
+    try_apply(text, edits).unwrap_or_else(|e| panic!("cannot apply edits: {}", e))
+}
+
+/// 編集のまとまり（lint の自動修正 1 つ分など）を、重ならないものだけ前から順に選ぶ。
+///
+/// まとまりの中の編集は、すべて選ぶかすべて選ばないかのどちらかになる。
+/// 選んだ編集と、選ばなかったまとまりの添字を返す。
+pub fn merge(text: &str, groups: &[Vec<TextEdit>]) -> (Vec<TextEdit>, Vec<usize>) {
+    let mut accepted: Vec<Range<usize>> = vec![];
+    let mut merged = vec![];
+    let mut skipped = vec![];
+    for (i, group) in groups.iter().enumerate() {
+        let ranges = match resolve(text, group) {
+            Ok(resolved) => resolved,
+            Err(_) => {
+                skipped.push(i);
+                continue;
+            }
+        };
+        let conflicts = ranges
+            .iter()
+            .any(|(range, _)| accepted.iter().any(|other| overlaps(range, other)));
+        if conflicts {
+            skipped.push(i);
+            continue;
+        }
+        for (range, edit) in ranges {
+            accepted.push(range);
+            merged.push(edit.clone());
+        }
+    }
+    (merged, skipped)
+}
+
+/// 複数のファイルにまたがる編集。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceEdit {
+    /// ファイルごとの編集。
+    pub changes: BTreeMap<PathBuf, Vec<TextEdit>>,
+}
+
+/// [`WorkspaceEdit`] を適用できない理由。
+#[derive(Debug)]
+pub enum WorkspaceEditError {
+    /// ファイルを読み書きできない。
+    Io(PathBuf, io::Error),
+    /// ファイルへの編集が正しくない。
+    Edit(PathBuf, EditError),
+}
+
+impl fmt::Display for WorkspaceEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkspaceEditError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            WorkspaceEditError::Edit(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceEditError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WorkspaceEditError::Io(_, e) => Some(e),
+            WorkspaceEditError::Edit(_, e) => Some(e),
+        }
+    }
+}
+
+impl WorkspaceEdit {
+    /// 空の WorkspaceEdit を作る。
+    pub fn new() -> Self {
+        WorkspaceEdit::default()
+    }
+
+    /// path への編集を加える。
+    pub fn push(&mut self, path: impl Into<PathBuf>, edit: TextEdit) {
+        self.changes.entry(path.into()).or_default().push(edit);
+    }
+
+    /// path への編集をまとめて加える。
+    pub fn extend(&mut self, path: impl Into<PathBuf>, edits: impl IntoIterator<Item = TextEdit>) {
+        self.changes.entry(path.into()).or_default().extend(edits);
+    }
+
+    /// 編集が 1 つもないかどうか。
+    pub fn is_empty(&self) -> bool {
+        self.changes.values().all(Vec::is_empty)
+    }
+
+    /// 各ファイルの中身を read で読み、編集を適用した結果を返す。
+    ///
+    /// どれか 1 つのファイルでも適用できなければ失敗する。
+    pub fn apply(
+        &self,
+        mut read: impl FnMut(&Path) -> io::Result<String>,
+    ) -> Result<BTreeMap<PathBuf, String>, WorkspaceEditError> {
+        let mut results = BTreeMap::new();
+        for (path, edits) in &self.changes {
+            let text = read(path).map_err(|e| WorkspaceEditError::Io(path.clone(), e))?;
+            let text =
+                try_apply(&text, edits).map_err(|e| WorkspaceEditError::Edit(path.clone(), e))?;
+            results.insert(path.clone(), text);
+        }
+        Ok(results)
+    }
+
+    /// ディスク上のファイルに編集を適用して書き戻す。
+    ///
+    /// すべてのファイルについて編集を適用できることを確かめてから書き込む。
+    pub fn write(&self) -> Result<(), WorkspaceEditError> {
+        for (path, text) in self.apply(|path| std::fs::read_to_string(path))? {
+            std::fs::write(&path, text).map_err(|e| WorkspaceEditError::Io(path.clone(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// 各行の先頭のバイト位置。
+pub(crate) struct LineOffsets(Vec<usize>);
+
+impl LineOffsets {
+    /// text の各行の先頭を求める。
+    pub(crate) fn new(text: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineOffsets(starts)
+    }
+
+    /// Location をバイト位置にする。
+    pub(crate) fn offset(&self, text: &str, loc: Location) -> usize {
+        let line = self.0[loc.row - 1];
+        let rest = &text[line..];
+        line + rest
+            .char_indices()
+            .nth(loc.col - 1)
+            .map_or(rest.len(), |(i, _)| i)
+    }
+
+    /// Location をバイト位置にする。行や列が text の外にあれば None。
+    ///
+    /// 行末（改行の直前）の位置は行の範囲内とみなす。
+    pub(crate) fn checked_offset(&self, text: &str, loc: Location) -> Option<usize> {
+        let line = *self.0.get(loc.row.checked_sub(1)?)?;
+        let rest = &text[line..];
+        let rest = rest.find('\n').map_or(rest, |end| &rest[..end]);
+        let col = loc.col.checked_sub(1)?;
+        match rest.char_indices().nth(col) {
+            Some((i, _)) => Some(line + i),
+            None if col == rest.chars().count() => Some(line + rest.len()),
+            None => None,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use super::*;
+use std::fs;
+
+/// row 行 col1 列から row 行 col2 列までを new_text で置き換える編集。
+fn edit(row: usize, col1: usize, col2: usize, new_text: &str) -> TextEdit {
+    let loc = |col| Location { row, col };
+    TextEdit::new(
+        Span {
+            start: loc(col1),
+            end: loc(col2),
+        },
+        new_text,
+    )
+}
+
+#[test]
+fn byte_range() {
+    let text = "ab\nあいう\n";
+    assert_eq!(edit(2, 2, 3, "").byte_range(text), Some(6..9));
+    // 行末は範囲内
+    assert_eq!(edit(2, 4, 4, "").byte_range(text), Some(12..12));
+    assert_eq!(edit(3, 1, 1, "").byte_range(text), Some(13..13));
+    assert_eq!(edit(1, 4, 4, "").byte_range(text), None);
+    assert_eq!(edit(4, 1, 1, "").byte_range(text), None);
+    assert_eq!(edit(1, 2, 1, "").byte_range(text), None);
+}
+
+#[test]
+fn apply_edits() {
+    let text = "let x = 1\nin x\n";
+    // 順序は問わない
+    let edits = [
+        edit(2, 4, 5, "y"),
+        edit(1, 5, 6, "y"),
+        edit(1, 10, 10, " + 1"),
+    ];
+    assert_eq!(try_apply(text, &edits).unwrap(), "let y = 1 + 1\nin y\n");
+    // まったく同じ編集は 1 つとみなす
+    let edits = [edit(1, 5, 6, "y"), edit(1, 5, 6, "y")];
+    assert_eq!(try_apply(text, &edits).unwrap(), "let y = 1\nin x\n");
+    // 隣り合う編集は重ならない
+    let edits = [edit(1, 5, 6, "y"), edit(1, 6, 8, "=")];
+    assert_eq!(try_apply(text, &edits).unwrap(), "let y= 1\nin x\n");
+}
+
+#[test]
+fn conflicts() {
+    let text = "let x = 1\nin x\n";
+    let a = edit(1, 5, 8, "y =");
+    let b = edit(1, 7, 10, "= 2");
+    assert_eq!(
+        try_apply(text, &[b.clone(), a.clone()]),
+        Err(EditError::Overlap(a.span, b.span))
+    );
+    // 同じ位置への挿入は順序が決まらない
+    let c = edit(1, 5, 5, "a");
+    let d = edit(1, 5, 5, "b");
+    assert_eq!(
+        check(text, &[c.clone(), d.clone()]),
+        Err(EditError::Overlap(c.span, d.span))
+    );
+    let e = edit(3, 1, 2, "");
+    assert_eq!(
+        check(text, std::slice::from_ref(&e)),
+        Err(EditError::OutOfRange(e.span))
+    );
+    assert_eq!(
+        EditError::Overlap(a.span, b.span).to_string(),
+        "edits at 1:5 and 1:7 overlap"
+    );
+}
+
+#[test]
+fn merge_groups() {
+    let text = "let x = 1\nin x\n";
+    let groups = vec![
+        vec![edit(1, 5, 6, "y"), edit(2, 4, 5, "y")],
+        // 1 つ目のまとまりと重なるため、まとめて選ばない
+        vec![edit(1, 9, 10, "2"), edit(2, 4, 5, "z")],
+        vec![edit(1, 9, 10, "3")],
+        vec![edit(9, 1, 1, "")],
+    ];
+    let (edits, skipped) = merge(text, &groups);
+    assert_eq!(skipped, vec![1, 3]);
+    assert_eq!(apply(text, &edits), "let y = 3\nin y\n");
+}
+
+#[test]
+fn workspace_edit() {
+    let dir = std::env::temp_dir().join(format!("satysfi-peg-parser-edit-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let (a, b) = (dir.join("a.saty"), dir.join("b.satyh"));
+    fs::write(&a, "let x = 1\nin x\n").unwrap();
+    fs::write(&b, "let x = 2\n").unwrap();
+
+    let mut edits = WorkspaceEdit::new();
+    assert!(edits.is_empty());
+    edits.extend(&a, vec![edit(1, 5, 6, "y"), edit(2, 4, 5, "y")]);
+    edits.push(&b, edit(1, 9, 10, "3"));
+    let results = edits.apply(|path| fs::read_to_string(path)).unwrap();
+    assert_eq!(results[&a], "let y = 1\nin y\n");
+    assert_eq!(results[&b], "let x = 3\n");
+
+    // 1 つでも適用できないファイルがあれば、どのファイルも書き換えない
+    let mut broken = edits.clone();
+    broken.push(&b, edit(1, 9, 10, "4"));
+    assert!(matches!(
+        broken.write(),
+        Err(WorkspaceEditError::Edit(path, EditError::Overlap(..))) if path == b
+    ));
+    assert_eq!(fs::read_to_string(&a).unwrap(), "let x = 1\nin x\n");
+
+    edits.write().unwrap();
+    assert_eq!(fs::read_to_string(&a).unwrap(), "let y = 1\nin y\n");
+    assert_eq!(fs::read_to_string(&b).unwrap(), "let x = 3\n");
+
+    let missing = dir.join("missing.saty");
+    let mut edits = WorkspaceEdit::new();
+    edits.push(&missing, edit(1, 1, 1, ""));
+    assert!(matches!(
+        edits.write(),
+        Err(WorkspaceEditError::Io(path, _)) if path == missing
+    ));
+    let _ = fs::remove_dir_all(&dir);
+}
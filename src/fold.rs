@@ -0,0 +1,716 @@
+//! AST-to-AST transformation.
+//!
+//! [`Fold`] は AST を消費しながら辿り、新しい AST を組み立て直す。
+//! 各メソッドは既定で対応する `walk_*` を呼び、子要素を変換した上で元と同じ範囲の構文要素を返す。
+//! そのため書き換えなかった構文要素の範囲はそのまま保たれる。
+//!
+//! 新たに構文要素を作る場合は [`Ranged::synthesized`] を使い、元になった構文要素の範囲を与える。
+//! 構文要素自体が範囲を持たない場合（[`Ranged`] で包まれていない場合）は、
+//! それを囲む最も内側の構文要素の範囲で一時的に包んで渡される。
+
+#![allow(clippy::missing_docs_in_private_items)]
+#![allow(missing_docs)]
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::{Grammar, Ranged, Span};
+use crate::grammar::{
+    ApplicationArg, Argument, CommandArg, Constraint, Expr, Header, Horizontal, HorizontalBullet,
+    HorizontalSingle, HorizontalToken, LetRecBinding, List, Literal, MatchArm, Math, MathCmdArg,
+    MathGroup, MathSingle, MathToken, MathUnary, Pattern, Preamble, Program, Record, RecordUnit,
+    SigStmt, Signature, Stage, Statement, Struct, Tuple, TypeExpr, TypeList, TypeListUnit,
+    TypeProd, TypeRecord, TypeRecordUnit, TypeUnary, Unary, ValName, Variable, Vertical,
+    VerticalElement,
+};
+
+/// AST を組み立て直しながら辿るトレイト。
+pub trait Fold {
+    fn fold_program(&mut self, node: Program) -> Program {
+        walk_program(self, node)
+    }
+    fn fold_stage(&mut self, node: Ranged<Stage>) -> Ranged<Stage> {
+        node
+    }
+    fn fold_header(&mut self, node: Ranged<Header>) -> Ranged<Header> {
+        node
+    }
+    fn fold_preamble(&mut self, node: Ranged<Preamble>) -> Ranged<Preamble> {
+        walk_preamble(self, node)
+    }
+    fn fold_statement(&mut self, node: Ranged<Statement>) -> Ranged<Statement> {
+        walk_statement(self, node)
+    }
+    fn fold_let_rec_binding(&mut self, node: Ranged<LetRecBinding>) -> Ranged<LetRecBinding> {
+        walk_let_rec_binding(self, node)
+    }
+    fn fold_argument(&mut self, node: Ranged<Argument>) -> Ranged<Argument> {
+        walk_argument(self, node)
+    }
+    fn fold_signature(&mut self, node: Ranged<Signature>) -> Ranged<Signature> {
+        walk_signature(self, node)
+    }
+    fn fold_sig_stmt(&mut self, node: Ranged<SigStmt>) -> Ranged<SigStmt> {
+        walk_sig_stmt(self, node)
+    }
+    fn fold_val_name(&mut self, node: Ranged<ValName>) -> Ranged<ValName> {
+        node
+    }
+    fn fold_struct(&mut self, node: Ranged<Struct>) -> Ranged<Struct> {
+        walk_struct(self, node)
+    }
+    fn fold_constraint(&mut self, node: Ranged<Constraint>) -> Ranged<Constraint> {
+        walk_constraint(self, node)
+    }
+    fn fold_type_expr(&mut self, node: Ranged<TypeExpr>) -> Ranged<TypeExpr> {
+        walk_type_expr(self, node)
+    }
+    fn fold_type_prod(&mut self, node: Ranged<TypeProd>) -> Ranged<TypeProd> {
+        walk_type_prod(self, node)
+    }
+    fn fold_type_unary(&mut self, node: Ranged<TypeUnary>) -> Ranged<TypeUnary> {
+        walk_type_unary(self, node)
+    }
+    fn fold_type_list(&mut self, node: Ranged<TypeList>) -> Ranged<TypeList> {
+        walk_type_list(self, node)
+    }
+    fn fold_type_list_unit(&mut self, node: Ranged<TypeListUnit>) -> Ranged<TypeListUnit> {
+        walk_type_list_unit(self, node)
+    }
+    fn fold_type_record(&mut self, node: Ranged<TypeRecord>) -> Ranged<TypeRecord> {
+        walk_type_record(self, node)
+    }
+    fn fold_type_record_unit(&mut self, node: Ranged<TypeRecordUnit>) -> Ranged<TypeRecordUnit> {
+        walk_type_record_unit(self, node)
+    }
+    fn fold_pattern(&mut self, node: Ranged<Pattern>) -> Ranged<Pattern> {
+        walk_pattern(self, node)
+    }
+    fn fold_expr(&mut self, node: Ranged<Expr>) -> Ranged<Expr> {
+        walk_expr(self, node)
+    }
+    fn fold_match_arm(&mut self, node: Ranged<MatchArm>) -> Ranged<MatchArm> {
+        walk_match_arm(self, node)
+    }
+    fn fold_application_arg(&mut self, node: Ranged<ApplicationArg>) -> Ranged<ApplicationArg> {
+        walk_application_arg(self, node)
+    }
+    fn fold_unary(&mut self, node: Ranged<Unary>) -> Ranged<Unary> {
+        walk_unary(self, node)
+    }
+    fn fold_record(&mut self, node: Ranged<Record>) -> Ranged<Record> {
+        walk_record(self, node)
+    }
+    fn fold_record_unit(&mut self, node: Ranged<RecordUnit>) -> Ranged<RecordUnit> {
+        walk_record_unit(self, node)
+    }
+    fn fold_list(&mut self, node: Ranged<List>) -> Ranged<List> {
+        walk_list(self, node)
+    }
+    fn fold_tuple(&mut self, node: Ranged<Tuple>) -> Ranged<Tuple> {
+        walk_tuple(self, node)
+    }
+    fn fold_variable(&mut self, node: Ranged<Variable>) -> Ranged<Variable> {
+        node
+    }
+    fn fold_literal(&mut self, node: Ranged<Literal>) -> Ranged<Literal> {
+        node
+    }
+    /// コマンド名。定義（`let-inline` など）と使用（`\cmd` や `+cmd`）の両方で呼ばれる。
+    fn fold_command_name(&mut self, node: Ranged<String>) -> Ranged<String> {
+        node
+    }
+    fn fold_command_arg(&mut self, node: Ranged<CommandArg>) -> Ranged<CommandArg> {
+        walk_command_arg(self, node)
+    }
+    fn fold_vertical(&mut self, node: Ranged<Vertical>) -> Ranged<Vertical> {
+        walk_vertical(self, node)
+    }
+    fn fold_vertical_element(&mut self, node: Ranged<VerticalElement>) -> Ranged<VerticalElement> {
+        walk_vertical_element(self, node)
+    }
+    fn fold_horizontal(&mut self, node: Ranged<Horizontal>) -> Ranged<Horizontal> {
+        walk_horizontal(self, node)
+    }
+    fn fold_horizontal_bullet(
+        &mut self,
+        node: Ranged<HorizontalBullet>,
+    ) -> Ranged<HorizontalBullet> {
+        walk_horizontal_bullet(self, node)
+    }
+    fn fold_horizontal_single(
+        &mut self,
+        node: Ranged<HorizontalSingle>,
+    ) -> Ranged<HorizontalSingle> {
+        walk_horizontal_single(self, node)
+    }
+    fn fold_horizontal_token(&mut self, node: Ranged<HorizontalToken>) -> Ranged<HorizontalToken> {
+        walk_horizontal_token(self, node)
+    }
+    fn fold_math(&mut self, node: Ranged<Math>) -> Ranged<Math> {
+        walk_math(self, node)
+    }
+    fn fold_math_single(&mut self, node: Ranged<MathSingle>) -> Ranged<MathSingle> {
+        walk_math_single(self, node)
+    }
+    fn fold_math_token(&mut self, node: Ranged<MathToken>) -> Ranged<MathToken> {
+        walk_math_token(self, node)
+    }
+    fn fold_math_group(&mut self, node: Ranged<MathGroup>) -> Ranged<MathGroup> {
+        walk_math_group(self, node)
+    }
+    fn fold_math_unary(&mut self, node: Ranged<MathUnary>) -> Ranged<MathUnary> {
+        walk_math_unary(self, node)
+    }
+    fn fold_math_cmd_arg(&mut self, node: Ranged<MathCmdArg>) -> Ranged<MathCmdArg> {
+        walk_math_cmd_arg(self, node)
+    }
+}
+
+/// 範囲を持たない構文要素を、囲む構文要素の範囲で一時的に包んで変換する。
+fn fold_unranged<T: Grammar>(node: T, span: Span, f: impl FnOnce(Ranged<T>) -> Ranged<T>) -> T {
+    f(Ranged::new(node, span)).body
+}
+
+/// Vec の各要素を変換する。
+fn fold_vec<T: Grammar, F: Fold + ?Sized>(
+    f: &mut F,
+    nodes: Vec<Ranged<T>>,
+    mut fold: impl FnMut(&mut F, Ranged<T>) -> Ranged<T>,
+) -> Vec<Ranged<T>> {
+    nodes.into_iter().map(|node| fold(f, node)).collect()
+}
+
+pub fn walk_program<F: Fold + ?Sized>(f: &mut F, node: Program) -> Program {
+    Program {
+        stage: node.stage.map(|stage| f.fold_stage(stage)),
+        header: fold_vec(f, node.header, F::fold_header),
+        preamble: node.preamble.map(|preamble| f.fold_preamble(preamble)),
+        expr: node.expr.map(|expr| f.fold_expr(expr)),
+    }
+}
+
+pub fn walk_preamble<F: Fold + ?Sized>(f: &mut F, node: Ranged<Preamble>) -> Ranged<Preamble> {
+    node.map(|body| Preamble(fold_vec(f, body.0, F::fold_statement)))
+}
+
+pub fn walk_statement<F: Fold + ?Sized>(f: &mut F, node: Ranged<Statement>) -> Ranged<Statement> {
+    node.map(|body| match body {
+        Statement::Let { ptn, args, expr } => Statement::Let {
+            ptn: f.fold_pattern(ptn),
+            args: fold_vec(f, args, F::fold_argument),
+            expr: f.fold_expr(expr),
+        },
+        Statement::LetRec(bindings) => {
+            Statement::LetRec(fold_vec(f, bindings, F::fold_let_rec_binding))
+        }
+        Statement::LetInline {
+            ctx,
+            name,
+            args,
+            expr,
+        } => Statement::LetInline {
+            ctx: ctx.map(|ctx| f.fold_variable(ctx)),
+            name: f.fold_command_name(name),
+            args: fold_vec(f, args, F::fold_argument),
+            expr: f.fold_expr(expr),
+        },
+        Statement::LetBlock {
+            ctx,
+            name,
+            args,
+            expr,
+        } => Statement::LetBlock {
+            ctx: ctx.map(|ctx| f.fold_variable(ctx)),
+            name: f.fold_command_name(name),
+            args: fold_vec(f, args, F::fold_argument),
+            expr: f.fold_expr(expr),
+        },
+        Statement::LetMath { name, args, expr } => Statement::LetMath {
+            name: f.fold_command_name(name),
+            args: fold_vec(f, args, F::fold_argument),
+            expr: f.fold_expr(expr),
+        },
+        Statement::LetMutable { var, expr } => Statement::LetMutable {
+            var: f.fold_variable(var),
+            expr: f.fold_expr(expr),
+        },
+        Statement::Module {
+            name,
+            signature,
+            body,
+        } => Statement::Module {
+            name,
+            signature: signature.map(|signature| f.fold_signature(signature)),
+            body: f.fold_struct(body),
+        },
+    })
+}
+
+pub fn walk_argument<F: Fold + ?Sized>(f: &mut F, node: Ranged<Argument>) -> Ranged<Argument> {
+    let span = node.span();
+    node.map(|body| match body {
+        Argument::Pattern(ptn) => {
+            Argument::Pattern(fold_unranged(ptn, span, |ptn| f.fold_pattern(ptn)))
+        }
+        Argument::Optional(name) => Argument::Optional(name),
+    })
+}
+
+pub fn walk_signature<F: Fold + ?Sized>(f: &mut F, node: Ranged<Signature>) -> Ranged<Signature> {
+    node.map(|body| Signature(fold_vec(f, body.0, F::fold_sig_stmt)))
+}
+
+pub fn walk_sig_stmt<F: Fold + ?Sized>(f: &mut F, node: Ranged<SigStmt>) -> Ranged<SigStmt> {
+    node.map(|body| match body {
+        SigStmt::Type {
+            params,
+            name,
+            constraints,
+        } => SigStmt::Type {
+            params,
+            name,
+            constraints: fold_vec(f, constraints, F::fold_constraint),
+        },
+        SigStmt::Val {
+            name,
+            ty,
+            constraints,
+        } => SigStmt::Val {
+            name: f.fold_val_name(name),
+            ty: f.fold_type_expr(ty),
+            constraints: fold_vec(f, constraints, F::fold_constraint),
+        },
+        SigStmt::Direct {
+            name,
+            ty,
+            constraints,
+        } => SigStmt::Direct {
+            name,
+            ty: f.fold_type_expr(ty),
+            constraints: fold_vec(f, constraints, F::fold_constraint),
+        },
+    })
+}
+
+pub fn walk_struct<F: Fold + ?Sized>(f: &mut F, node: Ranged<Struct>) -> Ranged<Struct> {
+    node.map(|body| Struct(fold_vec(f, body.0, F::fold_statement)))
+}
+
+pub fn walk_constraint<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<Constraint>,
+) -> Ranged<Constraint> {
+    node.map(|body| Constraint {
+        param: body.param,
+        record: f.fold_type_record(body.record),
+    })
+}
+
+pub fn walk_type_expr<F: Fold + ?Sized>(f: &mut F, node: Ranged<TypeExpr>) -> Ranged<TypeExpr> {
+    node.map(|body| TypeExpr {
+        optional_args: fold_vec(f, body.optional_args, F::fold_type_prod),
+        args: fold_vec(f, body.args, F::fold_type_prod),
+        ret: f.fold_type_prod(body.ret),
+    })
+}
+
+pub fn walk_type_prod<F: Fold + ?Sized>(f: &mut F, node: Ranged<TypeProd>) -> Ranged<TypeProd> {
+    node.map(|body| TypeProd(fold_vec(f, body.0, F::fold_type_unary)))
+}
+
+pub fn walk_type_unary<F: Fold + ?Sized>(f: &mut F, node: Ranged<TypeUnary>) -> Ranged<TypeUnary> {
+    let span = node.span();
+    node.map(|body| match body {
+        TypeUnary::InlineCmd(list) => TypeUnary::InlineCmd(f.fold_type_list(list)),
+        TypeUnary::BlockCmd(list) => TypeUnary::BlockCmd(f.fold_type_list(list)),
+        TypeUnary::MathCmd(list) => TypeUnary::MathCmd(f.fold_type_list(list)),
+        TypeUnary::Paren(ty) => TypeUnary::Paren(Box::new(f.fold_type_expr(*ty))),
+        TypeUnary::Record(record) => TypeUnary::Record(fold_unranged(record, span, |record| {
+            f.fold_type_record(record)
+        })),
+        TypeUnary::Application(units) => {
+            TypeUnary::Application(fold_vec(f, units, F::fold_type_unary))
+        }
+        param @ TypeUnary::Param(_) => param,
+        name @ TypeUnary::Name { .. } => name,
+    })
+}
+
+pub fn walk_type_list<F: Fold + ?Sized>(f: &mut F, node: Ranged<TypeList>) -> Ranged<TypeList> {
+    node.map(|body| TypeList(fold_vec(f, body.0, F::fold_type_list_unit)))
+}
+
+pub fn walk_type_list_unit<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<TypeListUnit>,
+) -> Ranged<TypeListUnit> {
+    let span = node.span();
+    node.map(|body| match body {
+        TypeListUnit::Optional(ty) => {
+            TypeListUnit::Optional(fold_unranged(ty, span, |ty| f.fold_type_prod(ty)))
+        }
+        TypeListUnit::Mandatory(ty) => {
+            TypeListUnit::Mandatory(fold_unranged(ty, span, |ty| f.fold_type_expr(ty)))
+        }
+    })
+}
+
+pub fn walk_type_record<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<TypeRecord>,
+) -> Ranged<TypeRecord> {
+    node.map(|body| TypeRecord(fold_vec(f, body.0, F::fold_type_record_unit)))
+}
+
+pub fn walk_type_record_unit<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<TypeRecordUnit>,
+) -> Ranged<TypeRecordUnit> {
+    node.map(|body| TypeRecordUnit {
+        key: body.key,
+        ty: f.fold_type_expr(body.ty),
+    })
+}
+
+pub fn walk_pattern<F: Fold + ?Sized>(f: &mut F, node: Ranged<Pattern>) -> Ranged<Pattern> {
+    let span = node.span();
+    node.map(|body| match body {
+        Pattern::List(ptns) => Pattern::List(fold_vec(f, ptns, F::fold_pattern)),
+        Pattern::Tuple(ptns) => Pattern::Tuple(fold_vec(f, ptns, F::fold_pattern)),
+        Pattern::Paren(ptn) => Pattern::Paren(Box::new(f.fold_pattern(*ptn))),
+        Pattern::Wildcard => Pattern::Wildcard,
+        Pattern::Variable(var) => {
+            Pattern::Variable(fold_unranged(var, span, |var| f.fold_variable(var)))
+        }
+        Pattern::Literal(lit) => {
+            Pattern::Literal(fold_unranged(lit, span, |lit| f.fold_literal(lit)))
+        }
+        Pattern::Variant { name, arg } => Pattern::Variant {
+            name,
+            arg: arg.map(|arg| Box::new(f.fold_pattern(*arg))),
+        },
+        Pattern::As { ptn, var } => Pattern::As {
+            ptn: Box::new(f.fold_pattern(*ptn)),
+            var: f.fold_variable(var),
+        },
+        Pattern::Cons { head, tail } => Pattern::Cons {
+            head: Box::new(f.fold_pattern(*head)),
+            tail: Box::new(f.fold_pattern(*tail)),
+        },
+    })
+}
+
+pub fn walk_let_rec_binding<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<LetRecBinding>,
+) -> Ranged<LetRecBinding> {
+    node.map(|body| LetRecBinding {
+        var: f.fold_variable(body.var),
+        args: fold_vec(f, body.args, F::fold_argument),
+        expr: f.fold_expr(body.expr),
+    })
+}
+
+pub fn walk_expr<F: Fold + ?Sized>(f: &mut F, node: Ranged<Expr>) -> Ranged<Expr> {
+    node.map(|body| match body {
+        Expr::Match { expr, arms } => Expr::Match {
+            expr: Box::new(f.fold_expr(*expr)),
+            arms: fold_vec(f, arms, F::fold_match_arm),
+        },
+        Expr::BindStmt { bind, body } => Expr::BindStmt {
+            bind: Box::new(f.fold_statement(*bind)),
+            body: Box::new(f.fold_expr(*body)),
+        },
+        Expr::Lambda { args, body } => Expr::Lambda {
+            args: fold_vec(f, args, F::fold_argument),
+            body: Box::new(f.fold_expr(*body)),
+        },
+        Expr::CtrlFlowWhile { condition, body } => Expr::CtrlFlowWhile {
+            condition: Box::new(f.fold_expr(*condition)),
+            body: Box::new(f.fold_expr(*body)),
+        },
+        Expr::CtrlFlowIf {
+            condition,
+            expr_true,
+            expr_false,
+        } => Expr::CtrlFlowIf {
+            condition: Box::new(f.fold_expr(*condition)),
+            expr_true: Box::new(f.fold_expr(*expr_true)),
+            expr_false: Box::new(f.fold_expr(*expr_false)),
+        },
+        Expr::Assign { var, expr } => Expr::Assign {
+            var: f.fold_variable(var),
+            expr: Box::new(f.fold_expr(*expr)),
+        },
+        Expr::Dyadic { lhs, rhs, binop } => Expr::Dyadic {
+            lhs: Box::new(f.fold_expr(*lhs)),
+            rhs: Box::new(f.fold_expr(*rhs)),
+            binop,
+        },
+        Expr::UnaryOperatorExpr { rhs, unaryop } => Expr::UnaryOperatorExpr {
+            rhs: Box::new(f.fold_expr(*rhs)),
+            unaryop,
+        },
+        Expr::VariantConstructor { variant, arg } => Expr::VariantConstructor {
+            variant,
+            arg: arg.map(|arg| Box::new(f.fold_unary(*arg))),
+        },
+        Expr::Application {
+            modname,
+            func,
+            args,
+        } => Expr::Application {
+            modname,
+            func: f.fold_variable(func),
+            args: fold_vec(f, args, F::fold_application_arg),
+        },
+        Expr::Command(name) => Expr::Command(f.fold_command_name(name)),
+        Expr::RecordMember { record, member } => Expr::RecordMember {
+            record: Box::new(f.fold_unary(*record)),
+            member: f.fold_variable(member),
+        },
+        Expr::Unary(unary) => Expr::Unary(f.fold_unary(unary)),
+    })
+}
+
+pub fn walk_match_arm<F: Fold + ?Sized>(f: &mut F, node: Ranged<MatchArm>) -> Ranged<MatchArm> {
+    node.map(|body| MatchArm {
+        ptn: f.fold_pattern(body.ptn),
+        guard: body.guard.map(|guard| f.fold_expr(guard)),
+        expr: f.fold_expr(body.expr),
+    })
+}
+
+pub fn walk_application_arg<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<ApplicationArg>,
+) -> Ranged<ApplicationArg> {
+    let span = node.span();
+    node.map(|body| match body {
+        ApplicationArg::Unary(unary) => {
+            ApplicationArg::Unary(fold_unranged(unary, span, |unary| f.fold_unary(unary)))
+        }
+        ApplicationArg::Optional(unary) => ApplicationArg::Optional(f.fold_unary(unary)),
+        variant @ ApplicationArg::Variant(_) => variant,
+        ApplicationArg::OptionalOmitted => ApplicationArg::OptionalOmitted,
+    })
+}
+
+pub fn walk_unary<F: Fold + ?Sized>(f: &mut F, node: Ranged<Unary>) -> Ranged<Unary> {
+    let span = node.span();
+    node.map(|body| match body {
+        Unary::BlockText(vertical) => {
+            Unary::BlockText(fold_unranged(vertical, span, |v| f.fold_vertical(v)))
+        }
+        Unary::HorizontalText(horizontal) => {
+            Unary::HorizontalText(fold_unranged(horizontal, span, |h| f.fold_horizontal(h)))
+        }
+        Unary::MathText(math) => Unary::MathText(fold_unranged(math, span, |m| f.fold_math(m))),
+        Unary::Record(record) => Unary::Record(fold_unranged(record, span, |r| f.fold_record(r))),
+        Unary::List(list) => Unary::List(fold_unranged(list, span, |l| f.fold_list(l))),
+        Unary::Tuple(tuple) => Unary::Tuple(fold_unranged(tuple, span, |t| f.fold_tuple(t))),
+        op @ Unary::BinOperator(_) => op,
+        Unary::Literal(lit) => Unary::Literal(fold_unranged(lit, span, |l| f.fold_literal(l))),
+        Unary::Expr { modname, expr } => Unary::Expr {
+            modname,
+            expr: Box::new(f.fold_expr(*expr)),
+        },
+        Unary::Variable { modname, var } => Unary::Variable {
+            modname,
+            var: f.fold_variable(var),
+        },
+        Unary::Deref(unary) => Unary::Deref(Box::new(f.fold_unary(*unary))),
+        Unary::Next(unary) => Unary::Next(Box::new(f.fold_unary(*unary))),
+        Unary::Prev(unary) => Unary::Prev(Box::new(f.fold_unary(*unary))),
+    })
+}
+
+pub fn walk_record<F: Fold + ?Sized>(f: &mut F, node: Ranged<Record>) -> Ranged<Record> {
+    node.map(|body| match body {
+        Record::Map(map) => Record::Map(fold_vec(f, map, F::fold_record_unit)),
+        Record::MapWithDefault { map, default } => {
+            let default = Box::new(f.fold_unary(*default));
+            Record::MapWithDefault {
+                map: fold_vec(f, map, F::fold_record_unit),
+                default,
+            }
+        }
+    })
+}
+
+pub fn walk_record_unit<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<RecordUnit>,
+) -> Ranged<RecordUnit> {
+    node.map(|body| RecordUnit {
+        key: body.key,
+        val: f.fold_expr(body.val),
+    })
+}
+
+pub fn walk_list<F: Fold + ?Sized>(f: &mut F, node: Ranged<List>) -> Ranged<List> {
+    node.map(|body| List(fold_vec(f, body.0, F::fold_expr)))
+}
+
+pub fn walk_tuple<F: Fold + ?Sized>(f: &mut F, node: Ranged<Tuple>) -> Ranged<Tuple> {
+    node.map(|body| Tuple(fold_vec(f, body.0, F::fold_expr)))
+}
+
+pub fn walk_command_arg<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<CommandArg>,
+) -> Ranged<CommandArg> {
+    let span = node.span();
+    node.map(|body| match body {
+        CommandArg::Expr(unary) => {
+            CommandArg::Expr(fold_unranged(unary, span, |u| f.fold_unary(u)))
+        }
+        CommandArg::Optional(unary) => CommandArg::Optional(f.fold_unary(unary)),
+        CommandArg::OptionalOmitted => CommandArg::OptionalOmitted,
+        CommandArg::Vertical(vertical) => {
+            CommandArg::Vertical(fold_unranged(vertical, span, |v| f.fold_vertical(v)))
+        }
+        CommandArg::Horizontal(horizontal) => {
+            CommandArg::Horizontal(fold_unranged(horizontal, span, |h| f.fold_horizontal(h)))
+        }
+    })
+}
+
+pub fn walk_vertical<F: Fold + ?Sized>(f: &mut F, node: Ranged<Vertical>) -> Ranged<Vertical> {
+    node.map(|body| Vertical(fold_vec(f, body.0, F::fold_vertical_element)))
+}
+
+pub fn walk_vertical_element<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<VerticalElement>,
+) -> Ranged<VerticalElement> {
+    node.map(|body| match body {
+        VerticalElement::BlockCmd { name, args } => VerticalElement::BlockCmd {
+            name: f.fold_command_name(name),
+            args: fold_vec(f, args, F::fold_command_arg),
+        },
+        embedding @ VerticalElement::BlockTextEmbedding { .. } => embedding,
+    })
+}
+
+pub fn walk_horizontal<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<Horizontal>,
+) -> Ranged<Horizontal> {
+    let span = node.span();
+    node.map(|body| match body {
+        Horizontal::Single(single) => {
+            Horizontal::Single(fold_unranged(single, span, |s| f.fold_horizontal_single(s)))
+        }
+        Horizontal::List(singles) => {
+            Horizontal::List(fold_vec(f, singles, F::fold_horizontal_single))
+        }
+        Horizontal::BulletList(bullets) => {
+            Horizontal::BulletList(fold_vec(f, bullets, F::fold_horizontal_bullet))
+        }
+    })
+}
+
+pub fn walk_horizontal_bullet<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<HorizontalBullet>,
+) -> Ranged<HorizontalBullet> {
+    node.map(|body| HorizontalBullet {
+        indent: body.indent,
+        body: f.fold_horizontal_single(body.body),
+    })
+}
+
+pub fn walk_horizontal_single<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<HorizontalSingle>,
+) -> Ranged<HorizontalSingle> {
+    node.map(|body| HorizontalSingle(fold_vec(f, body.0, F::fold_horizontal_token)))
+}
+
+pub fn walk_horizontal_token<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<HorizontalToken>,
+) -> Ranged<HorizontalToken> {
+    node.map(|body| match body {
+        HorizontalToken::InlineCmd { name, args } => HorizontalToken::InlineCmd {
+            name: f.fold_command_name(name),
+            args: fold_vec(f, args, F::fold_command_arg),
+        },
+        HorizontalToken::Math(math) => HorizontalToken::Math(f.fold_math(math)),
+        HorizontalToken::StringLiteral(lit) => HorizontalToken::StringLiteral(f.fold_literal(lit)),
+        token @ HorizontalToken::Text(_)
+        | token @ HorizontalToken::SpecialChar(_)
+        | token @ HorizontalToken::HorizontalTextEmbedding { .. } => token,
+    })
+}
+
+pub fn walk_math<F: Fold + ?Sized>(f: &mut F, node: Ranged<Math>) -> Ranged<Math> {
+    let span = node.span();
+    node.map(|body| match body {
+        Math::Single(single) => {
+            Math::Single(fold_unranged(single, span, |s| f.fold_math_single(s)))
+        }
+        Math::List(singles) => Math::List(fold_vec(f, singles, F::fold_math_single)),
+    })
+}
+
+pub fn walk_math_single<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<MathSingle>,
+) -> Ranged<MathSingle> {
+    node.map(|body| MathSingle(fold_vec(f, body.0, F::fold_math_token)))
+}
+
+pub fn walk_math_token<F: Fold + ?Sized>(f: &mut F, node: Ranged<MathToken>) -> Ranged<MathToken> {
+    node.map(|body| MathToken {
+        base: f.fold_math_unary(body.base),
+        sub: body.sub.map(|sub| f.fold_math_group(sub)),
+        sup: body.sup.map(|sup| f.fold_math_group(sup)),
+    })
+}
+
+pub fn walk_math_group<F: Fold + ?Sized>(f: &mut F, node: Ranged<MathGroup>) -> Ranged<MathGroup> {
+    let span = node.span();
+    node.map(|body| match body {
+        MathGroup::Group(single) => {
+            MathGroup::Group(fold_unranged(single, span, |s| f.fold_math_single(s)))
+        }
+        MathGroup::Unary(unary) => {
+            MathGroup::Unary(fold_unranged(unary, span, |u| f.fold_math_unary(u)))
+        }
+    })
+}
+
+pub fn walk_math_unary<F: Fold + ?Sized>(f: &mut F, node: Ranged<MathUnary>) -> Ranged<MathUnary> {
+    node.map(|body| match body {
+        MathUnary::Cmd { name, args } => MathUnary::Cmd {
+            name: f.fold_command_name(name),
+            args: fold_vec(f, args, F::fold_math_cmd_arg),
+        },
+        unary @ MathUnary::Char(_)
+        | unary @ MathUnary::SpecialChar(_)
+        | unary @ MathUnary::Symbol(_) => unary,
+    })
+}
+
+pub fn walk_math_cmd_arg<F: Fold + ?Sized>(
+    f: &mut F,
+    node: Ranged<MathCmdArg>,
+) -> Ranged<MathCmdArg> {
+    let span = node.span();
+    node.map(|body| match body {
+        MathCmdArg::Math(math) => MathCmdArg::Math(fold_unranged(math, span, |m| f.fold_math(m))),
+        MathCmdArg::Horizontal(horizontal) => {
+            MathCmdArg::Horizontal(fold_unranged(horizontal, span, |h| f.fold_horizontal(h)))
+        }
+        MathCmdArg::Vertical(vertical) => {
+            MathCmdArg::Vertical(fold_unranged(vertical, span, |v| f.fold_vertical(v)))
+        }
+        MathCmdArg::Expr(unary) => {
+            MathCmdArg::Expr(fold_unranged(unary, span, |u| f.fold_unary(u)))
+        }
+        MathCmdArg::Optional(arg) => MathCmdArg::Optional(Box::new(f.fold_math_cmd_arg(*arg))),
+    })
+}
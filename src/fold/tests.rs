@@ -0,0 +1,100 @@
+use super::*;
+use crate::printer::print;
+use crate::visit::{self, Visitor};
+
+/// テスト用の Fold。コマンド名を置き換える。
+struct CommandRenamer<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+impl Fold for CommandRenamer<'_> {
+    fn fold_command_name(&mut self, node: Ranged<String>) -> Ranged<String> {
+        node.map(|name| {
+            if name == self.from {
+                self.to.to_owned()
+            } else {
+                name
+            }
+        })
+    }
+}
+
+/// 式の範囲を順に集める Visitor。
+#[derive(Default)]
+struct ExprSpans(Vec<Span>);
+
+impl Visitor for ExprSpans {
+    fn visit_expr(&mut self, node: &Expr, span: Span) {
+        self.0.push(span);
+        visit::walk_expr(self, node, span);
+    }
+}
+
+/// 式の範囲の一覧。
+fn expr_spans(program: &Program) -> Vec<Span> {
+    let mut spans = ExprSpans::default();
+    spans.visit_program(program);
+    spans.0
+}
+
+#[test]
+fn rename_commands() {
+    let text = "let-inline ctx \\emph x = x\n\nin\n\n'<+p{\\emph{a} \\em{b}} +emph;>";
+    let program = Program::parse(text).unwrap();
+    let before = expr_spans(&program);
+
+    let program = CommandRenamer {
+        from: "\\emph",
+        to: "\\strong",
+    }
+    .fold_program(program);
+    let program = CommandRenamer {
+        from: "+emph",
+        to: "+strong",
+    }
+    .fold_program(program);
+
+    assert_eq!(
+        print(&program),
+        "let-inline ctx \\strong x = x\n\nin\n\n'<\n  +p{\\strong{a} \\em{b}}\n  +strong;\n>\n"
+    );
+    // 書き換えていない構文要素の範囲はそのまま保たれる
+    assert_eq!(expr_spans(&program), before);
+}
+
+#[test]
+fn inline_constants() {
+    /// 定数を値で置き換える Fold。
+    struct Inliner;
+
+    impl Fold for Inliner {
+        fn fold_unary(&mut self, node: Ranged<Unary>) -> Ranged<Unary> {
+            match &node.body {
+                Unary::Variable { modname: None, var } if var.body.name == "size" => {
+                    Ranged::synthesized(Unary::Literal(Literal::Int(12)), node.span())
+                }
+                _ => walk_unary(self, node),
+            }
+        }
+    }
+
+    let program = Program::parse("let r = 2 in [size * r; r]").unwrap();
+    let program = Inliner.fold_program(program);
+    assert_eq!(print(&program), "let r = 2\n\nin\n\n[12 * r; r]\n");
+
+    /// 単項式の synthesized フラグを集める Fold。
+    #[derive(Default)]
+    struct Flags(Vec<bool>);
+
+    impl Fold for Flags {
+        fn fold_unary(&mut self, node: Ranged<Unary>) -> Ranged<Unary> {
+            self.0.push(node.synthesized);
+            walk_unary(self, node)
+        }
+    }
+
+    let mut flags = Flags::default();
+    flags.fold_program(program);
+    assert_eq!(flags.0, vec![false, false, true, false, false]);
+}
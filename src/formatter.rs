@@ -0,0 +1,125 @@
+//! Source formatter for SATySFi.
+//!
+//! ソースコードをパースし、[`Printer`](crate::printer::Printer) で整形して出力する。
+//! コメントは AST に含まれないため、ソースコードから別途抜き出して元の位置の近くに差し込む。
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::{Grammar, Location};
+use crate::grammar::Program;
+use crate::parser::Rule;
+use crate::printer::{Comment, Print, Printer};
+
+/// 整形の設定。
+///
+/// `'< ... >` の中のブロックコマンドは設定によらず 1 行に 1 つずつ並べる。
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// 1 行の幅。これを超えるリストやレコードは要素ごとに改行する。
+    pub width: usize,
+    /// `{ ... }` の中のテキストを幅に合わせて詰め直すかどうか。
+    /// コマンドの引数の途中では改行せず、和文文字の間では空白を入れずに改行する。
+    pub reflow_text: bool,
+    /// 箇条書きの `*` の字下げと、表の `|` の前後の空白を揃えるかどうか。
+    pub normalize_lists: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            reflow_text: false,
+            normalize_lists: true,
+        }
+    }
+}
+
+/// ソースコード全体を整形する。
+pub fn format(text: &str, options: &FormatOptions) -> Result<String, pest::error::Error<Rule>> {
+    let program = Program::parse(text)?;
+    let mut p = Printer::with_config(Some(options.width), collect_comments(text))
+        .reflow_text(options.reflow_text)
+        .normalize_lists(options.normalize_lists);
+    program.print(&mut p);
+    Ok(p.finish())
+}
+
+/// ソースコードが整形済みかどうか。
+pub fn check(text: &str, options: &FormatOptions) -> Result<bool, pest::error::Error<Rule>> {
+    Ok(format(text, options)? == text)
+}
+
+/// ソースコード中のコメントを抜き出す。
+///
+/// 文字列リテラルの中の `%` や、`\%` のようにエスケープされた `%` はコメントとみなさない。
+pub fn collect_comments(text: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut chars = text.chars().peekable();
+    let mut loc = Location { row: 1, col: 1 };
+    // 現在の行にコメント以外の字句があるかどうか
+    let mut line_has_code = false;
+
+    /// 1 文字読み進め、位置を更新する。
+    fn advance(c: char, loc: &mut Location) {
+        if c == '\n' {
+            loc.row += 1;
+            loc.col = 1;
+        } else {
+            loc.col += 1;
+        }
+    }
+
+    while let Some(c) = chars.next() {
+        let start = loc;
+        advance(c, &mut loc);
+        match c {
+            '\n' => line_has_code = false,
+            '\\' => {
+                line_has_code = true;
+                if let Some(c) = chars.next() {
+                    advance(c, &mut loc);
+                }
+            }
+            '`' => {
+                // 開きと同じ数のバッククォートが連続する箇所までが文字列リテラル
+                let mut ticks = 1;
+                while chars.peek() == Some(&'`') {
+                    advance(chars.next().unwrap(), &mut loc);
+                    ticks += 1;
+                }
+                let mut run = 0;
+                for c in &mut chars {
+                    advance(c, &mut loc);
+                    if c == '`' {
+                        run += 1;
+                        if run == ticks {
+                            break;
+                        }
+                    } else {
+                        run = 0;
+                    }
+                }
+                line_has_code = true;
+            }
+            '%' => {
+                let mut comment = String::from("%");
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' || c == '\r' {
+                        break;
+                    }
+                    comment.push(c);
+                    advance(chars.next().unwrap(), &mut loc);
+                }
+                comments.push(Comment {
+                    start,
+                    text: comment.trim_end().to_owned(),
+                    trailing: line_has_code,
+                });
+            }
+            c if !c.is_whitespace() => line_has_code = true,
+            _ => {}
+        }
+    }
+    comments
+}
@@ -0,0 +1,217 @@
+use super::*;
+
+/// テスト用の関数。text を整形した結果が expect と一致し、それ以上整形しても変わらないことを検証する。
+fn assert_formatted(text: &str, expect: &str, width: usize) {
+    let options = FormatOptions {
+        width,
+        ..FormatOptions::default()
+    };
+    assert_formatted_with(text, expect, &options);
+}
+
+/// テスト用の関数。設定を指定して assert_formatted と同じことを検証する。
+fn assert_formatted_with(text: &str, expect: &str, options: &FormatOptions) {
+    let actual = format(text, options).unwrap();
+    assert_eq!(actual, expect);
+    assert_eq!(format(&actual, options).unwrap(), actual);
+}
+
+#[test]
+fn collect_comment() {
+    let comments = collect_comments("let x = 1 % one\n% two\nin `%` \\% x\n");
+    assert_eq!(
+        comments,
+        vec![
+            Comment {
+                start: Location { row: 1, col: 11 },
+                text: "% one".to_owned(),
+                trailing: true,
+            },
+            Comment {
+                start: Location { row: 2, col: 1 },
+                text: "% two".to_owned(),
+                trailing: false,
+            },
+        ]
+    );
+    assert_eq!(collect_comments("`` a`%b ``\n"), vec![]);
+}
+
+#[test]
+fn format_let_in() {
+    assert_formatted(
+        "f (let x=1 in let y =\n 2 in x+y)",
+        "f (let x = 1 in\nlet y = 2 in\nx + y)\n",
+        80,
+    );
+    assert_formatted(
+        "@require: base\nlet f x = match x with |0 -> 1 | _ -> 2\nin f 0",
+        "@require: base\n\nlet f x =\n  match x with\n  | 0 -> 1\n  | _ -> 2\n\nin\n\nf 0\n",
+        80,
+    );
+}
+
+#[test]
+fn format_module() {
+    assert_formatted(
+        "module M : sig val x : int val y : int end = struct let x = 1 let y = 2 end",
+        "module M : sig\n  val x : int\n  val y : int\nend = struct\n  let x = 1\n\n  let y = 2\nend\n",
+        80,
+    );
+}
+
+#[test]
+fn format_list_and_record() {
+    assert_formatted("[1;2;3]", "[1; 2; 3]\n", 80);
+    assert_formatted("[1;2;3]", "[\n  1;\n  2;\n  3;\n]\n", 8);
+    assert_formatted("(|a=1;b=2|)", "(| a = 1; b = 2 |)\n", 80);
+    assert_formatted("(|a=1;b=2|)", "(|\n  a = 1;\n  b = 2;\n|)\n", 10);
+    assert_formatted("(|r with a=[1;2]|)", "(| r with\n  a = [1; 2];\n|)\n", 16);
+}
+
+#[test]
+fn format_comment() {
+    assert_formatted(
+        "% head\n@require: base\n% body\nlet x = 1 % one\nin x % end\n",
+        "% head\n@require: base\n\n% body\nlet x = 1 % one\n\nin\n\nx % end\n",
+        80,
+    );
+    assert_formatted("[1; % one\n 2]", "[\n  1; % one\n  2;\n]\n", 80);
+    assert_formatted(
+        "module M = struct\n  let x = 1\n  % last\nend",
+        "module M = struct\n  let x = 1\n  % last\nend\n",
+        80,
+    );
+    assert_formatted(
+        "'<+p{a % one\n    b}\n% last\n>",
+        "'<\n  +p{\n    a % one\n    b\n  }\n  % last\n>\n",
+        80,
+    );
+}
+
+#[test]
+fn format_multiline_text() {
+    // 複数行にわたるテキストは中身を字下げした行に置く
+    assert_formatted(
+        "'<\n+p{\n        hello world\n  }\n  +p{hello\nworld}\n>",
+        "'<\n  +p{\n    hello world\n  }\n  +p{\n    hello\n    world\n  }\n>\n",
+        80,
+    );
+    // 1 行のテキストは前後の空白を取り除く
+    assert_formatted("'<+p{ hello }+p{}>", "'<\n  +p{hello}\n  +p{}\n>\n", 80);
+    assert_formatted(
+        "{a \\emph{\n b\n} c}",
+        "{\n  a \\emph{\n    b\n  } c\n}\n",
+        80,
+    );
+}
+
+#[test]
+fn format_trailing_comment() {
+    // 行末のコメントは前のコードと同じ行に残す
+    assert_formatted(
+        "let x = 1   % one\nlet y =   2 % two\n% own line\nlet z = 3",
+        "let x = 1 % one\n\nlet y = 2 % two\n\n% own line\nlet z = 3\n",
+        80,
+    );
+    assert_formatted(
+        "module M = struct\n  let x = 1 % one\n  let y = 2 % two\nend",
+        "module M = struct\n  let x = 1 % one\n\n  let y = 2 % two\nend\n",
+        80,
+    );
+    assert_formatted(
+        "[1; % one\n 2 % two\n]",
+        "[\n  1; % one\n  2; % two\n]\n",
+        80,
+    );
+    assert_formatted(
+        "(|a = 1; % one\n % own line\n b = 2 % two\n|)",
+        "(|\n  a = 1; % one\n  % own line\n  b = 2; % two\n|)\n",
+        80,
+    );
+}
+
+#[test]
+fn format_example() {
+    let text = std::fs::read_to_string("examples/example1.saty").unwrap();
+    let formatted = format(&text, &FormatOptions::default()).unwrap();
+    assert!(formatted.contains("% comment"));
+    assert!(check(&formatted, &FormatOptions::default()).unwrap());
+}
+
+/// テスト用の関数。テキストを詰め直す設定を作る。
+fn reflow(width: usize) -> FormatOptions {
+    FormatOptions {
+        width,
+        reflow_text: true,
+        ..FormatOptions::default()
+    }
+}
+
+#[test]
+fn format_reflow_text() {
+    assert_formatted_with("{foo   bar\n baz}", "{foo bar baz}\n", &reflow(80));
+    assert_formatted_with(
+        "'<+p{aaa bbb ccc ddd eee}>",
+        "'<\n  +p{\n    aaa bbb\n    ccc ddd\n    eee\n  }\n>\n",
+        &reflow(12),
+    );
+    // コマンドの引数の中では改行しない
+    assert_formatted_with(
+        "{aa \\emph{bb cc dd} ee}",
+        "{\n  aa\n  \\emph{bb cc dd}\n  ee\n}\n",
+        &reflow(10),
+    );
+    // コメントを含むテキストは詰め直さない
+    assert_formatted_with("{aa % c\n bb}", "{\n  aa % c\n  bb\n}\n", &reflow(80));
+}
+
+#[test]
+fn format_reflow_japanese() {
+    // 和文文字の間の改行は空白にならないので、詰め直すと連結される
+    assert_formatted_with("{あいう\nえお}", "{あいうえお}\n", &reflow(80));
+    // 和文文字の間の空白は改行に置き換えない
+    assert_formatted_with("{あ い}", "{\n  あ い\n}\n", &reflow(4));
+    assert_formatted_with(
+        "{あいうえお、かきくけこ}",
+        "{\n  あいう\n  えお、\n  かきく\n  けこ\n}\n",
+        &reflow(8),
+    );
+    assert_formatted_with(
+        "{日本語 and English}",
+        "{\n  日本語\n  and\n  English\n}\n",
+        &reflow(10),
+    );
+}
+
+#[test]
+fn format_bullet_list() {
+    assert_formatted(
+        "{* foo  ** bar\n     ** baz * qux}",
+        "{\n  * foo\n    ** bar\n    ** baz\n  * qux\n}\n",
+        80,
+    );
+    assert_formatted_with(
+        "{* aaa bbb ccc}",
+        "{\n  * aaa bbb\n    ccc\n}\n",
+        &reflow(12),
+    );
+    assert_formatted_with(
+        "{* foo  ** bar}",
+        "{* foo  ** bar}\n",
+        &FormatOptions {
+            normalize_lists: false,
+            ..FormatOptions::default()
+        },
+    );
+}
+
+#[test]
+fn format_table() {
+    assert_formatted("{|a|  b |c\n|}", "{| a | b | c |}\n", 80);
+    assert_formatted(
+        "{|aaa|bbb|ccc|}",
+        "{\n  | aaa\n  | bbb\n  | ccc\n  |\n}\n",
+        10,
+    );
+}
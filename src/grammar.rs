@@ -1,20 +1,36 @@
 //! Grammar of SATySFi.
+//!
+//! # JSON
+//!
+//! `serde` feature を有効にすると、AST の各構文要素が `Serialize` と `Deserialize` を実装する。
+//! JSON の形は次の通りで、互換性のない変更は行わない。
+//!
+//! - [`Location`](common::Location) は `{"row": 1, "col": 1}`。行と列はともに 1 始まり。
+//! - [`Ranged<T>`](common::Ranged) は `{"body": T, "start": Location, "end": Location, "synthesized": false}`。
+//!   `synthesized` は省略でき、その場合は `false` とみなす。
+//! - enum は `{"kind": "let_inline", "value": ...}` のように、
+//!   バリアント名を snake_case にした `kind` と中身の `value` を持つオブジェクトになる。
+//!   中身のないバリアントは `{"kind": "wildcard"}` のように `value` を持たない。
+//! - struct はフィールド名をキーとするオブジェクトになる。
+//!   `Preamble(Vec<..>)` のような 1 要素の tuple struct は中身の配列そのものになる。
 #![allow(clippy::missing_docs_in_private_items)]
 #![allow(missing_docs)]
 
-mod common;
+pub mod common;
+#[cfg(test)]
 mod tests;
 
 use crate::parser::{Pair, Rule};
-use common::{Grammar, Ranged};
+use common::{Grammar, Ranged, Span};
 
 /// プログラム全体。
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
-    stage: Option<Ranged<Stage>>,
-    header: Vec<Ranged<Header>>,
-    preamble: Option<Ranged<Preamble>>,
-    expr: Ranged<Expr>,
+    pub stage: Option<Ranged<Stage>>,
+    pub header: Vec<Ranged<Header>>,
+    pub preamble: Option<Ranged<Preamble>>,
+    pub expr: Option<Ranged<Expr>>,
 }
 
 impl Grammar for Program {
@@ -54,8 +70,11 @@ impl Grammar for Program {
             _ => {}
         }
 
-        // 最後に expr を追加
-        let expr = Expr::parse_pair_ranged(inner.next().unwrap());
+        // 最後に expr があれば追加
+        let expr = match inner.next() {
+            Some(pair) if pair.as_rule() == Rule::expr => Some(Expr::parse_pair_ranged(pair)),
+            _ => None,
+        };
 
         Program {
             stage,
@@ -67,7 +86,12 @@ impl Grammar for Program {
 }
 
 /// どのステージに属するか。
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Stage {
     /// `@stage: 0`
     Stage0,
@@ -95,6 +119,11 @@ impl Grammar for Stage {
 
 /// ヘッダ。
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Header {
     /// `@require: ...`
     Require(Ranged<String>),
@@ -123,7 +152,8 @@ impl Grammar for Header {
 
 /// プリアンブル部分。
 #[derive(Debug, PartialEq)]
-pub struct Preamble(Vec<Ranged<Statement>>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Preamble(pub Vec<Ranged<Statement>>);
 
 impl Grammar for Preamble {
     fn rule() -> Rule {
@@ -141,37 +171,711 @@ impl Grammar for Preamble {
 
 /// let や type, module などの宣言。
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Statement {
     /// `let ptn args = expr`
     Let {
-        ptn: Ranged<()>,
-        args: Vec<Ranged<()>>,
-        expr: Ranged<()>,
+        ptn: Ranged<Pattern>,
+        args: Vec<Ranged<Argument>>,
+        expr: Ranged<Expr>,
+    },
+    /// `let-rec f args = expr and g args = expr ...`
+    LetRec(Vec<Ranged<LetRecBinding>>),
+    /// `let-inline ctx \cmd args = expr`
+    LetInline {
+        ctx: Option<Ranged<Variable>>,
+        name: Ranged<String>,
+        args: Vec<Ranged<Argument>>,
+        expr: Ranged<Expr>,
+    },
+    /// `let-block ctx +cmd args = expr`
+    LetBlock {
+        ctx: Option<Ranged<Variable>>,
+        name: Ranged<String>,
+        args: Vec<Ranged<Argument>>,
+        expr: Ranged<Expr>,
+    },
+    /// `let-math \cmd args = expr`
+    LetMath {
+        name: Ranged<String>,
+        args: Vec<Ranged<Argument>>,
+        expr: Ranged<Expr>,
+    },
+    /// `let-mutable var <- expr`
+    LetMutable {
+        var: Ranged<Variable>,
+        expr: Ranged<Expr>,
+    },
+    /// `module Name : sig ... end = struct ... end`
+    Module {
+        name: Ranged<String>,
+        signature: Option<Ranged<Signature>>,
+        body: Ranged<Struct>,
     },
 }
 
 impl Grammar for Statement {
     fn rule() -> Rule {
-        todo!()
+        Rule::statement
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::statement | Rule::let_in_stmt => {
+                Statement::parse_pair(pair.into_inner().next().unwrap())
+            }
+            Rule::let_stmt => {
+                let mut inner = pair.into_inner();
+                let ptn = Pattern::parse_pair_ranged(inner.next().unwrap());
+                let mut args = vec![];
+                let mut next = inner.next().unwrap();
+                if next.as_rule() == Rule::stmt_argument {
+                    args = next.into_inner().map(Argument::parse_pair_ranged).collect();
+                    next = inner.next().unwrap();
+                }
+                let expr = Expr::parse_pair_ranged(next);
+                Statement::Let { ptn, args, expr }
+            }
+            Rule::let_rec_stmt => Statement::LetRec(
+                pair.into_inner()
+                    .map(LetRecBinding::parse_pair_ranged)
+                    .collect(),
+            ),
+            Rule::let_inline_stmt | Rule::let_block_stmt => {
+                let rule = pair.as_rule();
+                let mut inner: Vec<_> = pair.into_inner().collect();
+                let expr = Expr::parse_pair_ranged(inner.pop().unwrap());
+                let mut inner = inner.into_iter();
+
+                // ctx があれば追加
+                let mut ctx = None;
+                let mut pair_name = inner.next().unwrap();
+                if pair_name.as_rule() == Rule::var {
+                    ctx = Some(Variable::parse_pair_ranged(pair_name));
+                    pair_name = inner.next().unwrap();
+                }
+                let name = String::parse_pair_ranged(pair_name);
+                let args = inner.map(Argument::parse_pair_ranged).collect();
+
+                if rule == Rule::let_inline_stmt {
+                    Statement::LetInline {
+                        ctx,
+                        name,
+                        args,
+                        expr,
+                    }
+                } else {
+                    Statement::LetBlock {
+                        ctx,
+                        name,
+                        args,
+                        expr,
+                    }
+                }
+            }
+            Rule::let_math_stmt => {
+                let mut inner: Vec<_> = pair.into_inner().collect();
+                let expr = Expr::parse_pair_ranged(inner.pop().unwrap());
+                let mut inner = inner.into_iter();
+                let name = String::parse_pair_ranged(inner.next().unwrap());
+                let args = inner.map(Argument::parse_pair_ranged).collect();
+                Statement::LetMath { name, args, expr }
+            }
+            Rule::let_mutable_stmt => {
+                let mut inner = pair.into_inner();
+                let var = Variable::parse_pair_ranged(inner.next().unwrap());
+                let expr = Expr::parse_pair_ranged(inner.next().unwrap());
+                Statement::LetMutable { var, expr }
+            }
+            Rule::module_stmt => {
+                let mut inner = pair.into_inner();
+                let name = String::parse_pair_ranged(inner.next().unwrap());
+                let mut signature = None;
+                let mut next = inner.next().unwrap();
+                if next.as_rule() == Rule::sig_stmt {
+                    signature = Some(Signature::parse_pair_ranged(next));
+                    next = inner.next().unwrap();
+                }
+                let body = Struct::parse_pair_ranged(next);
+                Statement::Module {
+                    name,
+                    signature,
+                    body,
+                }
+            }
+            rule => unreachable!("invalid rule: '{:?}' in rule 'statement'", rule),
+        }
+    }
+}
+
+/// `let-rec` で定義する関数の 1 つ。`f args = expr`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LetRecBinding {
+    pub var: Ranged<Variable>,
+    pub args: Vec<Ranged<Argument>>,
+    pub expr: Ranged<Expr>,
+}
+
+impl Grammar for LetRecBinding {
+    fn rule() -> Rule {
+        Rule::let_rec_inner
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let mut inner = pair.into_inner();
+        let var = Variable::parse_pair_ranged(inner.next().unwrap());
+        let mut args = vec![];
+        let mut next = inner.next().unwrap();
+        if next.as_rule() == Rule::stmt_argument {
+            args = next.into_inner().map(Argument::parse_pair_ranged).collect();
+            next = inner.next().unwrap();
+        }
+        let expr = Expr::parse_pair_ranged(next);
+        LetRecBinding { var, args, expr }
+    }
+}
+
+/// let 文などの引数。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum Argument {
+    /// パターン。
+    Pattern(Pattern),
+    /// `?:var`
+    Optional(Ranged<String>),
+}
+
+impl Grammar for Argument {
+    fn rule() -> Rule {
+        Rule::arg
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::arg => Argument::parse_pair(pair.into_inner().next().unwrap()),
+            Rule::pattern => Argument::Pattern(Pattern::parse_pair(pair)),
+            Rule::var_ptn => Argument::Optional(String::parse_pair_ranged(pair)),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'arg'", rule),
+        }
+    }
+}
+
+/// モジュールのシグネチャ。`sig ... end`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Signature(pub Vec<Ranged<SigStmt>>);
+
+impl Grammar for Signature {
+    fn rule() -> Rule {
+        Rule::sig_stmt
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let stmts = match pair.into_inner().next() {
+            Some(sig_inner) => sig_inner
+                .into_inner()
+                .map(SigStmt::parse_pair_ranged)
+                .collect(),
+            None => vec![],
+        };
+        Signature(stmts)
+    }
+}
+
+/// シグネチャ内の宣言。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum SigStmt {
+    /// `type 'a t constraint ...`
+    Type {
+        params: Vec<Ranged<String>>,
+        name: Ranged<String>,
+        constraints: Vec<Ranged<Constraint>>,
+    },
+    /// `val x : type constraint ...`
+    Val {
+        name: Ranged<ValName>,
+        ty: Ranged<TypeExpr>,
+        constraints: Vec<Ranged<Constraint>>,
+    },
+    /// `direct \cmd : type constraint ...`
+    Direct {
+        name: Ranged<String>,
+        ty: Ranged<TypeExpr>,
+        constraints: Vec<Ranged<Constraint>>,
+    },
+}
+
+impl Grammar for SigStmt {
+    fn rule() -> Rule {
+        Rule::sig_inner
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::sig_type_stmt => {
+                let mut params = vec![];
+                let mut name = None;
+                let mut constraints = vec![];
+                for pair in pair.into_inner() {
+                    match pair.as_rule() {
+                        Rule::type_param => params.push(String::parse_pair_ranged(pair)),
+                        Rule::var => name = Some(String::parse_pair_ranged(pair)),
+                        Rule::constraint => constraints.push(Constraint::parse_pair_ranged(pair)),
+                        _ => unreachable!(),
+                    }
+                }
+                SigStmt::Type {
+                    params,
+                    name: name.unwrap(),
+                    constraints,
+                }
+            }
+            Rule::sig_val_stmt => {
+                let mut inner = pair.into_inner();
+                let name = ValName::parse_pair_ranged(inner.next().unwrap());
+                let ty = TypeExpr::parse_pair_ranged(inner.next().unwrap());
+                let constraints = inner.map(Constraint::parse_pair_ranged).collect();
+                SigStmt::Val {
+                    name,
+                    ty,
+                    constraints,
+                }
+            }
+            Rule::sig_direct_stmt => {
+                let mut inner = pair.into_inner();
+                let name = String::parse_pair_ranged(inner.next().unwrap());
+                let ty = TypeExpr::parse_pair_ranged(inner.next().unwrap());
+                let constraints = inner.map(Constraint::parse_pair_ranged).collect();
+                SigStmt::Direct {
+                    name,
+                    ty,
+                    constraints,
+                }
+            }
+            rule => unreachable!("invalid rule: '{:?}' in rule 'sig_inner'", rule),
+        }
+    }
+}
+
+/// `val` で宣言される名前。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum ValName {
+    /// `val x : ...`
+    Var(String),
+    /// `val (+) : ...`
+    BinOperator(String),
+    /// `val \cmd : ...`
+    InlineCmd(String),
+    /// `val +cmd : ...`
+    BlockCmd(String),
+}
+
+impl Grammar for ValName {
+    fn rule() -> Rule {
+        Rule::sig_val_stmt
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let name = pair.as_str().to_owned();
+        match pair.as_rule() {
+            Rule::var => ValName::Var(name),
+            Rule::bin_operator => ValName::BinOperator(name),
+            Rule::inline_cmd_name => ValName::InlineCmd(name),
+            Rule::block_cmd_name => ValName::BlockCmd(name),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'sig_val_stmt'", rule),
+        }
+    }
+}
+
+/// モジュールの本体。`struct ... end`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Struct(pub Vec<Ranged<Statement>>);
+
+impl Grammar for Struct {
+    fn rule() -> Rule {
+        Rule::struct_stmt
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let stmts = match pair.into_inner().next() {
+            Some(preamble) => preamble
+                .into_inner()
+                .map(Statement::parse_pair_ranged)
+                .collect(),
+            None => vec![],
+        };
+        Struct(stmts)
+    }
+}
+
+/// 型パラメータに対する制約。`constraint 'a :: (| ... |)`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Constraint {
+    pub param: Ranged<String>,
+    pub record: Ranged<TypeRecord>,
+}
+
+impl Grammar for Constraint {
+    fn rule() -> Rule {
+        Rule::constraint
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let mut inner = pair.into_inner();
+        let param = String::parse_pair_ranged(inner.next().unwrap());
+        let record = TypeRecord::parse_pair_ranged(inner.next().unwrap());
+        Constraint { param, record }
+    }
+}
+
+/// 型。`t1 ?-> t2 -> t3` など。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeExpr {
+    /// `?->` の左側に来る型。
+    pub optional_args: Vec<Ranged<TypeProd>>,
+    /// `->` の左側に来る型。
+    pub args: Vec<Ranged<TypeProd>>,
+    /// 最後の型。
+    pub ret: Ranged<TypeProd>,
+}
+
+impl Grammar for TypeExpr {
+    fn rule() -> Rule {
+        Rule::type_expr
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
-        todo!()
+        let mut optional_args = vec![];
+        let mut args = vec![];
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::type_optional_name => optional_args.push(TypeProd::parse_pair_ranged(
+                    pair.into_inner().next().unwrap(),
+                )),
+                Rule::type_prod => args.push(TypeProd::parse_pair_ranged(pair)),
+                _ => unreachable!(),
+            }
+        }
+        let ret = args.pop().unwrap();
+        TypeExpr {
+            optional_args,
+            args,
+            ret,
+        }
+    }
+}
+
+/// 直積型。`t1 * t2 * t3`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeProd(pub Vec<Ranged<TypeUnary>>);
+
+impl Grammar for TypeProd {
+    fn rule() -> Rule {
+        Rule::type_prod
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        TypeProd(pair.into_inner().map(TypeUnary::parse_pair_ranged).collect())
+    }
+}
+
+/// 単項の型。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum TypeUnary {
+    /// `[...] inline-cmd`
+    InlineCmd(Ranged<TypeList>),
+    /// `[...] block-cmd`
+    BlockCmd(Ranged<TypeList>),
+    /// `[...] math-cmd`
+    MathCmd(Ranged<TypeList>),
+    /// `(t)`
+    Paren(Box<Ranged<TypeExpr>>),
+    /// `(| key : t; ... |)`
+    Record(TypeRecord),
+    /// `t1 t2 ... tn`
+    Application(Vec<Ranged<TypeUnary>>),
+    /// `'a`
+    Param(String),
+    /// `t` や `Mod.t`
+    Name {
+        modname: Option<Ranged<String>>,
+        name: Ranged<String>,
+    },
+}
+
+impl Grammar for TypeUnary {
+    fn rule() -> Rule {
+        Rule::type_unary
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let text = pair.as_str();
+        let inner = pair.into_inner().next().unwrap();
+        match inner.as_rule() {
+            Rule::type_list => {
+                let list = TypeList::parse_pair_ranged(inner);
+                if text.ends_with("inline-cmd") {
+                    TypeUnary::InlineCmd(list)
+                } else if text.ends_with("block-cmd") {
+                    TypeUnary::BlockCmd(list)
+                } else {
+                    TypeUnary::MathCmd(list)
+                }
+            }
+            Rule::type_expr => TypeUnary::Paren(Box::new(TypeExpr::parse_pair_ranged(inner))),
+            Rule::type_record => TypeUnary::Record(TypeRecord::parse_pair(inner)),
+            Rule::type_application => TypeUnary::Application(
+                inner
+                    .into_inner()
+                    .map(TypeUnary::parse_pair_ranged)
+                    .collect(),
+            ),
+            Rule::type_param => TypeUnary::Param(String::parse_pair(inner)),
+            Rule::var => TypeUnary::Name {
+                modname: None,
+                name: String::parse_pair_ranged(inner),
+            },
+            Rule::modvar => {
+                let mut pairs = inner.into_inner();
+                let modname = String::parse_pair_ranged(pairs.next().unwrap());
+                let name = String::parse_pair_ranged(pairs.next().unwrap());
+                TypeUnary::Name {
+                    modname: Some(modname),
+                    name,
+                }
+            }
+            rule => unreachable!("invalid rule: '{:?}' in rule 'type_unary'", rule),
+        }
+    }
+}
+
+/// コマンドの引数の型のリスト。`[t1; t2?]`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeList(pub Vec<Ranged<TypeListUnit>>);
+
+impl Grammar for TypeList {
+    fn rule() -> Rule {
+        Rule::type_list
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        TypeList(
+            pair.into_inner()
+                .map(TypeListUnit::parse_pair_ranged)
+                .collect(),
+        )
+    }
+}
+
+/// コマンドの引数の型。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum TypeListUnit {
+    /// `t?`
+    Optional(TypeProd),
+    /// `t`
+    Mandatory(TypeExpr),
+}
+
+impl Grammar for TypeListUnit {
+    fn rule() -> Rule {
+        Rule::type_list
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::type_list_optional => {
+                TypeListUnit::Optional(TypeProd::parse_pair(pair.into_inner().next().unwrap()))
+            }
+            Rule::type_expr => TypeListUnit::Mandatory(TypeExpr::parse_pair(pair)),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'type_list'", rule),
+        }
+    }
+}
+
+/// レコード型。`(| key : t; ... |)`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeRecord(pub Vec<Ranged<TypeRecordUnit>>);
+
+impl Grammar for TypeRecord {
+    fn rule() -> Rule {
+        Rule::type_record
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        TypeRecord(
+            pair.into_inner()
+                .map(TypeRecordUnit::parse_pair_ranged)
+                .collect(),
+        )
+    }
+}
+
+/// レコード型の要素。`key : t`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeRecordUnit {
+    pub key: Ranged<String>,
+    pub ty: Ranged<TypeExpr>,
+}
+
+impl Grammar for TypeRecordUnit {
+    fn rule() -> Rule {
+        Rule::type_record_unit
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let mut inner = pair.into_inner();
+        let key = String::parse_pair_ranged(inner.next().unwrap());
+        let ty = TypeExpr::parse_pair_ranged(inner.next().unwrap());
+        TypeRecordUnit { key, ty }
+    }
+}
+
+/// パターン。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum Pattern {
+    /// `[p1; p2]`
+    List(Vec<Ranged<Pattern>>),
+    /// `(p1, p2)`
+    Tuple(Vec<Ranged<Pattern>>),
+    /// `(p)`
+    Paren(Box<Ranged<Pattern>>),
+    /// `_`
+    Wildcard,
+    /// `x`
+    Variable(Variable),
+    /// `1` や `` `str` `` など
+    Literal(Literal),
+    /// `Variant p`
+    Variant {
+        name: Ranged<String>,
+        arg: Option<Box<Ranged<Pattern>>>,
+    },
+    /// `p as x`
+    As {
+        ptn: Box<Ranged<Pattern>>,
+        var: Ranged<Variable>,
+    },
+    /// `p1 :: p2`
+    Cons {
+        head: Box<Ranged<Pattern>>,
+        tail: Box<Ranged<Pattern>>,
+    },
+}
+
+impl Grammar for Pattern {
+    fn rule() -> Rule {
+        Rule::match_ptn
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::match_ptn => {
+                let mut inner = pair.into_inner();
+                let first = inner.next().unwrap();
+                match inner.next() {
+                    None => Pattern::parse_pair(first),
+                    Some(second) if second.as_rule() == Rule::var => Pattern::As {
+                        ptn: Box::new(Pattern::parse_pair_ranged(first)),
+                        var: Variable::parse_pair_ranged(second),
+                    },
+                    Some(second) => Pattern::Cons {
+                        head: Box::new(Pattern::parse_pair_ranged(first)),
+                        tail: Box::new(Pattern::parse_pair_ranged(second)),
+                    },
+                }
+            }
+            Rule::pattern => match pair.into_inner().next() {
+                // "_" のときは中身が存在しない
+                None => Pattern::Wildcard,
+                Some(inner) => match inner.as_rule() {
+                    Rule::match_ptn => Pattern::Paren(Box::new(Pattern::parse_pair_ranged(inner))),
+                    Rule::var => Pattern::Variable(Variable::parse_pair(inner)),
+                    Rule::literal => Pattern::Literal(Literal::parse_pair(inner)),
+                    _ => Pattern::parse_pair(inner),
+                },
+            },
+            Rule::pat_variant => {
+                let mut inner = pair.into_inner();
+                let name = String::parse_pair_ranged(inner.next().unwrap());
+                let arg = inner
+                    .next()
+                    .map(|pair| Box::new(Pattern::parse_pair_ranged(pair)));
+                Pattern::Variant { name, arg }
+            }
+            Rule::pat_list => {
+                Pattern::List(pair.into_inner().map(Pattern::parse_pair_ranged).collect())
+            }
+            Rule::pat_tuple => {
+                Pattern::Tuple(pair.into_inner().map(Pattern::parse_pair_ranged).collect())
+            }
+            rule => unreachable!("invalid rule: '{:?}' in rule 'match_ptn'", rule),
+        }
     }
 }
 
 /// 式。
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Expr {
     /// `match xxx with ...`
     Match {
         expr: Box<Ranged<Expr>>,
-        arms: Vec<Ranged<()>>,
+        arms: Vec<Ranged<MatchArm>>,
     },
     /// `let xxx args = expr in`
     BindStmt {
-        bind: Ranged<()>,
-        body: Box<Ranged<Expr>>
+        bind: Box<Ranged<Statement>>,
+        body: Box<Ranged<Expr>>,
+    },
+    /// `fun args -> body`
+    Lambda {
+        args: Vec<Ranged<Argument>>,
+        body: Box<Ranged<Expr>>,
     },
     /// `while xxx do ...`
     CtrlFlowWhile {
@@ -184,6 +888,11 @@ pub enum Expr {
         expr_true: Box<Ranged<Expr>>,
         expr_false: Box<Ranged<Expr>>,
     },
+    /// `var <- expr`
+    Assign {
+        var: Ranged<Variable>,
+        expr: Box<Ranged<Expr>>,
+    },
     /// e.g. `1 + 2`
     Dyadic {
         lhs: Box<Ranged<Expr>>,
@@ -197,51 +906,295 @@ pub enum Expr {
     },
     /// `Variant(a, b, c, ...)`
     VariantConstructor {
-        variant: Ranged<()>,
-        args: Ranged<()>,
+        variant: Ranged<String>,
+        arg: Option<Box<Ranged<Unary>>>,
     },
     /// `func a b ...`
-    Application(Ranged<()>),
+    Application {
+        modname: Option<Ranged<String>>,
+        func: Ranged<Variable>,
+        args: Vec<Ranged<ApplicationArg>>,
+    },
+    /// `command \cmd`
+    Command(Ranged<String>),
     /// `record # member`
-    RecordMember,
+    RecordMember {
+        record: Box<Ranged<Unary>>,
+        member: Ranged<Variable>,
+    },
     /// unary
-    Unary(Ranged<()>),
+    Unary(Ranged<Unary>),
 }
 
 impl Grammar for Expr {
     fn rule() -> Rule {
-        todo!()
+        Rule::expr
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
-        let mut pairs = pair.into_inner();
-        let inner = pairs.next().unwrap();
+        match pair.as_rule() {
+            Rule::expr => {
+                let mut pairs = pair.into_inner();
+                let inner = pairs.next().unwrap();
+                if inner.as_rule() == Rule::let_in_stmt {
+                    let bind = Box::new(Statement::parse_pair_ranged(
+                        inner.into_inner().next().unwrap(),
+                    ));
+                    let body = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                    Expr::BindStmt { bind, body }
+                } else {
+                    Expr::parse_pair(inner)
+                }
+            }
+            Rule::match_expr => {
+                let mut pairs = pair.into_inner();
+                let expr = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                let arms = pairs.map(MatchArm::parse_pair_ranged).collect();
+                Expr::Match { expr, arms }
+            }
+            Rule::lambda => {
+                let mut pairs: Vec<_> = pair.into_inner().collect();
+                let body = Box::new(Expr::parse_pair_ranged(pairs.pop().unwrap()));
+                let args = pairs.into_iter().map(Argument::parse_pair_ranged).collect();
+                Expr::Lambda { args, body }
+            }
+            Rule::ctrl_while => {
+                let mut pairs = pair.into_inner();
+                let condition = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                let body = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                Expr::CtrlFlowWhile { condition, body }
+            }
+            Rule::ctrl_if => {
+                let mut pairs = pair.into_inner();
+                let condition = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                let expr_true = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                let expr_false = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                Expr::CtrlFlowIf {
+                    condition,
+                    expr_true,
+                    expr_false,
+                }
+            }
+            Rule::assignment => {
+                let mut pairs = pair.into_inner();
+                let var = Variable::parse_pair_ranged(pairs.next().unwrap());
+                let expr = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                Expr::Assign { var, expr }
+            }
+            Rule::dyadic_expr => {
+                let mut operands = vec![];
+                let mut operators = vec![];
+                flatten_dyadic(pair, &mut operands, &mut operators);
+                build_dyadic(operands, operators).body
+            }
+            Rule::unary_operator_expr => {
+                let mut pairs = pair.into_inner();
+                let unaryop = String::parse_pair_ranged(pairs.next().unwrap());
+                let rhs = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
+                Expr::UnaryOperatorExpr { rhs, unaryop }
+            }
+            Rule::variant_constructor => {
+                let mut pairs = pair.into_inner();
+                let variant = String::parse_pair_ranged(pairs.next().unwrap());
+                let arg = pairs
+                    .next()
+                    .map(|pair| Box::new(Unary::parse_pair_ranged(pair)));
+                Expr::VariantConstructor { variant, arg }
+            }
+            Rule::application => {
+                let mut pairs = pair.into_inner();
+                let pair_func = pairs.next().unwrap();
+                let (modname, func) = match pair_func.as_rule() {
+                    Rule::inline_cmd_name => {
+                        return Expr::Command(String::parse_pair_ranged(pair_func));
+                    }
+                    Rule::var => (None, Variable::parse_pair_ranged(pair_func)),
+                    Rule::modvar => {
+                        let mut inner = pair_func.into_inner();
+                        let modname = String::parse_pair_ranged(inner.next().unwrap());
+                        let func = Variable::parse_pair_ranged(inner.next().unwrap());
+                        (Some(modname), func)
+                    }
+                    _ => unreachable!(),
+                };
+                let args = pairs.map(ApplicationArg::parse_pair_ranged).collect();
+                Expr::Application {
+                    modname,
+                    func,
+                    args,
+                }
+            }
+            Rule::record_member => {
+                let mut pairs = pair.into_inner();
+                let record = Box::new(Unary::parse_pair_ranged(pairs.next().unwrap()));
+                let member = Variable::parse_pair_ranged(pairs.next().unwrap());
+                Expr::RecordMember { record, member }
+            }
+            Rule::unary => Expr::Unary(Unary::parse_pair_ranged(pair)),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'expr'", rule),
+        }
+    }
+}
 
-        match inner.as_rule() {
-            Rule::match_expr => todo!(),
-            Rule::bind_stmt => {
-                let sub_expr = pairs.next().unwrap();
-            }
-            Rule::ctrl_while => todo!(),
-            Rule::ctrl_if => todo!(),
-            Rule::dyadic_expr => todo!(),
-            Rule::unary_operator_expr => todo!(),
-            Rule::variant_constructor => todo!(),
-            Rule::application => todo!(),
-            Rule::record_member => todo!(),
-            Rule::unary => todo!(),
-            _ => unreachable!(),
+/// dyadic_expr を被演算子と演算子の列に分解する。
+fn flatten_dyadic<'i>(
+    pair: Pair<'i>,
+    operands: &mut Vec<Ranged<Expr>>,
+    operators: &mut Vec<Ranged<String>>,
+) {
+    let mut pairs = pair.into_inner();
+    operands.push(Expr::parse_pair_ranged(pairs.next().unwrap()));
+    operators.push(String::parse_pair_ranged(pairs.next().unwrap()));
+    let rhs = pairs.next().unwrap();
+    if rhs.as_rule() == Rule::dyadic_expr {
+        flatten_dyadic(rhs, operands, operators);
+    } else {
+        operands.push(Expr::parse_pair_ranged(rhs));
+    }
+}
+
+/// 被演算子と演算子の列から、演算子の優先順位に従って構文木を組み立てる。
+fn build_dyadic(operands: Vec<Ranged<Expr>>, operators: Vec<Ranged<String>>) -> Ranged<Expr> {
+    fn reduce(operands: &mut Vec<Ranged<Expr>>, operators: &mut Vec<Ranged<String>>) {
+        let binop = operators.pop().unwrap();
+        let rhs = operands.pop().unwrap();
+        let lhs = operands.pop().unwrap();
+        let span = Span {
+            start: lhs.start,
+            end: rhs.end,
+        };
+        operands.push(Ranged::new(
+            Expr::Dyadic {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                binop,
+            },
+            span,
+        ));
+    }
+
+    let mut operands_iter = operands.into_iter();
+    let mut operand_stack = vec![operands_iter.next().unwrap()];
+    let mut operator_stack: Vec<Ranged<String>> = vec![];
+    for (binop, operand) in operators.into_iter().zip(operands_iter) {
+        let (prec, assoc) = binop_precedence(&binop.body);
+        while let Some(top) = operator_stack.last() {
+            let (prec_top, _) = binop_precedence(&top.body);
+            if prec_top > prec || (prec_top == prec && assoc == Associativity::Left) {
+                reduce(&mut operand_stack, &mut operator_stack);
+            } else {
+                break;
+            }
+        }
+        operator_stack.push(binop);
+        operand_stack.push(operand);
+    }
+    while !operator_stack.is_empty() {
+        reduce(&mut operand_stack, &mut operator_stack);
+    }
+    operand_stack.pop().unwrap()
+}
+
+/// 二項演算子の結合の向き。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// 左結合。
+    Left,
+    /// 右結合。
+    Right,
+}
+
+/// 二項演算子の優先順位と結合の向きを返す。優先順位は値が大きいほど強く結合する。
+/// SATySFi と同様に、演算子の先頭の文字によって決まる。
+pub fn binop_precedence(binop: &str) -> (u8, Associativity) {
+    if binop == "mod" {
+        return (6, Associativity::Left);
+    }
+    if binop == "::" {
+        return (4, Associativity::Right);
+    }
+    match binop.chars().next() {
+        Some('|') => (1, Associativity::Right),
+        Some('&') => (2, Associativity::Right),
+        Some('=') | Some('<') | Some('>') => (3, Associativity::Right),
+        Some('^') => (4, Associativity::Right),
+        Some('+') | Some('-') => (5, Associativity::Left),
+        Some('*') | Some('/') => (6, Associativity::Left),
+        _ => unreachable!("invalid binary operator: '{}'", binop),
+    }
+}
+
+/// match 式の各パターン。`| ptn when guard -> expr`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchArm {
+    pub ptn: Ranged<Pattern>,
+    pub guard: Option<Ranged<Expr>>,
+    pub expr: Ranged<Expr>,
+}
+
+impl Grammar for MatchArm {
+    fn rule() -> Rule {
+        Rule::match_arm
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let mut pairs: Vec<_> = pair.into_inner().collect();
+        let expr = Expr::parse_pair_ranged(pairs.pop().unwrap());
+        let mut pairs = pairs.into_iter();
+        let ptn = Pattern::parse_pair_ranged(pairs.next().unwrap());
+        let guard = pairs.next().map(Expr::parse_pair_ranged);
+        MatchArm { ptn, guard, expr }
+    }
+}
+
+/// 関数適用の引数。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum ApplicationArg {
+    /// `f x` の `x`
+    Unary(Unary),
+    /// `f Variant` の `Variant`
+    Variant(String),
+    /// `f ?:x` の `?:x`
+    Optional(Ranged<Unary>),
+    /// `f ?*` の `?*`
+    OptionalOmitted,
+}
+
+impl Grammar for ApplicationArg {
+    fn rule() -> Rule {
+        Rule::application
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::unary => ApplicationArg::Unary(Unary::parse_pair(pair)),
+            Rule::variant_name => ApplicationArg::Variant(String::parse_pair(pair)),
+            Rule::application_opt_arg => ApplicationArg::Optional(Unary::parse_pair_ranged(
+                pair.into_inner().next().unwrap(),
+            )),
+            Rule::application_opt_omission => ApplicationArg::OptionalOmitted,
+            rule => unreachable!("invalid rule: '{:?}' in rule 'application'", rule),
         }
-        todo!()
     }
 }
 
 /// 単項式。
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Unary {
-    BlockText(()),
-    HorizontalText(()),
-    MathText(()),
+    BlockText(Vertical),
+    HorizontalText(Horizontal),
+    MathText(Math),
     Record(Record),
     List(List),
     Tuple(Tuple),
@@ -255,14 +1208,42 @@ pub enum Unary {
         modname: Option<Ranged<String>>,
         var: Ranged<Variable>,
     },
+    /// `!e`
+    Deref(Box<Ranged<Unary>>),
+    /// `&e`
+    Next(Box<Ranged<Unary>>),
+    /// `~e`
+    Prev(Box<Ranged<Unary>>),
 }
 
 impl Grammar for Unary {
     fn rule() -> Rule {
-        todo!()
+        Rule::unary
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::unary => {}
+            // コマンドの引数として書かれた式
+            Rule::cmd_expr_arg => {
+                return match pair.into_inner().next() {
+                    None => Unary::Literal(Literal::Unit),
+                    Some(inner) => match inner.as_rule() {
+                        Rule::expr => Unary::Expr {
+                            modname: None,
+                            expr: Box::new(Expr::parse_pair_ranged(inner)),
+                        },
+                        Rule::list => Unary::List(List::parse_pair(inner)),
+                        Rule::record => Unary::Record(Record::parse_pair(inner)),
+                        _ => unreachable!(),
+                    },
+                };
+            }
+            Rule::math_cmd_list_arg => return Unary::List(List::parse_pair(pair)),
+            Rule::math_cmd_record_arg => return Unary::Record(Record::parse_pair(pair)),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'unary'", rule),
+        }
+
         let inner = pair.into_inner().next().unwrap();
         match inner.as_rule() {
             Rule::record => Unary::Record(Record::parse_pair(inner)),
@@ -270,28 +1251,69 @@ impl Grammar for Unary {
             Rule::tuple => Unary::Tuple(Tuple::parse_pair(inner)),
             Rule::bin_operator => Unary::BinOperator(String::parse_pair(inner)),
             Rule::literal => Unary::Literal(Literal::parse_pair(inner)),
-            Rule::block_text => todo!(),
-            Rule::horizontal_text => todo!(),
-            Rule::math_text => todo!(),
-            Rule::expr => {
+            Rule::block_text => {
+                Unary::BlockText(Vertical::parse_pair(inner.into_inner().next().unwrap()))
+            }
+            Rule::horizontal_text => {
+                Unary::HorizontalText(Horizontal::parse_pair(inner.into_inner().next().unwrap()))
+            }
+            Rule::math_text => Unary::MathText(Math::parse_pair(inner.into_inner().next().unwrap())),
+            Rule::expr => Unary::Expr {
+                modname: None,
+                expr: Box::new(Expr::parse_pair_ranged(inner)),
+            },
+            Rule::expr_with_mod => {
+                let mut pairs = inner.into_inner();
+                let begin = pairs.next().unwrap();
+                let modname = String::parse_pair_ranged(begin.into_inner().next().unwrap());
+                let expr = Box::new(Expr::parse_pair_ranged(pairs.next().unwrap()));
                 Unary::Expr {
-                    modname: None,
-                    expr: Box::new(Expr::parse_pair_ranged(inner)),
+                    modname: Some(modname),
+                    expr,
                 }
+            }
+            Rule::modvar => {
+                let mut pairs = inner.into_inner();
+                let modname = String::parse_pair_ranged(pairs.next().unwrap());
+                let var = Variable::parse_pair_ranged(pairs.next().unwrap());
+                Unary::Variable {
+                    modname: Some(modname),
+                    var,
+                }
+            }
+            Rule::var => Unary::Variable {
+                modname: None,
+                var: Variable::parse_pair_ranged(inner),
             },
-            Rule::expr_with_mod => todo!(),
-            Rule::modvar => todo!(),
-            Rule::var => todo!(),
-            _ => unreachable!()
+            Rule::deref => {
+                Unary::Deref(Box::new(Unary::parse_pair_ranged(
+                    inner.into_inner().next().unwrap(),
+                )))
+            }
+            Rule::stage_next | Rule::stage_prev => {
+                let rule = inner.as_rule();
+                let unary = Box::new(Unary::parse_pair_ranged(inner.into_inner().nth(1).unwrap()));
+                if rule == Rule::stage_next {
+                    Unary::Next(unary)
+                } else {
+                    Unary::Prev(unary)
+                }
+            }
+            rule => unreachable!("invalid rule: '{:?}' in rule 'unary'", rule),
         }
     }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Record {
-    Map(Vec<RecordUnit>),
+    Map(Vec<Ranged<RecordUnit>>),
     MapWithDefault {
-        map: Vec<RecordUnit>,
+        map: Vec<Ranged<RecordUnit>>,
         default: Box<Ranged<Unary>>,
     },
 }
@@ -303,19 +1325,23 @@ impl Grammar for Record {
 
     fn parse_pair(pair: Pair<'_>) -> Self {
         let mut pairs = pair.into_inner();
-        let pair = pairs.peek();
+        let pair = pairs.next();
         if pair.is_none() {
             return Record::Map(vec![]);
         }
-        match pair.unwrap().as_rule() {
+        let pair = pair.unwrap();
+        match pair.as_rule() {
             Rule::unary => {
-                let pair_default = pairs.next().unwrap();
-                let default = Box::new(Unary::parse_pair_ranged(pair_default));
-                let map = pairs.map(RecordUnit::parse_pair).collect();
+                let default = Box::new(Unary::parse_pair_ranged(pair));
+                let pair_record_inner = pairs.next().unwrap();
+                let map = pair_record_inner
+                    .into_inner()
+                    .map(RecordUnit::parse_pair_ranged)
+                    .collect();
                 Record::MapWithDefault { map, default }
             }
             Rule::record_inner => {
-                let map = pairs.map(RecordUnit::parse_pair).collect();
+                let map = pair.into_inner().map(RecordUnit::parse_pair_ranged).collect();
                 Record::Map(map)
             }
             _ => unreachable!(),
@@ -324,9 +1350,10 @@ impl Grammar for Record {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecordUnit {
-    key: Ranged<String>,
-    val: Ranged<Expr>,
+    pub key: Ranged<String>,
+    pub val: Ranged<Expr>,
 }
 
 impl Grammar for RecordUnit {
@@ -345,7 +1372,8 @@ impl Grammar for RecordUnit {
 }
 
 #[derive(Debug, PartialEq)]
-pub struct List(Vec<Ranged<Expr>>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct List(pub Vec<Ranged<Expr>>);
 
 impl Grammar for List {
     fn rule() -> Rule {
@@ -359,7 +1387,8 @@ impl Grammar for List {
 }
 
 #[derive(Debug, PartialEq)]
-pub struct Tuple(Vec<Ranged<Expr>>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tuple(pub Vec<Ranged<Expr>>);
 
 impl Grammar for Tuple {
     fn rule() -> Rule {
@@ -373,13 +1402,15 @@ impl Grammar for Tuple {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Variant {
-    name: String,
+    pub name: String,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Variable {
-    name: String,
+    pub name: String,
 }
 
 impl Grammar for Variable {
@@ -388,13 +1419,59 @@ impl Grammar for Variable {
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
-        let var_ptn = pair.into_inner().next().unwrap();
-        Variable{ name: var_ptn.as_str().to_owned() }
+        Variable {
+            name: pair.as_str().to_owned(),
+        }
     }
 }
 
+/// コマンドの引数。
 #[derive(Debug, PartialEq)]
-pub struct Vertical(Vec<Ranged<VerticalElement>>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum CommandArg {
+    /// `(expr)` や `[...]`, `(| ... |)`
+    Expr(Unary),
+    /// `?:(expr)`
+    Optional(Ranged<Unary>),
+    /// `?*`
+    OptionalOmitted,
+    /// `<...>`
+    Vertical(Vertical),
+    /// `{...}`
+    Horizontal(Horizontal),
+}
+
+impl Grammar for CommandArg {
+    fn rule() -> Rule {
+        Rule::cmd_expr_arg
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::cmd_expr_arg => CommandArg::Expr(Unary::parse_pair(pair)),
+            Rule::cmd_expr_option => match pair.into_inner().next() {
+                Some(inner) => CommandArg::Optional(Unary::parse_pair_ranged(inner)),
+                None => CommandArg::OptionalOmitted,
+            },
+            Rule::cmd_text_arg => {
+                let inner = pair.into_inner().next().unwrap();
+                match inner.as_rule() {
+                    Rule::vertical_mode => CommandArg::Vertical(Vertical::parse_pair(inner)),
+                    _ => CommandArg::Horizontal(Horizontal::parse_pair(inner)),
+                }
+            }
+            rule => unreachable!("invalid rule: '{:?}' in command arguments", rule),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vertical(pub Vec<Ranged<VerticalElement>>);
 
 impl Grammar for Vertical {
     fn rule() -> Rule {
@@ -411,11 +1488,15 @@ impl Grammar for Vertical {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum VerticalElement {
     BlockCmd {
         name: Ranged<String>,
-        args: Vec<Ranged<Expr>>,
-        opts: Vec<Ranged<Expr>>,
+        args: Vec<Ranged<CommandArg>>,
     },
     BlockTextEmbedding {
         mod_name: Option<Ranged<String>>,
@@ -436,39 +1517,46 @@ impl Grammar for VerticalElement {
                 let name = inner_cmd.next().unwrap();
                 VerticalElement::BlockCmd {
                     name: String::parse_pair_ranged(name),
-                    opts: vec![],
-                    args: vec![],
+                    args: inner_cmd.map(CommandArg::parse_pair_ranged).collect(),
                 }
             }
             Rule::block_text_embedding => {
                 let name = inner_vertical_element.into_inner().next().unwrap();
-                match name.as_rule() {
-                    Rule::var_ptn => VerticalElement::BlockTextEmbedding {
-                        name: String::parse_pair_ranged(name),
-                        mod_name: None,
-                    },
-                    Rule::modvar => {
-                        let mut pairs = name.into_inner();
-                        let module_name = pairs.next().unwrap();
-                        let var_ptn = pairs.next().unwrap();
-                        VerticalElement::BlockTextEmbedding {
-                            name: String::parse_pair_ranged(var_ptn),
-                            mod_name: Some(String::parse_pair_ranged(module_name)),
-                        }
-                    }
-                    _ => unreachable!(),
-                }
+                let (mod_name, name) = parse_embedding_name(name);
+                VerticalElement::BlockTextEmbedding { mod_name, name }
             }
             _ => unreachable!(),
         }
     }
 }
 
+/// `#var;` や `#Mod.var;` の名前部分を読む。
+fn parse_embedding_name(pair: Pair<'_>) -> (Option<Ranged<String>>, Ranged<String>) {
+    match pair.as_rule() {
+        Rule::var_ptn => (None, String::parse_pair_ranged(pair)),
+        Rule::modvar => {
+            let mut pairs = pair.into_inner();
+            let module_name = pairs.next().unwrap();
+            let var_ptn = pairs.next().unwrap();
+            (
+                Some(String::parse_pair_ranged(module_name)),
+                String::parse_pair_ranged(var_ptn),
+            )
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Horizontal {
     Single(HorizontalSingle),
     List(Vec<Ranged<HorizontalSingle>>),
-    BulletList(Vec<Ranged<HorizontalBullet>>)
+    BulletList(Vec<Ranged<HorizontalBullet>>),
 }
 
 impl Grammar for Horizontal {
@@ -477,14 +1565,28 @@ impl Grammar for Horizontal {
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
-        todo!()
+        match pair.as_rule() {
+            Rule::horizontal_single => Horizontal::Single(HorizontalSingle::parse_pair(pair)),
+            Rule::horizontal_list => Horizontal::List(
+                pair.into_inner()
+                    .map(HorizontalSingle::parse_pair_ranged)
+                    .collect(),
+            ),
+            Rule::horizontal_bullet_list => Horizontal::BulletList(
+                pair.into_inner()
+                    .map(HorizontalBullet::parse_pair_ranged)
+                    .collect(),
+            ),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'horizontal_mode'", rule),
+        }
     }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HorizontalBullet {
-    indent: u32,
-    body: Ranged<HorizontalSingle>
+    pub indent: u32,
+    pub body: Ranged<HorizontalSingle>,
 }
 
 impl Grammar for HorizontalBullet {
@@ -493,12 +1595,19 @@ impl Grammar for HorizontalBullet {
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
-        todo!()
+        let mut inner = pair.into_inner();
+        let star = inner.next().unwrap();
+        let body = HorizontalSingle::parse_pair_ranged(inner.next().unwrap());
+        HorizontalBullet {
+            indent: star.as_str().matches('*').count() as u32,
+            body,
+        }
     }
 }
 
 #[derive(Debug, PartialEq)]
-pub struct HorizontalSingle (Vec<Ranged<HorizontalToken>>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HorizontalSingle(pub Vec<Ranged<HorizontalToken>>);
 
 impl Grammar for HorizontalSingle {
     fn rule() -> Rule {
@@ -506,11 +1615,20 @@ impl Grammar for HorizontalSingle {
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
-        todo!()
+        HorizontalSingle(
+            pair.into_inner()
+                .map(HorizontalToken::parse_pair_ranged)
+                .collect(),
+        )
     }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum HorizontalToken {
     Text(Ranged<String>),
     SpecialChar(Ranged<String>),
@@ -520,10 +1638,9 @@ pub enum HorizontalToken {
     },
     InlineCmd {
         name: Ranged<String>,
-        args: Vec<Ranged<Expr>>,
-        opts: Vec<Ranged<Expr>>,
+        args: Vec<Ranged<CommandArg>>,
     },
-    Math(Ranged<()>),
+    Math(Ranged<Math>),
     StringLiteral(Ranged<Literal>),
 }
 
@@ -533,11 +1650,237 @@ impl Grammar for HorizontalToken {
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
-        todo!()
+        let inner = pair.into_inner().next().unwrap();
+        match inner.as_rule() {
+            Rule::string_const => HorizontalToken::StringLiteral(Literal::parse_pair_ranged(inner)),
+            Rule::inline_cmd => {
+                let mut inner_cmd = inner.into_inner();
+                let name = inner_cmd.next().unwrap();
+                HorizontalToken::InlineCmd {
+                    name: String::parse_pair_ranged(name),
+                    args: inner_cmd.map(CommandArg::parse_pair_ranged).collect(),
+                }
+            }
+            Rule::horizontal_text_embedding => {
+                let name = inner.into_inner().next().unwrap();
+                let (mod_name, name) = parse_embedding_name(name);
+                HorizontalToken::HorizontalTextEmbedding { mod_name, name }
+            }
+            Rule::math_mode => HorizontalToken::Math(Math::parse_pair_ranged(inner)),
+            Rule::horizontal_special_char => {
+                HorizontalToken::SpecialChar(String::parse_pair_ranged(inner))
+            }
+            Rule::regular_text => HorizontalToken::Text(String::parse_pair_ranged(inner)),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'horizontal_token'", rule),
+        }
+    }
+}
+
+/// 数式。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum Math {
+    Single(MathSingle),
+    List(Vec<Ranged<MathSingle>>),
+}
+
+impl Grammar for Math {
+    fn rule() -> Rule {
+        Rule::math_mode
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::math_mode => Math::parse_pair(pair.into_inner().next().unwrap()),
+            Rule::math_single => Math::Single(MathSingle::parse_pair(pair)),
+            Rule::math_list => Math::List(
+                pair.into_inner()
+                    .map(MathSingle::parse_pair_ranged)
+                    .collect(),
+            ),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'math_mode'", rule),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MathSingle(pub Vec<Ranged<MathToken>>);
+
+impl Grammar for MathSingle {
+    fn rule() -> Rule {
+        Rule::math_single
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        MathSingle(pair.into_inner().map(MathToken::parse_pair_ranged).collect())
+    }
+}
+
+/// 上付き・下付きの添字を伴いうる数式の要素。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MathToken {
+    pub base: Ranged<MathUnary>,
+    pub sup: Option<Ranged<MathGroup>>,
+    pub sub: Option<Ranged<MathGroup>>,
+}
+
+impl Grammar for MathToken {
+    fn rule() -> Rule {
+        Rule::math_token
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let mut inner = pair.into_inner();
+        let base = MathUnary::parse_pair_ranged(inner.next().unwrap());
+        let mut sup = None;
+        let mut sub = None;
+        for script in inner {
+            let rule = script.as_rule();
+            let group = MathGroup::parse_pair_ranged(script.into_inner().next().unwrap());
+            match rule {
+                Rule::math_sup => sup = Some(group),
+                Rule::math_sub => sub = Some(group),
+                _ => unreachable!(),
+            }
+        }
+        MathToken { base, sup, sub }
+    }
+}
+
+/// 添字。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum MathGroup {
+    /// `{...}`
+    Group(MathSingle),
+    Unary(MathUnary),
+}
+
+impl Grammar for MathGroup {
+    fn rule() -> Rule {
+        Rule::math_group
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let inner = pair.into_inner().next().unwrap();
+        match inner.as_rule() {
+            Rule::math_single => MathGroup::Group(MathSingle::parse_pair(inner)),
+            Rule::math_unary => MathGroup::Unary(MathUnary::parse_pair(inner)),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'math_group'", rule),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum MathUnary {
+    /// 英数字 1 文字。
+    Char(String),
+    /// `\` でエスケープされた文字。
+    SpecialChar(String),
+    /// `+` や `<=` などの記号。
+    Symbol(String),
+    /// 数式コマンド。
+    Cmd {
+        name: Ranged<String>,
+        args: Vec<Ranged<MathCmdArg>>,
+    },
+}
+
+impl Grammar for MathUnary {
+    fn rule() -> Rule {
+        Rule::math_unary
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        let text = pair.as_str();
+        match pair.into_inner().next() {
+            None => MathUnary::Char(text.to_owned()),
+            Some(inner) => match inner.as_rule() {
+                Rule::math_special_char => MathUnary::SpecialChar(String::parse_pair(inner)),
+                Rule::math_symbol => MathUnary::Symbol(String::parse_pair(inner)),
+                Rule::math_cmd => {
+                    let mut inner_cmd = inner.into_inner();
+                    let name = String::parse_pair_ranged(inner_cmd.next().unwrap());
+                    let args = inner_cmd.map(MathCmdArg::parse_pair_ranged).collect();
+                    MathUnary::Cmd { name, args }
+                }
+                rule => unreachable!("invalid rule: '{:?}' in rule 'math_unary'", rule),
+            },
+        }
     }
 }
 
+/// 数式コマンドの引数。
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
+pub enum MathCmdArg {
+    /// `{...}`
+    Math(Math),
+    /// `!{...}`
+    Horizontal(Horizontal),
+    /// `!<...>`
+    Vertical(Vertical),
+    /// `!(expr)` や `![...]`, `!(| ... |)`
+    Expr(Unary),
+    /// `?:...`
+    Optional(Box<Ranged<MathCmdArg>>),
+}
+
+impl Grammar for MathCmdArg {
+    fn rule() -> Rule {
+        Rule::math_cmd_expr_arg
+    }
+
+    fn parse_pair(pair: Pair<'_>) -> Self {
+        match pair.as_rule() {
+            Rule::math_cmd_expr_option => MathCmdArg::Optional(Box::new(
+                MathCmdArg::parse_pair_ranged(pair.into_inner().next().unwrap()),
+            )),
+            Rule::math_cmd_expr_arg => {
+                let inner = pair.into_inner().next().unwrap();
+                match inner.as_rule() {
+                    Rule::math_mode => MathCmdArg::Math(Math::parse_pair(inner)),
+                    Rule::vertical_mode => MathCmdArg::Vertical(Vertical::parse_pair(inner)),
+                    Rule::expr => MathCmdArg::Expr(Unary::Expr {
+                        modname: None,
+                        expr: Box::new(Expr::parse_pair_ranged(inner)),
+                    }),
+                    Rule::math_cmd_list_arg | Rule::math_cmd_record_arg => {
+                        MathCmdArg::Expr(Unary::parse_pair(inner))
+                    }
+                    _ => MathCmdArg::Horizontal(Horizontal::parse_pair(inner)),
+                }
+            }
+            rule => unreachable!("invalid rule: '{:?}' in rule 'math_cmd'", rule),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "value", rename_all = "snake_case")
+)]
 pub enum Literal {
     Unit,
     Bool(bool),
@@ -553,7 +1896,10 @@ impl Grammar for Literal {
     }
 
     fn parse_pair(pair: Pair<'_>) -> Self {
-        let inner = pair.into_inner().next().unwrap();
+        let inner = match pair.as_rule() {
+            Rule::literal => pair.into_inner().next().unwrap(),
+            _ => pair,
+        };
 
         match inner.as_rule() {
             Rule::unit_const => Literal::Unit,
@@ -591,12 +1937,13 @@ impl Grammar for Literal {
 
                 body.to_owned()
             }),
-            rule => unreachable!(format!("invalid rule: '{:?}' in rule 'literal'", rule)),
+            rule => unreachable!("invalid rule: '{:?}' in rule 'literal'", rule),
         }
     }
 }
 
 #[derive(Debug, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Length {
     pub value: Ranged<f64>,
     pub unit: Ranged<String>,
@@ -659,5 +2006,3 @@ impl Grammar for i32 {
         }
     }
 }
-
-
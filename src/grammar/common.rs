@@ -2,11 +2,12 @@
 
 use crate::parser::{Pair, Pairs, Rule, SatysfiParser};
 use pest::Parser;
-use pest::Span;
+use pest::Span as PestSpan;
 
 /// ソースコード上の位置を表す構造体。
 // TODO: custom definition of Ord, PartialOrd
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location {
     /// 行。1始まり。
     pub row: usize,
@@ -14,8 +15,42 @@ pub struct Location {
     pub col: usize,
 }
 
+/// ソースコード上の範囲。
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    /// 開始位置。
+    pub start: Location,
+    /// 終了位置。
+    pub end: Location,
+}
+
+impl Location {
+    /// この位置から text を書き進めた後の位置。
+    pub fn advance(self, text: &str) -> Location {
+        match text.rfind('\n') {
+            Some(i) => Location {
+                row: self.row + text.matches('\n').count(),
+                col: text[i + 1..].chars().count() + 1,
+            },
+            None => Location {
+                row: self.row,
+                col: self.col + text.chars().count(),
+            },
+        }
+    }
+}
+
+impl Span {
+    /// loc が範囲に含まれるかどうか。終了位置は含まない。
+    pub fn contains(&self, loc: Location) -> bool {
+        self.start <= loc && loc < self.end
+    }
+}
+
 /// ソースコード上の範囲の情報が付いた構文要素。
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ranged<T: Grammar> {
     /// 構文要素本体。
     pub body: T,
@@ -23,6 +58,10 @@ pub struct Ranged<T: Grammar> {
     pub start: Location,
     /// body のソースコード上での終了位置。
     pub end: Location,
+    /// ソースコードに由来せず、変換によって作られた構文要素かどうか。
+    /// このとき start と end は元になった構文要素の範囲を指す。
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub synthesized: bool,
 }
 
 impl<T> Ranged<T>
@@ -30,7 +69,7 @@ where
     T: Grammar,
 {
     /// body と span から新たな Ranged を作成する。
-    pub fn wrap<'i>(body: T, span: &Span<'i>) -> Self {
+    pub fn wrap<'i>(body: T, span: &PestSpan<'i>) -> Self {
         let start = span.start_pos().line_col();
         let end = span.end_pos().line_col();
 
@@ -43,7 +82,44 @@ where
             col: end.1,
         };
 
-        Self { start, end, body }
+        Self::new(body, Span { start, end })
+    }
+
+    /// body と範囲から新たな Ranged を作成する。
+    pub fn new(body: T, span: Span) -> Self {
+        Self {
+            body,
+            start: span.start,
+            end: span.end,
+            synthesized: false,
+        }
+    }
+
+    /// 変換によって作られた構文要素として Ranged を作成する。
+    /// span には元になった構文要素の範囲を与える。
+    pub fn synthesized(body: T, span: Span) -> Self {
+        Self {
+            synthesized: true,
+            ..Self::new(body, span)
+        }
+    }
+
+    /// 範囲の情報を保ったまま body を変換する。
+    pub fn map<U: Grammar, F: FnOnce(T) -> U>(self, f: F) -> Ranged<U> {
+        Ranged {
+            body: f(self.body),
+            start: self.start,
+            end: self.end,
+            synthesized: self.synthesized,
+        }
+    }
+
+    /// body のソースコード上での範囲。
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.end,
+        }
     }
 }
 
@@ -51,17 +127,19 @@ where
 #[macro_export]
 macro_rules! ranged {
     ($body:expr, ($r1:expr, $r2:expr), ($c1: expr, $c2: expr)) => {
-        Ranged {
-            start: crate::grammar::common::Location { row: $r1, col: $c1 },
-            end: crate::grammar::common::Location { row: $r2, col: $c2 },
+        $crate::grammar::common::Ranged {
+            start: $crate::grammar::common::Location { row: $r1, col: $c1 },
+            end: $crate::grammar::common::Location { row: $r2, col: $c2 },
             body: $body,
+            synthesized: false,
         }
     };
     ($body:expr, ($c1: expr, $c2: expr)) => {
-        Ranged {
-            start: crate::grammar::common::Location { row: 1, col: $c1 },
-            end: crate::grammar::common::Location { row: 1, col: $c2 },
+        $crate::grammar::common::Ranged {
+            start: $crate::grammar::common::Location { row: 1, col: $c1 },
+            end: $crate::grammar::common::Location { row: 1, col: $c2 },
             body: $body,
+            synthesized: false,
         }
     };
 }
@@ -76,7 +154,7 @@ pub trait Grammar: Sized {
 
     /// pair を読んで自身のデータ構造に格納し、さらに範囲の情報を付ける。
     fn parse_pair_ranged(pair: Pair<'_>) -> Ranged<Self> {
-        let span = pair.as_span();
+        let span = span_without_trailing_skip(&pair);
         Ranged::wrap(Self::parse_pair(pair), &span)
     }
 
@@ -88,6 +166,25 @@ pub trait Grammar: Sized {
     }
 }
 
+/// pair の範囲から、末尾で読み飛ばされた空白やコメントを取り除いたものを返す。
+///
+/// pest では `a+` のような繰り返しで終わる規則の範囲に、その後の空白やコメントが含まれてしまう。
+/// 末尾が空白で終わる場合は、最後の子要素の終了位置までを範囲とする。
+/// 子要素を持たないもの（`regular_text` など）は末尾の空白も含めてそのまま返す。
+pub fn span_without_trailing_skip<'i>(pair: &Pair<'i>) -> PestSpan<'i> {
+    let span = pair.as_span();
+    if !span.as_str().ends_with(char::is_whitespace) {
+        return span;
+    }
+    match pair.clone().into_inner().last() {
+        Some(last) => {
+            let end = span_without_trailing_skip(&last).end();
+            PestSpan::new(span.get_input(), span.start(), end).unwrap()
+        }
+        None => span,
+    }
+}
+
 /// ダミーの構文要素。実際に todo の中身が実装されることはない。
 impl Grammar for () {
     fn rule() -> Rule {
@@ -18,11 +18,11 @@ fn assert_parsed<'i, T: std::fmt::Debug + Grammar + PartialEq>(text: &'i str, ex
 /// TODO: not fully parsed なときに OK とする
 fn assert_not_parsed<T: std::fmt::Debug + Grammar + PartialEq>(text: &str) {
     if SatysfiParser::parse(T::rule(), text).is_ok() {
-        panic!(format!(
+        panic!(
             r#"Text "{}" is successfully parsed by "{:?}" rule!"#,
             text,
             T::rule()
-        ));
+        );
     }
 }
 
@@ -165,3 +165,161 @@ fn parse_literal() {
     assert_parsed("` hoge\nfuga `", Literal::String("hoge\nfuga".to_owned()));
     assert_not_parsed::<Literal>("``");
 }
+
+
+/// テスト用の関数。整数リテラルを表す式を作る。
+fn int_expr(i: i32, (c1, c2): (usize, usize)) -> Ranged<Expr> {
+    ranged![Expr::Unary(ranged![Unary::Literal(Literal::Int(i)), (c1, c2)]), (c1, c2)]
+}
+
+#[test]
+fn parse_dyadic() {
+    assert_parsed(
+        "1 + 2 * 3",
+        Expr::Dyadic {
+            lhs: Box::new(int_expr(1, (1, 2))),
+            binop: ranged!["+".to_owned(), (3, 4)],
+            rhs: Box::new(ranged![
+                Expr::Dyadic {
+                    lhs: Box::new(int_expr(2, (5, 6))),
+                    binop: ranged!["*".to_owned(), (7, 8)],
+                    rhs: Box::new(int_expr(3, (9, 10))),
+                },
+                (5, 10)
+            ]),
+        },
+    );
+    assert_parsed(
+        "1 - 2 - 3",
+        Expr::Dyadic {
+            lhs: Box::new(ranged![
+                Expr::Dyadic {
+                    lhs: Box::new(int_expr(1, (1, 2))),
+                    binop: ranged!["-".to_owned(), (3, 4)],
+                    rhs: Box::new(int_expr(2, (5, 6))),
+                },
+                (1, 6)
+            ]),
+            binop: ranged!["-".to_owned(), (7, 8)],
+            rhs: Box::new(int_expr(3, (9, 10))),
+        },
+    );
+    assert_parsed(
+        "1 :: 2 :: []",
+        Expr::Dyadic {
+            lhs: Box::new(int_expr(1, (1, 2))),
+            binop: ranged!["::".to_owned(), (3, 5)],
+            rhs: Box::new(ranged![
+                Expr::Dyadic {
+                    lhs: Box::new(int_expr(2, (6, 7))),
+                    binop: ranged!["::".to_owned(), (8, 10)],
+                    rhs: Box::new(ranged![
+                        Expr::Unary(ranged![Unary::List(List(vec![])), (11, 13)]),
+                        (11, 13)
+                    ]),
+                },
+                (6, 13)
+            ]),
+        },
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_json() {
+    use serde_json::json;
+
+    let loc = |col| json!({"row": 1, "col": col});
+    let expr = Ranged::new(
+        Expr::parse("f x").unwrap(),
+        Span {
+            start: common::Location { row: 1, col: 1 },
+            end: common::Location { row: 1, col: 4 },
+        },
+    );
+    assert_eq!(
+        serde_json::to_value(&expr).unwrap(),
+        json!({
+            "body": {
+                "kind": "application",
+                "value": {
+                    "modname": null,
+                    "func": {
+                        "body": {"name": "f"},
+                        "start": loc(1),
+                        "end": loc(2),
+                        "synthesized": false,
+                    },
+                    "args": [{
+                        "body": {
+                            "kind": "unary",
+                            "value": {
+                                "kind": "variable",
+                                "value": {
+                                    "modname": null,
+                                    "var": {
+                                        "body": {"name": "x"},
+                                        "start": loc(3),
+                                        "end": loc(4),
+                                        "synthesized": false,
+                                    },
+                                },
+                            },
+                        },
+                        "start": loc(3),
+                        "end": loc(4),
+                        "synthesized": false,
+                    }],
+                },
+            },
+            "start": loc(1),
+            "end": loc(4),
+            "synthesized": false,
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(&Pattern::Wildcard).unwrap(),
+        json!({"kind": "wildcard"})
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn deserialize_json() {
+    let text = std::fs::read_to_string("examples/example1.saty").unwrap();
+    let program = Program::parse(&text).unwrap();
+    let json = serde_json::to_string(&program).unwrap();
+    let actual: Program = serde_json::from_str(&json).unwrap();
+    assert_eq!(actual, program);
+
+    // synthesized は省略できる
+    let lit: Ranged<Literal> = serde_json::from_str(
+        r#"{"body": {"kind": "int", "value": 1}, "start": {"row": 1, "col": 1}, "end": {"row": 1, "col": 2}}"#,
+    )
+    .unwrap();
+    assert_eq!(lit, ranged![Literal::Int(1), (1, 2)]);
+}
+
+/// テスト用の関数。text を T としてパースし、`src/grammar/snapshots/{name}.sexp` と比較する。
+fn assert_snapshot<T: Grammar + crate::sexp::ToSexp>(name: &str, text: &str, spans: bool) {
+    let node = T::parse(text).unwrap();
+    crate::sexp::assert_snapshot(
+        format!("src/grammar/snapshots/{}.sexp", name),
+        &crate::sexp::to_sexp(&node, spans),
+    );
+}
+
+#[test]
+fn snapshot_example1() {
+    let text = std::fs::read_to_string("examples/example1.saty").unwrap();
+    assert_snapshot::<Program>("example1", &text, false);
+}
+
+#[test]
+fn snapshot_horizontal() {
+    assert_snapshot::<Expr>(
+        "horizontal",
+        "{\n  * a \\emph{b}\n  ** ${x_1^2}\n  * `c`\n}",
+        true,
+    );
+}
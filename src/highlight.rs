@@ -0,0 +1,520 @@
+//! Semantic tokens for syntax highlighting.
+//!
+//! パース結果をたどり、コマンド名や変数、リテラル、地の文などの範囲を [`SemanticToken`] の列として返す。
+//! LSP の semantic tokens や HTML への色付けに使う。
+//! キーワードや記号、コメントはトークンにしない。
+//!
+//! [`to_html`] はトークンごとに `<span class="...">` で囲んだ HTML を作る。
+//! 構文の誤りがある場合は、誤りより前の文だけをパースし、残りはコメントや文字列、コマンド名を字句として色付けする。
+
+#[cfg(test)]
+mod tests;
+
+use crate::edit::LineOffsets;
+use crate::grammar::common::{Location, Span};
+use crate::parser::{skip_trivia, Pair, Rule, SatysfiParser};
+use pest::error::InputLocation;
+use pest::Parser;
+use std::ops::BitOr;
+
+/// トークンの種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// インラインコマンド名。`\emph`
+    InlineCmd,
+    /// ブロックコマンド名。`+p`
+    BlockCmd,
+    /// 数式コマンド名。`\alpha`
+    MathCmd,
+    /// 変数。
+    Variable,
+    /// モジュール名。
+    Module,
+    /// バリアントのコンストラクタ。
+    Variant,
+    /// 型引数。`'a`
+    TypeParam,
+    /// 型名。
+    Type,
+    /// レコードのフィールド名。
+    Field,
+    /// 整数、浮動小数点数、長さ。
+    Number,
+    /// `true` や `false`。
+    Bool,
+    /// `()`
+    Unit,
+    /// 文字列リテラルの中身。
+    String,
+    /// 文字列リテラルを囲むバッククォートと `#`。
+    StringDelimiter,
+    /// 地の文。`\{` のようなエスケープも含む。
+    Text,
+    /// コメント。パースできない部分を字句として色付けする場合にのみ現れる。
+    Comment,
+}
+
+impl TokenKind {
+    /// HTML で用いるクラス名。
+    pub fn class_name(self) -> &'static str {
+        match self {
+            TokenKind::InlineCmd => "inline-cmd",
+            TokenKind::BlockCmd => "block-cmd",
+            TokenKind::MathCmd => "math-cmd",
+            TokenKind::Variable => "variable",
+            TokenKind::Module => "module",
+            TokenKind::Variant => "variant",
+            TokenKind::TypeParam => "type-param",
+            TokenKind::Type => "type",
+            TokenKind::Field => "field",
+            TokenKind::Number => "number",
+            TokenKind::Bool => "bool",
+            TokenKind::Unit => "unit",
+            TokenKind::String => "string",
+            TokenKind::StringDelimiter => "string-delimiter",
+            TokenKind::Text => "text",
+            TokenKind::Comment => "comment",
+        }
+    }
+}
+
+/// トークンの修飾子の集合。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers(u32);
+
+impl Modifiers {
+    /// 修飾子なし。
+    pub const NONE: Modifiers = Modifiers(0);
+    /// `let` などで名前を定義している箇所。
+    pub const DEFINITION: Modifiers = Modifiers(1);
+    /// `sig` の中で名前を宣言している箇所。
+    pub const DECLARATION: Modifiers = Modifiers(1 << 1);
+
+    /// other の修飾子をすべて含むかどうか。
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// ビット列としての表現。
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, rhs: Modifiers) -> Modifiers {
+        Modifiers(self.0 | rhs.0)
+    }
+}
+
+/// 色付けの単位となるトークン。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SemanticToken {
+    /// 範囲。地の文は複数行にわたることがある。
+    pub span: Span,
+    /// 種類。
+    pub kind: TokenKind,
+    /// 修飾子。
+    pub modifiers: Modifiers,
+}
+
+/// プログラム全体をパースし、トークンを文書中の順に返す。トークン同士は重ならない。
+pub fn semantic_tokens(text: &str) -> Result<Vec<SemanticToken>, pest::error::Error<Rule>> {
+    let pair = SatysfiParser::parse(Rule::program, text)?.next().unwrap();
+    Ok(pair_tokens(pair))
+}
+
+/// pair 以下のトークンを文書中の順に返す。
+pub fn pair_tokens(pair: Pair<'_>) -> Vec<SemanticToken> {
+    let mut tokens = Tokens(vec![]);
+    tokens.walk(pair, Context::Expr);
+    tokens.0
+}
+
+/// 名前がどのような文脈に現れるか。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// 式の中。
+    Expr,
+    /// パターンや定義される名前の位置。
+    Pattern,
+    /// 型の中。
+    Type,
+    /// `sig` の中で宣言される名前の位置。
+    Signature,
+}
+
+impl Context {
+    /// 文脈に現れる名前に付く修飾子。
+    fn modifiers(self) -> Modifiers {
+        match self {
+            Context::Pattern => Modifiers::DEFINITION,
+            Context::Signature => Modifiers::DECLARATION,
+            Context::Expr | Context::Type => Modifiers::NONE,
+        }
+    }
+}
+
+/// トークンを集めるもの。
+struct Tokens(Vec<SemanticToken>);
+
+impl Tokens {
+    /// pest の範囲をトークンとして追加する。空の範囲は無視する。
+    fn push(&mut self, span: pest::Span<'_>, kind: TokenKind, modifiers: Modifiers) {
+        if span.start() == span.end() {
+            return;
+        }
+        self.0.push(SemanticToken {
+            span: convert_span(span),
+            kind,
+            modifiers,
+        });
+    }
+
+    /// 子要素をたどる。
+    fn walk_children(&mut self, pair: Pair<'_>, ctx: Context) {
+        for child in pair.into_inner() {
+            self.walk(child, ctx);
+        }
+    }
+
+    /// pair をたどってトークンを集める。
+    fn walk(&mut self, pair: Pair<'_>, ctx: Context) {
+        // 文脈を切り替える規則
+        let ctx = match pair.as_rule() {
+            Rule::statement | Rule::expr => Context::Expr,
+            Rule::pattern | Rule::match_ptn | Rule::arg => Context::Pattern,
+            Rule::type_expr | Rule::constraint => Context::Type,
+            Rule::sig_stmt => Context::Signature,
+            _ => ctx,
+        };
+        let span = pair.as_span();
+        match pair.as_rule() {
+            Rule::var | Rule::var_ptn => {
+                let kind = match ctx {
+                    Context::Type => TokenKind::Type,
+                    _ => TokenKind::Variable,
+                };
+                self.push(span, kind, ctx.modifiers());
+            }
+            Rule::module_name => self.push(span, TokenKind::Module, ctx.modifiers()),
+            Rule::variant_name => self.push(span, TokenKind::Variant, Modifiers::NONE),
+            Rule::type_param => self.push(span, TokenKind::TypeParam, ctx.modifiers()),
+            Rule::inline_cmd_name => self.push(span, TokenKind::InlineCmd, ctx.modifiers()),
+            Rule::block_cmd_name => self.push(span, TokenKind::BlockCmd, ctx.modifiers()),
+            // 定義される名前を Pattern の文脈で読む。本体の式や sig は文脈を切り替える
+            Rule::let_stmt
+            | Rule::let_rec_inner
+            | Rule::let_inline_stmt
+            | Rule::let_block_stmt
+            | Rule::let_mutable_stmt
+            | Rule::module_stmt => self.walk_children(pair, Context::Pattern),
+            // 数式コマンド名は inline_cmd_name として現れる
+            Rule::let_math_stmt | Rule::math_cmd => {
+                let ctx = match pair.as_rule() {
+                    Rule::let_math_stmt => Context::Pattern,
+                    _ => ctx,
+                };
+                for child in pair.into_inner() {
+                    match child.as_rule() {
+                        Rule::inline_cmd_name => {
+                            self.push(child.as_span(), TokenKind::MathCmd, ctx.modifiers())
+                        }
+                        _ => self.walk(child, ctx),
+                    }
+                }
+            }
+            Rule::sig_type_stmt => {
+                for child in pair.into_inner() {
+                    match child.as_rule() {
+                        Rule::var => {
+                            self.push(child.as_span(), TokenKind::Type, Modifiers::DECLARATION)
+                        }
+                        _ => self.walk(child, ctx),
+                    }
+                }
+            }
+            Rule::record_unit | Rule::type_record_unit | Rule::record_member => {
+                for child in pair.into_inner() {
+                    match child.as_rule() {
+                        Rule::var | Rule::var_ptn => {
+                            self.push(child.as_span(), TokenKind::Field, Modifiers::NONE)
+                        }
+                        _ => self.walk(child, ctx),
+                    }
+                }
+            }
+            Rule::unit_const => self.push(span, TokenKind::Unit, Modifiers::NONE),
+            Rule::bool_const => self.push(span, TokenKind::Bool, Modifiers::NONE),
+            Rule::int_const | Rule::float_const | Rule::length_const => {
+                self.push(span, TokenKind::Number, Modifiers::NONE)
+            }
+            Rule::string_const => self.push_string(pair),
+            Rule::regular_text => self.push(span, TokenKind::Text, Modifiers::NONE),
+            Rule::horizontal_token => {
+                let escaped = pair
+                    .clone()
+                    .into_inner()
+                    .next()
+                    .is_some_and(|child| child.as_rule() == Rule::horizontal_special_char);
+                if escaped {
+                    self.push(span, TokenKind::Text, Modifiers::NONE);
+                } else {
+                    self.walk_children(pair, ctx);
+                }
+            }
+            _ => self.walk_children(pair, ctx),
+        }
+    }
+
+    /// 文字列リテラルを、囲む記号と中身に分けて追加する。
+    fn push_string(&mut self, pair: Pair<'_>) {
+        let span = pair.as_span();
+        let interior = pair
+            .into_inner()
+            .find(|child| child.as_rule() == Rule::string_interior)
+            .unwrap()
+            .as_span();
+        let input = span.get_input();
+        let open = pest::Span::new(input, span.start(), interior.start()).unwrap();
+        let close = pest::Span::new(input, interior.end(), span.end()).unwrap();
+        self.push(open, TokenKind::StringDelimiter, Modifiers::NONE);
+        self.push(interior, TokenKind::String, Modifiers::NONE);
+        self.push(close, TokenKind::StringDelimiter, Modifiers::NONE);
+    }
+}
+
+/// pest の範囲を Span に変換する。
+fn convert_span(span: pest::Span<'_>) -> Span {
+    let location = |(row, col)| Location { row, col };
+    Span {
+        start: location(span.start_pos().line_col()),
+        end: location(span.end_pos().line_col()),
+    }
+}
+
+/// ソースコードを、トークンごとに `<span class="...">` で囲んだ HTML にする。
+///
+/// クラス名は [`TokenKind::class_name`] で、定義や宣言には `definition` や `declaration` も付く。
+/// プログラム全体としてパースできない場合は、式、ブロックテキストの中身の順に試す。
+/// いずれでもパースできない場合は、誤りの位置より前で終わる文までを色付けし、
+/// 残りはコメント、文字列、コマンド名だけを字句として色付けする。
+pub fn to_html(text: &str) -> String {
+    let tokens = lenient_tokens(text);
+    let offsets = LineOffsets::new(text);
+    let mut html = String::new();
+    let mut pos = 0;
+    for token in tokens {
+        let start = offsets.offset(text, token.span.start);
+        let end = offsets.offset(text, token.span.end);
+        escape_html(&mut html, &text[pos..start]);
+        html.push_str("<span class=\"");
+        html.push_str(token.kind.class_name());
+        if token.modifiers.contains(Modifiers::DEFINITION) {
+            html.push_str(" definition");
+        }
+        if token.modifiers.contains(Modifiers::DECLARATION) {
+            html.push_str(" declaration");
+        }
+        html.push_str("\">");
+        escape_html(&mut html, &text[start..end]);
+        html.push_str("</span>");
+        pos = end;
+    }
+    escape_html(&mut html, &text[pos..]);
+    html
+}
+
+/// パースできる範囲でトークンを返す。
+///
+/// プログラム全体やその断片としてパースできなければ、誤りの位置より前の文のトークンと、
+/// 残りの部分の字句としてのトークンを返す。
+fn lenient_tokens(text: &str) -> Vec<SemanticToken> {
+    let error = match semantic_tokens(text) {
+        Ok(tokens) => return tokens,
+        Err(error) => error,
+    };
+    if let Some(tokens) = fragment_tokens(text) {
+        return tokens;
+    }
+    let error_pos = match error.location {
+        InputLocation::Pos(pos) | InputLocation::Span((pos, _)) => pos,
+    };
+    let (mut tokens, end) = prefix_tokens(text, error_pos);
+    tokens.extend(lexical_tokens(text, end));
+    tokens
+}
+
+/// 式、あるいはブロックテキストの中身として全体をパースできればトークンを返す。
+fn fragment_tokens(text: &str) -> Option<Vec<SemanticToken>> {
+    // horizontal_mode はほぼ何でも地の文として受け付けてしまうため試さない
+    [Rule::expr, Rule::vertical_mode].iter().find_map(|&rule| {
+        let pairs = SatysfiParser::parse(rule, text).ok()?;
+        let end = pairs.clone().map(|pair| pair.as_span().end()).max()?;
+        if !skip_trivia(&text[end..]).is_empty() {
+            return None;
+        }
+        Some(pairs.flat_map(pair_tokens).collect())
+    })
+}
+
+/// 先頭から順にヘッダや文をパースし、error_pos までに終わるもののトークンを返す。
+///
+/// パースした部分の末尾のバイト位置も返す。
+fn prefix_tokens(text: &str, error_pos: usize) -> (Vec<SemanticToken>, usize) {
+    let mut tokens = vec![];
+    let mut end = 0;
+    let mut loc = Location { row: 1, col: 1 };
+    loop {
+        let rest = skip_trivia(&text[end..]);
+        let start = text.len() - rest.len();
+        let pair = [Rule::header_stage, Rule::header, Rule::statement]
+            .iter()
+            .find_map(|&rule| SatysfiParser::parse(rule, rest).ok()?.next());
+        let pair = match pair {
+            Some(pair) if start + pair.as_span().end() <= error_pos => pair,
+            _ => return (tokens, end),
+        };
+        // rest の中の位置を text の中の位置に直す
+        loc = loc.advance(&text[end..start]);
+        end = start + pair.as_span().end();
+        let shift = |l: Location| match l.row {
+            1 => Location {
+                row: loc.row,
+                col: loc.col + l.col - 1,
+            },
+            row => Location {
+                row: loc.row + row - 1,
+                col: l.col,
+            },
+        };
+        tokens.extend(pair_tokens(pair).into_iter().map(|token| SemanticToken {
+            span: Span {
+                start: shift(token.span.start),
+                end: shift(token.span.end),
+            },
+            ..token
+        }));
+        loc = loc.advance(&text[start..end]);
+    }
+}
+
+/// text の start バイト目以降を、コメント、文字列、コマンド名の字句に分けてトークンを返す。
+///
+/// `\` に続く名前はインラインコマンド名とみなす。
+/// `+` に続く名前は、空白や括弧の直後にある場合のみブロックコマンド名とみなす。
+fn lexical_tokens(text: &str, start: usize) -> Vec<SemanticToken> {
+    let mut ranges = vec![];
+    let bytes = text.as_bytes();
+    let mut pos = start;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        if rest.starts_with('%') {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            ranges.push((pos..pos + len, TokenKind::Comment));
+            pos += len;
+        } else if let Some(len) = rest.strip_prefix('\\').map(command_name_len) {
+            if len > 0 {
+                ranges.push((pos..pos + 1 + len, TokenKind::InlineCmd));
+                pos += 1 + len;
+            } else {
+                // `\{` のようなエスケープは読み飛ばす
+                pos += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+            }
+        } else if let Some(len) = rest
+            .strip_prefix('+')
+            .filter(|_| pos == 0 || b" \t\r\n<>{}();|".contains(&bytes[pos - 1]))
+            .map(command_name_len)
+            .filter(|&len| len > 0)
+        {
+            ranges.push((pos..pos + 1 + len, TokenKind::BlockCmd));
+            pos += 1 + len;
+        } else if let Some((open, interior, close)) = string_parts(rest) {
+            let (interior_start, interior_end) = (pos + open, pos + open + interior);
+            ranges.push((pos..interior_start, TokenKind::StringDelimiter));
+            ranges.push((interior_start..interior_end, TokenKind::String));
+            ranges.push((
+                interior_end..interior_end + close,
+                TokenKind::StringDelimiter,
+            ));
+            pos = interior_end + close;
+        } else {
+            pos += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    let mut tokens = vec![];
+    let mut loc = Location { row: 1, col: 1 }.advance(&text[..start]);
+    let mut last = start;
+    for (range, kind) in ranges {
+        if range.is_empty() {
+            continue;
+        }
+        let token_start = loc.advance(&text[last..range.start]);
+        loc = token_start.advance(&text[range.clone()]);
+        last = range.end;
+        tokens.push(SemanticToken {
+            span: Span {
+                start: token_start,
+                end: loc,
+            },
+            kind,
+            modifiers: Modifiers::NONE,
+        });
+    }
+    tokens
+}
+
+/// rest の先頭にあるコマンド名の、`\\` や `+` を除いた部分のバイト数。名前がなければ 0。
+fn command_name_len(rest: &str) -> usize {
+    let ident_len = |s: &str, first: fn(&char) -> bool| {
+        if !s.chars().next().as_ref().is_some_and(first) {
+            return 0;
+        }
+        s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(s.len())
+    };
+    let module = ident_len(rest, char::is_ascii_uppercase);
+    if module > 0 && rest[module..].starts_with('.') {
+        match ident_len(&rest[module + 1..], char::is_ascii_lowercase) {
+            0 => 0,
+            var => module + 1 + var,
+        }
+    } else {
+        ident_len(rest, char::is_ascii_lowercase)
+    }
+}
+
+/// rest の先頭が文字列リテラルであれば、開く記号、中身、閉じる記号のバイト数を返す。
+///
+/// 閉じられていない場合は、残り全体を中身とする。
+fn string_parts(rest: &str) -> Option<(usize, usize, usize)> {
+    let hash = usize::from(rest.starts_with('#'));
+    let quotes = rest[hash..].len() - rest[hash..].trim_start_matches('`').len();
+    if quotes == 0 {
+        return None;
+    }
+    let open = hash + quotes;
+    match rest[open..].find(&rest[hash..open]) {
+        Some(interior) => {
+            let after = open + interior + quotes;
+            let close = quotes + usize::from(rest[after..].starts_with('#'));
+            Some((open, interior, close))
+        }
+        None => Some((open, rest.len() - open, 0)),
+    }
+}
+
+/// HTML の特殊文字をエスケープして追加する。
+fn escape_html(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            c => html.push(c),
+        }
+    }
+}
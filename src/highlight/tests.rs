@@ -0,0 +1,183 @@
+use super::*;
+
+/// text の span の部分。
+fn slice(text: &str, span: Span) -> String {
+    let offset = |loc: Location| {
+        let line: usize = text
+            .split_inclusive('\n')
+            .take(loc.row - 1)
+            .map(str::len)
+            .sum();
+        let rest = &text[line..];
+        line + rest
+            .char_indices()
+            .nth(loc.col - 1)
+            .map_or(rest.len(), |(i, _)| i)
+    };
+    text[offset(span.start)..offset(span.end)].to_owned()
+}
+
+/// トークンを (文字列, 種類, 修飾子) の列にする。
+fn tokens(text: &str) -> Vec<(String, TokenKind, Modifiers)> {
+    semantic_tokens(text)
+        .unwrap()
+        .into_iter()
+        .map(|t| (slice(text, t.span), t.kind, t.modifiers))
+        .collect()
+}
+
+/// 修飾子のないトークン。
+fn plain(text: &str, kind: TokenKind) -> (String, TokenKind, Modifiers) {
+    (text.to_owned(), kind, Modifiers::NONE)
+}
+
+/// 定義のトークン。
+fn def(text: &str, kind: TokenKind) -> (String, TokenKind, Modifiers) {
+    (text.to_owned(), kind, Modifiers::DEFINITION)
+}
+
+/// 宣言のトークン。
+fn decl(text: &str, kind: TokenKind) -> (String, TokenKind, Modifiers) {
+    (text.to_owned(), kind, Modifiers::DECLARATION)
+}
+
+#[test]
+fn statements() {
+    use TokenKind::*;
+    let text = "\
+let-inline ctx \\emph ?:opt inner = read-inline ctx inner
+let-math \\alpha = ${x}
+let-mutable cnt <- 0
+module M : sig
+  type 'a t
+  val f : 'a -> 'a M.t
+end = struct
+  let (a, _) = (Some(1.5), true)
+end
+";
+    assert_eq!(
+        tokens(text),
+        vec![
+            def("ctx", Variable),
+            def("\\emph", InlineCmd),
+            def("opt", Variable),
+            def("inner", Variable),
+            plain("read-inline", Variable),
+            plain("ctx", Variable),
+            plain("inner", Variable),
+            def("\\alpha", MathCmd),
+            def("cnt", Variable),
+            plain("0", Number),
+            def("M", Module),
+            decl("'a", TypeParam),
+            decl("t", Type),
+            decl("f", Variable),
+            plain("'a", TypeParam),
+            plain("'a", TypeParam),
+            plain("M", Module),
+            plain("t", Type),
+            def("a", Variable),
+            plain("Some", Variant),
+            plain("1.5", Number),
+            plain("true", Bool),
+        ]
+    );
+}
+
+#[test]
+fn expressions() {
+    use TokenKind::*;
+    let text = "f List.map (| key = #`a b`; len = 2pt |) ((| key = 1 |)#key)";
+    assert_eq!(
+        tokens(text),
+        vec![
+            plain("f", Variable),
+            plain("List", Module),
+            plain("map", Variable),
+            plain("key", Field),
+            plain("#`", StringDelimiter),
+            plain("a b", String),
+            plain("`", StringDelimiter),
+            plain("len", Field),
+            plain("2pt", Number),
+            plain("key", Field),
+            plain("1", Number),
+            plain("key", Field),
+        ]
+    );
+}
+
+#[test]
+fn texts() {
+    use TokenKind::*;
+    let text = "'<+p?:(()){Hello \\emph{world}\\{ ${\\sqrt{a}^2}}>";
+    assert_eq!(
+        tokens(text),
+        vec![
+            plain("+p", BlockCmd),
+            plain("()", Unit),
+            plain("Hello ", Text),
+            plain("\\emph", InlineCmd),
+            plain("world", Text),
+            plain("\\{", Text),
+            plain(" ", Text),
+            plain("\\sqrt", MathCmd),
+        ]
+    );
+}
+
+#[test]
+fn html() {
+    assert_eq!(
+        to_html("let x = {a & ${\\alpha}}"),
+        "let <span class=\"variable definition\">x</span> = \
+         {<span class=\"text\">a &amp; </span>${<span class=\"math-cmd\">\\alpha</span>}}",
+    );
+}
+
+#[test]
+fn html_nested_modes() {
+    assert_eq!(
+        to_html("'<+p{ぬ\\emph{${x^{\\bar{y}}}}}>"),
+        "'&lt;<span class=\"block-cmd\">+p</span>{<span class=\"text\">ぬ</span>\
+         <span class=\"inline-cmd\">\\emph</span>{${x^{<span class=\"math-cmd\">\\bar</span>{y}}}}}&gt;",
+    );
+}
+
+#[test]
+fn html_fragment() {
+    assert_eq!(
+        to_html("+p{a}\n"),
+        "<span class=\"block-cmd\">+p</span>{<span class=\"text\">a</span>}\n",
+    );
+}
+
+#[test]
+fn html_syntax_error() {
+    assert_eq!(to_html("let x = <a>"), "let x = &lt;a&gt;");
+}
+
+#[test]
+fn html_partial_syntax_error() {
+    let text = "\
+let x = 1
+let f z =
+  z
+let y = (x
+% comment
+let-block +p ?:opt s = `a``b` \\emph
+";
+    assert_eq!(
+        to_html(text),
+        "let <span class=\"variable definition\">x</span> = <span class=\"number\">1</span>\n\
+         let <span class=\"variable definition\">f</span> \
+         <span class=\"variable definition\">z</span> =\n  <span class=\"variable\">z</span>\n\
+         let y = (x\n\
+         <span class=\"comment\">% comment</span>\n\
+         let-block <span class=\"block-cmd\">+p</span> ?:opt s = \
+         <span class=\"string-delimiter\">`</span><span class=\"string\">a</span>\
+         <span class=\"string-delimiter\">`</span><span class=\"string-delimiter\">`</span>\
+         <span class=\"string\">b</span><span class=\"string-delimiter\">`</span> \
+         <span class=\"inline-cmd\">\\emph</span>\n",
+    );
+}
@@ -8,6 +8,25 @@
 #[macro_use]
 extern crate pest_derive;
 
-mod parser;
+pub mod parser;
 
 pub mod grammar;
+pub mod formatter;
+pub mod printer;
+pub mod visit;
+pub mod visit_mut;
+pub mod fold;
+pub mod sexp;
+pub mod diagnostic;
+pub mod outline;
+pub mod highlight;
+pub mod resolve;
+pub mod edit;
+pub mod rename;
+pub mod package;
+pub mod workspace;
+pub mod stage;
+pub mod types;
+pub mod arity;
+pub mod lint;
+pub mod plaintext;
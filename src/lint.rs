@@ -0,0 +1,321 @@
+//! Lint framework.
+//!
+//! 規則（[`LintRule`]）ごとにプログラムを調べ、気になる箇所を [`Report`] として返す。
+//! 規則の有効・無効や重大度、ファイルごとに無視する規則は [`Config`] で指定する。
+//!
+//! `% lint: allow(rule-a, rule-b)` というコメントで、特定の箇所の報告を抑えられる。
+//! コメントだけの行に書いた場合は次の行に、コードの後に書いた場合はその行に効く。
+//!
+//! [`Linter::fix`] は、報告に付いた自動修正のうち互いに重ならないものを適用する。
+
+#[cfg(test)]
+mod tests;
+
+mod config;
+pub mod rules;
+
+pub use config::{Config, ConfigError, RuleSetting};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::edit::{self, LineOffsets, TextEdit};
+use crate::formatter::collect_comments;
+use crate::grammar::common::{Grammar, Span};
+use crate::grammar::Program;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+/// [`Linter::fix`] で、修正と再検査を繰り返す最大の回数。
+const MAX_FIX_PASSES: usize = 10;
+
+/// lint の規則。
+///
+/// 多くの規則は [`crate::visit::Visitor`] でプログラムをたどって実装する。
+pub trait LintRule {
+    /// 規則の ID。設定ファイルや `% lint: allow(...)` で使う。
+    fn id(&self) -> &'static str;
+
+    /// 規則の短い説明。
+    fn description(&self) -> &'static str;
+
+    /// 設定で指定されなかった場合の重大度。
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// 設定で指定されなかった場合に有効かどうか。
+    fn default_enabled(&self) -> bool {
+        true
+    }
+
+    /// プログラムを調べ、気になる箇所を返す。
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding>;
+}
+
+/// 規則に渡す、調べる対象。
+#[derive(Debug, Clone, Copy)]
+pub struct LintContext<'a> {
+    /// ソースコード。
+    pub text: &'a str,
+    /// text をパースしたもの。
+    pub program: &'a Program,
+}
+
+impl LintContext<'_> {
+    /// span の範囲のソースコード。
+    pub fn source(&self, span: Span) -> &str {
+        let offsets = LineOffsets::new(self.text);
+        let start = offsets.offset(self.text, span.start);
+        let end = offsets.offset(self.text, span.end);
+        &self.text[start..end]
+    }
+}
+
+/// 規則が見つけた箇所。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// 範囲。
+    pub span: Span,
+    /// メッセージ。
+    pub message: String,
+    /// 自動修正のための編集。修正できない場合は空。
+    pub fix: Vec<TextEdit>,
+}
+
+impl Finding {
+    /// 自動修正のない Finding を作る。
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Finding {
+            span,
+            message: message.into(),
+            fix: vec![],
+        }
+    }
+
+    /// 自動修正のための編集を付ける。
+    pub fn with_fix(mut self, fix: Vec<TextEdit>) -> Self {
+        self.fix = fix;
+        self
+    }
+}
+
+/// lint の結果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+    /// 規則の ID。
+    pub rule: &'static str,
+    /// 重大度。
+    pub severity: Severity,
+    /// 範囲。
+    pub span: Span,
+    /// メッセージ。
+    pub message: String,
+    /// 自動修正のための編集。修正できない場合は空。
+    pub fix: Vec<TextEdit>,
+}
+
+/// 自動修正の結果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixed {
+    /// 修正後のソースコード。
+    pub text: String,
+    /// 適用した修正の報告。後の回の報告の範囲は、その回の修正前のソースコード上のもの。
+    pub applied: Vec<Report>,
+    /// 修正後のソースコードに残った報告。
+    pub remaining: Vec<Report>,
+}
+
+/// 自動修正を適用できない理由。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixError {
+    /// 元のソースコードをパースできない。
+    Parse(Diagnostic),
+    /// 修正を適用するとパースできなくなる。
+    Unparsable {
+        /// パースできなくなる、修正後のソースコード。
+        text: String,
+        /// そのパースの失敗。
+        diagnostic: Diagnostic,
+    },
+}
+
+impl fmt::Display for FixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixError::Parse(d) => write!(f, "{}", d.message),
+            FixError::Unparsable { diagnostic, .. } => write!(
+                f,
+                "applying fixes would break the source: {}",
+                diagnostic.message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FixError {}
+
+/// 規則の集まりと設定を持ち、ファイルを調べる。
+pub struct Linter {
+    /// 規則。
+    rules: Vec<Box<dyn LintRule>>,
+    /// 設定。
+    config: Config,
+}
+
+impl Linter {
+    /// 組み込みの規則（[`rules::builtin`]）を config に従って使う Linter を作る。
+    pub fn new(config: Config) -> Result<Self, ConfigError> {
+        Linter::with_rules(rules::builtin(), config)
+    }
+
+    /// rules を config に従って使う Linter を作る。
+    ///
+    /// config に rules にない規則の ID があれば失敗する。
+    pub fn with_rules(rules: Vec<Box<dyn LintRule>>, config: Config) -> Result<Self, ConfigError> {
+        let known: BTreeSet<_> = rules.iter().map(|rule| rule.id()).collect();
+        let configured = config.rules.keys().map(String::as_str).chain(
+            config
+                .ignores
+                .iter()
+                .flat_map(|(_, ids)| ids.iter().map(String::as_str)),
+        );
+        for id in configured {
+            if !known.contains(id) {
+                return Err(ConfigError::UnknownRule(id.to_owned()));
+            }
+        }
+        Ok(Linter { rules, config })
+    }
+
+    /// 規則。
+    pub fn rules(&self) -> impl Iterator<Item = &dyn LintRule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// 設定を反映した規則の重大度。無効な規則では None。
+    pub fn severity(&self, rule: &dyn LintRule) -> Option<Severity> {
+        match self.config.rules.get(rule.id()) {
+            Some(RuleSetting::Off) => None,
+            Some(RuleSetting::On) => Some(rule.default_severity()),
+            Some(RuleSetting::Severity(severity)) => Some(*severity),
+            None if rule.default_enabled() => Some(rule.default_severity()),
+            None => None,
+        }
+    }
+
+    /// ファイル path の中身 text を調べる。パースに失敗した場合はその診断を返す。
+    ///
+    /// path は [`Config`] のファイルごとの設定を選ぶのに使う。
+    pub fn check(&self, path: Option<&Path>, text: &str) -> Result<Vec<Report>, Diagnostic> {
+        let program = Program::parse(text).map_err(Diagnostic::from)?;
+        Ok(self.check_program(path, text, &program))
+    }
+
+    /// text をパースした program を調べる。結果は文書中の順に並べる。
+    pub fn check_program(&self, path: Option<&Path>, text: &str, program: &Program) -> Vec<Report> {
+        let ignored = path.map_or_else(BTreeSet::new, |path| self.config.ignored(path));
+        let allowed = allowed_rules(text);
+        let cx = LintContext { text, program };
+        let mut reports = vec![];
+        for rule in self.rules() {
+            let severity = match self.severity(rule) {
+                Some(severity) if !ignored.contains(rule.id()) => severity,
+                _ => continue,
+            };
+            for finding in rule.check(&cx) {
+                let row = finding.span.start.row;
+                if allowed.get(&row).is_some_and(|ids| ids.contains(rule.id())) {
+                    continue;
+                }
+                reports.push(Report {
+                    rule: rule.id(),
+                    severity,
+                    span: finding.span,
+                    message: finding.message,
+                    fix: finding.fix,
+                });
+            }
+        }
+        reports.sort_by(|a, b| (a.span, a.rule).cmp(&(b.span, b.rule)));
+        reports
+    }
+
+    /// ファイル path の中身 text を調べ、自動修正を適用する。
+    ///
+    /// 互いに重なる修正は文書中で先にあるものを適用し、残りは修正後に改めて調べて適用する。
+    /// 適用のたびにパースし直し、パースできなくなる場合は失敗する。
+    pub fn fix(&self, path: Option<&Path>, text: &str) -> Result<Fixed, FixError> {
+        let program = Program::parse(text).map_err(|e| FixError::Parse(e.into()))?;
+        let mut reports = self.check_program(path, text, &program);
+        let mut fixed = Fixed {
+            text: text.to_owned(),
+            applied: vec![],
+            remaining: vec![],
+        };
+        for _ in 0..MAX_FIX_PASSES {
+            let (fixable, rest): (Vec<_>, Vec<_>) =
+                reports.into_iter().partition(|r| !r.fix.is_empty());
+            let groups: Vec<_> = fixable.iter().map(|r| r.fix.clone()).collect();
+            let (edits, skipped) = edit::merge(&fixed.text, &groups);
+            if edits.is_empty() {
+                reports = fixable.into_iter().chain(rest).collect();
+                break;
+            }
+            let text = edit::apply(&fixed.text, &edits);
+            let program = Program::parse(&text).map_err(|e| FixError::Unparsable {
+                text: text.clone(),
+                diagnostic: e.into(),
+            })?;
+            fixed.applied.extend(
+                fixable
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| !skipped.contains(i))
+                    .map(|(_, r)| r),
+            );
+            reports = self.check_program(path, &text, &program);
+            fixed.text = text;
+        }
+        fixed.remaining = reports;
+        Ok(fixed)
+    }
+}
+
+/// `% lint: allow(...)` コメントから、行ごとに報告を抑える規則を求める。
+fn allowed_rules(text: &str) -> BTreeMap<usize, BTreeSet<String>> {
+    let lines: Vec<_> = text.lines().collect();
+    let mut allowed: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for comment in collect_comments(text) {
+        let ids = match parse_allow(&comment.text) {
+            Some(ids) => ids,
+            None => continue,
+        };
+        let row = comment.start.row;
+        // コメントより前が空白だけなら、コメントだけの行
+        let own_line = lines.get(row - 1).is_some_and(|line| {
+            line.chars()
+                .take(comment.start.col - 1)
+                .all(char::is_whitespace)
+        });
+        let target = if own_line { row + 1 } else { row };
+        allowed.entry(target).or_default().extend(ids);
+    }
+    allowed
+}
+
+/// `% lint: allow(a, b)` の形のコメントであれば、規則の ID を返す。
+fn parse_allow(comment: &str) -> Option<Vec<String>> {
+    let rest = comment.strip_prefix('%')?.trim_start();
+    let rest = rest.strip_prefix("lint:")?.trim_start();
+    let rest = rest.strip_prefix("allow")?.trim_start();
+    let inner = rest.strip_prefix('(')?.split(')').next()?;
+    Some(
+        inner
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_owned)
+            .collect(),
+    )
+}
@@ -0,0 +1,169 @@
+//! lint の設定ファイル。
+//!
+//! ```toml
+//! [rules]
+//! needless-bool = "error"   # "off" / "warning" / "error"、または true / false
+//!
+//! [per-file-ignores]
+//! "examples/*.saty" = ["needless-bool"]
+//! ```
+//!
+//! `per-file-ignores` のパターンは、パスの末尾（`/` の直後から）と一致すればよい。
+//! `*` と `?` は `/` 以外の文字列と 1 文字に一致する。
+//! `**/` は 0 個以上のディレクトリに、それ以外の `**` は `/` を含む任意の文字列に一致する。
+
+use crate::diagnostic::Severity;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+/// 規則ごとの設定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleSetting {
+    /// 無効にする。
+    Off,
+    /// 既定の重大度で有効にする。
+    On,
+    /// 指定した重大度で有効にする。
+    Severity(Severity),
+}
+
+/// lint の設定。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// 規則の ID ごとの設定。
+    pub rules: BTreeMap<String, RuleSetting>,
+    /// パスのパターンと、そのパターンに一致するファイルで無視する規則の組。
+    pub ignores: Vec<(String, Vec<String>)>,
+}
+
+/// 設定の誤り。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// TOML として正しくない。
+    Syntax(String),
+    /// 値の型や種類が正しくない。
+    InvalidValue {
+        /// 値の位置を表すキー。`rules.foo` など。
+        key: String,
+        /// 期待される値の説明。
+        expected: &'static str,
+    },
+    /// 知らないキーがある。
+    UnknownKey(String),
+    /// 知らない規則の ID がある。
+    UnknownRule(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Syntax(message) => write!(f, "invalid TOML: {}", message),
+            ConfigError::InvalidValue { key, expected } => {
+                write!(f, "invalid value for `{}`: expected {}", key, expected)
+            }
+            ConfigError::UnknownKey(key) => write!(f, "unknown key `{}`", key),
+            ConfigError::UnknownRule(id) => write!(f, "unknown lint rule `{}`", id),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// TOML で書かれた設定を読む。
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| ConfigError::Syntax(e.message().to_owned()))?;
+        let mut config = Config::default();
+        for (key, value) in &table {
+            match key.as_str() {
+                "rules" => {
+                    for (id, value) in as_table(key, value)? {
+                        let setting = parse_setting(&format!("rules.{}", id), value)?;
+                        config.rules.insert(id.clone(), setting);
+                    }
+                }
+                "per-file-ignores" => {
+                    for (pattern, value) in as_table(key, value)? {
+                        let key = format!("per-file-ignores.\"{}\"", pattern);
+                        let invalid = || ConfigError::InvalidValue {
+                            key: key.clone(),
+                            expected: "an array of rule IDs",
+                        };
+                        let ids = value
+                            .as_array()
+                            .ok_or_else(invalid)?
+                            .iter()
+                            .map(|id| id.as_str().map(str::to_owned).ok_or_else(invalid))
+                            .collect::<Result<_, _>>()?;
+                        config.ignores.push((pattern.clone(), ids));
+                    }
+                }
+                _ => return Err(ConfigError::UnknownKey(key.clone())),
+            }
+        }
+        Ok(config)
+    }
+
+    /// path のファイルで無視する規則。
+    pub fn ignored(&self, path: &Path) -> BTreeSet<&str> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.ignores
+            .iter()
+            .filter(|(pattern, _)| matches_path(pattern, &path))
+            .flat_map(|(_, ids)| ids.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+/// value を表として読む。
+fn as_table<'a>(key: &str, value: &'a toml::Value) -> Result<&'a toml::Table, ConfigError> {
+    value.as_table().ok_or_else(|| ConfigError::InvalidValue {
+        key: key.to_owned(),
+        expected: "a table",
+    })
+}
+
+/// 規則ごとの設定を読む。
+fn parse_setting(key: &str, value: &toml::Value) -> Result<RuleSetting, ConfigError> {
+    match value {
+        toml::Value::Boolean(true) => Ok(RuleSetting::On),
+        toml::Value::Boolean(false) => Ok(RuleSetting::Off),
+        toml::Value::String(s) if s == "off" => Ok(RuleSetting::Off),
+        toml::Value::String(s) if s == "warning" => Ok(RuleSetting::Severity(Severity::Warning)),
+        toml::Value::String(s) if s == "error" => Ok(RuleSetting::Severity(Severity::Error)),
+        _ => Err(ConfigError::InvalidValue {
+            key: key.to_owned(),
+            expected: "\"off\", \"warning\", \"error\" or a boolean",
+        }),
+    }
+}
+
+/// パスの末尾が pattern と一致するかどうか。
+fn matches_path(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let path: Vec<_> = path.chars().collect();
+    (0..path.len()).any(|i| (i == 0 || path[i - 1] == '/') && glob(&pattern, &path[i..]))
+}
+
+/// text 全体が pattern と一致するかどうか。
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // `**/` は 0 個以上のディレクトリ全体に一致する
+            (0..text.len())
+                .filter(|&i| i == 0 || text[i - 1] == '/')
+                .any(|i| glob(rest, &text[i..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        ['*', rest @ ..] => {
+            let end = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=end).any(|i| glob(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => matches!(text, [c, ..] if *c != '/') && glob(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
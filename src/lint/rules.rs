@@ -0,0 +1,303 @@
+//! 組み込みの lint の規則。
+
+use super::{Finding, LintContext, LintRule};
+use crate::edit::TextEdit;
+use crate::grammar::common::{Ranged, Span};
+use crate::grammar::{Expr, Literal, Program, Statement, Unary};
+use crate::resolve::{resolve, Binding, BindingKind, Resolution};
+use crate::visit::{self, Visitor};
+use std::collections::BTreeSet;
+
+mod prose;
+
+pub use prose::{
+    DoubledSpace, EmptyParagraph, MixedPunctuation, TrailingWhitespace, UnbalancedBracket,
+};
+
+/// 組み込みの規則すべて。
+pub fn builtin() -> Vec<Box<dyn LintRule>> {
+    vec![
+        Box::new(NeedlessBool),
+        Box::new(UnusedBinding),
+        Box::new(UnassignedMutable),
+        Box::new(Shadowing),
+        Box::new(DoubledSpace),
+        Box::new(MixedPunctuation),
+        Box::new(UnbalancedBracket),
+        Box::new(TrailingWhitespace),
+        Box::new(EmptyParagraph),
+    ]
+}
+
+/// `if c then true else false` を `c` と書けることを知らせる。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NeedlessBool;
+
+impl LintRule for NeedlessBool {
+    fn id(&self) -> &'static str {
+        "needless-bool"
+    }
+
+    fn description(&self) -> &'static str {
+        "`if` expressions returning boolean literals"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        let mut visitor = NeedlessBoolVisitor {
+            cx,
+            findings: vec![],
+        };
+        visitor.visit_program(cx.program);
+        visitor.findings
+    }
+}
+
+/// [`NeedlessBool`] の Visitor。
+struct NeedlessBoolVisitor<'a> {
+    /// 調べる対象。
+    cx: &'a LintContext<'a>,
+    /// 見つけた箇所。
+    findings: Vec<Finding>,
+}
+
+/// 式が真偽値のリテラルであれば、その値。
+fn bool_literal(expr: &Ranged<Expr>) -> Option<bool> {
+    match &expr.body {
+        Expr::Unary(unary) => match &unary.body {
+            Unary::Literal(Literal::Bool(b)) => Some(*b),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Visitor for NeedlessBoolVisitor<'_> {
+    fn visit_expr(&mut self, node: &Expr, span: Span) {
+        if let Expr::CtrlFlowIf {
+            condition,
+            expr_true,
+            expr_false,
+        } = node
+        {
+            let condition_text = self.cx.source(condition.span());
+            let replacement = match (bool_literal(expr_true), bool_literal(expr_false)) {
+                (Some(true), Some(false)) => Some(condition_text.to_owned()),
+                (Some(false), Some(true)) => Some(match condition.body {
+                    Expr::Unary(_) => format!("not {}", condition_text),
+                    _ => format!("not ({})", condition_text),
+                }),
+                _ => None,
+            };
+            if let Some(replacement) = replacement {
+                let message = format!("this `if` expression can be written as `{}`", replacement);
+                self.findings.push(
+                    Finding::new(span, message).with_fix(vec![TextEdit::new(span, replacement)]),
+                );
+            }
+        }
+        visit::walk_expr(self, node, span);
+    }
+}
+
+/// 使われない `let` や `let-inline` などの定義と、`match` のパターン中の変数を知らせる。
+///
+/// `.satyh` のように本体の式を持たないファイルの最上位の定義や、
+/// `sig` を持たないモジュールのメンバは外から使われうるため知らせない。
+/// 関数やコマンドの引数も知らせない。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnusedBinding;
+
+impl LintRule for UnusedBinding {
+    fn id(&self) -> &'static str {
+        "unused-binding"
+    }
+
+    fn description(&self) -> &'static str {
+        "definitions and pattern variables that are never used"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        let resolution = resolve(cx.program);
+        let exported = exported_spans(cx.program);
+        let open_modules = open_modules(cx.program);
+        let mut findings = vec![];
+        for (id, binding) in resolution.bindings.iter().enumerate() {
+            let pattern = match binding.kind {
+                BindingKind::Pattern => true,
+                BindingKind::Let
+                | BindingKind::LetRec
+                | BindingKind::LetMutable
+                | BindingKind::LetInline
+                | BindingKind::LetBlock
+                | BindingKind::LetMath => false,
+                BindingKind::Parameter | BindingKind::Module => continue,
+            };
+            let is_exported = exported.iter().any(|span| contains(*span, binding.span))
+                || binding
+                    .module
+                    .is_some_and(|module| open_modules.contains(&resolution.bindings[module].span));
+            if is_exported || resolution.references_to(id).next().is_some() {
+                continue;
+            }
+            let message = if pattern {
+                format!("`{}` is bound in the pattern but never used", binding.name)
+            } else {
+                format!("`{}` is defined but never used", binding.name)
+            };
+            findings.push(Finding::new(binding.span, message));
+        }
+        findings
+    }
+}
+
+/// outer が inner を含むかどうか。
+fn contains(outer: Span, inner: Span) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// 本体の式を持たないファイルで、最上位の文が名前を定義する範囲。
+fn exported_spans(program: &Program) -> Vec<Span> {
+    let preamble = match (&program.preamble, &program.expr) {
+        (Some(preamble), None) => preamble,
+        _ => return vec![],
+    };
+    preamble
+        .body
+        .0
+        .iter()
+        .flat_map(|stmt| match &stmt.body {
+            Statement::Let { ptn, .. } => vec![ptn.span()],
+            Statement::LetRec(bindings) => bindings
+                .iter()
+                .map(|binding| binding.body.var.span())
+                .collect(),
+            Statement::LetInline { name, .. }
+            | Statement::LetBlock { name, .. }
+            | Statement::LetMath { name, .. }
+            | Statement::Module { name, .. } => vec![name.span()],
+            Statement::LetMutable { var, .. } => vec![var.span()],
+        })
+        .collect()
+}
+
+/// `sig` を持たないモジュールの名前の範囲。
+fn open_modules(program: &Program) -> BTreeSet<Span> {
+    /// モジュールを探す Visitor。
+    struct Modules(BTreeSet<Span>);
+
+    impl Visitor for Modules {
+        fn visit_statement(&mut self, node: &Statement, span: Span) {
+            if let Statement::Module {
+                name,
+                signature: None,
+                ..
+            } = node
+            {
+                self.0.insert(name.span());
+            }
+            visit::walk_statement(self, node, span);
+        }
+    }
+
+    let mut modules = Modules(BTreeSet::new());
+    modules.visit_program(program);
+    modules.0
+}
+
+/// `!` で値を読まれるだけで、代入されることのない `let-mutable` の変数を知らせる。
+///
+/// `!` を付けずに関数に渡すなど、他の場所で代入されうる使い方をしたものは知らせない。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnassignedMutable;
+
+impl LintRule for UnassignedMutable {
+    fn id(&self) -> &'static str {
+        "unassigned-mutable"
+    }
+
+    fn description(&self) -> &'static str {
+        "`let-mutable` cells that are never assigned"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        /// `!x` で読まれる変数の範囲を集める Visitor。
+        struct Reads(BTreeSet<Span>);
+
+        impl Visitor for Reads {
+            fn visit_unary(&mut self, node: &Unary, span: Span) {
+                if let Unary::Deref(inner) = node {
+                    if let Unary::Variable { modname: None, var } = &inner.body {
+                        self.0.insert(var.span());
+                    }
+                }
+                visit::walk_unary(self, node, span);
+            }
+        }
+
+        let resolution = resolve(cx.program);
+        let mut reads = Reads(BTreeSet::new());
+        reads.visit_program(cx.program);
+        resolution
+            .bindings
+            .iter()
+            .enumerate()
+            .filter(|(id, binding)| {
+                // 一度も使われないものは unused-binding が知らせる
+                let mut references = resolution.references_to(*id).peekable();
+                binding.kind == BindingKind::LetMutable
+                    && references.peek().is_some()
+                    && references.all(|(span, _)| reads.0.contains(&span))
+            })
+            .map(|(_, binding)| {
+                let message = format!(
+                    "`{}` is never assigned; consider using `let` instead of `let-mutable`",
+                    binding.name
+                );
+                Finding::new(binding.span, message)
+            })
+            .collect()
+    }
+}
+
+/// 同じスコープにある定義や、`direct` などでモジュール名なしに見えているメンバを隠す定義を知らせる。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Shadowing;
+
+impl LintRule for Shadowing {
+    fn id(&self) -> &'static str {
+        "shadowing"
+    }
+
+    fn description(&self) -> &'static str {
+        "definitions hiding another in the same scope or an imported module member"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        let resolution = resolve(cx.program);
+        resolution
+            .bindings
+            .iter()
+            .filter_map(|binding| {
+                let shadowed = &resolution.bindings[binding.shadowed?];
+                Some(Finding::new(
+                    binding.span,
+                    shadowing_message(&resolution, binding, shadowed),
+                ))
+            })
+            .collect()
+    }
+}
+
+/// binding が shadowed を隠すことを知らせるメッセージ。
+fn shadowing_message(resolution: &Resolution, binding: &Binding, shadowed: &Binding) -> String {
+    match shadowed.module {
+        Some(module) => format!(
+            "`{}` shadows a member of module `{}` defined at line {}",
+            binding.name, resolution.bindings[module].name, shadowed.span.start.row
+        ),
+        None => format!(
+            "`{}` shadows the definition at line {} in the same scope",
+            binding.name, shadowed.span.start.row
+        ),
+    }
+}
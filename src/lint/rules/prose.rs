@@ -0,0 +1,345 @@
+//! 地の文（`regular_text`）についての規則。
+//!
+//! 報告の範囲は、水平モードの AST にある地の文のトークンの範囲から求める。
+
+use super::super::{Finding, LintContext, LintRule};
+use crate::edit::TextEdit;
+use crate::grammar::common::{Location, Ranged, Span};
+use crate::grammar::{CommandArg, Horizontal, HorizontalSingle, HorizontalToken, VerticalElement};
+use crate::visit::{self, Visitor};
+
+/// 地の文のトークン。
+#[derive(Debug, Clone, Copy)]
+struct Text<'a> {
+    /// 開始位置。
+    start: Location,
+    /// 中身。
+    body: &'a str,
+}
+
+impl<'a> Text<'a> {
+    /// 地の文のトークン token から作る。
+    fn new(token: &'a Ranged<String>) -> Self {
+        Text {
+            start: token.start,
+            body: &token.body,
+        }
+    }
+
+    /// offset バイト目の位置。
+    fn location(&self, offset: usize) -> Location {
+        self.start.advance(&self.body[..offset])
+    }
+
+    /// start バイト目から end バイト目までの範囲。
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span {
+            start: self.location(start),
+            end: self.location(end),
+        }
+    }
+}
+
+/// single の中の地の文のトークン。
+fn texts(single: &HorizontalSingle) -> impl Iterator<Item = Text<'_>> {
+    single.0.iter().filter_map(|token| match &token.body {
+        HorizontalToken::Text(text) => Some(Text::new(text)),
+        _ => None,
+    })
+}
+
+/// 空白だけからなるか、何もない水平モードのテキストかどうか。
+fn is_blank(single: &HorizontalSingle) -> bool {
+    single.0.iter().all(|token| match &token.body {
+        HorizontalToken::Text(text) => text.body.trim().is_empty(),
+        _ => false,
+    })
+}
+
+/// 水平モードのテキストごとに findings を集める Visitor。
+struct SingleVisitor<F> {
+    /// 水平モードのテキストを調べる関数。
+    check: F,
+    /// 見つけた箇所。
+    findings: Vec<Finding>,
+}
+
+impl<F: FnMut(&HorizontalSingle, &mut Vec<Finding>)> Visitor for SingleVisitor<F> {
+    fn visit_horizontal_single(&mut self, node: &HorizontalSingle, span: Span) {
+        (self.check)(node, &mut self.findings);
+        visit::walk_horizontal_single(self, node, span);
+    }
+}
+
+/// プログラム中の水平モードのテキストそれぞれを check で調べる。
+fn check_singles(
+    cx: &LintContext<'_>,
+    check: impl FnMut(&HorizontalSingle, &mut Vec<Finding>),
+) -> Vec<Finding> {
+    let mut visitor = SingleVisitor {
+        check,
+        findings: vec![],
+    };
+    visitor.visit_program(cx.program);
+    visitor.findings
+}
+
+/// 単語の間に続けて置かれた 2 つ以上の半角空白を知らせる。
+///
+/// 行頭のインデントや行末の空白は対象にしない。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DoubledSpace;
+
+impl LintRule for DoubledSpace {
+    fn id(&self) -> &'static str {
+        "doubled-space"
+    }
+
+    fn description(&self) -> &'static str {
+        "consecutive spaces between words"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        check_singles(cx, |single, findings| {
+            for text in texts(single) {
+                let mut rest = text.body;
+                while let Some(start) = rest.find("  ") {
+                    let offset = text.body.len() - rest.len();
+                    let run = rest[start..].len() - rest[start..].trim_start_matches(' ').len();
+                    let (before, after) = (&rest[..start], &rest[start + run..]);
+                    let between_words = before.chars().last().is_some_and(|c| !c.is_whitespace())
+                        && after.chars().next().is_some_and(|c| !c.is_whitespace());
+                    if between_words {
+                        let span = text.span(offset + start, offset + start + run);
+                        findings.push(
+                            Finding::new(span, "consecutive spaces in text")
+                                .with_fix(vec![TextEdit::new(span, " ")]),
+                        );
+                    }
+                    rest = after;
+                }
+            }
+        })
+    }
+}
+
+/// 読点・句点の組。最初に現れた書き方に揃える。
+const PUNCTUATIONS: [[char; 3]; 2] = [['，', '、', ','], ['．', '。', '.']];
+
+/// 全角と半角など、異なる書き方の読点・句点の混在を知らせる。
+///
+/// 文書中で最初に現れた書き方と異なるものを報告する。
+/// 半角の `,` と `.` は、英文や数値の中のものを除くため、全角の文字に続く場合のみ数える。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MixedPunctuation;
+
+impl LintRule for MixedPunctuation {
+    fn id(&self) -> &'static str {
+        "mixed-punctuation"
+    }
+
+    fn description(&self) -> &'static str {
+        "mixed full-width and half-width punctuation such as `，` and `,`"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        let mut visitor = PunctuationVisitor {
+            styles: [None, None],
+            findings: vec![],
+        };
+        visitor.visit_program(cx.program);
+        visitor.findings
+    }
+}
+
+/// [`MixedPunctuation`] の Visitor。
+struct PunctuationVisitor {
+    /// [`PUNCTUATIONS`] の組ごとの、最初に現れた書き方とその位置。
+    styles: [Option<(char, Location)>; 2],
+    /// 見つけた箇所。
+    findings: Vec<Finding>,
+}
+
+impl PunctuationVisitor {
+    /// 地の文のトークンを調べる。
+    fn check(&mut self, text: Text<'_>) {
+        let mut prev = None;
+        for (i, c) in text.body.char_indices() {
+            let after_wide = prev.is_some_and(|p: char| !p.is_ascii() && !p.is_whitespace());
+            prev = Some(c);
+            let group = match PUNCTUATIONS.iter().position(|group| group.contains(&c)) {
+                Some(group) if !c.is_ascii() || after_wide => group,
+                _ => continue,
+            };
+            let (style, first) = *self.styles[group].get_or_insert((c, text.location(i)));
+            if style == c {
+                continue;
+            }
+            let mut end = i + c.len_utf8();
+            if c.is_ascii() {
+                // 全角に直すときは後ろの空白も取り除く
+                end += text.body[end..].len() - text.body[end..].trim_start_matches(' ').len();
+            }
+            let message = format!(
+                "`{}` is mixed with `{}` used at line {}",
+                c, style, first.row
+            );
+            let edit = TextEdit::new(text.span(i, end), style.to_string());
+            self.findings
+                .push(Finding::new(text.span(i, i + c.len_utf8()), message).with_fix(vec![edit]));
+        }
+    }
+}
+
+impl Visitor for PunctuationVisitor {
+    fn visit_horizontal_token(&mut self, node: &HorizontalToken, span: Span) {
+        if let HorizontalToken::Text(text) = node {
+            self.check(Text::new(text));
+        }
+        visit::walk_horizontal_token(self, node, span);
+    }
+}
+
+/// 対応する括弧の組。
+const BRACKETS: [(char, char); 7] = [
+    ('(', ')'),
+    ('[', ']'),
+    ('（', '）'),
+    ('［', '］'),
+    ('「', '」'),
+    ('『', '』'),
+    ('【', '】'),
+];
+
+/// 地の文の括弧の対応の誤りを知らせる。
+///
+/// 水平モードのテキストごとに、インラインコマンドなどを挟んだ地の文のトークンをまとめて調べる。
+/// コマンドの引数の中はそれぞれ別に調べる。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnbalancedBracket;
+
+impl LintRule for UnbalancedBracket {
+    fn id(&self) -> &'static str {
+        "unbalanced-bracket"
+    }
+
+    fn description(&self) -> &'static str {
+        "unbalanced brackets in text"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        check_singles(cx, |single, findings| {
+            let mut open: Vec<(char, Span)> = vec![];
+            for text in texts(single) {
+                for (i, c) in text.body.char_indices() {
+                    let span = text.span(i, i + c.len_utf8());
+                    if BRACKETS.iter().any(|(left, _)| *left == c) {
+                        open.push((c, span));
+                    } else if let Some((left, _)) = BRACKETS.iter().find(|(_, right)| *right == c) {
+                        if open.last().is_some_and(|(last, _)| last == left) {
+                            open.pop();
+                        } else {
+                            findings.push(Finding::new(span, format!("unmatched `{}`", c)));
+                        }
+                    }
+                }
+            }
+            for (c, span) in open {
+                findings.push(Finding::new(span, format!("unclosed `{}`", c)));
+            }
+        })
+    }
+}
+
+/// `}` の直前の、同じ行にある空白を知らせる。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrailingWhitespace;
+
+impl LintRule for TrailingWhitespace {
+    fn id(&self) -> &'static str {
+        "trailing-whitespace"
+    }
+
+    fn description(&self) -> &'static str {
+        "whitespace before the closing `}` of inline text"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        /// [`TrailingWhitespace`] の Visitor。
+        struct Trailing(Vec<Finding>);
+
+        impl Visitor for Trailing {
+            fn visit_horizontal(&mut self, node: &Horizontal, span: Span) {
+                let last = match node {
+                    Horizontal::Single(single) if !is_blank(single) => single.0.last(),
+                    Horizontal::BulletList(bullets) => bullets
+                        .last()
+                        .and_then(|bullet| bullet.body.body.body.0.last()),
+                    _ => None,
+                };
+                // span は `{` から `}` までを含む
+                let close = Location {
+                    row: span.end.row,
+                    col: span.end.col - 1,
+                };
+                if let Some(HorizontalToken::Text(token)) = last.map(|token| &token.body) {
+                    let text = Text::new(token);
+                    let trimmed = text.body.trim_end().len();
+                    let trailing = &text.body[trimmed..];
+                    if token.end == close && !trailing.is_empty() && !trailing.contains('\n') {
+                        let span = text.span(trimmed, text.body.len());
+                        self.0.push(
+                            Finding::new(span, "whitespace before `}`")
+                                .with_fix(vec![TextEdit::new(span, "")]),
+                        );
+                    }
+                }
+                visit::walk_horizontal(self, node, span);
+            }
+        }
+
+        let mut visitor = Trailing(vec![]);
+        visitor.visit_program(cx.program);
+        visitor.0
+    }
+}
+
+/// 中身のない `+p{}` を知らせる。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmptyParagraph;
+
+impl LintRule for EmptyParagraph {
+    fn id(&self) -> &'static str {
+        "empty-paragraph"
+    }
+
+    fn description(&self) -> &'static str {
+        "`+p` paragraphs with no content"
+    }
+
+    fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+        /// [`EmptyParagraph`] の Visitor。
+        struct Paragraphs(Vec<Finding>);
+
+        impl Visitor for Paragraphs {
+            fn visit_vertical_element(&mut self, node: &VerticalElement, span: Span) {
+                if let VerticalElement::BlockCmd { name, args } = node {
+                    let empty = match args.last().map(|arg| &arg.body) {
+                        Some(CommandArg::Horizontal(Horizontal::Single(single))) => {
+                            is_blank(single)
+                        }
+                        _ => false,
+                    };
+                    if name.body == "+p" && empty {
+                        self.0.push(Finding::new(span, "empty paragraph"));
+                    }
+                }
+                visit::walk_vertical_element(self, node, span);
+            }
+        }
+
+        let mut visitor = Paragraphs(vec![]);
+        visitor.visit_program(cx.program);
+        visitor.0
+    }
+}
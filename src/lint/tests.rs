@@ -0,0 +1,505 @@
+use super::*;
+use crate::edit::apply;
+
+/// linter で text を調べた結果の、規則、行、重大度。
+fn reports(
+    linter: &Linter,
+    path: Option<&str>,
+    text: &str,
+) -> Vec<(&'static str, usize, Severity)> {
+    linter
+        .check(path.map(Path::new), text)
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.rule, r.span.start.row, r.severity))
+        .collect()
+}
+
+/// needless-bool だけを使う Linter。
+fn needless_bool_linter(config: Config) -> Linter {
+    Linter::with_rules(vec![Box::new(rules::NeedlessBool)], config).unwrap()
+}
+
+const TEXT: &str = "\
+let a = if 1 < 2 then true else false
+let b = if a then false else true
+let c = if a then 1 else 0
+in b
+";
+
+#[test]
+fn needless_bool() {
+    let linter = needless_bool_linter(Config::default());
+    let reports = linter.check(None, TEXT).unwrap();
+    assert_eq!(
+        reports
+            .iter()
+            .map(|r| (
+                r.rule,
+                r.span.start.row,
+                r.span.start.col,
+                r.message.as_str()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "needless-bool",
+                1,
+                9,
+                "this `if` expression can be written as `1 < 2`"
+            ),
+            (
+                "needless-bool",
+                2,
+                9,
+                "this `if` expression can be written as `not a`"
+            ),
+        ]
+    );
+    let edits: Vec<_> = reports.into_iter().flat_map(|r| r.fix).collect();
+    assert_eq!(
+        apply(TEXT, &edits),
+        "let a = 1 < 2\nlet b = not a\nlet c = if a then 1 else 0\nin b\n"
+    );
+}
+
+#[test]
+fn config() {
+    let config = Config::parse(
+        r#"
+[rules]
+needless-bool = "error"
+
+[per-file-ignores]
+"generated/**/*.saty" = ["needless-bool"]
+"a/**/b.saty" = ["needless-bool"]
+"#,
+    )
+    .unwrap();
+    let linter = needless_bool_linter(config);
+    let error = ("needless-bool", 1, Severity::Error);
+    assert_eq!(reports(&linter, None, TEXT)[0], error);
+    assert_eq!(reports(&linter, Some("doc/a.saty"), TEXT)[0], error);
+    assert!(reports(&linter, Some("/tmp/generated/a.saty"), TEXT).is_empty());
+    assert!(reports(&linter, Some("generated/x/y/a.saty"), TEXT).is_empty());
+    assert_eq!(reports(&linter, Some("generated/a.satyh"), TEXT).len(), 2);
+    // `**/` はディレクトリ全体にのみ一致する
+    assert!(reports(&linter, Some("a/b.saty"), TEXT).is_empty());
+    assert!(reports(&linter, Some("a/x/y/b.saty"), TEXT).is_empty());
+    assert_eq!(reports(&linter, Some("a/xb.saty"), TEXT).len(), 2);
+    assert_eq!(reports(&linter, Some("a/x/yb.saty"), TEXT).len(), 2);
+
+    let config = Config::parse("[rules]\nneedless-bool = false").unwrap();
+    let linter = needless_bool_linter(config);
+    assert!(linter.rules().all(|rule| linter.severity(rule).is_none()));
+    assert!(reports(&linter, None, TEXT).is_empty());
+}
+
+#[test]
+fn config_errors() {
+    let error = |text| {
+        Config::parse(text)
+            .and_then(Linter::new)
+            .err()
+            .unwrap()
+            .to_string()
+    };
+    assert!(error("[rules").starts_with("invalid TOML"));
+    assert_eq!(error("width = 80"), "unknown key `width`");
+    assert_eq!(error("[rules]\nfoo = true"), "unknown lint rule `foo`");
+    assert_eq!(
+        error("[rules]\nneedless-bool = \"info\""),
+        "invalid value for `rules.needless-bool`: expected \"off\", \"warning\", \"error\" or a boolean"
+    );
+    assert_eq!(
+        error("[per-file-ignores]\n\"*.saty\" = \"needless-bool\""),
+        "invalid value for `per-file-ignores.\"*.saty\"`: expected an array of rule IDs"
+    );
+}
+
+#[test]
+fn allow_comments() {
+    let linter = needless_bool_linter(Config::default());
+    let text = "\
+% lint: allow(needless-bool)
+let a = if 1 < 2 then true else false
+let b = if a then false else true % lint: allow(other, needless-bool)
+let c = if a then false else true % lint: allow(other)
+let d = `% lint: allow(needless-bool)`
+let e = if a then false else true
+in b
+";
+    let rows: Vec<_> = reports(&linter, None, text)
+        .into_iter()
+        .map(|(_, row, _)| row)
+        .collect();
+    assert_eq!(rows, vec![4, 6]);
+}
+
+#[test]
+fn parse_error() {
+    let linter = Linter::new(Config::default()).unwrap();
+    let d = linter.check(None, "let = 1").unwrap_err();
+    assert_eq!(d.severity, Severity::Error);
+}
+
+/// 組み込みの規則で text を調べた結果の、規則、位置、メッセージ。
+fn findings(text: &str) -> Vec<(&'static str, usize, usize, String)> {
+    Linter::new(Config::default())
+        .unwrap()
+        .check(None, text)
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.rule, r.span.start.row, r.span.start.col, r.message))
+        .collect()
+}
+
+#[test]
+fn unused_bindings() {
+    let text = "\
+let f x y = x
+let (a, b) = (1, 2)
+let-inline ctx \\foo = {}
+let-block ctx +bar = '<>
+let g opt = match opt with
+  | Some(v) -> 1
+  | None -> a
+in
+g (f 1 2)
+";
+    assert_eq!(
+        findings(text),
+        vec![
+            (
+                "unused-binding",
+                2,
+                9,
+                "`b` is defined but never used".to_owned()
+            ),
+            (
+                "unused-binding",
+                3,
+                16,
+                "`\\foo` is defined but never used".to_owned()
+            ),
+            (
+                "unused-binding",
+                4,
+                15,
+                "`+bar` is defined but never used".to_owned()
+            ),
+            (
+                "unused-binding",
+                6,
+                10,
+                "`v` is bound in the pattern but never used".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn unused_bindings_exported() {
+    // 本体の式がなければ最上位の定義は外から使われうる
+    let text = "\
+module M = struct
+  let x = 1
+  let y = let z = 2 in x
+end
+module N : sig
+  val a : int
+end = struct
+  let a = 1
+  let b = 2
+end
+let f x = x
+";
+    assert_eq!(
+        findings(text),
+        vec![
+            (
+                "unused-binding",
+                3,
+                15,
+                "`z` is defined but never used".to_owned()
+            ),
+            (
+                "unused-binding",
+                9,
+                7,
+                "`b` is defined but never used".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn unassigned_mutables() {
+    let text = "\
+let-mutable a <- 0
+let-mutable b <- 0
+let-mutable c <- 0
+let-mutable d <- 0
+let f () = (b <- 1)
+let g r = (r <- 2)
+let h () = let-mutable n <- 0 in !n
+in
+(f (), g d, h (), !a, !b)
+";
+    assert_eq!(
+        findings(text),
+        vec![
+            (
+                "unassigned-mutable",
+                1,
+                13,
+                "`a` is never assigned; consider using `let` instead of `let-mutable`".to_owned()
+            ),
+            (
+                "unused-binding",
+                3,
+                13,
+                "`c` is defined but never used".to_owned()
+            ),
+            (
+                "unassigned-mutable",
+                7,
+                24,
+                "`n` is never assigned; consider using `let` instead of `let-mutable`".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn shadowing() {
+    let text = "\
+module M = struct
+  let x = 1
+end
+let y = 1
+let y = y + 1
+let f y = y
+in
+f M.(let x = 2 in x + y)
+";
+    assert_eq!(
+        findings(text),
+        vec![
+            (
+                "shadowing",
+                5,
+                5,
+                "`y` shadows the definition at line 4 in the same scope".to_owned()
+            ),
+            (
+                "shadowing",
+                8,
+                10,
+                "`x` shadows a member of module `M` defined at line 2".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn doubled_spaces() {
+    let text = "'<\n  +p{\n    foo  bar \\emph{a   b}  \n    baz\n  }\n>\n";
+    assert_eq!(
+        findings(text),
+        vec![
+            (
+                "doubled-space",
+                3,
+                8,
+                "consecutive spaces in text".to_owned()
+            ),
+            (
+                "doubled-space",
+                3,
+                21,
+                "consecutive spaces in text".to_owned()
+            ),
+        ]
+    );
+    let linter = Linter::new(Config::default()).unwrap();
+    let edits: Vec<_> = linter
+        .check(None, text)
+        .unwrap()
+        .into_iter()
+        .flat_map(|r| r.fix)
+        .collect();
+    assert_eq!(
+        apply(text, &edits),
+        "'<\n  +p{\n    foo bar \\emph{a b}  \n    baz\n  }\n>\n"
+    );
+}
+
+#[test]
+fn mixed_punctuation() {
+    let text = "{これは，ペン．\\emph{あれは、ペン, それも}。\nAt 1.5pt, fine.}";
+    let reports = Linter::new(Config::default())
+        .unwrap()
+        .check(None, text)
+        .unwrap();
+    assert_eq!(
+        reports
+            .iter()
+            .map(|r| (
+                r.rule,
+                r.span.start.row,
+                r.span.start.col,
+                r.message.as_str()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "mixed-punctuation",
+                1,
+                18,
+                "`、` is mixed with `，` used at line 1"
+            ),
+            (
+                "mixed-punctuation",
+                1,
+                21,
+                "`,` is mixed with `，` used at line 1"
+            ),
+            (
+                "mixed-punctuation",
+                1,
+                27,
+                "`。` is mixed with `．` used at line 1"
+            ),
+        ]
+    );
+    let edits: Vec<_> = reports.into_iter().flat_map(|r| r.fix).collect();
+    assert_eq!(
+        apply(text, &edits),
+        "{これは，ペン．\\emph{あれは，ペン，それも}．\nAt 1.5pt, fine.}"
+    );
+}
+
+#[test]
+fn unbalanced_brackets() {
+    let text = "{（see \\ref(`a`); and [1]） \\emph{「a)} (b}";
+    assert_eq!(
+        findings(text),
+        vec![
+            ("unbalanced-bracket", 1, 33, "unclosed `「`".to_owned()),
+            ("unbalanced-bracket", 1, 35, "unmatched `)`".to_owned()),
+            ("unbalanced-bracket", 1, 38, "unclosed `(`".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn trailing_whitespace() {
+    let text = "'<\n  +p{foo \\emph{bar }  }\n  +p{\n    baz\n  }\n  +listing{* a * b }\n>\n";
+    assert_eq!(
+        findings(text),
+        vec![
+            (
+                "trailing-whitespace",
+                2,
+                19,
+                "whitespace before `}`".to_owned()
+            ),
+            (
+                "trailing-whitespace",
+                2,
+                21,
+                "whitespace before `}`".to_owned()
+            ),
+            (
+                "trailing-whitespace",
+                6,
+                19,
+                "whitespace before `}`".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn empty_paragraphs() {
+    let text = "'<\n  +p{}\n  +p{ % comment\n  }\n  +section{}<>\n  +p{\\foo;}\n>\n";
+    assert_eq!(
+        findings(text),
+        vec![
+            ("empty-paragraph", 2, 3, "empty paragraph".to_owned()),
+            ("empty-paragraph", 3, 3, "empty paragraph".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn fix() {
+    let linter = Linter::new(Config::default()).unwrap();
+    let text = "\
+let a = if 1 < 2 then true else false
+let b = if (if a then false else true) then false else true
+in
+{foo  bar \\emph{baz }}
+";
+    let fixed = linter.fix(None, text).unwrap();
+    // 重なる修正は、1 回目の修正の後に改めて適用する
+    assert_eq!(
+        fixed.text,
+        "let a = 1 < 2\nlet b = not (not a)\nin\n{foo bar \\emph{baz}}\n"
+    );
+    assert_eq!(
+        fixed
+            .applied
+            .iter()
+            .map(|r| (r.rule, r.span.start.row))
+            .collect::<Vec<_>>(),
+        vec![
+            ("needless-bool", 1),
+            ("needless-bool", 2),
+            ("doubled-space", 4),
+            ("trailing-whitespace", 4),
+            ("needless-bool", 2),
+        ]
+    );
+    // 自動修正のない報告は残る
+    assert_eq!(
+        fixed
+            .remaining
+            .iter()
+            .map(|r| (r.rule, r.span.start.row))
+            .collect::<Vec<_>>(),
+        vec![("unused-binding", 2)]
+    );
+    assert!(matches!(
+        linter.fix(None, "let = 1"),
+        Err(FixError::Parse(_))
+    ));
+}
+
+#[test]
+fn fix_unparsable() {
+    /// 壊れた修正を提案する規則。
+    struct Breaking;
+
+    impl LintRule for Breaking {
+        fn id(&self) -> &'static str {
+            "breaking"
+        }
+
+        fn description(&self) -> &'static str {
+            "breaks the source"
+        }
+
+        fn check(&self, cx: &LintContext<'_>) -> Vec<Finding> {
+            let span = cx.program.expr.as_ref().unwrap().span();
+            vec![Finding::new(span, "broken").with_fix(vec![TextEdit::new(span, "in")])]
+        }
+    }
+
+    let linter = Linter::with_rules(vec![Box::new(Breaking)], Config::default()).unwrap();
+    match linter.fix(None, "1 + 2") {
+        Err(FixError::Unparsable { text, .. }) => assert_eq!(text, "in"),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
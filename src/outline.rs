@@ -0,0 +1,264 @@
+//! Document structure for editors.
+//!
+//! エディタの機能（アウトライン表示、折り畳み、選択範囲の拡大）に使う情報を AST から集める。
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::{Location, Ranged, Span};
+use crate::grammar::{
+    ApplicationArg, Argument, CommandArg, Constraint, Expr, Header, Horizontal, HorizontalBullet,
+    HorizontalSingle, HorizontalToken, List, Literal, MatchArm, Math, MathCmdArg, MathGroup,
+    MathSingle, MathToken, MathUnary, Pattern, Preamble, Program, Record, RecordUnit, SigStmt,
+    Signature, Stage, Statement, Struct, Tuple, TypeExpr, TypeList, TypeListUnit, TypeProd,
+    TypeRecord, TypeRecordUnit, TypeUnary, Unary, ValName, Variable, Vertical, VerticalElement,
+};
+use crate::printer::print;
+use crate::visit::{self, Visitor};
+
+/// シンボルの種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    /// `let`
+    Let,
+    /// `let-inline`
+    LetInline,
+    /// `let-block`
+    LetBlock,
+    /// `let-math`
+    LetMath,
+    /// `module`
+    Module,
+}
+
+/// 定義されたシンボル。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// 名前。`let` で変数以外のパターンを束縛する場合はパターンを文字列にしたもの。
+    pub name: String,
+    /// 種類。
+    pub kind: SymbolKind,
+    /// 定義全体の範囲。
+    pub span: Span,
+    /// 名前の範囲。
+    pub name_span: Span,
+    /// モジュールの中で定義されたシンボル。
+    pub children: Vec<Symbol>,
+}
+
+/// プリアンブルで定義されたシンボルを、モジュールの入れ子構造を保って返す。
+pub fn document_symbols(program: &Program) -> Vec<Symbol> {
+    match &program.preamble {
+        Some(preamble) => statement_symbols(&preamble.body.0),
+        None => vec![],
+    }
+}
+
+/// 文の列で定義されたシンボル。
+fn statement_symbols(stmts: &[Ranged<Statement>]) -> Vec<Symbol> {
+    stmts.iter().flat_map(statement_symbol).collect()
+}
+
+/// 文で定義されたシンボル。`let-rec` では定義した関数ごとに返す。
+fn statement_symbol(stmt: &Ranged<Statement>) -> Vec<Symbol> {
+    let symbol = |kind, name: &Ranged<String>| Symbol {
+        name: name.body.clone(),
+        kind,
+        span: stmt.span(),
+        name_span: name.span(),
+        children: vec![],
+    };
+    let symbol = match &stmt.body {
+        Statement::Let { ptn, .. } => {
+            let name = match &ptn.body {
+                Pattern::Variable(var) => var.name.clone(),
+                ptn => print(ptn),
+            };
+            Symbol {
+                name,
+                kind: SymbolKind::Let,
+                span: stmt.span(),
+                name_span: ptn.span(),
+                children: vec![],
+            }
+        }
+        Statement::LetRec(bindings) => {
+            return bindings
+                .iter()
+                .map(|binding| Symbol {
+                    name: binding.body.var.body.name.clone(),
+                    kind: SymbolKind::Let,
+                    span: binding.span(),
+                    name_span: binding.body.var.span(),
+                    children: vec![],
+                })
+                .collect();
+        }
+        Statement::LetMutable { var, .. } => Symbol {
+            name: var.body.name.clone(),
+            kind: SymbolKind::Let,
+            span: stmt.span(),
+            name_span: var.span(),
+            children: vec![],
+        },
+        Statement::LetInline { name, .. } => symbol(SymbolKind::LetInline, name),
+        Statement::LetBlock { name, .. } => symbol(SymbolKind::LetBlock, name),
+        Statement::LetMath { name, .. } => symbol(SymbolKind::LetMath, name),
+        Statement::Module { name, body, .. } => Symbol {
+            children: statement_symbols(&body.body.0),
+            ..symbol(SymbolKind::Module, name)
+        },
+    };
+    vec![symbol]
+}
+
+/// 折り畳み範囲の種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FoldingKind {
+    /// `struct ... end`
+    Struct,
+    /// `sig ... end`
+    Sig,
+    /// `'< ... >` や `< ... >`
+    Vertical,
+    /// `{ ... }`
+    Horizontal,
+}
+
+/// 折り畳める範囲を、開始位置の順に返す。1 行に収まる範囲は含めない。
+pub fn folding_ranges(program: &Program) -> Vec<(Span, FoldingKind)> {
+    /// 折り畳み範囲を集める Visitor。
+    struct Folding(Vec<(Span, FoldingKind)>);
+
+    impl Folding {
+        /// 複数行にわたる範囲を追加する。
+        fn push(&mut self, span: Span, kind: FoldingKind) {
+            if span.start.row < span.end.row {
+                self.0.push((span, kind));
+            }
+        }
+    }
+
+    impl Visitor for Folding {
+        fn visit_struct(&mut self, node: &Struct, span: Span) {
+            self.push(span, FoldingKind::Struct);
+            visit::walk_struct(self, node, span);
+        }
+        fn visit_signature(&mut self, node: &Signature, span: Span) {
+            self.push(span, FoldingKind::Sig);
+            visit::walk_signature(self, node, span);
+        }
+        fn visit_vertical(&mut self, node: &Vertical, span: Span) {
+            self.push(span, FoldingKind::Vertical);
+            visit::walk_vertical(self, node, span);
+        }
+        fn visit_horizontal(&mut self, node: &Horizontal, span: Span) {
+            self.push(span, FoldingKind::Horizontal);
+            visit::walk_horizontal(self, node, span);
+        }
+    }
+
+    let mut folding = Folding(vec![]);
+    folding.visit_program(program);
+    folding
+        .0
+        .sort_by_key(|(span, _)| (span.start, std::cmp::Reverse(span.end)));
+    folding.0.dedup_by_key(|(span, _)| *span);
+    folding.0
+}
+
+/// loc を含む構文要素の範囲を、内側のものから順に返す。
+pub fn selection_ranges(program: &Program, loc: Location) -> Vec<Span> {
+    /// loc を含む範囲を集める Visitor。
+    struct Selection {
+        loc: Location,
+        spans: Vec<Span>,
+    }
+
+    macro_rules! record {
+        ($($visit:ident($ty:ty) => $walk:ident;)*) => {
+            $(
+                fn $visit(&mut self, node: &$ty, span: Span) {
+                    if span.contains(self.loc) {
+                        self.spans.push(span);
+                        visit::$walk(self, node, span);
+                    }
+                }
+            )*
+        };
+    }
+
+    macro_rules! record_leaf {
+        ($($visit:ident($ty:ty);)*) => {
+            $(
+                fn $visit(&mut self, _node: &$ty, span: Span) {
+                    if span.contains(self.loc) {
+                        self.spans.push(span);
+                    }
+                }
+            )*
+        };
+    }
+
+    impl Visitor for Selection {
+        record! {
+            visit_preamble(Preamble) => walk_preamble;
+            visit_statement(Statement) => walk_statement;
+            visit_argument(Argument) => walk_argument;
+            visit_signature(Signature) => walk_signature;
+            visit_sig_stmt(SigStmt) => walk_sig_stmt;
+            visit_struct(Struct) => walk_struct;
+            visit_constraint(Constraint) => walk_constraint;
+            visit_type_expr(TypeExpr) => walk_type_expr;
+            visit_type_prod(TypeProd) => walk_type_prod;
+            visit_type_unary(TypeUnary) => walk_type_unary;
+            visit_type_list(TypeList) => walk_type_list;
+            visit_type_list_unit(TypeListUnit) => walk_type_list_unit;
+            visit_type_record(TypeRecord) => walk_type_record;
+            visit_type_record_unit(TypeRecordUnit) => walk_type_record_unit;
+            visit_pattern(Pattern) => walk_pattern;
+            visit_expr(Expr) => walk_expr;
+            visit_match_arm(MatchArm) => walk_match_arm;
+            visit_application_arg(ApplicationArg) => walk_application_arg;
+            visit_unary(Unary) => walk_unary;
+            visit_record(Record) => walk_record;
+            visit_record_unit(RecordUnit) => walk_record_unit;
+            visit_list(List) => walk_list;
+            visit_tuple(Tuple) => walk_tuple;
+            visit_command_arg(CommandArg) => walk_command_arg;
+            visit_vertical(Vertical) => walk_vertical;
+            visit_vertical_element(VerticalElement) => walk_vertical_element;
+            visit_horizontal(Horizontal) => walk_horizontal;
+            visit_horizontal_bullet(HorizontalBullet) => walk_horizontal_bullet;
+            visit_horizontal_single(HorizontalSingle) => walk_horizontal_single;
+            visit_horizontal_token(HorizontalToken) => walk_horizontal_token;
+            visit_math(Math) => walk_math;
+            visit_math_single(MathSingle) => walk_math_single;
+            visit_math_token(MathToken) => walk_math_token;
+            visit_math_group(MathGroup) => walk_math_group;
+            visit_math_unary(MathUnary) => walk_math_unary;
+            visit_math_cmd_arg(MathCmdArg) => walk_math_cmd_arg;
+        }
+        record_leaf! {
+            visit_stage(Stage);
+            visit_header(Header);
+            visit_val_name(ValName);
+            visit_variable(Variable);
+            visit_literal(Literal);
+        }
+    }
+
+    let mut selection = Selection { loc, spans: vec![] };
+    selection.visit_program(program);
+    // 外側の構文要素から先に訪れるため、逆順にすると内側から並ぶ
+    let mut spans: Vec<Span> = vec![];
+    for span in selection.spans.into_iter().rev() {
+        let nested = spans.last().is_none_or(|inner| {
+            span != *inner && span.start <= inner.start && inner.end <= span.end
+        });
+        if nested {
+            spans.push(span);
+        }
+    }
+    spans
+}
@@ -0,0 +1,88 @@
+use super::*;
+use crate::grammar::common::Grammar;
+
+/// 1 行目の範囲。
+fn span(start: usize, end: usize) -> Span {
+    Span {
+        start: Location { row: 1, col: start },
+        end: Location { row: 1, col: end },
+    }
+}
+
+/// 範囲。
+fn span2((r1, c1): (usize, usize), (r2, c2): (usize, usize)) -> Span {
+    Span {
+        start: Location { row: r1, col: c1 },
+        end: Location { row: r2, col: c2 },
+    }
+}
+
+#[test]
+fn symbols() {
+    let text = "\
+let x = 1
+let (a, b) = (1, 2)
+let-inline ctx \\emph x = x
+module M : sig
+  val y : int
+end = struct
+  let-block ctx +p x = x
+end
+";
+    let program = Program::parse(text).unwrap();
+    let symbols = document_symbols(&program);
+    let summary: Vec<_> = symbols
+        .iter()
+        .map(|s| (s.name.as_str(), s.kind, s.name_span))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("x", SymbolKind::Let, span2((1, 5), (1, 6))),
+            ("(a, b)", SymbolKind::Let, span2((2, 5), (2, 11))),
+            ("\\emph", SymbolKind::LetInline, span2((3, 16), (3, 21))),
+            ("M", SymbolKind::Module, span2((4, 8), (4, 9))),
+        ]
+    );
+    assert_eq!(symbols[3].span, span2((4, 1), (8, 4)));
+    let children = &symbols[3].children;
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].name, "+p");
+    assert_eq!(children[0].kind, SymbolKind::LetBlock);
+}
+
+#[test]
+fn folding() {
+    let text = "\
+module M : sig
+  val y : int
+end = struct
+  let a = {x}
+  let b = '<
+    +p{
+      a
+    }
+  >
+end
+";
+    let program = Program::parse(text).unwrap();
+    assert_eq!(
+        folding_ranges(&program),
+        vec![
+            (span2((1, 12), (3, 4)), FoldingKind::Sig),
+            (span2((3, 7), (10, 4)), FoldingKind::Struct),
+            (span2((5, 11), (9, 4)), FoldingKind::Vertical),
+            (span2((6, 7), (8, 6)), FoldingKind::Horizontal),
+        ]
+    );
+}
+
+#[test]
+fn selection() {
+    let program = Program::parse("f (1 + 2) x").unwrap();
+    // `1` の上
+    assert_eq!(
+        selection_ranges(&program, Location { row: 1, col: 4 }),
+        vec![span(4, 5), span(4, 9), span(3, 10), span(1, 12)]
+    );
+}
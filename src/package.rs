@@ -0,0 +1,266 @@
+//! Package dependency graph.
+//!
+//! `@require:` と `@import:` で読み込まれるファイルを探し、依存関係のグラフを作る。
+//!
+//! - `@require: pkg` はライブラリのディレクトリ（`~/.satysfi/dist/packages` など）から
+//!   `pkg.satyh` か `pkg.satyg` を探す。
+//! - `@import: path` は読み込む側のファイルがあるディレクトリから
+//!   `path.satyh` か `path.satyg` を探す。
+
+#[cfg(test)]
+mod tests;
+
+use crate::diagnostic::Diagnostic;
+use crate::grammar::common::{Grammar, Span};
+use crate::grammar::{Header, Program};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 探すファイルの拡張子。先にあるものを優先する。
+pub const EXTENSIONS: [&str; 2] = ["satyh", "satyg"];
+
+/// SATySFi が標準で用いるライブラリのディレクトリ。
+pub fn default_roots() -> Vec<PathBuf> {
+    let mut roots = vec![];
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home).join(".satysfi");
+        roots.push(home.join("local/packages"));
+        roots.push(home.join("dist/packages"));
+    }
+    roots.push(PathBuf::from("/usr/local/share/satysfi/dist/packages"));
+    roots.push(PathBuf::from("/usr/share/satysfi/dist/packages"));
+    roots
+}
+
+/// `@require:` や `@import:` の指すファイルを探すもの。
+#[derive(Debug, Clone, Default)]
+pub struct PackageResolver {
+    /// `@require:` で探すディレクトリ。先にあるものを優先する。
+    roots: Vec<PathBuf>,
+}
+
+/// ファイル間の依存関係。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// 読み込む側のファイル。
+    pub from: usize,
+    /// 読み込まれるファイル。
+    pub to: usize,
+    /// 読み込む側のファイルでのパッケージ名の範囲。
+    pub span: Span,
+}
+
+/// 依存関係のグラフ。
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// グラフに含まれるファイル。最初のものが起点となったファイルである。
+    pub files: Vec<PathBuf>,
+    /// ファイル間の依存関係。ファイルごとに、ヘッダに書かれた順に並ぶ。
+    pub dependencies: Vec<Dependency>,
+    /// 循環している依存関係。ファイルの列で表し、最後のファイルは最初のファイルに依存する。
+    pub cycles: Vec<Vec<usize>>,
+    /// 見つからないパッケージ、パースできないファイル、循環などの診断と、それが生じたファイル。
+    pub diagnostics: Vec<(usize, Diagnostic)>,
+}
+
+impl PackageResolver {
+    /// `@require:` で roots を探すものを作る。
+    pub fn new(roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        PackageResolver {
+            roots: roots.into_iter().collect(),
+        }
+    }
+
+    /// header の指すファイルを探す。dir は header を書いたファイルのあるディレクトリ。
+    pub fn locate(&self, header: &Header, dir: &Path) -> Option<PathBuf> {
+        let (name, dirs) = match header {
+            Header::Require(name) => (name, self.roots.iter().map(PathBuf::as_path).collect()),
+            Header::Import(name) => (name, vec![dir]),
+        };
+        let name = name.body.trim();
+        dirs.into_iter()
+            .flat_map(|dir| {
+                EXTENSIONS
+                    .iter()
+                    .map(move |ext| dir.join(format!("{}.{}", name, ext)))
+            })
+            .find(|path| path.is_file())
+    }
+
+    /// entry から推移的に読み込まれるファイルの依存関係のグラフを作る。
+    ///
+    /// entry 自体が読めない場合のみエラーを返す。
+    /// 読み込まれるファイルの問題は [`DependencyGraph::diagnostics`] に記録する。
+    pub fn dependency_graph(&self, entry: &Path) -> io::Result<DependencyGraph> {
+        let text = std::fs::read_to_string(entry)?;
+        let mut builder = GraphBuilder {
+            resolver: self,
+            graph: DependencyGraph::default(),
+            indices: HashMap::new(),
+        };
+        builder.add_file(entry.to_owned());
+        builder.read(0, &text);
+        let mut next = 1;
+        while next < builder.graph.files.len() {
+            let path = builder.graph.files[next].clone();
+            match std::fs::read_to_string(&path) {
+                Ok(text) => builder.read(next, &text),
+                Err(e) => builder.unreadable(next, &path, &e),
+            }
+            next += 1;
+        }
+        let mut graph = builder.graph;
+        graph.find_cycles();
+        Ok(graph)
+    }
+}
+
+impl DependencyGraph {
+    /// file が直接読み込むファイル。
+    pub fn dependencies_of(&self, file: usize) -> impl Iterator<Item = &Dependency> {
+        self.dependencies.iter().filter(move |dep| dep.from == file)
+    }
+
+    /// file で生じた診断。
+    pub fn diagnostics_of(&self, file: usize) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(move |(f, _)| *f == file)
+            .map(|(_, diagnostic)| diagnostic)
+    }
+
+    /// 深さ優先探索で循環を探し、循環を閉じる依存関係の位置に診断を付ける。
+    pub(crate) fn find_cycles(&mut self) {
+        /// 探索中のファイルの状態。
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            /// まだ訪れていない。
+            New,
+            /// 探索中のパス上にある。
+            OnPath,
+            /// 探索を終えた。
+            Done,
+        }
+
+        /// file から深さ優先で探索する。
+        fn visit(
+            graph: &DependencyGraph,
+            file: usize,
+            states: &mut [State],
+            path: &mut Vec<usize>,
+            found: &mut Vec<(Vec<usize>, Span)>,
+        ) {
+            states[file] = State::OnPath;
+            path.push(file);
+            for dep in graph.dependencies_of(file) {
+                match states[dep.to] {
+                    State::New => visit(graph, dep.to, states, path, found),
+                    State::OnPath => {
+                        let start = path.iter().position(|&f| f == dep.to).unwrap();
+                        found.push((path[start..].to_vec(), dep.span));
+                    }
+                    State::Done => {}
+                }
+            }
+            path.pop();
+            states[file] = State::Done;
+        }
+
+        let mut states = vec![State::New; self.files.len()];
+        let mut found = vec![];
+        for file in 0..self.files.len() {
+            if states[file] == State::New {
+                visit(self, file, &mut states, &mut vec![], &mut found);
+            }
+        }
+        for (cycle, span) in found {
+            let names: Vec<_> = cycle
+                .iter()
+                .chain(cycle.first())
+                .map(|&f| self.files[f].display().to_string())
+                .collect();
+            let last = *cycle.last().unwrap();
+            let message = format!("import cycle: {}", names.join(" -> "));
+            self.diagnostics
+                .push((last, Diagnostic::error(span, message)));
+            self.cycles.push(cycle);
+        }
+    }
+}
+
+/// グラフを作る途中の状態。
+struct GraphBuilder<'a> {
+    /// ファイルを探すもの。
+    resolver: &'a PackageResolver,
+    /// 作成中のグラフ。
+    graph: DependencyGraph,
+    /// 正規化したパスからファイルの番号への対応。
+    indices: HashMap<PathBuf, usize>,
+}
+
+impl GraphBuilder<'_> {
+    /// ファイルをグラフに加え、その番号を返す。すでにあれば何もしない。
+    fn add_file(&mut self, path: PathBuf) -> usize {
+        // 同じファイルを別のパスで指すことがあるため、正規化して比べる
+        let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+        let files = &mut self.graph.files;
+        *self.indices.entry(key).or_insert_with(|| {
+            files.push(path);
+            files.len() - 1
+        })
+    }
+
+    /// file の中身 text をパースし、依存するファイルを加える。
+    fn read(&mut self, file: usize, text: &str) {
+        let program = match Program::parse(text) {
+            Ok(program) => program,
+            Err(e) => {
+                self.graph.diagnostics.push((file, e.into()));
+                return;
+            }
+        };
+        let dir = self.graph.files[file]
+            .parent()
+            .map(Path::to_owned)
+            .unwrap_or_default();
+        for header in &program.header {
+            let name = match &header.body {
+                Header::Require(name) | Header::Import(name) => name,
+            };
+            match self.resolver.locate(&header.body, &dir) {
+                Some(path) => {
+                    let to = self.add_file(path);
+                    self.graph.dependencies.push(Dependency {
+                        from: file,
+                        to,
+                        span: name.span(),
+                    });
+                }
+                None => {
+                    let message = format!("package not found: {}", name.body.trim());
+                    self.graph
+                        .diagnostics
+                        .push((file, Diagnostic::error(name.span(), message)));
+                }
+            }
+        }
+    }
+
+    /// file を読めなかったことを、読み込む側のヘッダの位置に記録する。
+    fn unreadable(&mut self, file: usize, path: &Path, e: &io::Error) {
+        let importers: Vec<_> = self
+            .graph
+            .dependencies
+            .iter()
+            .filter(|dep| dep.to == file)
+            .map(|dep| (dep.from, dep.span))
+            .collect();
+        for (from, span) in importers {
+            let message = format!("cannot read {}: {}", path.display(), e);
+            self.graph
+                .diagnostics
+                .push((from, Diagnostic::error(span, message)));
+        }
+    }
+}
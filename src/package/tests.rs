@@ -0,0 +1,108 @@
+use super::*;
+use std::fs;
+
+/// テストごとの一時ディレクトリに files を書き出し、そのディレクトリを返す。
+fn setup(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "satysfi-peg-parser-package-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, text) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+    dir
+}
+
+/// グラフ中のファイルを dir からの相対パスで表したもの。
+fn names(graph: &DependencyGraph, dir: &Path) -> Vec<String> {
+    graph
+        .files
+        .iter()
+        .map(|f| f.strip_prefix(dir).unwrap().display().to_string())
+        .collect()
+}
+
+#[test]
+fn transitive_dependencies() {
+    let dir = setup(
+        "transitive",
+        &[
+            ("doc.saty", "@require: stdja\n@import: local/util\n'<>"),
+            ("local/util.satyg", "@import: ../lib/common\nlet x = 1\n"),
+            ("lib/common.satyh", "let y = 2\n"),
+            ("lib/common.satyg", "let y = 3\n"),
+            ("packages/stdja.satyh", "@require: list\nlet z = 3\n"),
+            ("packages/list.satyg", "let w = 4\n"),
+        ],
+    );
+    let resolver = PackageResolver::new(vec![dir.join("none"), dir.join("packages")]);
+    let graph = resolver.dependency_graph(&dir.join("doc.saty")).unwrap();
+    assert_eq!(
+        names(&graph, &dir),
+        vec![
+            "doc.saty",
+            "packages/stdja.satyh",
+            "local/util.satyg",
+            "packages/list.satyg",
+            "local/../lib/common.satyh",
+        ]
+    );
+    let edges: Vec<_> = graph.dependencies.iter().map(|d| (d.from, d.to)).collect();
+    assert_eq!(edges, vec![(0, 1), (0, 2), (1, 3), (2, 4)]);
+    assert_eq!(graph.dependencies[1].span.start.row, 2);
+    assert!(graph.cycles.is_empty());
+    assert!(graph.diagnostics.is_empty());
+}
+
+#[test]
+fn missing_packages_and_syntax_errors() {
+    let dir = setup(
+        "missing",
+        &[
+            ("doc.saty", "@require: nothing\n@import: broken\n'<>"),
+            ("broken.satyh", "let = 1\n"),
+        ],
+    );
+    let graph = PackageResolver::new(vec![dir.clone()])
+        .dependency_graph(&dir.join("doc.saty"))
+        .unwrap();
+    assert_eq!(graph.files.len(), 2);
+    let missing: Vec<_> = graph.diagnostics_of(0).collect();
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].message, "package not found: nothing");
+    assert_eq!(missing[0].span.start.row, 1);
+    assert_eq!(missing[0].span.start.col, 11);
+    assert_eq!(graph.diagnostics_of(1).count(), 1);
+    assert!(PackageResolver::default()
+        .dependency_graph(&dir.join("none.saty"))
+        .is_err());
+}
+
+#[test]
+fn cycles() {
+    let dir = setup(
+        "cycles",
+        &[
+            ("doc.saty", "@import: a\n'<>"),
+            ("a.satyh", "@import: b\nlet a = 1\n"),
+            ("b.satyh", "@import: c\n@import: a\nlet b = 1\n"),
+            ("c.satyh", "@import: c\nlet c = 1\n"),
+        ],
+    );
+    let graph = PackageResolver::default()
+        .dependency_graph(&dir.join("doc.saty"))
+        .unwrap();
+    assert_eq!(
+        names(&graph, &dir),
+        vec!["doc.saty", "a.satyh", "b.satyh", "c.satyh"]
+    );
+    assert_eq!(graph.cycles, vec![vec![3], vec![1, 2]]);
+    let (file, diagnostic) = &graph.diagnostics[1];
+    assert_eq!(*file, 2);
+    assert_eq!(diagnostic.span.start.row, 2);
+    assert!(diagnostic.message.starts_with("import cycle: "));
+}
@@ -1,5 +1,6 @@
 //! pest parser for SATySFi.
 
+/// pest によって生成されるパーサ。
 mod peg_parser {
     /// pest parser struct for SATySFi.
     #[derive(Parser)]
@@ -12,8 +13,23 @@ use pest::iterators::Pair as PestPair;
 use pest::iterators::Pairs as PestPairs;
 
 pub use peg_parser::SatysfiParser;
+/// SATySFi の構文規則。
 pub type Rule = SatysfiRule;
+/// パース結果の構文要素。
 pub type Pair<'i> = PestPair<'i, Rule>;
+/// パース結果の構文要素の列。
 pub type Pairs<'i> = PestPairs<'i, Rule>;
 
+/// 先頭の空白とコメントを読み飛ばす。
+pub fn skip_trivia(mut text: &str) -> &str {
+    loop {
+        let trimmed = text.trim_start();
+        match trimmed.strip_prefix('%') {
+            Some(comment) => text = comment.split_once('\n').map_or("", |(_, rest)| rest),
+            None => return trimmed,
+        }
+    }
+}
+
+#[cfg(test)]
 mod tests;
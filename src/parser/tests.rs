@@ -1,3 +1,5 @@
+#![allow(clippy::missing_docs_in_private_items)]
+
 mod expr;
 mod header;
 mod horizontal;
@@ -19,17 +21,17 @@ pub mod common{
     pub fn assert_success(rule: Rule, text: &str) {
         let pairs: Pairs<'_> = SatysfiParser::parse(rule, text).unwrap();
         if pairs.as_str() != text {
-            panic!(format!(
+            panic!(
                     "Not fully consumed. original: {}, consumed: {}",
-                    text, pairs.as_str()))
+                    text, pairs.as_str())
         }
     }
 
     pub fn assert_fail(rule: Rule, text: &str) {
         if let Ok(pairs) = SatysfiParser::parse(rule, text) {
             if pairs.as_str() == text {
-                panic!(format!(
-                        "Successfully parsed and fully consumed!: {}", text))
+                panic!(
+                        "Successfully parsed and fully consumed!: {}", text)
             }
         }
     }
@@ -26,10 +26,13 @@ mod tests {
         assert_success(Rule::bin_operator, "--");
         assert_success(Rule::bin_operator, "^!'");
         assert_success(Rule::bin_operator, "::");
+        assert_success(Rule::bin_operator, "mod");
         assert_fail(Rule::bin_operator, "`");
         assert_fail(Rule::bin_operator, "#");
         assert_fail(Rule::bin_operator, "!");
         assert_fail(Rule::bin_operator, "!+");
+        assert_fail(Rule::bin_operator, "&");
+        assert_success(Rule::bin_operator, "&&");
     }
 
     #[test]
@@ -38,6 +41,8 @@ mod tests {
         assert_success(Rule::dyadic_expr, "`a` ^ `b`");
         assert_success(Rule::dyadic_expr, "`a` ^ `b` ^ `c`");
         assert_success(Rule::dyadic_expr, "a ^ b");
+        assert_success(Rule::dyadic_expr, "a mod b");
+        assert_fail(Rule::dyadic_expr, "a module");
         assert_success(Rule::dyadic_expr, "mira ^ #`` `fuga` `` ^ kimyo");
     }
 
@@ -203,6 +208,15 @@ mod tests {
         assert_success(Rule::unary, "Mod.text");
         assert_success(Rule::unary, "Mod.(let x = 3 in x + 4)");
         assert_success(Rule::unary, "`constant`");
+        assert_success(Rule::unary, "&(x + 1)");
+        assert_success(Rule::unary, "&{foo}");
+        assert_success(Rule::unary, "~x");
+        assert_success(Rule::unary, "~ (f x)");
+        assert_success(Rule::unary, "& ~x");
+        assert_success(Rule::unary, "!x");
+        assert_success(Rule::unary, "!(f x)");
+        assert_success(Rule::unary, "!M.x");
+        assert_fail(Rule::unary, "&&x");
         assert_fail(Rule::unary, "");
         assert_fail(Rule::unary, "hoge fuga");
     }
@@ -225,6 +239,7 @@ mod tests {
         assert_success(Rule::variant_constructor, "Variant(1, x)");
         assert_success(Rule::variant_constructor, "Variant (1, x)");
         assert_fail(Rule::variant_constructor, "Variant 1 2");
+        assert_fail(Rule::variant_constructor, "Mod.x");
     }
 
     #[test]
@@ -273,6 +288,15 @@ mod tests {
         assert_success(Rule::ctrl_if, "if let y = 1 in x > y then x else 2");
     }
 
+    #[test]
+    fn lambda() {
+        assert_success(Rule::lambda, "fun x -> x");
+        assert_success(Rule::lambda, "fun (x, y) ?:z -> x + y");
+        assert_success(Rule::lambda, "fun x -> fun y -> x y");
+        assert_fail(Rule::lambda, "fun -> x");
+        assert_fail(Rule::lambda, "fun x y");
+    }
+
     #[test]
     fn expr() {
         assert_success(Rule::expr, "let hoge = 1pt in 1pt");
@@ -282,6 +306,15 @@ mod tests {
 
         assert_success(Rule::expr, "let hoge = 1pt in hoge +' fuga");
         assert_success(Rule::expr, "math-char MathOrd `α`");
+        assert_success(Rule::expr, "x <- 1 + 2");
+        assert_success(Rule::expr, "if b then x <- 1 else x <- 2");
+        assert_success(Rule::expr, "let-rec f n = if n <= 0 then 1 else n * f (n - 1) in f 5");
+        assert_success(Rule::expr, "map (fun x -> x + 1) xs");
+        assert_success(Rule::expr, "let-mutable x <- 1 in !x");
+        assert_success(Rule::expr, "let y = Mod.x in y");
+        assert_success(Rule::expr, "Mod.f x");
+        assert_success(Rule::expr, "Mod.(f x)");
+        assert_success(Rule::expr, "Mod.x + 1");
 
         assert_fail(Rule::expr, "");
         assert_fail(Rule::expr, "let hoge = 1pt in");
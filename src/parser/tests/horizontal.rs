@@ -85,6 +85,10 @@ mod tests {
         assert_fail(Rule::inline_cmd, r"\textbf(hoge)");
         assert_fail(Rule::inline_cmd, r"\hoge{};");
         assert_fail(Rule::inline_cmd, r"\hoge{}();");
+        // 省略可能な引数は必須の引数より前に置く
+        assert_success(Rule::inline_cmd, r"\hoge?:(x)?*(y){}");
+        assert_fail(Rule::inline_cmd, r"\hoge(x)?:(y){}");
+        assert_fail(Rule::inline_cmd, r"\hoge(x)?*;");
     }
 
     #[test]
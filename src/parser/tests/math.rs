@@ -67,6 +67,8 @@ mod tests {
         assert_success(Rule::math_cmd, r"\frac{f}{t}");
         assert_success(Rule::math_cmd, r"\alpha!(t)");
         assert_fail(Rule::math_cmd, r"\alpha;");
+        assert_success(Rule::math_cmd, r"\frac?:!(1){f}{t}");
+        assert_fail(Rule::math_cmd, r"\frac{f}?:!(1){t}");
     }
 
     #[test]
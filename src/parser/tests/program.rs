@@ -12,6 +12,9 @@ mod tests {
         assert_success(Rule::program, "let a = 1 in let b = 2 in a + b");
         assert_success(Rule::program, "let a = let b = 2 in a + b in a");
 
+        assert_success(Rule::program, "let a = 1");
+        assert_success(Rule::program, "@require: base\nmodule M = struct let a = 1 end");
+
         assert_fail(Rule::program, "let a = 1 in");
     }
 
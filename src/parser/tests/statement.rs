@@ -24,6 +24,15 @@ mod tests {
         assert_fail(Rule::stmt_argument, "| hoge | fuga");
     }
 
+    #[test]
+    fn let_rec_stmt() {
+        assert_success(Rule::let_rec_stmt, "let-rec f x = f x");
+        assert_success(Rule::let_rec_stmt, "let-rec f = fun x -> f x");
+        assert_success(Rule::let_rec_stmt, "let-rec even n = odd n and odd n = even n");
+        assert_fail(Rule::let_rec_stmt, "let-rec (f, g) = (1, 2)");
+        assert_fail(Rule::let_rec_stmt, "let-rec f x = x and");
+    }
+
     #[test]
     fn let_mutable_stmt() {
         assert_success(Rule::let_mutable_stmt, "let-mutable x <- 1");
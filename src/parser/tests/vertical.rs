@@ -30,6 +30,7 @@ mod tests {
         assert_success(Rule::block_cmd, "+ctx< +par; >{}");
         assert_success(Rule::block_cmd, "+ctx()< +par; >");
         assert_success(Rule::block_cmd, "+ctx?:()< +par; >");
+        assert_success(Rule::block_cmd, "+p?*?:(x)(y)<>");
 
         assert_fail(Rule::block_cmd, "+p");
         assert_fail(Rule::block_cmd, "+p(hoge)");
@@ -37,6 +38,8 @@ mod tests {
         assert_fail(Rule::block_cmd, "+ctx<>< +par; >;");
         assert_fail(Rule::block_cmd, "+ctx();< +par; >");
         assert_fail(Rule::block_cmd, "+ctx?:< +par; >();");
+        assert_fail(Rule::block_cmd, "+p(x)?:(y)<>");
+        assert_fail(Rule::block_cmd, "+p(x)?*;");
     }
 
     #[test]
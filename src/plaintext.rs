@@ -0,0 +1,280 @@
+//! Plain-text extraction.
+//!
+//! スペルチェックや文字数の計測、検索の索引づくりのために、文書から地の文を抜き出す。
+//!
+//! - 地の文（`regular_text`）はそのまま残し、空白や改行の並びは 1 つの空白にまとめる。
+//! - `\@` のようにエスケープした文字は、その文字にする。
+//! - コマンドの名前や `(...)` の引数、`#var;` は取り除き、`{...}` や `<...>` の引数の中身は残す。
+//! - 段落（ブロックモードに直接置かれた水平モードのテキスト）の間には空行を入れる。
+//! - 数式は取り除くか、[`Options::math_placeholder`] に置き換える。
+//!
+//! 抜き出した文字列の各位置から、元のソースコード上の範囲を [`PlainText::span_at`] で求められる。
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::{Location, Ranged, Span};
+use crate::grammar::{
+    CommandArg, Horizontal, HorizontalSingle, HorizontalToken, Literal, Program, Vertical,
+    VerticalElement,
+};
+use crate::visit::Visitor;
+use std::ops::Range;
+
+/// 抜き出し方の設定。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// 数式を置き換える文字列。None の場合は数式を取り除く。
+    pub math_placeholder: Option<String>,
+}
+
+/// 抜き出した文字列の一部と、その元になったソースコード上の範囲。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// 抜き出した文字列上のバイト位置の範囲。
+    pub range: Range<usize>,
+    /// 元になったソースコード上の範囲。
+    pub span: Span,
+    /// ソースコードの文字をそのまま写したものかどうか。
+    /// 真の場合、range の各文字は span の各文字に順に対応する。
+    pub verbatim: bool,
+}
+
+/// 文書から抜き出した文字列。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlainText {
+    /// 抜き出した文字列。
+    pub text: String,
+    /// text を先頭から隙間なく区切った各部分と、その元になった範囲。
+    pub segments: Vec<Segment>,
+}
+
+impl PlainText {
+    /// text の offset バイト目の文字の元になったソースコード上の範囲。
+    ///
+    /// そのまま写した文字の場合はその 1 文字の範囲を、
+    /// エスケープや数式、まとめた空白などの場合は元になった構文要素全体の範囲を返す。
+    /// offset が text の外にある場合は None。
+    pub fn span_at(&self, offset: usize) -> Option<Span> {
+        if offset >= self.text.len() {
+            return None;
+        }
+        let offset = (0..=offset)
+            .rev()
+            .find(|&i| self.text.is_char_boundary(i))?;
+        let i = self
+            .segments
+            .partition_point(|segment| segment.range.end <= offset);
+        let segment = self.segments.get(i)?;
+        if !segment.verbatim {
+            return Some(segment.span);
+        }
+        let start = segment
+            .span
+            .start
+            .advance(&self.text[segment.range.start..offset]);
+        let c = self.text[offset..].chars().next()?;
+        Some(Span {
+            start,
+            end: start.advance(c.encode_utf8(&mut [0; 4])),
+        })
+    }
+
+    /// text の range の部分の元になったソースコード上の範囲。range が空か text の外にある場合は None。
+    pub fn span_of(&self, range: Range<usize>) -> Option<Span> {
+        if range.is_empty() {
+            return None;
+        }
+        let first = self.span_at(range.start)?;
+        let last = self.span_at(range.end - 1)?;
+        Some(Span {
+            start: first.start,
+            end: last.end,
+        })
+    }
+}
+
+/// program から地の文を抜き出す。
+pub fn extract(program: &Program, options: &Options) -> PlainText {
+    let mut extractor = Extractor {
+        options,
+        plain: PlainText::default(),
+        pending: None,
+    };
+    extractor.visit_program(program);
+    extractor.plain
+}
+
+/// 区切り。強いものほど大きい。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Break {
+    /// 空白。
+    Space,
+    /// 改行。
+    Line,
+    /// 空行。
+    Paragraph,
+}
+
+/// 地の文を抜き出す Visitor。
+///
+/// 式の中に現れる `{...}` や `'<...>` を探し、その中は自身でたどる。
+struct Extractor<'a> {
+    /// 設定。
+    options: &'a Options,
+    /// 抜き出した結果。
+    plain: PlainText,
+    /// 次に文字列を加える前に入れる区切りと、その元になった範囲。
+    /// 文書の先頭と末尾の区切りは入れない。
+    pending: Option<(Break, Span)>,
+}
+
+impl Extractor<'_> {
+    /// s を span から得た文字列として加える。
+    fn push(&mut self, s: &str, span: Span, verbatim: bool) {
+        if s.is_empty() {
+            return;
+        }
+        if let Some((brk, brk_span)) = self.pending.take() {
+            if !self.plain.text.is_empty() {
+                let sep = match brk {
+                    Break::Space => " ",
+                    Break::Line => "\n",
+                    Break::Paragraph => "\n\n",
+                };
+                self.append(sep, brk_span, false);
+            }
+        }
+        self.append(s, span, verbatim);
+    }
+
+    /// s をそのまま加える。
+    fn append(&mut self, s: &str, span: Span, verbatim: bool) {
+        let start = self.plain.text.len();
+        self.plain.text.push_str(s);
+        self.plain.segments.push(Segment {
+            range: start..self.plain.text.len(),
+            span,
+            verbatim,
+        });
+    }
+
+    /// 次に文字列を加える前に区切りを入れる。すでにより強い区切りがあればそれを使う。
+    fn request(&mut self, brk: Break, span: Span) {
+        if self.pending.is_none_or(|(pending, _)| pending < brk) {
+            self.pending = Some((brk, span));
+        }
+    }
+
+    /// at の位置で区切りを入れる。区切りは at の位置の空の範囲に対応させる。
+    fn request_at(&mut self, brk: Break, at: Location) {
+        self.request(brk, Span { start: at, end: at });
+    }
+
+    /// 地の文のトークンを加える。空白の並びは 1 つの空白にまとめる。
+    fn text(&mut self, text: &Ranged<String>) {
+        let mut rest = text.body.as_str();
+        let mut loc = text.start;
+        while !rest.is_empty() {
+            let is_space = rest.starts_with(char::is_whitespace);
+            let len = rest
+                .find(|c: char| c.is_whitespace() != is_space)
+                .unwrap_or(rest.len());
+            let (run, next) = rest.split_at(len);
+            let span = Span {
+                start: loc,
+                end: loc.advance(run),
+            };
+            if is_space {
+                self.request(Break::Space, span);
+            } else {
+                self.push(run, span, true);
+            }
+            loc = span.end;
+            rest = next;
+        }
+    }
+
+    /// 水平モードのテキストの中身を加える。リストの項目は改行で区切る。
+    fn horizontal(&mut self, node: &Horizontal) {
+        match node {
+            Horizontal::Single(single) => self.horizontal_single(single),
+            Horizontal::List(singles) => {
+                for single in singles {
+                    self.horizontal_single(&single.body);
+                    self.request_at(Break::Line, single.end);
+                }
+            }
+            Horizontal::BulletList(bullets) => {
+                for bullet in bullets {
+                    self.horizontal_single(&bullet.body.body.body);
+                    self.request_at(Break::Line, bullet.end);
+                }
+            }
+        }
+    }
+
+    /// 水平モードのトークン列を加える。
+    fn horizontal_single(&mut self, node: &HorizontalSingle) {
+        for token in &node.0 {
+            let span = token.span();
+            match &token.body {
+                HorizontalToken::Text(text) => self.text(text),
+                HorizontalToken::SpecialChar(c) => self.push(&c.body, span, false),
+                HorizontalToken::StringLiteral(lit) => {
+                    if let Literal::String(s) = &lit.body {
+                        self.push(s, span, false);
+                    }
+                }
+                HorizontalToken::Math(_) => {
+                    if let Some(placeholder) = &self.options.math_placeholder {
+                        self.push(placeholder, span, false);
+                    }
+                }
+                HorizontalToken::InlineCmd { args, .. } => self.command_args(args, false),
+                HorizontalToken::HorizontalTextEmbedding { .. } => {}
+            }
+        }
+    }
+
+    /// コマンドの引数のうち、テキストの中身を加える。
+    ///
+    /// block が真の場合（ブロックコマンドの場合）は、水平モードの引数をそれぞれ段落とする。
+    fn command_args(&mut self, args: &[Ranged<CommandArg>], block: bool) {
+        for arg in args {
+            match &arg.body {
+                CommandArg::Horizontal(horizontal) if block => {
+                    self.request_at(Break::Paragraph, arg.start);
+                    self.horizontal(horizontal);
+                    self.request_at(Break::Paragraph, arg.end);
+                }
+                CommandArg::Horizontal(horizontal) => self.horizontal(horizontal),
+                CommandArg::Vertical(vertical) => self.vertical(vertical, arg.span()),
+                CommandArg::Expr(_) | CommandArg::Optional(_) | CommandArg::OptionalOmitted => {}
+            }
+        }
+    }
+
+    /// ブロックモードのテキストの中身を加える。
+    fn vertical(&mut self, node: &Vertical, span: Span) {
+        self.request_at(Break::Paragraph, span.start);
+        for elem in &node.0 {
+            if let VerticalElement::BlockCmd { args, .. } = &elem.body {
+                self.command_args(args, true);
+            }
+        }
+        self.request_at(Break::Paragraph, span.end);
+    }
+}
+
+impl Visitor for Extractor<'_> {
+    fn visit_vertical(&mut self, node: &Vertical, span: Span) {
+        self.vertical(node, span);
+    }
+
+    fn visit_horizontal(&mut self, node: &Horizontal, span: Span) {
+        self.request_at(Break::Paragraph, span.start);
+        self.horizontal(node);
+        self.request_at(Break::Paragraph, span.end);
+    }
+}
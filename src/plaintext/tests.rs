@@ -0,0 +1,88 @@
+use super::*;
+use crate::grammar::common::Grammar;
+
+/// text から既定の設定で地の文を抜き出す。
+fn plain(text: &str) -> PlainText {
+    extract(&Program::parse(text).unwrap(), &Options::default())
+}
+
+/// row 行 col1 列から row 行 col2 列までの範囲。
+fn span(row: usize, col1: usize, col2: usize) -> Span {
+    Span {
+        start: Location { row, col: col1 },
+        end: Location { row, col: col2 },
+    }
+}
+
+#[test]
+fn inline_text() {
+    let text = "{Hello,   \\emph{SATy\\@SFi}\n  world #x; ${x^2}!}";
+    assert_eq!(plain(text).text, "Hello, SATy@SFi world !");
+    let options = Options {
+        math_placeholder: Some("[math]".to_owned()),
+    };
+    let extracted = extract(&Program::parse(text).unwrap(), &options);
+    assert_eq!(extracted.text, "Hello, SATy@SFi world [math]!");
+    // 数式は全体に対応する
+    assert_eq!(extracted.span_at(22), Some(span(2, 13, 19)));
+    assert_eq!(extracted.span_of(22..28), Some(span(2, 13, 19)));
+}
+
+#[test]
+fn blocks() {
+    let text = "\
+document (|title = {Title}|) '<
+  +section{Intro}<
+    +p{
+      First paragraph
+      with \\ref(`a`); two lines.
+    }
+    +p{}
+    +listing{
+      * one
+      * two
+    }
+  >
+  +p{Last `` `literal` ``.}
+>
+";
+    assert_eq!(
+        plain(text).text,
+        "Title\n\nIntro\n\nFirst paragraph with two lines.\n\none\ntwo\n\nLast `literal`."
+    );
+}
+
+#[test]
+fn source_mapping() {
+    let text = "'<\n  +p{ab  c\\%d\n    えお}\n>";
+    let extracted = plain(text);
+    assert_eq!(extracted.text, "ab c%d えお");
+    // そのまま写した文字はその 1 文字に対応する
+    assert_eq!(extracted.span_at(0), Some(span(2, 6, 7)));
+    assert_eq!(extracted.span_at(1), Some(span(2, 7, 8)));
+    // まとめた空白は元の空白全体に
+    assert_eq!(extracted.span_at(2), Some(span(2, 8, 10)));
+    assert_eq!(extracted.span_at(3), Some(span(2, 10, 11)));
+    // エスケープした文字はエスケープ全体に
+    assert_eq!(extracted.span_at(4), Some(span(2, 11, 13)));
+    assert_eq!(
+        extracted.span_at(6),
+        Some(Span {
+            start: Location { row: 2, col: 14 },
+            end: Location { row: 3, col: 5 },
+        })
+    );
+    // 複数バイトの文字は、途中の位置でもその文字に対応する
+    assert_eq!(extracted.span_at(10), Some(span(3, 6, 7)));
+    assert_eq!(extracted.span_at(11), Some(span(3, 6, 7)));
+    assert_eq!(extracted.span_at(13), None);
+    assert_eq!(extracted.span_of(0..6), Some(span(2, 6, 14)));
+    assert_eq!(extracted.span_of(3..3), None);
+
+    // 区切りを含め、すべての位置が元の範囲に対応する
+    let text = "'<\n  +p{a}\n  +p{b}\n>";
+    let extracted = plain(text);
+    assert_eq!(extracted.text, "a\n\nb");
+    assert!((0..extracted.text.len()).all(|i| extracted.span_at(i).is_some()));
+    assert_eq!(extracted.span_at(1), Some(span(2, 8, 8)));
+}
@@ -0,0 +1,1416 @@
+//! AST を SATySFi のソースコードに戻すプリンタ。
+//!
+//! 括弧は構文上必要な箇所と、演算子の優先順位から必要な箇所にのみ付ける。
+//! パースして得られた AST に対しては、出力を再びパースすると（位置情報を除いて）元の AST に戻る。
+
+#[cfg(test)]
+mod tests;
+mod text;
+
+use crate::grammar::common::{Grammar, Location, Ranged};
+use crate::grammar::{
+    binop_precedence, ApplicationArg, Argument, Associativity, CommandArg, Constraint, Expr,
+    Header, Horizontal, HorizontalBullet, HorizontalSingle, HorizontalToken, Length, LetRecBinding,
+    List, Literal, MatchArm, Math, MathCmdArg, MathGroup, MathSingle, MathToken, MathUnary,
+    Pattern, Preamble, Program, Record, RecordUnit, SigStmt, Signature, Stage, Statement, Struct,
+    Tuple, TypeExpr, TypeList, TypeListUnit, TypeProd, TypeRecord, TypeRecordUnit, TypeUnary,
+    Unary, ValName, Variable, Vertical, VerticalElement,
+};
+use std::collections::VecDeque;
+
+/// 構文要素を文字列に変換する。
+pub fn print<T: Print>(node: &T) -> String {
+    let mut p = Printer::new();
+    node.print(&mut p);
+    p.finish()
+}
+
+/// ソースコード中のコメント。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// `%` の位置。
+    pub start: Location,
+    /// `%` から行末の直前までの文字列。
+    pub text: String,
+    /// 同じ行のコメントより前に、コードが書かれているかどうか。
+    /// 真の場合、整形後もコメントの前のコードと同じ行に置く。
+    pub trailing: bool,
+}
+
+/// 出力先の文字列とインデントの状態を持つ構造体。
+#[derive(Debug, Default)]
+pub struct Printer {
+    /// 出力された文字列。
+    buf: String,
+    /// 現在のインデントの深さ。
+    indent: usize,
+    /// リストやレコードを折り返す幅。None のときは折り返さない。
+    width: Option<usize>,
+    /// まだ出力していないコメント。位置の昇順に並ぶ。
+    comments: VecDeque<Comment>,
+    /// 出力中の構文要素の範囲。内側のものほど後ろに積まれる。
+    ranges: Vec<(Location, Location)>,
+    /// 最後に出力し終えた構文要素の終了位置。
+    last_end: Option<Location>,
+    /// テキストを幅に合わせて詰め直すかどうか。
+    reflow_text: bool,
+    /// 箇条書きや表の字下げと空白を揃えるかどうか。
+    normalize_lists: bool,
+}
+
+impl Printer {
+    /// 1 段のインデントに用いる空白。
+    const INDENT: &'static str = "  ";
+
+    /// 空の Printer を作成する。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 折り返し幅と、出力中に元の位置へ差し込むコメントを指定して Printer を作成する。
+    pub fn with_config(width: Option<usize>, mut comments: Vec<Comment>) -> Self {
+        comments.sort_by_key(|comment| comment.start);
+        Self {
+            width,
+            comments: comments.into(),
+            ..Self::default()
+        }
+    }
+
+    /// テキストを幅に合わせて詰め直すかどうかを設定する。
+    pub fn reflow_text(mut self, reflow_text: bool) -> Self {
+        self.reflow_text = reflow_text;
+        self
+    }
+
+    /// 箇条書きや表の字下げと空白を揃えるかどうかを設定する。
+    pub fn normalize_lists(mut self, normalize_lists: bool) -> Self {
+        self.normalize_lists = normalize_lists;
+        self
+    }
+
+    /// 同じ設定でコメントを持たない Printer を作成する。
+    fn sub_printer(&self, width: Option<usize>) -> Self {
+        Self {
+            width,
+            reflow_text: self.reflow_text,
+            normalize_lists: self.normalize_lists,
+            ..Self::default()
+        }
+    }
+
+    /// 出力された文字列を返す。
+    pub fn finish(self) -> String {
+        self.buf
+    }
+
+    /// 文字列をそのまま出力する。
+    pub fn write(&mut self, s: &str) {
+        self.buf.push_str(s);
+    }
+
+    /// 改行し、現在のインデントまで空白を出力する。
+    ///
+    /// 直前に出力した構文要素と同じ行にあった行末のコメントは、改行の前に出力する。
+    pub fn newline(&mut self) {
+        self.flush_line_comments();
+        self.break_line();
+    }
+
+    /// コメントを出力せずに改行し、現在のインデントまで空白を出力する。
+    fn break_line(&mut self) {
+        let trimmed_len = self.buf.trim_end_matches(' ').len();
+        self.buf.truncate(trimmed_len);
+        self.buf.push('\n');
+        for _ in 0..self.indent {
+            self.buf.push_str(Self::INDENT);
+        }
+    }
+
+    /// インデントを 1 段深くして f を実行する。
+    pub fn indented<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        self.indent += 1;
+        f(self);
+        self.indent -= 1;
+    }
+
+    /// 現在の行に出力済みの文字列の幅。
+    fn column(&self) -> usize {
+        let line_start = self.buf.rfind('\n').map_or(0, |i| i + 1);
+        text::display_width(&self.buf[line_start..])
+    }
+
+    /// f の出力を 1 行に収めて現在の位置に続けられるかどうか。
+    ///
+    /// 出力中の構文要素の中にコメントがある場合は、コメントを保つため収まらないものとみなす。
+    fn fits<F: FnOnce(&mut Self)>(&self, f: F) -> bool {
+        let width = match self.width {
+            Some(width) => width,
+            None => return true,
+        };
+        if let Some(&(start, end)) = self.ranges.last() {
+            if self.has_comment_within(start, end) {
+                return false;
+            }
+        }
+        let mut flat = self.sub_printer(None);
+        f(&mut flat);
+        !flat.buf.contains('\n') && self.column() + text::display_width(&flat.buf) <= width
+    }
+
+    /// start から end までの間にコメントが残っているかどうか。
+    fn has_comment_within(&self, start: Location, end: Location) -> bool {
+        self.comments
+            .iter()
+            .any(|comment| start <= comment.start && comment.start < end)
+    }
+
+    /// 出力中の構文要素の終了位置。
+    fn enclosing_end(&self) -> Option<Location> {
+        self.ranges.last().map(|&(_, end)| end)
+    }
+
+    /// loc より前にあるコメントが残っているかどうか。
+    fn has_comment_before(&self, loc: Location) -> bool {
+        self.comments
+            .front()
+            .is_some_and(|comment| comment.start < loc)
+    }
+
+    /// loc より前にあるコメントを、それぞれ 1 行ずつ出力する。
+    /// 出力したコメントがあれば真を返す。
+    pub fn flush_comments(&mut self, loc: Location) -> bool {
+        let mut flushed = false;
+        while self.has_comment_before(loc) {
+            let comment = self.comments.pop_front().unwrap();
+            self.write_comment(&comment);
+            self.break_line();
+            flushed = true;
+        }
+        flushed
+    }
+
+    /// ブロックの末尾に loc より前にあるコメントを出力する。
+    /// 各コメントの前で改行し、最後のコメントの後では改行しない。
+    fn flush_trailing_comments(&mut self, loc: Location) {
+        self.flush_line_comments();
+        while self.has_comment_before(loc) {
+            let comment = self.comments.pop_front().unwrap();
+            self.break_line();
+            self.write(&comment.text);
+        }
+    }
+
+    /// 残っているすべてのコメントを出力する。
+    pub fn flush_remaining_comments(&mut self) {
+        self.flush_line_comments();
+        while let Some(comment) = self.comments.pop_front() {
+            self.write(&comment.text);
+            self.break_line();
+        }
+    }
+
+    /// 最後に出力し終えた構文要素と同じ行か、それより前の行にある行末のコメントを、現在の行の末尾に出力する。
+    fn flush_line_comments(&mut self) {
+        let row = match self.last_end {
+            Some(end) => end.row,
+            None => return,
+        };
+        let mut first = true;
+        while self
+            .comments
+            .front()
+            .is_some_and(|comment| comment.trailing && comment.start.row <= row)
+        {
+            let comment = self.comments.pop_front().unwrap();
+            if !first {
+                self.break_line();
+            }
+            self.write_comment(&comment);
+            first = false;
+        }
+    }
+
+    /// コメントを出力する。
+    /// 行末のコメントで、現在の行にすでに出力した文字列があれば、空白 1 つを挟んで続ける。
+    fn write_comment(&mut self, comment: &Comment) {
+        let line_start = self.buf.rfind('\n').map_or(0, |i| i + 1);
+        if comment.trailing && !self.buf[line_start..].trim().is_empty() {
+            let trimmed_len = self.buf.trim_end_matches(' ').len();
+            self.buf.truncate(trimmed_len);
+            self.buf.push(' ');
+        }
+        self.write(&comment.text);
+    }
+
+    /// 区切り文字を挟みながら要素を出力する。
+    fn write_separated<T: Print>(&mut self, items: &[T], sep: &str) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.write(sep);
+            }
+            item.print(self);
+        }
+    }
+
+    /// 要素を 1 行に 1 つずつ、末尾に `;` を付けて字下げして出力する。
+    fn write_broken<T: Print + Grammar>(&mut self, items: &[Ranged<T>]) {
+        let end = self.enclosing_end();
+        self.indented(|p| {
+            for item in items {
+                p.newline();
+                p.flush_comments(item.start);
+                item.print(p);
+                p.write(";");
+            }
+            if let Some(end) = end {
+                p.flush_trailing_comments(end);
+            }
+        });
+        self.newline();
+    }
+
+    /// 括弧で囲んで出力する。
+    fn write_paren<T: Print>(&mut self, node: &T) {
+        self.write("(");
+        node.print(self);
+        self.write(")");
+    }
+}
+
+/// ソースコードとして出力できる構文要素。
+pub trait Print {
+    /// p に自身を出力する。
+    fn print(&self, p: &mut Printer);
+}
+
+impl<T: Print + Grammar> Print for Ranged<T> {
+    fn print(&self, p: &mut Printer) {
+        p.ranges.push((self.start, self.end));
+        self.body.print(p);
+        p.ranges.pop();
+        p.last_end = Some(self.end);
+    }
+}
+
+impl<T: Print> Print for Box<T> {
+    fn print(&self, p: &mut Printer) {
+        (**self).print(p)
+    }
+}
+
+impl Print for String {
+    fn print(&self, p: &mut Printer) {
+        p.write(self)
+    }
+}
+
+impl Print for Program {
+    fn print(&self, p: &mut Printer) {
+        if let Some(stage) = &self.stage {
+            p.flush_comments(stage.start);
+            stage.print(p);
+        }
+        for header in &self.header {
+            p.flush_comments(header.start);
+            header.print(p);
+        }
+        if self.stage.is_some() || !self.header.is_empty() {
+            p.newline();
+        }
+        if let Some(preamble) = &self.preamble {
+            preamble.print(p);
+            p.newline();
+            if self.expr.is_some() {
+                p.newline();
+                p.write("in");
+                p.newline();
+                p.newline();
+            }
+        }
+        if let Some(expr) = &self.expr {
+            p.flush_comments(expr.start);
+            expr.print(p);
+            p.newline();
+        }
+        p.flush_remaining_comments();
+    }
+}
+
+impl Print for Stage {
+    fn print(&self, p: &mut Printer) {
+        let stage = match self {
+            Stage::Stage0 => "0",
+            Stage::Stage1 => "1",
+            Stage::Persistent => "persistent",
+        };
+        p.write("@stage: ");
+        p.write(stage);
+        p.newline();
+    }
+}
+
+impl Print for Header {
+    fn print(&self, p: &mut Printer) {
+        let (kind, pkgname) = match self {
+            Header::Require(pkgname) => ("require", pkgname),
+            Header::Import(pkgname) => ("import", pkgname),
+        };
+        p.write("@");
+        p.write(kind);
+        p.write(": ");
+        pkgname.print(p);
+        p.newline();
+    }
+}
+
+impl Print for Preamble {
+    fn print(&self, p: &mut Printer) {
+        for (i, stmt) in self.0.iter().enumerate() {
+            if i > 0 {
+                p.newline();
+                p.newline();
+            }
+            p.flush_comments(stmt.start);
+            stmt.print(p);
+        }
+    }
+}
+
+impl Print for Statement {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Statement::Let { ptn, args, expr } => {
+                p.write("let ");
+                print_pattern_unit(p, &ptn.body);
+                print_args(p, args);
+                print_bound_expr(p, expr);
+            }
+            Statement::LetRec(bindings) => {
+                p.write("let-rec ");
+                for (i, binding) in bindings.iter().enumerate() {
+                    if i > 0 {
+                        p.newline();
+                        p.flush_comments(binding.start);
+                        p.write("and ");
+                    }
+                    binding.print(p);
+                }
+            }
+            Statement::LetInline {
+                ctx,
+                name,
+                args,
+                expr,
+            }
+            | Statement::LetBlock {
+                ctx,
+                name,
+                args,
+                expr,
+            } => {
+                if let Statement::LetInline { .. } = self {
+                    p.write("let-inline ");
+                } else {
+                    p.write("let-block ");
+                }
+                if let Some(ctx) = ctx {
+                    ctx.print(p);
+                    p.write(" ");
+                }
+                name.print(p);
+                print_args(p, args);
+                print_bound_expr(p, expr);
+            }
+            Statement::LetMath { name, args, expr } => {
+                p.write("let-math ");
+                name.print(p);
+                print_args(p, args);
+                print_bound_expr(p, expr);
+            }
+            Statement::LetMutable { var, expr } => {
+                p.write("let-mutable ");
+                var.print(p);
+                p.write(" <- ");
+                expr.print(p);
+            }
+            Statement::Module {
+                name,
+                signature,
+                body,
+            } => {
+                p.write("module ");
+                name.print(p);
+                if let Some(signature) = signature {
+                    p.write(" : ");
+                    signature.print(p);
+                }
+                p.write(" = ");
+                body.print(p);
+            }
+        }
+    }
+}
+
+/// let 文などの引数を、先頭に空白を付けて出力する。
+fn print_args(p: &mut Printer, args: &[Ranged<Argument>]) {
+    for arg in args {
+        p.write(" ");
+        arg.print(p);
+    }
+}
+
+/// 右辺を改行して字下げする束縛かどうか。
+fn is_multiline_bind(stmt: &Statement) -> bool {
+    let expr = match stmt {
+        Statement::Let { expr, .. }
+        | Statement::LetInline { expr, .. }
+        | Statement::LetBlock { expr, .. }
+        | Statement::LetMath { expr, .. } => expr,
+        Statement::LetRec(bindings) => match bindings.last() {
+            Some(binding) => &binding.body.expr,
+            None => return false,
+        },
+        _ => return false,
+    };
+    matches!(expr.body, Expr::BindStmt { .. } | Expr::Match { .. })
+}
+
+/// let 文などの右辺を ` = ` に続けて出力する。複数行にわたる式は改行して字下げする。
+fn print_bound_expr(p: &mut Printer, expr: &Ranged<Expr>) {
+    match &expr.body {
+        Expr::BindStmt { .. } | Expr::Match { .. } => {
+            p.write(" =");
+            p.indented(|p| {
+                p.newline();
+                expr.print(p);
+            });
+        }
+        _ => {
+            p.write(" = ");
+            expr.print(p);
+        }
+    }
+}
+
+impl Print for LetRecBinding {
+    fn print(&self, p: &mut Printer) {
+        self.var.print(p);
+        print_args(p, &self.args);
+        print_bound_expr(p, &self.expr);
+    }
+}
+
+impl Print for Argument {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Argument::Pattern(ptn) => print_pattern_unit(p, ptn),
+            Argument::Optional(var) => {
+                p.write("?:");
+                var.print(p);
+            }
+        }
+    }
+}
+
+impl Print for Signature {
+    fn print(&self, p: &mut Printer) {
+        print_block(p, "sig", &self.0, false);
+    }
+}
+
+/// `sig ... end` や `struct ... end` を、中身を字下げして出力する。
+/// separate が真のときは各文の間に空行を入れる。
+fn print_block<T: Print + Grammar>(
+    p: &mut Printer,
+    keyword: &str,
+    stmts: &[Ranged<T>],
+    separate: bool,
+) {
+    let end = p.enclosing_end();
+    p.write(keyword);
+    p.indented(|p| {
+        for (i, stmt) in stmts.iter().enumerate() {
+            if separate && i > 0 {
+                p.newline();
+            }
+            p.newline();
+            p.flush_comments(stmt.start);
+            stmt.print(p);
+        }
+        if let Some(end) = end {
+            p.flush_trailing_comments(end);
+        }
+    });
+    p.newline();
+    p.write("end");
+}
+
+impl Print for SigStmt {
+    fn print(&self, p: &mut Printer) {
+        let constraints = match self {
+            SigStmt::Type {
+                params,
+                name,
+                constraints,
+            } => {
+                p.write("type ");
+                for param in params {
+                    param.print(p);
+                    p.write(" ");
+                }
+                name.print(p);
+                constraints
+            }
+            SigStmt::Val {
+                name,
+                ty,
+                constraints,
+            } => {
+                p.write("val ");
+                name.print(p);
+                p.write(" : ");
+                ty.print(p);
+                constraints
+            }
+            SigStmt::Direct {
+                name,
+                ty,
+                constraints,
+            } => {
+                p.write("direct ");
+                name.print(p);
+                p.write(" : ");
+                ty.print(p);
+                constraints
+            }
+        };
+        for constraint in constraints {
+            p.write(" ");
+            constraint.print(p);
+        }
+    }
+}
+
+impl Print for ValName {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            ValName::BinOperator(op) => p.write_paren(op),
+            ValName::Var(name) | ValName::InlineCmd(name) | ValName::BlockCmd(name) => {
+                p.write(name)
+            }
+        }
+    }
+}
+
+impl Print for Struct {
+    fn print(&self, p: &mut Printer) {
+        print_block(p, "struct", &self.0, true);
+    }
+}
+
+impl Print for Constraint {
+    fn print(&self, p: &mut Printer) {
+        p.write("constraint ");
+        self.param.print(p);
+        p.write(" :: ");
+        self.record.print(p);
+    }
+}
+
+impl Print for TypeExpr {
+    fn print(&self, p: &mut Printer) {
+        for arg in &self.optional_args {
+            arg.print(p);
+            p.write(" ?-> ");
+        }
+        for arg in &self.args {
+            arg.print(p);
+            p.write(" -> ");
+        }
+        self.ret.print(p);
+    }
+}
+
+impl Print for TypeProd {
+    fn print(&self, p: &mut Printer) {
+        p.write_separated(&self.0, " * ");
+    }
+}
+
+impl Print for TypeUnary {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            TypeUnary::InlineCmd(list) => {
+                list.print(p);
+                p.write(" inline-cmd");
+            }
+            TypeUnary::BlockCmd(list) => {
+                list.print(p);
+                p.write(" block-cmd");
+            }
+            TypeUnary::MathCmd(list) => {
+                list.print(p);
+                p.write(" math-cmd");
+            }
+            TypeUnary::Paren(ty) => p.write_paren(ty),
+            TypeUnary::Record(record) => record.print(p),
+            TypeUnary::Application(units) => {
+                for (i, unit) in units.iter().enumerate() {
+                    if i > 0 {
+                        p.write(" ");
+                    }
+                    match unit.body {
+                        TypeUnary::Paren(_) | TypeUnary::Param(_) | TypeUnary::Name { .. } => {
+                            unit.print(p)
+                        }
+                        _ => p.write_paren(unit),
+                    }
+                }
+            }
+            TypeUnary::Param(param) => p.write(param),
+            TypeUnary::Name { modname, name } => {
+                if let Some(modname) = modname {
+                    modname.print(p);
+                    p.write(".");
+                }
+                name.print(p);
+            }
+        }
+    }
+}
+
+impl Print for TypeList {
+    fn print(&self, p: &mut Printer) {
+        p.write("[");
+        p.write_separated(&self.0, "; ");
+        p.write("]");
+    }
+}
+
+impl Print for TypeListUnit {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            TypeListUnit::Optional(ty) => {
+                ty.print(p);
+                p.write("?");
+            }
+            TypeListUnit::Mandatory(ty) => ty.print(p),
+        }
+    }
+}
+
+impl Print for TypeRecord {
+    fn print(&self, p: &mut Printer) {
+        if self.0.is_empty() {
+            p.write("(||)");
+            return;
+        }
+        p.write("(| ");
+        p.write_separated(&self.0, "; ");
+        p.write(" |)");
+    }
+}
+
+impl Print for TypeRecordUnit {
+    fn print(&self, p: &mut Printer) {
+        self.key.print(p);
+        p.write(" : ");
+        self.ty.print(p);
+    }
+}
+
+impl Print for Pattern {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Pattern::List(ptns) => {
+                p.write("[");
+                p.write_separated(ptns, "; ");
+                p.write("]");
+            }
+            Pattern::Tuple(ptns) => {
+                p.write("(");
+                p.write_separated(ptns, ", ");
+                p.write(")");
+            }
+            Pattern::Paren(ptn) => p.write_paren(ptn),
+            Pattern::Wildcard => p.write("_"),
+            Pattern::Variable(var) => var.print(p),
+            Pattern::Literal(lit) => lit.print(p),
+            Pattern::Variant { name, arg } => {
+                name.print(p);
+                if let Some(arg) = arg {
+                    if !matches!(arg.body, Pattern::Paren(_) | Pattern::Tuple(_)) {
+                        p.write(" ");
+                    }
+                    print_pattern_unit(p, &arg.body);
+                }
+            }
+            Pattern::As { ptn, var } => {
+                print_pattern_unit(p, &ptn.body);
+                p.write(" as ");
+                var.print(p);
+            }
+            Pattern::Cons { head, tail } => {
+                print_pattern_unit(p, &head.body);
+                p.write(" :: ");
+                tail.print(p);
+            }
+        }
+    }
+}
+
+/// 文法上 `pattern` しか書けない位置にパターンを出力する。
+fn print_pattern_unit(p: &mut Printer, ptn: &Pattern) {
+    match ptn {
+        Pattern::Variant { .. } | Pattern::As { .. } | Pattern::Cons { .. } => p.write_paren(ptn),
+        _ => ptn.print(p),
+    }
+}
+
+impl Print for Expr {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Expr::Match { expr, arms } => {
+                p.write("match ");
+                expr.print(p);
+                p.write(" with");
+                for (i, arm) in arms.iter().enumerate() {
+                    p.newline();
+                    p.flush_comments(arm.start);
+                    p.write("| ");
+                    print_match_arm(p, arm, i + 1 == arms.len());
+                }
+            }
+            Expr::BindStmt { bind, body } => {
+                bind.print(p);
+                if is_multiline_bind(&bind.body) {
+                    p.newline();
+                    p.write("in");
+                } else {
+                    p.write(" in");
+                }
+                p.newline();
+                p.flush_comments(body.start);
+                body.print(p);
+            }
+            Expr::Lambda { args, body } => {
+                p.write("fun");
+                print_args(p, args);
+                p.write(" -> ");
+                body.print(p);
+            }
+            Expr::CtrlFlowWhile { condition, body } => {
+                p.write("while ");
+                condition.print(p);
+                p.write(" do ");
+                body.print(p);
+            }
+            Expr::CtrlFlowIf {
+                condition,
+                expr_true,
+                expr_false,
+            } => {
+                p.write("if ");
+                condition.print(p);
+                p.write(" then ");
+                expr_true.print(p);
+                p.write(" else ");
+                expr_false.print(p);
+            }
+            Expr::Assign { var, expr } => {
+                var.print(p);
+                p.write(" <- ");
+                expr.print(p);
+            }
+            Expr::Dyadic { lhs, rhs, binop } => {
+                let (prec, assoc) = binop_precedence(&binop.body);
+                print_operand(p, lhs, prec, assoc == Associativity::Right);
+                p.write(" ");
+                binop.print(p);
+                p.write(" ");
+                print_operand(p, rhs, prec, assoc == Associativity::Left);
+            }
+            Expr::UnaryOperatorExpr { rhs, unaryop } => {
+                unaryop.print(p);
+                if unaryop.body != "-" {
+                    p.write(" ");
+                }
+                match rhs.body {
+                    Expr::Application { .. }
+                    | Expr::Command(_)
+                    | Expr::RecordMember { .. }
+                    | Expr::Unary(_) => rhs.print(p),
+                    _ => p.write_paren(rhs),
+                }
+            }
+            Expr::VariantConstructor { variant, arg } => {
+                variant.print(p);
+                if let Some(arg) = arg {
+                    if !is_parenthesized(&arg.body) {
+                        p.write(" ");
+                    }
+                    arg.print(p);
+                }
+            }
+            Expr::Application {
+                modname,
+                func,
+                args,
+            } => {
+                if let Some(modname) = modname {
+                    modname.print(p);
+                    p.write(".");
+                }
+                func.print(p);
+                for arg in args {
+                    p.write(" ");
+                    arg.print(p);
+                }
+            }
+            Expr::Command(name) => {
+                p.write("command ");
+                name.print(p);
+            }
+            Expr::RecordMember { record, member } => {
+                record.print(p);
+                if let Unary::Literal(_) = record.body {
+                    // 文字列リテラルの直後の `#` は文字列の一部とみなされてしまう
+                    p.write(" ");
+                }
+                p.write("#");
+                member.print(p);
+            }
+            Expr::Unary(unary) => unary.print(p),
+        }
+    }
+}
+
+/// 括弧で始まる単項式かどうか。
+fn is_parenthesized(unary: &Unary) -> bool {
+    matches!(
+        unary,
+        Unary::Expr { modname: None, .. } | Unary::Tuple(_) | Unary::Literal(Literal::Unit)
+    )
+}
+
+/// 二項演算の被演算子を出力する。
+///
+/// prec は親の演算子の優先順位で、weak_on_tie が真のときは同じ優先順位の演算子にも括弧を付ける。
+fn print_operand(p: &mut Printer, expr: &Ranged<Expr>, prec: u8, weak_on_tie: bool) {
+    let needs_paren = match &expr.body {
+        Expr::Dyadic { binop, .. } => {
+            let (prec_child, _) = binop_precedence(&binop.body);
+            prec_child < prec || (prec_child == prec && weak_on_tie)
+        }
+        Expr::UnaryOperatorExpr { .. }
+        | Expr::VariantConstructor { .. }
+        | Expr::Application { .. }
+        | Expr::Command(_)
+        | Expr::Unary(_) => false,
+        _ => true,
+    };
+    if needs_paren {
+        p.write_paren(expr);
+    } else {
+        expr.print(p);
+    }
+}
+
+/// match 式の各パターンを出力する。
+///
+/// 最後のパターン以外では、式の末尾に match 式が来ると後続のパターンを取り込んでしまうため括弧で囲む。
+fn print_match_arm(p: &mut Printer, arm: &Ranged<MatchArm>, is_last: bool) {
+    let arm = &arm.body;
+    arm.ptn.print(p);
+    if let Some(guard) = &arm.guard {
+        p.write(" when ");
+        print_arm_expr(p, guard, false);
+    }
+    p.write(" -> ");
+    print_arm_expr(p, &arm.expr, is_last);
+}
+
+/// match 式のガードや本体を出力する。
+fn print_arm_expr(p: &mut Printer, expr: &Ranged<Expr>, is_last: bool) {
+    let needs_paren = match expr.body {
+        // match 式から始まる式は書けない
+        Expr::Match { .. } => true,
+        _ => !is_last && ends_with_match(&expr.body),
+    };
+    if needs_paren {
+        p.write_paren(expr);
+    } else {
+        p.indented(|p| expr.print(p));
+    }
+}
+
+/// 括弧なしで出力したときに、末尾が match 式で終わるかどうか。
+fn ends_with_match(expr: &Expr) -> bool {
+    match expr {
+        Expr::Match { .. } => true,
+        Expr::BindStmt { body, .. } | Expr::Lambda { body, .. } => ends_with_match(&body.body),
+        Expr::CtrlFlowWhile { body, .. } => ends_with_match(&body.body),
+        Expr::CtrlFlowIf { expr_false, .. } => ends_with_match(&expr_false.body),
+        Expr::Assign { expr, .. } => ends_with_match(&expr.body),
+        _ => false,
+    }
+}
+
+impl Print for ApplicationArg {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            ApplicationArg::Unary(unary) => unary.print(p),
+            ApplicationArg::Variant(name) => p.write(name),
+            ApplicationArg::Optional(unary) => {
+                p.write("?:");
+                unary.print(p);
+            }
+            ApplicationArg::OptionalOmitted => p.write("?*"),
+        }
+    }
+}
+
+impl Print for Unary {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Unary::BlockText(vertical) => {
+                p.write("'<");
+                vertical.print(p);
+                p.write(">");
+            }
+            Unary::HorizontalText(horizontal) => {
+                p.write("{");
+                horizontal.print(p);
+                p.write("}");
+            }
+            Unary::MathText(math) => {
+                p.write("${");
+                math.print(p);
+                p.write("}");
+            }
+            Unary::Record(record) => record.print(p),
+            Unary::List(list) => list.print(p),
+            Unary::Tuple(tuple) => tuple.print(p),
+            Unary::BinOperator(op) => p.write_paren(op),
+            Unary::Literal(lit) => lit.print(p),
+            Unary::Expr { modname, expr } => {
+                if let Some(modname) = modname {
+                    modname.print(p);
+                    p.write(".");
+                }
+                p.write_paren(expr);
+            }
+            Unary::Variable { modname, var } => {
+                if let Some(modname) = modname {
+                    modname.print(p);
+                    p.write(".");
+                }
+                var.print(p);
+            }
+            Unary::Deref(unary) => {
+                p.write("!");
+                unary.print(p);
+            }
+            Unary::Next(unary) => print_staged(p, "&", unary),
+            Unary::Prev(unary) => print_staged(p, "~", unary),
+        }
+    }
+}
+
+/// `&e` や `~e` を出力する。
+fn print_staged(p: &mut Printer, op: &str, unary: &Ranged<Unary>) {
+    p.write(op);
+    // `&&` や `~~` は二項演算子とみなされてしまう
+    if let Unary::Next(_) | Unary::Prev(_) = unary.body {
+        p.write(" ");
+    }
+    unary.print(p);
+}
+
+impl Print for Record {
+    fn print(&self, p: &mut Printer) {
+        let (default, map) = match self {
+            Record::Map(map) => (None, map),
+            Record::MapWithDefault { map, default } => (Some(default), map),
+        };
+        if default.is_none() && map.is_empty() {
+            p.write("(||)");
+            return;
+        }
+        let broken = !p.fits(|p| self.print(p));
+        p.write("(|");
+        if let Some(default) = default {
+            p.write(" ");
+            default.print(p);
+            p.write(" with");
+        }
+        if broken {
+            p.write_broken(map);
+        } else {
+            p.write(" ");
+            p.write_separated(map, "; ");
+            p.write(" ");
+        }
+        p.write("|)");
+    }
+}
+
+impl Print for RecordUnit {
+    fn print(&self, p: &mut Printer) {
+        self.key.print(p);
+        p.write(" = ");
+        self.val.print(p);
+    }
+}
+
+impl Print for List {
+    fn print(&self, p: &mut Printer) {
+        let broken = !self.0.is_empty() && !p.fits(|p| self.print(p));
+        p.write("[");
+        if broken {
+            p.write_broken(&self.0);
+        } else {
+            p.write_separated(&self.0, "; ");
+        }
+        p.write("]");
+    }
+}
+
+impl Print for Tuple {
+    fn print(&self, p: &mut Printer) {
+        p.write("(");
+        p.write_separated(&self.0, ", ");
+        p.write(")");
+    }
+}
+
+impl Print for Variable {
+    fn print(&self, p: &mut Printer) {
+        p.write(&self.name)
+    }
+}
+
+/// コマンドの引数を出力する。テキスト引数が無い場合は末尾に `;` を付ける。
+fn print_command_args(p: &mut Printer, args: &[Ranged<CommandArg>]) {
+    for arg in args {
+        arg.print(p);
+    }
+    let has_text_arg = args.iter().any(|arg| {
+        matches!(
+            arg.body,
+            CommandArg::Vertical(_) | CommandArg::Horizontal(_)
+        )
+    });
+    if !has_text_arg {
+        p.write(";");
+    }
+}
+
+/// コマンドの引数となる式を出力する。
+fn print_command_expr_arg(p: &mut Printer, unary: &Unary) {
+    match unary {
+        Unary::Expr { modname: None, .. }
+        | Unary::List(_)
+        | Unary::Record(_)
+        | Unary::Literal(Literal::Unit) => unary.print(p),
+        _ => p.write_paren(unary),
+    }
+}
+
+impl Print for CommandArg {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            CommandArg::Expr(unary) => print_command_expr_arg(p, unary),
+            CommandArg::Optional(unary) => {
+                p.write("?:");
+                print_command_expr_arg(p, &unary.body);
+            }
+            CommandArg::OptionalOmitted => p.write("?*"),
+            CommandArg::Vertical(vertical) => {
+                p.write("<");
+                vertical.print(p);
+                p.write(">");
+            }
+            CommandArg::Horizontal(horizontal) => {
+                p.write("{");
+                horizontal.print(p);
+                p.write("}");
+            }
+        }
+    }
+}
+
+impl Print for Vertical {
+    fn print(&self, p: &mut Printer) {
+        let end = p.enclosing_end();
+        let has_trailing_comment = end.is_some_and(|end| p.has_comment_before(end));
+        if self.0.is_empty() && !has_trailing_comment {
+            return;
+        }
+        p.indented(|p| {
+            for elem in &self.0 {
+                p.newline();
+                p.flush_comments(elem.start);
+                elem.print(p);
+            }
+            if let Some(end) = end {
+                p.flush_trailing_comments(end);
+            }
+        });
+        p.newline();
+    }
+}
+
+impl Print for VerticalElement {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            VerticalElement::BlockCmd { name, args } => {
+                name.print(p);
+                print_command_args(p, args);
+            }
+            VerticalElement::BlockTextEmbedding { mod_name, name } => {
+                print_embedding(p, mod_name, name);
+            }
+        }
+    }
+}
+
+/// `#var;` や `#Mod.var;` を出力する。
+fn print_embedding(p: &mut Printer, mod_name: &Option<Ranged<String>>, name: &Ranged<String>) {
+    p.write("#");
+    if let Some(mod_name) = mod_name {
+        mod_name.print(p);
+        p.write(".");
+    }
+    name.print(p);
+    p.write(";");
+}
+
+impl Print for Horizontal {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Horizontal::Single(single) if p.reflow_text => text::print_reflowed(p, single),
+            Horizontal::Single(single) => text::print_single(p, single),
+            Horizontal::List(cells) if p.normalize_lists => text::print_table(p, cells),
+            Horizontal::List(cells) => {
+                p.write("|");
+                for cell in cells {
+                    p.write(" ");
+                    cell.print(p);
+                    p.write("|");
+                }
+            }
+            Horizontal::BulletList(bullets) if p.normalize_lists => text::print_bullets(p, bullets),
+            Horizontal::BulletList(bullets) => {
+                for bullet in bullets {
+                    bullet.print(p);
+                }
+            }
+        }
+    }
+}
+
+impl Print for HorizontalBullet {
+    fn print(&self, p: &mut Printer) {
+        p.write(&"*".repeat(self.indent as usize));
+        p.write(" ");
+        self.body.print(p);
+    }
+}
+
+impl Print for HorizontalSingle {
+    fn print(&self, p: &mut Printer) {
+        text::print_tokens(p, &self.0, false);
+    }
+}
+
+impl Print for HorizontalToken {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            HorizontalToken::Text(text) => text.print(p),
+            HorizontalToken::SpecialChar(c) => {
+                p.write("\\");
+                c.print(p);
+            }
+            HorizontalToken::HorizontalTextEmbedding { mod_name, name } => {
+                print_embedding(p, mod_name, name);
+            }
+            HorizontalToken::InlineCmd { name, args } => {
+                name.print(p);
+                print_command_args(p, args);
+            }
+            HorizontalToken::Math(math) => {
+                p.write("${");
+                math.print(p);
+                p.write("}");
+            }
+            HorizontalToken::StringLiteral(lit) => lit.print(p),
+        }
+    }
+}
+
+impl Print for Math {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Math::Single(single) => single.print(p),
+            Math::List(singles) => {
+                p.write("|");
+                for single in singles {
+                    p.write(" ");
+                    single.print(p);
+                    p.write(" |");
+                }
+            }
+        }
+    }
+}
+
+impl Print for MathSingle {
+    fn print(&self, p: &mut Printer) {
+        for (i, token) in self.0.iter().enumerate() {
+            // 英数字どうしは 1 文字ずつの要素なので空白なしで続けてよい
+            let both_char = i > 0
+                && matches!(self.0[i - 1].body.base.body, MathUnary::Char(_))
+                && matches!(token.body.base.body, MathUnary::Char(_))
+                && self.0[i - 1].body.sup.is_none()
+                && self.0[i - 1].body.sub.is_none();
+            if i > 0 && !both_char {
+                p.write(" ");
+            }
+            token.print(p);
+        }
+    }
+}
+
+impl Print for MathToken {
+    fn print(&self, p: &mut Printer) {
+        self.base.print(p);
+        if let Some(sub) = &self.sub {
+            p.write("_");
+            sub.print(p);
+        }
+        if let Some(sup) = &self.sup {
+            p.write("^");
+            sup.print(p);
+        }
+    }
+}
+
+impl Print for MathGroup {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            MathGroup::Group(single) => {
+                p.write("{");
+                single.print(p);
+                p.write("}");
+            }
+            MathGroup::Unary(unary) => unary.print(p),
+        }
+    }
+}
+
+impl Print for MathUnary {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            MathUnary::Char(c) | MathUnary::Symbol(c) => p.write(c),
+            MathUnary::SpecialChar(c) => {
+                p.write("\\");
+                p.write(c);
+            }
+            MathUnary::Cmd { name, args } => {
+                name.print(p);
+                for arg in args {
+                    arg.print(p);
+                }
+            }
+        }
+    }
+}
+
+impl Print for MathCmdArg {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            MathCmdArg::Math(math) => {
+                p.write("{");
+                math.print(p);
+                p.write("}");
+            }
+            MathCmdArg::Horizontal(horizontal) => {
+                p.write("!{");
+                horizontal.print(p);
+                p.write("}");
+            }
+            MathCmdArg::Vertical(vertical) => {
+                p.write("!<");
+                vertical.print(p);
+                p.write(">");
+            }
+            MathCmdArg::Expr(unary) => {
+                p.write("!");
+                match unary {
+                    Unary::Expr { modname: None, .. } | Unary::List(_) | Unary::Record(_) => {
+                        unary.print(p)
+                    }
+                    _ => p.write_paren(unary),
+                }
+            }
+            MathCmdArg::Optional(arg) => {
+                p.write("?:");
+                arg.print(p);
+            }
+        }
+    }
+}
+
+impl Print for Literal {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Literal::Unit => p.write("()"),
+            Literal::Bool(b) => p.write(if *b { "true" } else { "false" }),
+            Literal::String(s) => p.write(&string_literal(s)),
+            Literal::Length(length) => length.print(p),
+            Literal::Float(f) => {
+                let mut s = f.to_string();
+                if !s.contains('.') {
+                    s.push('.');
+                }
+                p.write(&s);
+            }
+            Literal::Int(i) => p.write(&i.to_string()),
+        }
+    }
+}
+
+impl Print for Length {
+    fn print(&self, p: &mut Printer) {
+        p.write(&self.value.body.to_string());
+        self.unit.print(p);
+    }
+}
+
+/// 文字列 s を表す文字列リテラルを作る。
+///
+/// s の中に現れるどのバッククォートの連続よりも長いバッククォートで囲み、
+/// 先頭や末尾が空白の場合は `#` を付けて空白が取り除かれないようにする。
+fn string_literal(s: &str) -> String {
+    if s.is_empty() {
+        return "` `".to_owned();
+    }
+    let longest_run = s.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let quote = "`".repeat(longest_run + 1);
+    let (prefix, pad_start) = match s.chars().next() {
+        Some(c) if c.is_whitespace() => ("#", ""),
+        Some('`') => ("", " "),
+        _ => ("", ""),
+    };
+    let (suffix, pad_end) = match s.chars().last() {
+        Some(c) if c.is_whitespace() => ("#", ""),
+        Some('`') => ("", " "),
+        _ => ("", ""),
+    };
+    format!(
+        "{}{}{}{}{}{}{}",
+        prefix, quote, pad_start, s, pad_end, quote, suffix
+    )
+}
@@ -0,0 +1,143 @@
+use super::*;
+use crate::sexp::{to_sexp, ToSexp};
+
+/// テスト用の関数。text をパースして出力した結果が expect と一致し、
+/// さらにその出力を再びパースすると、範囲を除いて元と同じ AST になり、
+/// それを出力しても変わらないことを検証する。
+fn assert_printed<T: Grammar + Print + ToSexp>(text: &str, expect: &str) {
+    let parsed = T::parse(text).unwrap();
+    let actual = print(&parsed);
+    assert_eq!(actual, expect);
+
+    let reparsed =
+        T::parse(&actual).unwrap_or_else(|e| panic!("failed to reparse {:?}: {}", actual, e));
+    assert_eq!(
+        to_sexp(&reparsed, false),
+        to_sexp(&parsed, false),
+        "AST changed by printing {:?}",
+        text
+    );
+    assert_eq!(print(&reparsed), actual);
+}
+
+#[test]
+fn print_literal() {
+    assert_printed::<Literal>("(  )", "()");
+    assert_printed::<Literal>("true", "true");
+    assert_printed::<Literal>("0x2F", "47");
+    assert_printed::<Literal>("123.", "123.");
+    assert_printed::<Literal>(".56", "0.56");
+    assert_printed::<Literal>("12.3pt", "12.3pt");
+    assert_printed::<Literal>("` `", "` `");
+    assert_printed::<Literal>("`a`", "`a`");
+    assert_printed::<Literal>("`` `a` ``", "`` `a` ``");
+    assert_printed::<Literal>("`` hoge`fuga ``", "``hoge`fuga``");
+    assert_printed::<Literal>("#`` hoge`fuga ``#", "#`` hoge`fuga ``#");
+}
+
+#[test]
+fn print_dyadic() {
+    assert_printed::<Expr>("1+2*3", "1 + 2 * 3");
+    assert_printed::<Expr>("(1 + 2) * 3", "(1 + 2) * 3");
+    assert_printed::<Expr>("1 - (2 - 3)", "1 - (2 - 3)");
+    assert_printed::<Expr>("(1 - 2) - 3", "(1 - 2) - 3");
+    assert_printed::<Expr>("a :: (b :: c)", "a :: (b :: c)");
+    assert_printed::<Expr>("a :: b :: c", "a :: b :: c");
+    assert_printed::<Expr>("f x + g y", "f x + g y");
+    assert_printed::<Expr>("-12pt +' 3pt", "-12pt +' 3pt");
+    assert_printed::<Expr>("not (a || b)", "not (a || b)");
+}
+
+#[test]
+fn print_staged() {
+    assert_printed::<Expr>("f & ( x + ~ y )", "f &(x + ~y)");
+    assert_printed::<Expr>("f & ~x", "f & ~x");
+    assert_printed::<Expr>("a && b", "a && b");
+}
+
+#[test]
+fn print_mutable() {
+    assert_printed::<Expr>("let-mutable x<-1 in ! x", "let-mutable x <- 1 in\n!x");
+    assert_printed::<Expr>("f !( g x ) !M.y", "f !(g x) !M.y");
+}
+
+#[test]
+fn print_bind_stmt() {
+    assert_printed::<Expr>(
+        "let x = 1 in let f y = y in f x",
+        "let x = 1 in\nlet f y = y in\nf x",
+    );
+    assert_printed::<Expr>(
+        "let x = let y = 1 in y in x",
+        "let x =\n  let y = 1 in\n  y\nin\nx",
+    );
+}
+
+#[test]
+fn print_let_rec() {
+    assert_printed::<Expr>(
+        "let-rec f x = f x and g = fun  y  ->f y in g",
+        "let-rec f x = f x\nand g = fun y -> f y in\ng",
+    );
+    assert_printed::<Expr>(
+        "let-rec f x = match x with | _ -> f x in f",
+        "let-rec f x =\n  match x with\n  | _ -> f x\nin\nf",
+    );
+}
+
+#[test]
+fn print_lambda() {
+    assert_printed::<Expr>("fun  x  ?:y -> x", "fun x ?:y -> x");
+    assert_printed::<Expr>("f (fun x -> x) 1", "f (fun x -> x) 1");
+    assert_printed::<Expr>("(fun x -> x) + 1", "(fun x -> x) + 1");
+    assert_printed::<Expr>(
+        "match x with | _ -> (fun y -> match y with | _ -> 1) | z -> z",
+        "match x with\n| _ -> (fun y -> match y with\n  | _ -> 1)\n| z -> z",
+    );
+}
+
+#[test]
+fn print_assign() {
+    assert_printed::<Expr>("x<-1+2", "x <- 1 + 2");
+    assert_printed::<Expr>("f (x <- 1)", "f (x <- 1)");
+}
+
+#[test]
+fn print_match() {
+    assert_printed::<Expr>(
+        "match x with | [] -> 0 | y :: ys -> 1",
+        "match x with\n| [] -> 0\n| y :: ys -> 1",
+    );
+    assert_printed::<Expr>(
+        "match x with | Some(y) -> (match y with | _ -> 1) | None -> 0",
+        "match x with\n| Some(y) -> (match y with\n  | _ -> 1)\n| None -> 0",
+    );
+}
+
+#[test]
+fn print_text() {
+    assert_printed::<Expr>("{foo \\bar{baz}}", "{foo \\bar{baz}}");
+    assert_printed::<Expr>("{\\cmd(1)(x);}", "{\\cmd(1)(x);}");
+    assert_printed::<Expr>("{|a|b|}", "{| a| b|}");
+    assert_printed::<Expr>("'<+p{a}+q;>", "'<\n  +p{a}\n  +q;\n>");
+    // 複数行のテキストの中身は `{` の次の行から始める
+    assert_printed::<Expr>(
+        "'<\n  +p{\n    hello\n    world\n  }\n>",
+        "'<\n  +p{\n    hello\n    world\n  }\n>",
+    );
+    assert_printed::<Expr>(
+        "'<\n  +section{Title}<\n    +p{\n      hello world\n    }\n  >\n>",
+        "'<\n  +section{Title}<\n    +p{\n      hello world\n    }\n  >\n>",
+    );
+    assert_printed::<Expr>("${x^2_i + \\alpha}", "${x_i^2 + \\alpha}");
+}
+
+#[test]
+fn print_program() {
+    let text = std::fs::read_to_string("examples/example1.saty").unwrap();
+    let parsed = Program::parse(&text).unwrap();
+    let printed = print(&parsed);
+    let reparsed = Program::parse(&printed).unwrap();
+    assert_eq!(to_sexp(&reparsed, false), to_sexp(&parsed, false));
+    assert_eq!(print(&reparsed), printed);
+}
@@ -0,0 +1,313 @@
+//! Layout of horizontal text.
+//!
+//! `regular_text` を語に分解して幅に合わせて詰め直す処理と、
+//! 箇条書きや表の正規化を行う。
+
+use super::{Print, Printer};
+use crate::grammar::common::{Location, Ranged};
+use crate::grammar::{HorizontalBullet, HorizontalSingle, HorizontalToken};
+
+/// 直前で改行してはならない文字。
+const NO_BREAK_BEFORE: &str =
+    "、。，．・：；？！ー）」』】〉》〕］｝ぁぃぅぇぉっゃゅょゎァィゥェォッャュョヮヵヶ々";
+
+/// 直後で改行してはならない文字。
+const NO_BREAK_AFTER: &str = "（「『【〈《〔［｛";
+
+/// 和文文字（改行を挟んでも空白が入らない文字）かどうか。
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{303F}'   // 和文の記号と句読点
+        | '\u{3040}'..='\u{30FF}' // 平仮名・片仮名
+        | '\u{3400}'..='\u{4DBF}' // CJK 統合漢字拡張 A
+        | '\u{4E00}'..='\u{9FFF}' // CJK 統合漢字
+        | '\u{F900}'..='\u{FAFF}' // CJK 互換漢字
+        | '\u{FF01}'..='\u{FF60}' // 全角英数と記号
+        | '\u{FFE0}'..='\u{FFE6}'
+    )
+}
+
+/// 文字列の表示幅。和文文字は 2 文字分の幅とする。
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if is_cjk(c) { 2 } else { 1 }).sum()
+}
+
+/// 語と語の間の区切り。
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Gap {
+    /// 空白。改行に置き換えてよい。
+    Space,
+    /// 和文文字の間の空白。改行にすると空白が消えてしまうため改行できない。
+    FixedSpace,
+    /// 和文文字の間。空白を入れずに改行できる。
+    Break,
+}
+
+/// 改行位置の候補で区切られた語。
+#[derive(Debug)]
+struct Word {
+    /// 直前の語との区切り。先頭の語では使わない。
+    gap: Gap,
+    /// 語の文字列。
+    text: String,
+}
+
+/// テキストを改行位置の候補で区切られた語の列に分解する。
+///
+/// range の中にコメントがある場合や、コマンドなどを 1 行に収められない場合は None を返す。
+fn split_words(
+    p: &Printer,
+    single: &HorizontalSingle,
+    range: Option<(Location, Location)>,
+) -> Option<Vec<Word>> {
+    if let Some((start, end)) = range {
+        if p.has_comment_within(start, end) {
+            return None;
+        }
+    }
+
+    let mut words: Vec<Word> = vec![];
+    // 直前の語の後に続く空白
+    let mut space = String::new();
+    // 直前の文字。コマンドなどの後では None
+    let mut last_char: Option<char> = None;
+
+    /// 語を追加するか、区切りがなければ直前の語に連結する。
+    fn push(words: &mut Vec<Word>, gap: Option<Gap>, text: &str) {
+        match (gap, words.last_mut()) {
+            (None, Some(word)) => word.text.push_str(text),
+            (gap, _) => words.push(Word {
+                gap: gap.unwrap_or(Gap::Space),
+                text: text.to_owned(),
+            }),
+        }
+    }
+
+    for token in &single.0 {
+        match &token.body {
+            HorizontalToken::Text(text) => {
+                for c in text.body.chars() {
+                    if c.is_whitespace() {
+                        space.push(c);
+                        continue;
+                    }
+                    let both_cjk = last_char.is_some_and(is_cjk) && is_cjk(c);
+                    let gap = if space.is_empty() {
+                        let breakable = both_cjk
+                            && !NO_BREAK_BEFORE.contains(c)
+                            && !last_char.is_some_and(|last| NO_BREAK_AFTER.contains(last));
+                        if breakable {
+                            Some(Gap::Break)
+                        } else {
+                            None
+                        }
+                    } else if both_cjk && space.contains('\n') {
+                        // 和文文字の間の改行は空白にならない
+                        Some(Gap::Break)
+                    } else if both_cjk {
+                        Some(Gap::FixedSpace)
+                    } else {
+                        Some(Gap::Space)
+                    };
+                    push(&mut words, gap, c.encode_utf8(&mut [0; 4]));
+                    space.clear();
+                    last_char = Some(c);
+                }
+            }
+            _ => {
+                let mut flat = p.sub_printer(None);
+                token.print(&mut flat);
+                let atom = flat.finish();
+                if atom.contains('\n') {
+                    return None;
+                }
+                let gap = if space.is_empty() {
+                    None
+                } else {
+                    Some(Gap::Space)
+                };
+                push(&mut words, gap, &atom);
+                space.clear();
+                last_char = None;
+            }
+        }
+    }
+    Some(words)
+}
+
+/// 語の列を 1 行に並べた文字列。
+fn join_flat(words: &[Word]) -> String {
+    let mut s = String::new();
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 && word.gap != Gap::Break {
+            s.push(' ');
+        }
+        s.push_str(&word.text);
+    }
+    s
+}
+
+/// 語の列を幅に収まるように改行しながら出力する。
+fn fill(p: &mut Printer, words: &[Word]) {
+    let width = p.width.unwrap_or(usize::MAX);
+    for (i, word) in words.iter().enumerate() {
+        let word_width = display_width(&word.text);
+        if i > 0 {
+            match word.gap {
+                Gap::FixedSpace => p.write(" "),
+                Gap::Space if p.column() + 1 + word_width > width => p.newline(),
+                Gap::Space => p.write(" "),
+                Gap::Break if p.column() + word_width > width => p.newline(),
+                Gap::Break => {}
+            }
+        }
+        p.write(&word.text);
+    }
+}
+
+/// `{ ... }` の中のテキストを詰め直して出力する。
+/// 1 行に収まらない場合は中身を字下げして複数行にする。
+pub(super) fn print_reflowed(p: &mut Printer, single: &HorizontalSingle) {
+    let words = match split_words(p, single, p.ranges.last().copied()) {
+        Some(words) => words,
+        None => return print_single(p, single),
+    };
+    let flat = join_flat(&words);
+    // 閉じ括弧の分も含めて収まるかどうか
+    if p.width
+        .is_none_or(|width| p.column() + display_width(&flat) < width)
+    {
+        p.write(&flat);
+        return;
+    }
+    p.indented(|p| {
+        p.newline();
+        fill(p, &words);
+    });
+    p.newline();
+}
+
+/// 箇条書きの項目や表のセルを、前後の空白を取り除いて出力する。
+fn print_item(p: &mut Printer, single: &Ranged<HorizontalSingle>) {
+    if p.reflow_text {
+        if let Some(words) = split_words(p, &single.body, Some((single.start, single.end))) {
+            return fill(p, &words);
+        }
+    }
+    print_tokens(p, &single.body.0, true);
+}
+
+/// `{ ... }` の中のテキストを、前後の空白を取り除いて出力する。
+/// 中身が複数行になる場合やコメントを含む場合は、中身を字下げした行に置いて `}` の前で改行する。
+pub(super) fn print_single(p: &mut Printer, single: &HorizontalSingle) {
+    let mut flat = p.sub_printer(None);
+    print_tokens(&mut flat, &single.0, true);
+    let has_newline = flat.finish().contains('\n')
+        || single.0.iter().any(|token| match &token.body {
+            HorizontalToken::Text(text) => text.body.contains('\n'),
+            _ => false,
+        });
+    let has_comment = p
+        .ranges
+        .last()
+        .is_some_and(|&(start, end)| p.has_comment_within(start, end));
+    if !has_newline && !has_comment {
+        return print_tokens(p, &single.0, true);
+    }
+    let end = p.enclosing_end();
+    p.indented(|p| {
+        // 中身の行末のコメントを `{` の後に出力しないよう、コメントを出力せずに改行する
+        p.break_line();
+        print_tokens(p, &single.0, true);
+        if let Some(end) = end {
+            p.flush_trailing_comments(end);
+        }
+    });
+    p.newline();
+}
+
+/// テキストのトークンを出力する。
+///
+/// trim が真のときは前後の空白を取り除き、改行の後の字下げを現在の字下げに揃える。
+pub(super) fn print_tokens(p: &mut Printer, tokens: &[Ranged<HorizontalToken>], trim: bool) {
+    for (i, token) in tokens.iter().enumerate() {
+        let flushed = p.flush_comments(token.start);
+        match &token.body {
+            HorizontalToken::Text(text) => {
+                let mut text = text.body.as_str();
+                if trim && i == 0 {
+                    text = text.trim_start();
+                }
+                if trim && i + 1 == tokens.len() {
+                    text = text.trim_end();
+                }
+                if flushed {
+                    // コメントの後の字下げは出力したものに置き換える
+                    text = text.trim_start_matches([' ', '\t']);
+                }
+                if !trim {
+                    p.write(text);
+                    continue;
+                }
+                let mut lines = text.split('\n');
+                p.write(lines.next().unwrap_or_default());
+                for line in lines {
+                    p.break_line();
+                    p.write(line.trim_start_matches([' ', '\t']));
+                }
+            }
+            _ => token.print(p),
+        }
+    }
+}
+
+/// 箇条書きを、1 行に 1 項目ずつ深さに応じて字下げして出力する。
+pub(super) fn print_bullets(p: &mut Printer, bullets: &[Ranged<HorizontalBullet>]) {
+    let end = p.enclosing_end();
+    p.indented(|p| {
+        for bullet in bullets {
+            let depth = bullet.body.indent as usize;
+            p.newline();
+            p.flush_comments(bullet.start);
+            p.write(&Printer::INDENT.repeat(depth.saturating_sub(1)));
+            p.write(&"*".repeat(depth));
+            p.write(" ");
+            p.indent += depth;
+            print_item(p, &bullet.body.body);
+            p.indent -= depth;
+        }
+        if let Some(end) = end {
+            p.flush_trailing_comments(end);
+        }
+    });
+    p.newline();
+}
+
+/// 表のセルを `| a | b |` の形に揃えて出力する。
+/// 1 行に収まらない場合はセルごとに改行する。
+pub(super) fn print_table(p: &mut Printer, cells: &[Ranged<HorizontalSingle>]) {
+    if p.fits(|p| print_table(p, cells)) {
+        p.write("|");
+        for cell in cells {
+            p.write(" ");
+            print_item(p, cell);
+            p.write(" |");
+        }
+        return;
+    }
+    let end = p.enclosing_end();
+    p.indented(|p| {
+        for cell in cells {
+            p.newline();
+            p.flush_comments(cell.start);
+            p.write("| ");
+            p.indented(|p| print_item(p, cell));
+        }
+        if let Some(end) = end {
+            p.flush_trailing_comments(end);
+        }
+        p.newline();
+        p.write("|");
+    });
+    p.newline();
+}
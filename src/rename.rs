@@ -0,0 +1,146 @@
+//! Rename refactoring.
+//!
+//! 変数やコマンド、モジュールの名前を変更するための [`TextEdit`] の列を求める。
+//! 定義、使用箇所、`sig` の `val` や `direct` の宣言、テキスト中の `#var;` がすべて書き換えの対象となる。
+//!
+//! 新しい名前が予約語である場合や、名前を変えることで参照先が変わってしまう場合
+//! （別の定義に隠される場合や、別の名前を隠してしまう場合）は変更を拒む。
+
+#[cfg(test)]
+mod tests;
+
+use crate::edit::{apply, TextEdit};
+use crate::grammar::common::{Grammar, Location, Span};
+use crate::grammar::Program;
+use crate::parser::{Rule, SatysfiParser};
+use crate::resolve::{resolve, Namespace, Resolution};
+use pest::Parser;
+use std::fmt;
+
+/// 名前を変更できない理由。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    /// 指定した位置に、定義の分かる名前がない。
+    NotFound,
+    /// 新しい名前が、その種類の名前として正しくない。
+    InvalidName(String),
+    /// 新しい名前が予約語である。
+    ReservedWord(String),
+    /// 名前を変えると、この範囲にある名前の参照先が変わってしまう。
+    Conflict(Span),
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameError::NotFound => write!(f, "no renamable name at the position"),
+            RenameError::InvalidName(name) => write!(f, "invalid name: {}", name),
+            RenameError::ReservedWord(name) => write!(f, "{} is a reserved word", name),
+            RenameError::Conflict(span) => write!(
+                f,
+                "renaming would change the meaning of the name at {}:{}",
+                span.start.row, span.start.col
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+/// loc にある名前を new_name に変更する編集を、文書中の順に返す。
+///
+/// program は text をパースしたものでなければならない。
+/// コマンドの場合、new_name の先頭の `\` や `+` は省略してもよい。
+pub fn rename(
+    text: &str,
+    program: &Program,
+    loc: Location,
+    new_name: &str,
+) -> Result<Vec<TextEdit>, RenameError> {
+    let resolution = resolve(program);
+    let id = resolution.binding_of(loc).ok_or(RenameError::NotFound)?;
+    let binding = &resolution.bindings[id];
+    let new_name = validate(binding.namespace, new_name)?;
+
+    let mut edits = vec![TextEdit::new(binding.span, new_name.clone())];
+    for (span, reference) in resolution.references_to(id) {
+        let new_text = match &reference.qualifier {
+            // `\M.cmd` はコマンド名全体が使用箇所の範囲になる
+            Some(module) if is_command(reference.namespace) => {
+                let (prefix, name) = new_name.split_at(1);
+                format!("{}{}.{}", prefix, module, name)
+            }
+            _ => new_name.clone(),
+        };
+        edits.push(TextEdit::new(span, new_text));
+    }
+    edits.sort();
+
+    check_conflicts(text, &resolution, &edits, &new_name)?;
+    Ok(edits)
+}
+
+/// コマンドの名前空間かどうか。
+fn is_command(namespace: Namespace) -> bool {
+    matches!(
+        namespace,
+        Namespace::InlineCmd | Namespace::BlockCmd | Namespace::MathCmd
+    )
+}
+
+/// text 全体が rule に一致するかどうか。
+fn matches_rule(rule: Rule, text: &str) -> bool {
+    SatysfiParser::parse(rule, text).is_ok_and(|pairs| pairs.as_str() == text)
+}
+
+/// 新しい名前を検査し、コマンドの場合は先頭の記号を補ったものを返す。
+fn validate(namespace: Namespace, new_name: &str) -> Result<String, RenameError> {
+    let invalid = || RenameError::InvalidName(new_name.to_owned());
+    match namespace {
+        Namespace::Variable => {
+            if !matches_rule(Rule::var_ptn, new_name) {
+                return Err(invalid());
+            }
+            if matches_rule(Rule::reserved_word, new_name) {
+                return Err(RenameError::ReservedWord(new_name.to_owned()));
+            }
+            Ok(new_name.to_owned())
+        }
+        Namespace::Module => match matches_rule(Rule::module_name, new_name) {
+            true => Ok(new_name.to_owned()),
+            false => Err(invalid()),
+        },
+        Namespace::InlineCmd | Namespace::BlockCmd | Namespace::MathCmd => {
+            let prefix = if namespace == Namespace::BlockCmd {
+                "+"
+            } else {
+                "\\"
+            };
+            let name = new_name.strip_prefix(prefix).unwrap_or(new_name);
+            if !matches_rule(Rule::var_ptn, name) {
+                return Err(invalid());
+            }
+            Ok(format!("{}{}", prefix, name))
+        }
+    }
+}
+
+/// 編集後のテキストで、すべての使用箇所の参照先が変わらないことを確かめる。
+fn check_conflicts(
+    text: &str,
+    resolution: &Resolution,
+    edits: &[TextEdit],
+    new_name: &str,
+) -> Result<(), RenameError> {
+    let renamed = apply(text, edits);
+    let program =
+        Program::parse(&renamed).map_err(|_| RenameError::InvalidName(new_name.to_owned()))?;
+    let after = resolve(&program);
+    // 名前を置き換えても使用箇所の並び順は変わらないため、順に対応させて比べる
+    for ((span, before), after) in resolution.references.iter().zip(after.references.values()) {
+        if before.binding != after.binding {
+            return Err(RenameError::Conflict(*span));
+        }
+    }
+    Ok(())
+}
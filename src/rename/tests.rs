@@ -0,0 +1,150 @@
+use super::*;
+use crate::grammar::common::Grammar;
+
+/// text 中で n 番目（0 始まり）に単語として現れる needle の開始位置。
+fn find(text: &str, needle: &str, n: usize) -> Location {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '-';
+    let (offset, _) = text
+        .match_indices(needle)
+        .filter(|(i, _)| {
+            !text[..*i].ends_with(is_word) && !text[i + needle.len()..].starts_with(is_word)
+        })
+        .nth(n)
+        .unwrap();
+    let before = &text[..offset];
+    let line = before.rfind('\n').map_or(before, |i| &before[i + 1..]);
+    Location {
+        row: before.matches('\n').count() + 1,
+        col: line.chars().count() + 1,
+    }
+}
+
+/// loc にある名前を new_name に変えたテキスト。
+fn renamed(text: &str, loc: Location, new_name: &str) -> Result<String, RenameError> {
+    let program = Program::parse(text).unwrap();
+    rename(text, &program, loc, new_name).map(|edits| apply(text, &edits))
+}
+
+#[test]
+fn variables() {
+    let text = "let x = 1 in let f y = x + y in f x";
+    assert_eq!(
+        renamed(text, find(text, "x", 2), "width").unwrap(),
+        "let width = 1 in let f y = width + y in f width"
+    );
+    assert_eq!(
+        renamed(text, find(text, "y", 0), "z").unwrap(),
+        "let x = 1 in let f z = x + z in f x"
+    );
+}
+
+#[test]
+fn commands_and_embeddings() {
+    let text = "\
+let-inline ctx \\emph inner = read-inline ctx inner
+let-block ctx +p inner = '<>
+let-inline ctx \\show x = read-inline ctx {(#x;)}
+in '<+p{\\emph{a} \\show(1);}>
+";
+    assert_eq!(
+        renamed(text, find(text, "\\emph", 1), "strong").unwrap(),
+        text.replace("\\emph", "\\strong")
+    );
+    assert_eq!(
+        renamed(text, find(text, "+p", 0), "+para").unwrap(),
+        text.replace("+p{", "+para{")
+            .replace("+p inner", "+para inner")
+    );
+    assert_eq!(
+        renamed(text, find(text, "x", 1), "value").unwrap(),
+        text.replace("show x", "show value")
+            .replace("#x;", "#value;")
+    );
+}
+
+#[test]
+fn modules() {
+    let text = "\
+module M : sig
+  val x : int
+  val \\a : [inline-text] inline-cmd
+  direct \\c : [inline-text] inline-cmd
+end = struct
+  let x = 1
+  let-inline ctx \\a = x
+  let-inline ctx \\c = x
+end
+in {#M.x; \\M.a; \\c; \\M.c;} ++ f M.x
+";
+    assert_eq!(
+        renamed(text, find(text, "x", 1), "y").unwrap(),
+        text.replace("val x", "val y")
+            .replace("let x", "let y")
+            .replace("= x", "= y")
+            .replace("M.x", "M.y")
+    );
+    assert_eq!(
+        renamed(text, find(text, "\\a", 0), "\\b").unwrap(),
+        text.replace("\\a", "\\b").replace("\\M.a", "\\M.b")
+    );
+    assert_eq!(
+        renamed(text, find(text, "\\c", 2), "d").unwrap(),
+        text.replace("\\c", "\\d").replace("\\M.c", "\\M.d")
+    );
+    assert_eq!(
+        renamed(text, find(text, "M", 0), "Mod").unwrap(),
+        text.replace("M ", "Mod ").replace("M.", "Mod.")
+    );
+}
+
+#[test]
+fn invalid_names() {
+    let text = "let x = 1 in x";
+    let loc = find(text, "x", 0);
+    assert_eq!(
+        renamed(text, loc, "let"),
+        Err(RenameError::ReservedWord("let".to_owned()))
+    );
+    assert_eq!(
+        renamed(text, loc, "Foo"),
+        Err(RenameError::InvalidName("Foo".to_owned()))
+    );
+    assert_eq!(
+        renamed(text, loc, "a b"),
+        Err(RenameError::InvalidName("a b".to_owned()))
+    );
+    // 予約語で始まるだけの名前はよい
+    assert_eq!(
+        renamed(text, loc, "letter").unwrap(),
+        "let letter = 1 in letter"
+    );
+    assert_eq!(
+        renamed(text, Location { row: 1, col: 9 }, "y"),
+        Err(RenameError::NotFound)
+    );
+}
+
+#[test]
+fn conflicts() {
+    let text = "let x = 1 in let y = 2 in x + y";
+    // 内側の y に隠されてしまう
+    assert!(matches!(
+        renamed(text, find(text, "x", 0), "y"),
+        Err(RenameError::Conflict(_))
+    ));
+    // 外側の x を隠してしまう
+    assert!(matches!(
+        renamed(text, find(text, "y", 0), "x"),
+        Err(RenameError::Conflict(_))
+    ));
+    // 定義の見つからない名前を隠してしまう
+    let text = "let x = 1 in f x";
+    assert!(matches!(
+        renamed(text, find(text, "x", 0), "f"),
+        Err(RenameError::Conflict(_))
+    ));
+    assert_eq!(
+        renamed(text, find(text, "x", 0), "g").unwrap(),
+        "let g = 1 in f g"
+    );
+}
@@ -0,0 +1,586 @@
+//! Name resolution.
+//!
+//! 変数やコマンド名、モジュール名の使用箇所が、どの定義を指しているかを求める。
+//!
+//! - `let ... in` で定義した名前は `in` の後の式でのみ見える。
+//! - `let` などの右辺からは、定義しようとしている名前自身は見えない。
+//! - `let-rec` で定義した名前は、自身の右辺と、`and` で並べた他の定義の右辺からも見える。
+//! - `fun` の引数は `->` の後の式でのみ見える。
+//! - `module` の中で定義した名前は `M.x` や `\M.cmd` の形で参照できる。
+//!   `sig` がある場合は、そこで `val` や `direct` により宣言した名前だけが外から見える。
+//!   `direct` で宣言したコマンドはモジュール名を付けずにも参照できる。
+//! - `M.( ... )` の中では M の名前をモジュール名を付けずに参照できる。
+//!
+//! 定義の見つからない名前は、プリミティブなどの大域的な名前として [`Reference::binding`] を None とする。
+//!
+//! エディタの定義へのジャンプや参照の検索には [`definition_at`] と [`references_of`] を使う。
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::{Location, Ranged, Span};
+use crate::grammar::{
+    Argument, Expr, HorizontalToken, LetRecBinding, MatchArm, MathUnary, Pattern, Program, SigStmt,
+    Signature, Statement, Unary, ValName, Variable, VerticalElement,
+};
+use crate::visit::{self, Visitor};
+use std::collections::{BTreeMap, HashMap};
+
+/// 名前空間。同じ名前でも名前空間が異なれば別のものを指す。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Namespace {
+    /// 変数。
+    Variable,
+    /// インラインコマンド。`\cmd`
+    InlineCmd,
+    /// ブロックコマンド。`+cmd`
+    BlockCmd,
+    /// 数式コマンド。`\cmd`
+    MathCmd,
+    /// モジュール。
+    Module,
+}
+
+/// 名前を導入した構文。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindingKind {
+    /// `let` のパターン。
+    Let,
+    /// `let-rec` で定義した関数。
+    LetRec,
+    /// `let-mutable`
+    LetMutable,
+    /// `let-inline` で定義したコマンド。
+    LetInline,
+    /// `let-block` で定義したコマンド。
+    LetBlock,
+    /// `let-math` で定義したコマンド。
+    LetMath,
+    /// `let` や `let-inline`、`fun` などの引数。`let-inline ctx` の ctx も含む。
+    Parameter,
+    /// `match` のパターン。
+    Pattern,
+    /// `module`
+    Module,
+}
+
+/// 定義の番号。[`Resolution::bindings`] の添字。
+pub type BindingId = usize;
+
+/// 名前の定義。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    /// 名前。コマンドの場合は `\` や `+` を含む。
+    pub name: String,
+    /// 名前空間。
+    pub namespace: Namespace,
+    /// 名前を導入した構文。
+    pub kind: BindingKind,
+    /// 名前の範囲。
+    pub span: Span,
+    /// モジュールの直下で定義された場合はそのモジュール。
+    pub module: Option<BindingId>,
+    /// この定義によって隠された定義。
+    /// 同じスコープにある同じ名前の定義か、`direct` や `M.( ... )` によって
+    /// モジュール名なしで見えていたモジュールのメンバに限る。
+    pub shadowed: Option<BindingId>,
+}
+
+/// 名前の使用箇所。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// 名前。コマンドの場合は `\` や `+` を含み、モジュール名は含まない。
+    pub name: String,
+    /// 名前空間。
+    pub namespace: Namespace,
+    /// `M.x` のようにモジュール名を付けて参照した場合はそのモジュール名。
+    pub qualifier: Option<String>,
+    /// 参照先の定義。見つからない場合は None。
+    pub binding: Option<BindingId>,
+}
+
+/// 名前解決の結果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolution {
+    /// 定義の一覧。出現順に並ぶ。
+    pub bindings: Vec<Binding>,
+    /// 使用箇所の範囲から使用箇所への対応。
+    /// `\M.cmd` のようなコマンド名は全体の範囲を持ち、モジュール名の部分は別の使用箇所となる。
+    pub references: BTreeMap<Span, Reference>,
+}
+
+impl Resolution {
+    /// 名前の範囲が span である定義。
+    pub fn binding_at(&self, span: Span) -> Option<BindingId> {
+        self.bindings
+            .iter()
+            .position(|binding| binding.span == span)
+    }
+
+    /// id の定義を参照している使用箇所。
+    pub fn references_to(&self, id: BindingId) -> impl Iterator<Item = (Span, &Reference)> {
+        self.references
+            .iter()
+            .filter(move |(_, reference)| reference.binding == Some(id))
+            .map(|(span, reference)| (*span, reference))
+    }
+
+    /// loc にある名前（定義または使用箇所）が指す定義。
+    ///
+    /// 名前の直後の位置も名前の上とみなす。
+    /// `\M.cmd` のモジュール名の部分のように範囲が入れ子になる場合は、内側のものを選ぶ。
+    pub fn binding_of(&self, loc: Location) -> Option<BindingId> {
+        let on = |span: &Span| span.start <= loc && loc <= span.end;
+        let definitions = self
+            .bindings
+            .iter()
+            .enumerate()
+            .filter(|(_, binding)| on(&binding.span))
+            .map(|(id, binding)| (binding.span, Some(id)));
+        let references = self
+            .references
+            .iter()
+            .filter(|(span, _)| on(span))
+            .map(|(span, reference)| (*span, reference.binding));
+        definitions
+            .chain(references)
+            .max_by_key(|(span, _)| (span.start, std::cmp::Reverse(span.end)))
+            .and_then(|(_, id)| id)
+    }
+
+    /// 定義の見つからない使用箇所。
+    pub fn unresolved(&self) -> impl Iterator<Item = (Span, &Reference)> {
+        self.references
+            .iter()
+            .filter(|(_, reference)| reference.binding.is_none())
+            .map(|(span, reference)| (*span, reference))
+    }
+}
+
+/// プログラム中の名前を解決する。
+pub fn resolve(program: &Program) -> Resolution {
+    let mut resolver = Resolver {
+        resolution: Resolution::default(),
+        scopes: vec![Scope::new()],
+        modules: HashMap::new(),
+    };
+    resolver.visit_program(program);
+    resolver.resolution
+}
+
+/// loc にある名前の定義の範囲を返す。定義の上にある場合はその定義自身を返す。
+///
+/// 定義の見つからない名前の上にある場合は None を返す。
+pub fn definition_at(program: &Program, loc: Location) -> Option<Span> {
+    let resolution = resolve(program);
+    let id = resolution.binding_of(loc)?;
+    Some(resolution.bindings[id].span)
+}
+
+/// loc にある名前と同じ定義を指す使用箇所の範囲を、文書中の順に返す。定義自身の範囲は含まない。
+pub fn references_of(program: &Program, loc: Location) -> Vec<Span> {
+    let resolution = resolve(program);
+    match resolution.binding_of(loc) {
+        Some(id) => resolution.references_to(id).map(|(span, _)| span).collect(),
+        None => vec![],
+    }
+}
+
+/// 名前空間と名前の組から定義への対応。
+type Scope = HashMap<(Namespace, String), BindingId>;
+
+/// 名前解決を行う Visitor。
+struct Resolver {
+    /// 結果。
+    resolution: Resolution,
+    /// 見えている名前。内側のスコープほど後ろにある。
+    scopes: Vec<Scope>,
+    /// モジュールごとの、外から見える名前。
+    modules: HashMap<BindingId, Scope>,
+}
+
+impl Resolver {
+    /// 現在のスコープに定義を追加する。
+    fn bind(&mut self, name: &str, namespace: Namespace, kind: BindingKind, span: Span) {
+        let id = self.resolution.bindings.len();
+        let key = (namespace, name.to_owned());
+        let shadowed = self.scopes.last().unwrap().get(&key).copied().or_else(|| {
+            self.lookup(namespace, name)
+                .filter(|&other| self.resolution.bindings[other].module.is_some())
+        });
+        self.resolution.bindings.push(Binding {
+            name: name.to_owned(),
+            namespace,
+            kind,
+            span,
+            module: None,
+            shadowed,
+        });
+        self.scopes.last_mut().unwrap().insert(key, id);
+    }
+
+    /// パターン中の変数を定義する。
+    fn bind_pattern(&mut self, ptn: &Pattern, span: Span, kind: BindingKind) {
+        match ptn {
+            Pattern::List(ptns) | Pattern::Tuple(ptns) => {
+                for ptn in ptns {
+                    self.bind_pattern(&ptn.body, ptn.span(), kind);
+                }
+            }
+            Pattern::Paren(ptn) => self.bind_pattern(&ptn.body, ptn.span(), kind),
+            Pattern::Wildcard | Pattern::Literal(_) => {}
+            Pattern::Variable(var) => self.bind(&var.name, Namespace::Variable, kind, span),
+            Pattern::Variant { arg, .. } => {
+                if let Some(arg) = arg {
+                    self.bind_pattern(&arg.body, arg.span(), kind);
+                }
+            }
+            Pattern::As { ptn, var } => {
+                self.bind_pattern(&ptn.body, ptn.span(), kind);
+                self.bind(&var.body.name, Namespace::Variable, kind, var.span());
+            }
+            Pattern::Cons { head, tail } => {
+                self.bind_pattern(&head.body, head.span(), kind);
+                self.bind_pattern(&tail.body, tail.span(), kind);
+            }
+        }
+    }
+
+    /// 引数を定義する。
+    fn bind_args(&mut self, args: &[Ranged<Argument>]) {
+        for arg in args {
+            match &arg.body {
+                Argument::Pattern(ptn) => {
+                    self.bind_pattern(ptn, arg.span(), BindingKind::Parameter)
+                }
+                Argument::Optional(var) => self.bind(
+                    &var.body,
+                    Namespace::Variable,
+                    BindingKind::Parameter,
+                    var.span(),
+                ),
+            }
+        }
+    }
+
+    /// 見えている名前から定義を探す。
+    fn lookup(&self, namespace: Namespace, name: &str) -> Option<BindingId> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&(namespace, name.to_owned())).copied())
+    }
+
+    /// 使用箇所を記録する。
+    fn refer(
+        &mut self,
+        span: Span,
+        namespace: Namespace,
+        name: &str,
+        qualifier: Option<String>,
+        binding: Option<BindingId>,
+    ) {
+        let reference = Reference {
+            name: name.to_owned(),
+            namespace,
+            qualifier,
+            binding,
+        };
+        self.resolution.references.insert(span, reference);
+    }
+
+    /// モジュール名の使用箇所を記録し、そのモジュールを返す。
+    fn refer_module(&mut self, name: &str, span: Span) -> Option<BindingId> {
+        let id = self.lookup(Namespace::Module, name);
+        self.refer(span, Namespace::Module, name, None, id);
+        id
+    }
+
+    /// 名前の使用箇所を記録する。module が与えられた場合はそのモジュールの中から探す。
+    fn refer_name(
+        &mut self,
+        namespace: Namespace,
+        name: &str,
+        span: Span,
+        module: Option<(&str, Span)>,
+    ) {
+        let binding = match module {
+            Some((module, module_span)) => self.refer_module(module, module_span).and_then(|id| {
+                self.modules[&id]
+                    .get(&(namespace, name.to_owned()))
+                    .copied()
+            }),
+            None => self.lookup(namespace, name),
+        };
+        let qualifier = module.map(|(module, _)| module.to_owned());
+        self.refer(span, namespace, name, qualifier, binding);
+    }
+
+    /// `\M.cmd` のようなコマンド名の使用箇所を記録する。
+    fn refer_command(&mut self, namespace: Namespace, name: &Ranged<String>) {
+        let (prefix, rest) = name.body.split_at(1);
+        match rest.split_once('.') {
+            Some((module, cmd)) => {
+                // コマンド名は 1 行に収まるため、列をずらしてモジュール名の範囲を求める
+                let start = Location {
+                    row: name.start.row,
+                    col: name.start.col + 1,
+                };
+                let end = Location {
+                    row: start.row,
+                    col: start.col + module.chars().count(),
+                };
+                let cmd = format!("{}{}", prefix, cmd);
+                self.refer_name(
+                    namespace,
+                    &cmd,
+                    name.span(),
+                    Some((module, Span { start, end })),
+                );
+            }
+            None => self.refer_name(namespace, &name.body, name.span(), None),
+        }
+    }
+
+    /// 変数の使用箇所を記録する。
+    fn refer_variable(&mut self, modname: &Option<Ranged<String>>, var: &Ranged<Variable>) {
+        let module = modname.as_ref().map(|m| (m.body.as_str(), m.span()));
+        self.refer_name(Namespace::Variable, &var.body.name, var.span(), module);
+    }
+
+    /// `sig` で宣言された名前の使用箇所を記録し、外から見える名前を返す。
+    /// direct で宣言されたものは外側のスコープにも追加する。
+    fn export(&mut self, signature: &Signature, members: &Scope) -> Scope {
+        let mut exported = Scope::new();
+        for stmt in &signature.0 {
+            let (namespace, name, span, direct) = match &stmt.body {
+                SigStmt::Type { .. } => continue,
+                SigStmt::Val { name, .. } => {
+                    let namespace = match &name.body {
+                        ValName::Var(_) => Namespace::Variable,
+                        ValName::InlineCmd(_) => Namespace::InlineCmd,
+                        ValName::BlockCmd(_) => Namespace::BlockCmd,
+                        ValName::BinOperator(_) => continue,
+                    };
+                    let name_str = match &name.body {
+                        ValName::Var(s)
+                        | ValName::InlineCmd(s)
+                        | ValName::BlockCmd(s)
+                        | ValName::BinOperator(s) => s,
+                    };
+                    (namespace, name_str, name.span(), false)
+                }
+                SigStmt::Direct { name, .. } => {
+                    let namespace = if name.body.starts_with('+') {
+                        Namespace::BlockCmd
+                    } else {
+                        Namespace::InlineCmd
+                    };
+                    (namespace, &name.body, name.span(), true)
+                }
+            };
+            let key = (namespace, name.clone());
+            let binding = members.get(&key).copied();
+            self.refer(span, namespace, name, None, binding);
+            if let Some(id) = binding {
+                exported.insert(key.clone(), id);
+                if direct {
+                    self.scopes.last_mut().unwrap().insert(key, id);
+                }
+            }
+        }
+        exported
+    }
+
+    /// 文を読み、定義された名前を現在のスコープに追加する。
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let { ptn, args, expr } => {
+                self.scopes.push(Scope::new());
+                self.bind_args(args);
+                self.visit_expr(&expr.body, expr.span());
+                self.scopes.pop();
+                self.bind_pattern(&ptn.body, ptn.span(), BindingKind::Let);
+            }
+            Statement::LetRec(bindings) => {
+                // 右辺を読む前にすべての名前を定義しておく
+                for binding in bindings {
+                    let var = &binding.body.var;
+                    let kind = BindingKind::LetRec;
+                    self.bind(&var.body.name, Namespace::Variable, kind, var.span());
+                }
+                for binding in bindings {
+                    let LetRecBinding { args, expr, .. } = &binding.body;
+                    self.scopes.push(Scope::new());
+                    self.bind_args(args);
+                    self.visit_expr(&expr.body, expr.span());
+                    self.scopes.pop();
+                }
+            }
+            Statement::LetInline {
+                ctx,
+                name,
+                args,
+                expr,
+            }
+            | Statement::LetBlock {
+                ctx,
+                name,
+                args,
+                expr,
+            } => {
+                self.scopes.push(Scope::new());
+                if let Some(ctx) = ctx {
+                    let kind = BindingKind::Parameter;
+                    self.bind(&ctx.body.name, Namespace::Variable, kind, ctx.span());
+                }
+                self.bind_args(args);
+                self.visit_expr(&expr.body, expr.span());
+                self.scopes.pop();
+                let (namespace, kind) = match stmt {
+                    Statement::LetInline { .. } => (Namespace::InlineCmd, BindingKind::LetInline),
+                    _ => (Namespace::BlockCmd, BindingKind::LetBlock),
+                };
+                self.bind(&name.body, namespace, kind, name.span());
+            }
+            Statement::LetMath { name, args, expr } => {
+                self.scopes.push(Scope::new());
+                self.bind_args(args);
+                self.visit_expr(&expr.body, expr.span());
+                self.scopes.pop();
+                let kind = BindingKind::LetMath;
+                self.bind(&name.body, Namespace::MathCmd, kind, name.span());
+            }
+            Statement::LetMutable { var, expr } => {
+                self.visit_expr(&expr.body, expr.span());
+                let kind = BindingKind::LetMutable;
+                self.bind(&var.body.name, Namespace::Variable, kind, var.span());
+            }
+            Statement::Module {
+                name,
+                signature,
+                body,
+            } => {
+                self.scopes.push(Scope::new());
+                self.visit_struct(&body.body, body.span());
+                let members = self.scopes.pop().unwrap();
+                let exported = match signature {
+                    Some(signature) => self.export(&signature.body, &members),
+                    None => members.clone(),
+                };
+                let id = self.resolution.bindings.len();
+                for &member in members.values() {
+                    self.resolution.bindings[member].module = Some(id);
+                }
+                self.bind(
+                    &name.body,
+                    Namespace::Module,
+                    BindingKind::Module,
+                    name.span(),
+                );
+                self.modules.insert(id, exported);
+            }
+        }
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_statement(&mut self, node: &Statement, _span: Span) {
+        self.statement(node);
+    }
+
+    fn visit_expr(&mut self, node: &Expr, span: Span) {
+        match node {
+            Expr::BindStmt { bind, body } => {
+                self.scopes.push(Scope::new());
+                self.statement(&bind.body);
+                self.visit_expr(&body.body, body.span());
+                self.scopes.pop();
+            }
+            Expr::Lambda { args, body } => {
+                self.scopes.push(Scope::new());
+                self.bind_args(args);
+                self.visit_expr(&body.body, body.span());
+                self.scopes.pop();
+            }
+            Expr::Application {
+                modname,
+                func,
+                args,
+            } => {
+                self.refer_variable(modname, func);
+                for arg in args {
+                    self.visit_application_arg(&arg.body, arg.span());
+                }
+            }
+            Expr::Assign { var, expr } => {
+                self.refer_variable(&None, var);
+                self.visit_expr(&expr.body, expr.span());
+            }
+            Expr::Command(name) => self.refer_command(Namespace::InlineCmd, name),
+            // メンバ名はレコードのフィールドであり、変数ではない
+            Expr::RecordMember { record, .. } => self.visit_unary(&record.body, record.span()),
+            _ => visit::walk_expr(self, node, span),
+        }
+    }
+
+    fn visit_match_arm(&mut self, node: &MatchArm, _span: Span) {
+        self.scopes.push(Scope::new());
+        self.bind_pattern(&node.ptn.body, node.ptn.span(), BindingKind::Pattern);
+        if let Some(guard) = &node.guard {
+            self.visit_expr(&guard.body, guard.span());
+        }
+        self.visit_expr(&node.expr.body, node.expr.span());
+        self.scopes.pop();
+    }
+
+    fn visit_unary(&mut self, node: &Unary, span: Span) {
+        match node {
+            Unary::Variable { modname, var } => self.refer_variable(modname, var),
+            Unary::Expr {
+                modname: Some(modname),
+                expr,
+            } => {
+                // M.( ... ) の中では M の名前が直接見える
+                let module = self.refer_module(&modname.body, modname.span());
+                let scope = module.map(|id| self.modules[&id].clone());
+                self.scopes.push(scope.unwrap_or_default());
+                self.visit_expr(&expr.body, expr.span());
+                self.scopes.pop();
+            }
+            _ => visit::walk_unary(self, node, span),
+        }
+    }
+
+    fn visit_horizontal_token(&mut self, node: &HorizontalToken, span: Span) {
+        match node {
+            HorizontalToken::InlineCmd { name, .. } => {
+                self.refer_command(Namespace::InlineCmd, name)
+            }
+            HorizontalToken::HorizontalTextEmbedding { mod_name, name } => {
+                let module = mod_name.as_ref().map(|m| (m.body.as_str(), m.span()));
+                self.refer_name(Namespace::Variable, &name.body, name.span(), module);
+            }
+            _ => {}
+        }
+        visit::walk_horizontal_token(self, node, span);
+    }
+
+    fn visit_vertical_element(&mut self, node: &VerticalElement, span: Span) {
+        match node {
+            VerticalElement::BlockCmd { name, .. } => self.refer_command(Namespace::BlockCmd, name),
+            VerticalElement::BlockTextEmbedding { mod_name, name } => {
+                let module = mod_name.as_ref().map(|m| (m.body.as_str(), m.span()));
+                self.refer_name(Namespace::Variable, &name.body, name.span(), module);
+            }
+        }
+        visit::walk_vertical_element(self, node, span);
+    }
+
+    fn visit_math_unary(&mut self, node: &MathUnary, span: Span) {
+        if let MathUnary::Cmd { name, .. } = node {
+            self.refer_command(Namespace::MathCmd, name);
+        }
+        visit::walk_math_unary(self, node, span);
+    }
+}
@@ -0,0 +1,346 @@
+use super::*;
+use crate::grammar::common::Grammar;
+
+/// text 中で n 番目（0 始まり）に単語として現れる needle の範囲。
+fn find(text: &str, needle: &str, n: usize) -> Span {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '-';
+    let (offset, _) = text
+        .match_indices(needle)
+        .filter(|(i, _)| {
+            !text[..*i].ends_with(is_word) && !text[i + needle.len()..].starts_with(is_word)
+        })
+        .nth(n)
+        .unwrap();
+    let location = |offset: usize| {
+        let before = &text[..offset];
+        let row = before.matches('\n').count() + 1;
+        let line = before.rfind('\n').map_or(before, |i| &before[i + 1..]);
+        Location {
+            row,
+            col: line.chars().count() + 1,
+        }
+    };
+    Span {
+        start: location(offset),
+        end: location(offset + needle.len()),
+    }
+}
+
+/// 使用箇所が参照する定義の範囲。
+fn target(resolution: &Resolution, span: Span) -> Option<Span> {
+    let reference = &resolution.references[&span];
+    reference.binding.map(|id| resolution.bindings[id].span)
+}
+
+#[test]
+fn let_in_scoping() {
+    let text = "let x = 1 in let x = x + 1 in let f y = f y x in f x";
+    let resolution = resolve(&Program::parse(text).unwrap());
+    // 右辺からは定義しようとしている名前は見えない
+    assert_eq!(
+        target(&resolution, find(text, "x", 2)),
+        Some(find(text, "x", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "y", 1)),
+        Some(find(text, "y", 0))
+    );
+    assert_eq!(target(&resolution, find(text, "f", 1)), None);
+    assert_eq!(
+        target(&resolution, find(text, "x", 3)),
+        Some(find(text, "x", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "f", 2)),
+        Some(find(text, "f", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "x", 4)),
+        Some(find(text, "x", 1))
+    );
+    let f = resolution.binding_at(find(text, "f", 0)).unwrap();
+    assert_eq!(resolution.bindings[f].kind, BindingKind::Let);
+    assert_eq!(resolution.references_to(f).count(), 1);
+}
+
+#[test]
+fn let_rec_scoping() {
+    let text = "let-rec even n = odd n and odd n = even n in let-rec f x = f x in f";
+    let resolution = resolve(&Program::parse(text).unwrap());
+    // 自身の右辺や、and で並べた他の定義の右辺から見える
+    assert_eq!(
+        target(&resolution, find(text, "odd", 0)),
+        Some(find(text, "odd", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "even", 1)),
+        Some(find(text, "even", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "f", 1)),
+        Some(find(text, "f", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "f", 2)),
+        Some(find(text, "f", 0))
+    );
+    // 引数は各定義の右辺でのみ見える
+    assert_eq!(
+        target(&resolution, find(text, "n", 3)),
+        Some(find(text, "n", 2))
+    );
+    let f = resolution.binding_at(find(text, "f", 0)).unwrap();
+    assert_eq!(resolution.bindings[f].kind, BindingKind::LetRec);
+    assert_eq!(resolution.references_to(f).count(), 2);
+}
+
+#[test]
+fn lambda_parameters() {
+    let text = "let x = 1 in let f = fun x ?:y -> x y in f (fun z -> z) x";
+    let resolution = resolve(&Program::parse(text).unwrap());
+    let binding =
+        |needle, n| &resolution.bindings[resolution.binding_at(find(text, needle, n)).unwrap()];
+    assert_eq!(binding("x", 1).kind, BindingKind::Parameter);
+    assert_eq!(binding("y", 0).kind, BindingKind::Parameter);
+    assert_eq!(binding("z", 0).kind, BindingKind::Parameter);
+    assert_eq!(
+        target(&resolution, find(text, "x", 2)),
+        Some(find(text, "x", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "y", 1)),
+        Some(find(text, "y", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "z", 1)),
+        Some(find(text, "z", 0))
+    );
+    // 引数は `->` の後の式の外からは見えない
+    assert_eq!(
+        target(&resolution, find(text, "x", 3)),
+        Some(find(text, "x", 0))
+    );
+}
+
+#[test]
+fn let_mutable_scoping() {
+    let text = "let-mutable x <- 1 in let f () = (x <- !x + 1) in !x";
+    let resolution = resolve(&Program::parse(text).unwrap());
+    let binding =
+        |needle, n| &resolution.bindings[resolution.binding_at(find(text, needle, n)).unwrap()];
+    assert_eq!(binding("x", 0).kind, BindingKind::LetMutable);
+    for n in 1..4 {
+        assert_eq!(
+            target(&resolution, find(text, "x", n)),
+            Some(find(text, "x", 0))
+        );
+    }
+}
+
+#[test]
+fn commands_and_patterns() {
+    let text = "\
+let-inline ctx \\emph ?:size inner = read-inline ctx inner
+let-block ctx +p inner = match inner with
+  | (a, b) when a -> '<+p{#b;}>
+  | c -> '<+p{\\emph{#c;}}>
+in '<+p{\\emph{x}}>
+";
+    let resolution = resolve(&Program::parse(text).unwrap());
+    let binding =
+        |needle, n| &resolution.bindings[resolution.binding_at(find(text, needle, n)).unwrap()];
+    assert_eq!(binding("ctx", 0).kind, BindingKind::Parameter);
+    assert_eq!(binding("size", 0).kind, BindingKind::Parameter);
+    assert_eq!(binding("\\emph", 0).kind, BindingKind::LetInline);
+    assert_eq!(binding("+p", 0).kind, BindingKind::LetBlock);
+    assert_eq!(binding("a", 0).kind, BindingKind::Pattern);
+    assert_eq!(
+        target(&resolution, find(text, "ctx", 1)),
+        Some(find(text, "ctx", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "b", 1)),
+        Some(find(text, "b", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "c", 1)),
+        Some(find(text, "c", 0))
+    );
+    // let-block の本体では +p 自身はまだ定義されていない
+    assert_eq!(target(&resolution, find(text, "+p", 1)), None);
+    assert_eq!(
+        target(&resolution, find(text, "+p", 3)),
+        Some(find(text, "+p", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "\\emph", 2)),
+        Some(find(text, "\\emph", 0))
+    );
+    let unresolved: Vec<_> = resolution
+        .unresolved()
+        .map(|(_, r)| r.name.as_str())
+        .collect();
+    assert_eq!(unresolved, vec!["read-inline", "+p", "+p"]);
+}
+
+#[test]
+fn shadowed() {
+    let text = "\
+module M : sig
+  val x : int
+end = struct
+  let x = 1
+end
+let-mutable y <- 1
+let y = 2
+let f y = (y <- 3)
+in
+f M.(let x = 2 in x)
+";
+    let resolution = resolve(&Program::parse(text).unwrap());
+    let shadowed = |span: Span| {
+        let binding = resolution.bindings.iter().find(|b| b.span == span).unwrap();
+        binding.shadowed.map(|id| resolution.bindings[id].span)
+    };
+    assert_eq!(shadowed(find(text, "y", 1)), Some(find(text, "y", 0)));
+    // 引数は新しいスコープに入る
+    assert_eq!(shadowed(find(text, "y", 2)), None);
+    assert_eq!(shadowed(find(text, "x", 2)), Some(find(text, "x", 1)));
+    assert_eq!(shadowed(find(text, "x", 1)), None);
+}
+
+#[test]
+fn modules() {
+    let text = "\
+module M : sig
+  val x : int
+  val \\a : [inline-text] inline-cmd
+  direct \\c : [inline-text] inline-cmd
+end = struct
+  let x = 1
+  let y = x
+  let-inline ctx \\a = y
+  let-inline ctx \\c = y
+end
+in {#M.x; \\M.a; \\c; \\M.c;} ++ f M.y M.(x) y
+";
+    let resolution = resolve(&Program::parse(text).unwrap());
+    let m = resolution.binding_at(find(text, "M", 0)).unwrap();
+    let x = resolution.binding_at(find(text, "x", 1)).unwrap();
+    assert_eq!(resolution.bindings[x].module, Some(m));
+    // sig の宣言は定義への使用箇所になる
+    assert_eq!(
+        target(&resolution, find(text, "x", 0)),
+        Some(find(text, "x", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "x", 2)),
+        Some(find(text, "x", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "x", 3)),
+        Some(find(text, "x", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "M", 1)),
+        Some(find(text, "M", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "\\M.a", 0)),
+        Some(find(text, "\\a", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "\\c", 2)),
+        Some(find(text, "\\c", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "\\M.c", 0)),
+        Some(find(text, "\\c", 1))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "x", 4)),
+        Some(find(text, "x", 1))
+    );
+    // sig で宣言されていない y は外から見えない
+    let reference = &resolution.references[&find(text, "y", 3)];
+    assert_eq!(reference.qualifier.as_deref(), Some("M"));
+    assert_eq!(reference.binding, None);
+    assert_eq!(target(&resolution, find(text, "y", 4)), None);
+}
+
+#[test]
+fn module_access_at_head() {
+    let text = "\
+module Mod = struct
+  let x = 1
+  let f y = y
+end
+in
+let y = Mod.x in Mod.f y
+";
+    let resolution = resolve(&Program::parse(text).unwrap());
+    assert_eq!(
+        target(&resolution, find(text, "x", 1)),
+        Some(find(text, "x", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "f", 1)),
+        Some(find(text, "f", 0))
+    );
+    assert_eq!(
+        target(&resolution, find(text, "y", 3)),
+        Some(find(text, "y", 2))
+    );
+}
+
+#[test]
+fn definition_and_references() {
+    let text = "\
+module Mod = struct
+  let x = 1
+  let-inline ctx \\foo = read-inline ctx {foo}
+end
+let-inline ctx \\bar = read-inline ctx {bar}
+in {\\foo; \\bar; \\Mod.foo; #Mod.x;} ++ f Mod.x
+";
+    let program = Program::parse(text).unwrap();
+    let start = |span: Span| span.start;
+    // horizontal text 中の \bar から let-inline へ
+    assert_eq!(
+        definition_at(&program, start(find(text, "\\bar", 1))),
+        Some(find(text, "\\bar", 0))
+    );
+    // モジュールの中の \foo はモジュール名を付けなければ見えない
+    assert_eq!(definition_at(&program, start(find(text, "\\foo", 1))), None);
+    // \Mod.foo のコマンド名の部分からは struct の中の定義へ
+    let cmd = find(text, "\\Mod.foo", 0);
+    let loc = Location {
+        row: cmd.start.row,
+        col: cmd.start.col + 5,
+    };
+    assert_eq!(definition_at(&program, loc), Some(find(text, "\\foo", 0)));
+    // モジュール名の部分からはモジュールの定義へ
+    let loc = Location {
+        row: cmd.start.row,
+        col: cmd.start.col + 1,
+    };
+    assert_eq!(definition_at(&program, loc), Some(find(text, "Mod", 0)));
+    // 名前の直後でもよい
+    assert_eq!(
+        definition_at(&program, find(text, "x", 2).end),
+        Some(find(text, "x", 0))
+    );
+    assert_eq!(
+        references_of(&program, start(find(text, "x", 0))),
+        vec![find(text, "x", 1), find(text, "x", 2)]
+    );
+    assert_eq!(
+        references_of(&program, start(find(text, "Mod", 3))),
+        vec![
+            find(text, "Mod", 1),
+            find(text, "Mod", 2),
+            find(text, "Mod", 3)
+        ]
+    );
+    assert_eq!(references_of(&program, Location { row: 1, col: 1 }), vec![]);
+}
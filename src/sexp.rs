@@ -0,0 +1,804 @@
+//! S-expression dump of the AST.
+//!
+//! 構文要素を `(let (var x) (int 1))` のような S 式で表す。
+//! [`to_sexp`] に `spans: true` を与えると、`(var@1:5-1:6 x)` のように各構文要素の先頭に範囲が付く。
+//! 1 行に収まらない S 式は子要素ごとに改行して字下げする。
+//!
+//! [`assert_snapshot`] を使うと、出力をリポジトリに置いた `.sexp` ファイルと比較するテストが書ける。
+
+use crate::grammar::common::{Grammar, Ranged, Span};
+use crate::grammar::{
+    ApplicationArg, Argument, CommandArg, Constraint, Expr, Header, Horizontal, HorizontalBullet,
+    HorizontalSingle, HorizontalToken, Length, LetRecBinding, List, Literal, MatchArm, Math,
+    MathCmdArg, MathGroup, MathSingle, MathToken, MathUnary, Pattern, Preamble, Program, Record,
+    RecordUnit, SigStmt, Signature, Stage, Statement, Struct, Tuple, TypeExpr, TypeList,
+    TypeListUnit, TypeProd, TypeRecord, TypeRecordUnit, TypeUnary, Unary, ValName, Variable,
+    Vertical, VerticalElement,
+};
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+/// 1 行の幅。これを超える S 式は子要素ごとに改行する。
+const WIDTH: usize = 80;
+
+/// S 式。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sexp {
+    /// アトム。
+    Atom(String),
+    /// リスト。先頭の要素が構文要素の種類を表す。
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    /// 文字列をアトムにする。空白や括弧を含む場合は引用符で囲む。
+    pub fn atom(s: &str) -> Self {
+        let plain = !s.is_empty()
+            && !s
+                .chars()
+                .any(|c| c.is_whitespace() || matches!(c, '(' | ')' | '"' | '@'));
+        if plain {
+            Sexp::Atom(s.to_owned())
+        } else {
+            Sexp::Atom(format!("{:?}", s))
+        }
+    }
+
+    /// head を先頭とするリストを作る。
+    pub fn list(head: &str, items: Vec<Sexp>) -> Self {
+        let mut list = vec![Sexp::Atom(head.to_owned())];
+        list.extend(items);
+        Sexp::List(list)
+    }
+
+    /// 先頭のアトムに範囲を付ける。
+    /// 内側の構文要素が同じアトムに既に範囲を付けている場合はそちらを残す。
+    fn with_span(self, span: Span) -> Self {
+        /// アトムが範囲を持つかどうか。
+        fn has_span(atom: &str) -> bool {
+            atom.rsplit_once('@').is_some_and(|(_, tail)| {
+                !tail.is_empty()
+                    && tail
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || c == ':' || c == '-')
+            })
+        }
+
+        let suffix = format!(
+            "@{}:{}-{}:{}",
+            span.start.row, span.start.col, span.end.row, span.end.col
+        );
+        match self {
+            Sexp::Atom(s) if has_span(&s) => Sexp::Atom(s),
+            Sexp::Atom(s) => Sexp::Atom(s + &suffix),
+            Sexp::List(mut list) => {
+                if let Some(Sexp::Atom(head)) = list.first_mut() {
+                    if !has_span(head) {
+                        head.push_str(&suffix);
+                    }
+                }
+                Sexp::List(list)
+            }
+        }
+    }
+
+    /// 1 行で表した文字列。
+    fn flat(&self) -> String {
+        match self {
+            Sexp::Atom(s) => s.clone(),
+            Sexp::List(list) => {
+                let items: Vec<_> = list.iter().map(Sexp::flat).collect();
+                format!("({})", items.join(" "))
+            }
+        }
+    }
+
+    /// indent 文字分字下げされた位置から、幅に収まるように改行しながら書き出す。
+    fn write(&self, buf: &mut String, indent: usize) {
+        let flat = self.flat();
+        let list = match self {
+            Sexp::List(list) if indent + flat.chars().count() > WIDTH => list,
+            _ => return buf.push_str(&flat),
+        };
+        buf.push('(');
+        for (i, item) in list.iter().enumerate() {
+            if i > 0 {
+                buf.push('\n');
+                buf.push_str(&" ".repeat(indent + 2));
+            }
+            item.write(buf, indent + 2);
+        }
+        buf.push(')');
+    }
+}
+
+/// S 式に変換できる構文要素。
+pub trait ToSexp {
+    /// S 式に変換する。spans が真のときは範囲も付ける。
+    fn to_sexp(&self, spans: bool) -> Sexp;
+}
+
+/// 構文要素を S 式の文字列にする。末尾に改行が付く。
+pub fn to_sexp<T: ToSexp>(node: &T, spans: bool) -> String {
+    let mut buf = String::new();
+    node.to_sexp(spans).write(&mut buf, 0);
+    buf.push('\n');
+    buf
+}
+
+/// actual を `.sexp` ファイルの中身と比較し、異なれば panic する。
+///
+/// 環境変数 `UPDATE_SNAPSHOTS` が設定されているか、ファイルが存在しない場合は
+/// actual をファイルに書き出す。ファイルが存在しなかった場合は書き出した上で panic する。
+pub fn assert_snapshot<P: AsRef<Path>>(path: P, actual: &str) {
+    let path = path.as_ref();
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let expect = std::fs::read_to_string(path).ok();
+    if update || expect.is_none() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(path, actual).unwrap();
+    }
+    match expect {
+        _ if update => {}
+        None => panic!("new snapshot written to {}; review it", path.display()),
+        Some(expect) => assert!(
+            expect == actual,
+            "snapshot {} does not match\n--- expect\n{}--- actual\n{}\nrun with UPDATE_SNAPSHOTS=1 to update",
+            path.display(),
+            expect,
+            actual
+        ),
+    }
+}
+
+impl<T: Grammar + ToSexp> ToSexp for Ranged<T> {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        let sexp = self.body.to_sexp(spans);
+        if spans {
+            sexp.with_span(self.span())
+        } else {
+            sexp
+        }
+    }
+}
+
+impl<T: ToSexp> ToSexp for Box<T> {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        (**self).to_sexp(spans)
+    }
+}
+
+impl ToSexp for String {
+    fn to_sexp(&self, _spans: bool) -> Sexp {
+        Sexp::atom(self)
+    }
+}
+
+impl ToSexp for f64 {
+    fn to_sexp(&self, _spans: bool) -> Sexp {
+        Sexp::Atom(self.to_string())
+    }
+}
+
+/// 構文要素の列を S 式の列にする。
+fn all<T: ToSexp>(nodes: &[T], spans: bool) -> Vec<Sexp> {
+    nodes.iter().map(|node| node.to_sexp(spans)).collect()
+}
+
+/// None でなければ S 式にする。
+fn opt<T: ToSexp>(node: &Option<T>, spans: bool) -> Option<Sexp> {
+    node.as_ref().map(|node| node.to_sexp(spans))
+}
+
+/// 空でなければ head を先頭とするリストにする。
+fn non_empty<T: ToSexp>(head: &str, nodes: &[T], spans: bool) -> Option<Sexp> {
+    if nodes.is_empty() {
+        None
+    } else {
+        Some(Sexp::list(head, all(nodes, spans)))
+    }
+}
+
+/// モジュール名の付いた名前。`Mod.name` のように表す。
+fn qualified(modname: &Option<Ranged<String>>, name: &Ranged<String>, spans: bool) -> Vec<Sexp> {
+    let mut items = vec![];
+    if let Some(modname) = modname {
+        items.push(Sexp::list("module", vec![modname.to_sexp(spans)]));
+    }
+    items.push(name.to_sexp(spans));
+    items
+}
+
+impl ToSexp for Program {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        let mut items = vec![];
+        items.extend(opt(&self.stage, spans));
+        items.extend(all(&self.header, spans));
+        items.extend(opt(&self.preamble, spans));
+        items.extend(opt(&self.expr, spans));
+        Sexp::list("program", items)
+    }
+}
+
+impl ToSexp for Stage {
+    fn to_sexp(&self, _spans: bool) -> Sexp {
+        let stage = match self {
+            Stage::Stage0 => "0",
+            Stage::Stage1 => "1",
+            Stage::Persistent => "persistent",
+        };
+        Sexp::list("stage", vec![Sexp::atom(stage)])
+    }
+}
+
+impl ToSexp for Header {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Header::Require(name) => Sexp::list("require", vec![name.to_sexp(spans)]),
+            Header::Import(name) => Sexp::list("import", vec![name.to_sexp(spans)]),
+        }
+    }
+}
+
+impl ToSexp for Preamble {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("preamble", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for Statement {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Statement::Let { ptn, args, expr } => {
+                let mut items = vec![ptn.to_sexp(spans)];
+                items.extend(non_empty("args", args, spans));
+                items.push(expr.to_sexp(spans));
+                Sexp::list("let", items)
+            }
+            Statement::LetRec(bindings) => Sexp::list("let-rec", all(bindings, spans)),
+            Statement::LetInline {
+                ctx,
+                name,
+                args,
+                expr,
+            }
+            | Statement::LetBlock {
+                ctx,
+                name,
+                args,
+                expr,
+            } => {
+                let head = match self {
+                    Statement::LetInline { .. } => "let-inline",
+                    _ => "let-block",
+                };
+                let mut items = vec![];
+                items.extend(opt(ctx, spans).map(|ctx| Sexp::list("ctx", vec![ctx])));
+                items.push(name.to_sexp(spans));
+                items.extend(non_empty("args", args, spans));
+                items.push(expr.to_sexp(spans));
+                Sexp::list(head, items)
+            }
+            Statement::LetMath { name, args, expr } => {
+                let mut items = vec![name.to_sexp(spans)];
+                items.extend(non_empty("args", args, spans));
+                items.push(expr.to_sexp(spans));
+                Sexp::list("let-math", items)
+            }
+            Statement::LetMutable { var, expr } => {
+                Sexp::list("let-mutable", vec![var.to_sexp(spans), expr.to_sexp(spans)])
+            }
+            Statement::Module {
+                name,
+                signature,
+                body,
+            } => {
+                let mut items = vec![name.to_sexp(spans)];
+                items.extend(opt(signature, spans));
+                items.push(body.to_sexp(spans));
+                Sexp::list("module", items)
+            }
+        }
+    }
+}
+
+impl ToSexp for Argument {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Argument::Pattern(ptn) => ptn.to_sexp(spans),
+            Argument::Optional(name) => Sexp::list("optional", vec![name.to_sexp(spans)]),
+        }
+    }
+}
+
+impl ToSexp for Signature {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("sig", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for SigStmt {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        let (head, mut items, constraints) = match self {
+            SigStmt::Type {
+                params,
+                name,
+                constraints,
+            } => {
+                let mut items = vec![];
+                items.extend(non_empty("params", params, spans));
+                items.push(name.to_sexp(spans));
+                ("type", items, constraints)
+            }
+            SigStmt::Val {
+                name,
+                ty,
+                constraints,
+            } => (
+                "val",
+                vec![name.to_sexp(spans), ty.to_sexp(spans)],
+                constraints,
+            ),
+            SigStmt::Direct {
+                name,
+                ty,
+                constraints,
+            } => (
+                "direct",
+                vec![name.to_sexp(spans), ty.to_sexp(spans)],
+                constraints,
+            ),
+        };
+        items.extend(all(constraints, spans));
+        Sexp::list(head, items)
+    }
+}
+
+impl ToSexp for ValName {
+    fn to_sexp(&self, _spans: bool) -> Sexp {
+        let (head, name) = match self {
+            ValName::Var(name) => ("var", name),
+            ValName::BinOperator(name) => ("binop", name),
+            ValName::InlineCmd(name) => ("inline-cmd", name),
+            ValName::BlockCmd(name) => ("block-cmd", name),
+        };
+        Sexp::list(head, vec![Sexp::atom(name)])
+    }
+}
+
+impl ToSexp for Struct {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("struct", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for Constraint {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list(
+            "constraint",
+            vec![self.param.to_sexp(spans), self.record.to_sexp(spans)],
+        )
+    }
+}
+
+impl ToSexp for TypeExpr {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        let mut items = vec![];
+        items.extend(non_empty("optional", &self.optional_args, spans));
+        items.extend(all(&self.args, spans));
+        items.push(self.ret.to_sexp(spans));
+        Sexp::list("type-expr", items)
+    }
+}
+
+impl ToSexp for TypeProd {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("type-prod", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for TypeUnary {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            TypeUnary::InlineCmd(list) => Sexp::list("inline-cmd", vec![list.to_sexp(spans)]),
+            TypeUnary::BlockCmd(list) => Sexp::list("block-cmd", vec![list.to_sexp(spans)]),
+            TypeUnary::MathCmd(list) => Sexp::list("math-cmd", vec![list.to_sexp(spans)]),
+            TypeUnary::Paren(ty) => Sexp::list("paren", vec![ty.to_sexp(spans)]),
+            TypeUnary::Record(record) => record.to_sexp(spans),
+            TypeUnary::Application(units) => Sexp::list("type-app", all(units, spans)),
+            TypeUnary::Param(name) => Sexp::list("param", vec![Sexp::atom(name)]),
+            TypeUnary::Name { modname, name } => {
+                Sexp::list("type-name", qualified(modname, name, spans))
+            }
+        }
+    }
+}
+
+impl ToSexp for TypeList {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("type-list", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for TypeListUnit {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            TypeListUnit::Optional(ty) => Sexp::list("optional", vec![ty.to_sexp(spans)]),
+            TypeListUnit::Mandatory(ty) => ty.to_sexp(spans),
+        }
+    }
+}
+
+impl ToSexp for TypeRecord {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("type-record", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for TypeRecordUnit {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list(
+            "field",
+            vec![self.key.to_sexp(spans), self.ty.to_sexp(spans)],
+        )
+    }
+}
+
+impl ToSexp for Pattern {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Pattern::List(ptns) => Sexp::list("list", all(ptns, spans)),
+            Pattern::Tuple(ptns) => Sexp::list("tuple", all(ptns, spans)),
+            Pattern::Paren(ptn) => Sexp::list("paren", vec![ptn.to_sexp(spans)]),
+            Pattern::Wildcard => Sexp::list("wildcard", vec![]),
+            Pattern::Variable(var) => var.to_sexp(spans),
+            Pattern::Literal(lit) => lit.to_sexp(spans),
+            Pattern::Variant { name, arg } => {
+                let mut items = vec![name.to_sexp(spans)];
+                items.extend(opt(arg, spans));
+                Sexp::list("variant", items)
+            }
+            Pattern::As { ptn, var } => {
+                Sexp::list("as", vec![ptn.to_sexp(spans), var.to_sexp(spans)])
+            }
+            Pattern::Cons { head, tail } => {
+                Sexp::list("cons", vec![head.to_sexp(spans), tail.to_sexp(spans)])
+            }
+        }
+    }
+}
+
+impl ToSexp for Expr {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Expr::Match { expr, arms } => {
+                let mut items = vec![expr.to_sexp(spans)];
+                items.extend(all(arms, spans));
+                Sexp::list("match", items)
+            }
+            Expr::BindStmt { bind, body } => {
+                Sexp::list("in", vec![bind.to_sexp(spans), body.to_sexp(spans)])
+            }
+            Expr::Lambda { args, body } => Sexp::list(
+                "fun",
+                vec![Sexp::list("args", all(args, spans)), body.to_sexp(spans)],
+            ),
+            Expr::CtrlFlowWhile { condition, body } => {
+                Sexp::list("while", vec![condition.to_sexp(spans), body.to_sexp(spans)])
+            }
+            Expr::CtrlFlowIf {
+                condition,
+                expr_true,
+                expr_false,
+            } => Sexp::list(
+                "if",
+                vec![
+                    condition.to_sexp(spans),
+                    expr_true.to_sexp(spans),
+                    expr_false.to_sexp(spans),
+                ],
+            ),
+            Expr::Assign { var, expr } => {
+                Sexp::list("assign", vec![var.to_sexp(spans), expr.to_sexp(spans)])
+            }
+            Expr::Dyadic { lhs, rhs, binop } => Sexp::list(
+                "binop",
+                vec![binop.to_sexp(spans), lhs.to_sexp(spans), rhs.to_sexp(spans)],
+            ),
+            Expr::UnaryOperatorExpr { rhs, unaryop } => {
+                Sexp::list("unop", vec![unaryop.to_sexp(spans), rhs.to_sexp(spans)])
+            }
+            Expr::VariantConstructor { variant, arg } => {
+                let mut items = vec![variant.to_sexp(spans)];
+                items.extend(opt(arg, spans));
+                Sexp::list("variant", items)
+            }
+            Expr::Application {
+                modname,
+                func,
+                args,
+            } => {
+                let mut items = vec![];
+                items.extend(opt(modname, spans).map(|m| Sexp::list("module", vec![m])));
+                items.push(func.to_sexp(spans));
+                items.extend(all(args, spans));
+                Sexp::list("app", items)
+            }
+            Expr::Command(name) => Sexp::list("command", vec![name.to_sexp(spans)]),
+            Expr::RecordMember { record, member } => {
+                Sexp::list("member", vec![record.to_sexp(spans), member.to_sexp(spans)])
+            }
+            Expr::Unary(unary) => unary.to_sexp(spans),
+        }
+    }
+}
+
+impl ToSexp for LetRecBinding {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        let mut items = vec![self.var.to_sexp(spans)];
+        items.extend(non_empty("args", &self.args, spans));
+        items.push(self.expr.to_sexp(spans));
+        Sexp::list("bind", items)
+    }
+}
+
+impl ToSexp for MatchArm {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        let mut items = vec![self.ptn.to_sexp(spans)];
+        items.extend(opt(&self.guard, spans).map(|guard| Sexp::list("when", vec![guard])));
+        items.push(self.expr.to_sexp(spans));
+        Sexp::list("arm", items)
+    }
+}
+
+impl ToSexp for ApplicationArg {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            ApplicationArg::Unary(unary) => unary.to_sexp(spans),
+            ApplicationArg::Variant(name) => Sexp::list("variant", vec![Sexp::atom(name)]),
+            ApplicationArg::Optional(unary) => Sexp::list("optional", vec![unary.to_sexp(spans)]),
+            ApplicationArg::OptionalOmitted => Sexp::list("omitted", vec![]),
+        }
+    }
+}
+
+impl ToSexp for Unary {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Unary::BlockText(vertical) => vertical.to_sexp(spans),
+            Unary::HorizontalText(horizontal) => horizontal.to_sexp(spans),
+            Unary::MathText(math) => math.to_sexp(spans),
+            Unary::Record(record) => record.to_sexp(spans),
+            Unary::List(list) => list.to_sexp(spans),
+            Unary::Tuple(tuple) => tuple.to_sexp(spans),
+            Unary::BinOperator(op) => Sexp::list("binop", vec![Sexp::atom(op)]),
+            Unary::Literal(lit) => lit.to_sexp(spans),
+            Unary::Expr { modname, expr } => match modname {
+                Some(modname) => {
+                    Sexp::list("open", vec![modname.to_sexp(spans), expr.to_sexp(spans)])
+                }
+                None => Sexp::list("paren", vec![expr.to_sexp(spans)]),
+            },
+            Unary::Variable { modname, var } => match modname {
+                Some(modname) => {
+                    Sexp::list("access", vec![modname.to_sexp(spans), var.to_sexp(spans)])
+                }
+                None => var.to_sexp(spans),
+            },
+            Unary::Deref(unary) => Sexp::list("deref", vec![unary.to_sexp(spans)]),
+            Unary::Next(unary) => Sexp::list("next", vec![unary.to_sexp(spans)]),
+            Unary::Prev(unary) => Sexp::list("prev", vec![unary.to_sexp(spans)]),
+        }
+    }
+}
+
+impl ToSexp for Record {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Record::Map(map) => Sexp::list("record", all(map, spans)),
+            Record::MapWithDefault { map, default } => {
+                let mut items = vec![Sexp::list("with", vec![default.to_sexp(spans)])];
+                items.extend(all(map, spans));
+                Sexp::list("record", items)
+            }
+        }
+    }
+}
+
+impl ToSexp for RecordUnit {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list(
+            "field",
+            vec![self.key.to_sexp(spans), self.val.to_sexp(spans)],
+        )
+    }
+}
+
+impl ToSexp for List {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("list", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for Tuple {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("tuple", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for Variable {
+    fn to_sexp(&self, _spans: bool) -> Sexp {
+        Sexp::list("var", vec![Sexp::atom(&self.name)])
+    }
+}
+
+impl ToSexp for CommandArg {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            CommandArg::Expr(unary) => unary.to_sexp(spans),
+            CommandArg::Optional(unary) => Sexp::list("optional", vec![unary.to_sexp(spans)]),
+            CommandArg::OptionalOmitted => Sexp::list("omitted", vec![]),
+            CommandArg::Vertical(vertical) => vertical.to_sexp(spans),
+            CommandArg::Horizontal(horizontal) => horizontal.to_sexp(spans),
+        }
+    }
+}
+
+impl ToSexp for Vertical {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("vertical", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for VerticalElement {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            VerticalElement::BlockCmd { name, args } => {
+                let mut items = vec![name.to_sexp(spans)];
+                items.extend(all(args, spans));
+                Sexp::list("block-cmd", items)
+            }
+            VerticalElement::BlockTextEmbedding { mod_name, name } => {
+                Sexp::list("embed", qualified(mod_name, name, spans))
+            }
+        }
+    }
+}
+
+impl ToSexp for Horizontal {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Horizontal::Single(single) => single.to_sexp(spans),
+            Horizontal::List(singles) => Sexp::list("horizontal-list", all(singles, spans)),
+            Horizontal::BulletList(bullets) => {
+                Sexp::list("horizontal-bullets", all(bullets, spans))
+            }
+        }
+    }
+}
+
+impl ToSexp for HorizontalBullet {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list(
+            "bullet",
+            vec![
+                Sexp::Atom(self.indent.to_string()),
+                self.body.to_sexp(spans),
+            ],
+        )
+    }
+}
+
+impl ToSexp for HorizontalSingle {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("horizontal", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for HorizontalToken {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            HorizontalToken::Text(text) => Sexp::list("text", vec![Sexp::atom(&text.body)]),
+            HorizontalToken::SpecialChar(c) => Sexp::list("special", vec![Sexp::atom(&c.body)]),
+            HorizontalToken::HorizontalTextEmbedding { mod_name, name } => {
+                Sexp::list("embed", qualified(mod_name, name, spans))
+            }
+            HorizontalToken::InlineCmd { name, args } => {
+                let mut items = vec![name.to_sexp(spans)];
+                items.extend(all(args, spans));
+                Sexp::list("inline-cmd", items)
+            }
+            HorizontalToken::Math(math) => math.to_sexp(spans),
+            HorizontalToken::StringLiteral(lit) => lit.to_sexp(spans),
+        }
+    }
+}
+
+impl ToSexp for Math {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Math::Single(single) => single.to_sexp(spans),
+            Math::List(singles) => Sexp::list("math-list", all(singles, spans)),
+        }
+    }
+}
+
+impl ToSexp for MathSingle {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list("math", all(&self.0, spans))
+    }
+}
+
+impl ToSexp for MathToken {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        if self.sub.is_none() && self.sup.is_none() {
+            return self.base.to_sexp(spans);
+        }
+        let mut items = vec![self.base.to_sexp(spans)];
+        items.extend(opt(&self.sub, spans).map(|sub| Sexp::list("sub", vec![sub])));
+        items.extend(opt(&self.sup, spans).map(|sup| Sexp::list("sup", vec![sup])));
+        Sexp::list("script", items)
+    }
+}
+
+impl ToSexp for MathGroup {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            MathGroup::Group(single) => single.to_sexp(spans),
+            MathGroup::Unary(unary) => unary.to_sexp(spans),
+        }
+    }
+}
+
+impl ToSexp for MathUnary {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            MathUnary::Char(c) => Sexp::list("char", vec![Sexp::atom(c)]),
+            MathUnary::SpecialChar(c) => Sexp::list("special", vec![Sexp::atom(c)]),
+            MathUnary::Symbol(s) => Sexp::list("symbol", vec![Sexp::atom(s)]),
+            MathUnary::Cmd { name, args } => {
+                let mut items = vec![name.to_sexp(spans)];
+                items.extend(all(args, spans));
+                Sexp::list("math-cmd", items)
+            }
+        }
+    }
+}
+
+impl ToSexp for MathCmdArg {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            MathCmdArg::Math(math) => math.to_sexp(spans),
+            MathCmdArg::Horizontal(horizontal) => horizontal.to_sexp(spans),
+            MathCmdArg::Vertical(vertical) => vertical.to_sexp(spans),
+            MathCmdArg::Expr(unary) => unary.to_sexp(spans),
+            MathCmdArg::Optional(arg) => Sexp::list("optional", vec![arg.to_sexp(spans)]),
+        }
+    }
+}
+
+impl ToSexp for Literal {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        match self {
+            Literal::Unit => Sexp::list("unit", vec![]),
+            Literal::Bool(b) => Sexp::list("bool", vec![Sexp::Atom(b.to_string())]),
+            Literal::String(s) => Sexp::list("string", vec![Sexp::Atom(format!("{:?}", s))]),
+            Literal::Length(length) => length.to_sexp(spans),
+            Literal::Float(f) => Sexp::list("float", vec![f.to_sexp(spans)]),
+            Literal::Int(i) => Sexp::list("int", vec![Sexp::Atom(i.to_string())]),
+        }
+    }
+}
+
+impl ToSexp for Length {
+    fn to_sexp(&self, spans: bool) -> Sexp {
+        Sexp::list(
+            "length",
+            vec![self.value.to_sexp(spans), self.unit.to_sexp(spans)],
+        )
+    }
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+/// テスト用の関数。text を T としてパースし、S 式にしたものを検証する。
+fn assert_sexp<T: Grammar + ToSexp>(text: &str, spans: bool, expect: &str) {
+    let node = T::parse(text).unwrap();
+    assert_eq!(to_sexp(&node, spans), expect);
+}
+
+#[test]
+fn atom() {
+    assert_eq!(Sexp::atom("x"), Sexp::Atom("x".to_owned()));
+    assert_eq!(Sexp::atom("a b"), Sexp::Atom("\"a b\"".to_owned()));
+    assert_eq!(Sexp::atom(""), Sexp::Atom("\"\"".to_owned()));
+    assert_eq!(Sexp::atom("f(x)"), Sexp::Atom("\"f(x)\"".to_owned()));
+}
+
+#[test]
+fn expr() {
+    assert_sexp::<Expr>(
+        "1 + 2 * x",
+        false,
+        "(binop + (int 1) (binop * (int 2) (var x)))\n",
+    );
+    assert_sexp::<Expr>(
+        "f ?:1 x",
+        false,
+        "(app (var f) (optional (int 1)) (var x))\n",
+    );
+    assert_sexp::<Expr>(
+        "let x = 1 in x",
+        false,
+        "(in (let (var x) (int 1)) (var x))\n",
+    );
+}
+
+#[test]
+fn spans() {
+    assert_sexp::<Expr>("f x", true, "(app (var@1:1-1:2 f) (var@1:3-1:4 x))\n");
+    assert_sexp::<Expr>(
+        "{a \\b{c}}",
+        true,
+        "(horizontal@1:1-1:10
+  (text@1:2-1:4 \"a \")
+  (inline-cmd@1:4-1:9 \\b@1:4-1:6 (horizontal@1:6-1:9 (text@1:7-1:8 c))))
+",
+    );
+}
+
+#[test]
+fn break_lines() {
+    let text = "[aaaaaaaaaa; bbbbbbbbbb; cccccccccc; dddddddddd; eeeeeeeeee; ffffffffff]";
+    assert_sexp::<Expr>(
+        text,
+        false,
+        "(list
+  (var aaaaaaaaaa)
+  (var bbbbbbbbbb)
+  (var cccccccccc)
+  (var dddddddddd)
+  (var eeeeeeeeee)
+  (var ffffffffff))
+",
+    );
+}
@@ -0,0 +1,135 @@
+//! Stage validation.
+//!
+//! `@stage:` で指定したステージで使えない構文や、ステージの合わないパッケージの読み込みを報告する。
+//!
+//! - `@stage:` がないファイルはステージ 1 とする。
+//! - `&e` はステージ 0 でのみ使え、e はステージ 1 のコードとなる。
+//! - `~e` は `&` の中でのみ使え、e はステージ 0 で評価される。
+//! - `@stage: persistent` のファイルはどちらのステージからも読み込まれるため、`&` も `~` も使えない。
+//!   また、定義のみからなり、文書本体となる式を持てない。
+//! - 読み込むパッケージのステージは、読み込む側と同じか persistent でなければならない。
+
+#[cfg(test)]
+mod tests;
+
+use crate::diagnostic::Diagnostic;
+use crate::grammar::common::{Location, Span};
+use crate::grammar::{Program, Stage, Unary};
+use crate::visit::{self, Visitor};
+use crate::workspace::Workspace;
+use std::path::Path;
+
+/// program のステージ。
+pub fn stage_of(program: &Program) -> Stage {
+    program
+        .stage
+        .as_ref()
+        .map_or(Stage::Stage1, |stage| stage.body)
+}
+
+/// `@stage:` に書く名前。
+pub fn stage_name(stage: Stage) -> &'static str {
+    match stage {
+        Stage::Stage0 => "0",
+        Stage::Stage1 => "1",
+        Stage::Persistent => "persistent",
+    }
+}
+
+/// program 中の、そのステージで使えない構文を報告する。
+pub fn check_stages(program: &Program) -> Vec<Diagnostic> {
+    let stage = stage_of(program);
+    let mut checker = StageChecker {
+        levels: vec![stage],
+        diagnostics: vec![],
+    };
+    checker.visit_program(program);
+    if stage == Stage::Persistent {
+        if let Some(expr) = &program.expr {
+            let message = "a file at stage persistent cannot have a document body";
+            checker
+                .diagnostics
+                .push(Diagnostic::error(expr.span(), message));
+        }
+    }
+    checker.diagnostics
+}
+
+/// workspace 中の path が読み込むパッケージのうち、ステージが合わないものを報告する。
+pub fn check_imports(workspace: &Workspace, path: &Path) -> Vec<Diagnostic> {
+    let stage = match workspace.program(path) {
+        Some(program) => stage_of(program),
+        None => return vec![],
+    };
+    let mut diagnostics = vec![];
+    for import in workspace.imports(path) {
+        let package = match import.path.as_deref().and_then(|p| workspace.program(p)) {
+            Some(package) => stage_of(package),
+            None => continue,
+        };
+        if package != stage && package != Stage::Persistent {
+            let message = format!(
+                "package {} is at stage {}, but this file is at stage {}",
+                import.name,
+                stage_name(package),
+                stage_name(stage)
+            );
+            diagnostics.push(Diagnostic::error(import.span, message));
+        }
+    }
+    diagnostics
+}
+
+/// `&` と `~` の使い方を調べる Visitor。
+struct StageChecker {
+    /// 現在のステージ。`&` や `~` に入るたびに積む。
+    levels: Vec<Stage>,
+    /// 見つかった問題。
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl StageChecker {
+    /// span の先頭にある演算子 op の誤りを記録する。
+    fn report(&mut self, span: Span, op: &str, message: String) {
+        let end = Location {
+            row: span.start.row,
+            col: span.start.col + op.len(),
+        };
+        self.diagnostics.push(Diagnostic::error(
+            Span {
+                start: span.start,
+                end,
+            },
+            message,
+        ));
+    }
+}
+
+impl Visitor for StageChecker {
+    fn visit_unary(&mut self, node: &Unary, span: Span) {
+        let current = *self.levels.last().unwrap();
+        let (op, allowed, inner) = match node {
+            Unary::Next(_) => ("&", current == Stage::Stage0, Stage::Stage1),
+            // 一番外側はファイル自体のステージであり、戻る先のステージがない
+            Unary::Prev(_) => (
+                "~",
+                current == Stage::Stage1 && self.levels.len() > 1,
+                Stage::Stage0,
+            ),
+            _ => return visit::walk_unary(self, node, span),
+        };
+        if !allowed {
+            let message = match node {
+                Unary::Next(_) => format!(
+                    "`&` can only be used at stage 0, but here is stage {}",
+                    stage_name(current)
+                ),
+                _ => "`~` can only be used inside `&`".to_owned(),
+            };
+            self.report(span, op, message);
+        }
+        self.levels.push(inner);
+        visit::walk_unary(self, node, span);
+        self.levels.pop();
+    }
+}
@@ -0,0 +1,97 @@
+use super::*;
+use crate::grammar::common::Grammar;
+use crate::package::PackageResolver;
+use std::fs;
+
+/// text の診断の、位置とメッセージ。
+fn check(text: &str) -> Vec<(usize, usize, String)> {
+    check_stages(&Program::parse(text).unwrap())
+        .into_iter()
+        .map(|d| (d.span.start.row, d.span.start.col, d.message))
+        .collect()
+}
+
+#[test]
+fn next_and_prev() {
+    let text = "@stage: 0\nlet code = &(f ~x (~(g &y)))\nin code";
+    assert_eq!(check(text), vec![]);
+    let text = "@stage: 0\nlet x = ~y\nlet code = &(f &x)\nin code";
+    assert_eq!(
+        check(text),
+        vec![
+            (2, 9, "`~` can only be used inside `&`".to_owned()),
+            (
+                3,
+                16,
+                "`&` can only be used at stage 0, but here is stage 1".to_owned()
+            ),
+        ]
+    );
+    // ステージ 1 のファイルでは `&` も `~` も使えない
+    let text = "let x = &y in ~x";
+    assert_eq!(check(text).len(), 2);
+    assert_eq!(check("let x = 1 in x"), vec![]);
+}
+
+#[test]
+fn persistent() {
+    let text = "@stage: persistent\nlet f x = &x\n";
+    assert_eq!(
+        check(text),
+        vec![(
+            2,
+            11,
+            "`&` can only be used at stage 0, but here is stage persistent".to_owned()
+        )]
+    );
+    let text = "@stage: persistent\nlet x = 1\nin x";
+    assert_eq!(
+        check(text),
+        vec![(
+            3,
+            4,
+            "a file at stage persistent cannot have a document body".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn package_stages() {
+    let dir = std::env::temp_dir().join(format!("satysfi-peg-parser-stage-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("macro.satyh"), "@stage: 0\nlet m = 1\n").unwrap();
+    fs::write(dir.join("list.satyg"), "@stage: persistent\nlet l = 1\n").unwrap();
+    fs::write(dir.join("text.satyh"), "let t = 1\n").unwrap();
+    let mut workspace = Workspace::new(PackageResolver::new(vec![dir.clone()]));
+    let doc = dir.join("doc.saty");
+    workspace.update(
+        &doc,
+        "@require: macro\n@require: list\n@require: text\n'<>".to_owned(),
+    );
+    let messages = |workspace: &Workspace| -> Vec<_> {
+        check_imports(workspace, &doc)
+            .into_iter()
+            .map(|d| (d.span.start.row, d.message))
+            .collect()
+    };
+    assert_eq!(
+        messages(&workspace),
+        vec![(
+            1,
+            "package macro is at stage 0, but this file is at stage 1".to_owned()
+        )]
+    );
+    workspace.update(
+        &doc,
+        "@stage: 0\n@require: macro\n@require: list\n@require: text\n'<>".to_owned(),
+    );
+    assert_eq!(
+        messages(&workspace),
+        vec![(
+            4,
+            "package text is at stage 1, but this file is at stage 0".to_owned()
+        )]
+    );
+    assert_eq!(workspace.diagnostics()[doc.as_path()].len(), 1);
+}
@@ -0,0 +1,2031 @@
+//! Type inference.
+//!
+//! Hindley–Milner 型推論によって式の型を求め、型の誤りを報告する。
+//!
+//! - 組み込みの型や関数は、`sig` の中身と同じ書式で書いた [`Prelude`] として与える。
+//! - `let` で定義した名前は、右辺が値であれば多相になる（値制限）。
+//! - `let-rec` で定義した名前は、右辺の中では単相の型で見え、すべての右辺を推論した後で一般化する。
+//! - レコードのメンバアクセス `r # x` は、`constraint 'a :: (| x : t |)` と同じく
+//!   「少なくとも x を持つレコード」という制約の付いた型変数として扱う。
+//! - `?->` の引数は、後ろの引数が与えられた時点で省略されたものとみなす。
+//! - `sig` のあるモジュールは、宣言された型で外から見える。
+//!   `type` で宣言した型は、モジュールの外では中身の分からない型となる。
+//!
+//! この文法には代数的データ型の定義がないため、コンストラクタは [`Prelude::add_variant`] で与える。
+
+#[cfg(test)]
+mod tests;
+
+use crate::diagnostic::Diagnostic;
+use crate::grammar::common::{Grammar, Ranged, Span};
+use crate::grammar::{
+    ApplicationArg, Argument, CommandArg, Constraint, Expr, Horizontal, HorizontalSingle,
+    HorizontalToken, LetRecBinding, Literal, Math, MathCmdArg, MathGroup, MathSingle, MathUnary,
+    Pattern, Program, Record, SigStmt, Signature, Statement, TypeExpr, TypeList, TypeListUnit,
+    TypeProd, TypeRecord, TypeUnary, Unary, ValName, Vertical, VerticalElement,
+};
+use crate::parser::{Rule, SatysfiParser};
+use crate::resolve::Namespace;
+use crate::workspace::split_qualified;
+use pest::Parser;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// 型変数の番号。
+pub type TypeVarId = usize;
+
+/// 型。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// 型変数。
+    Var(TypeVarId),
+    /// `int` や `'a list` などの名前の付いた型。
+    Con(String, Vec<Type>),
+    /// `t1 * t2`
+    Tuple(Vec<Type>),
+    /// `(| key : t; ... |)`
+    Record(BTreeMap<String, Type>),
+    /// `t1 -> t2`
+    Func(Box<Type>, Box<Type>),
+    /// `t1 ?-> t2`
+    OptFunc(Box<Type>, Box<Type>),
+    /// `[t1; t2?] inline-cmd` など。
+    Command(CommandKind, Vec<CommandParam>),
+}
+
+/// コマンドの種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CommandKind {
+    /// `inline-cmd`
+    Inline,
+    /// `block-cmd`
+    Block,
+    /// `math-cmd`
+    Math,
+}
+
+/// コマンドの引数の型。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandParam {
+    /// 引数の型。
+    pub ty: Type,
+    /// `t?` のように省略できるかどうか。
+    pub optional: bool,
+}
+
+impl Type {
+    /// 引数のない名前の付いた型。
+    fn con(name: &str) -> Type {
+        Type::Con(name.to_owned(), vec![])
+    }
+
+    /// 直下にある型。
+    fn children(&self) -> Vec<&Type> {
+        match self {
+            Type::Var(_) => vec![],
+            Type::Con(_, args) | Type::Tuple(args) => args.iter().collect(),
+            Type::Record(fields) => fields.values().collect(),
+            Type::Func(param, ret) | Type::OptFunc(param, ret) => vec![param, ret],
+            Type::Command(_, params) => params.iter().map(|param| &param.ty).collect(),
+        }
+    }
+
+    /// 直下にある型を f で置き換えたもの。
+    fn map_children(&self, f: &mut dyn FnMut(&Type) -> Type) -> Type {
+        match self {
+            Type::Var(id) => Type::Var(*id),
+            Type::Con(name, args) => Type::Con(name.clone(), args.iter().map(&mut *f).collect()),
+            Type::Tuple(elems) => Type::Tuple(elems.iter().map(&mut *f).collect()),
+            Type::Record(fields) => {
+                Type::Record(fields.iter().map(|(k, t)| (k.clone(), f(t))).collect())
+            }
+            Type::Func(param, ret) => Type::Func(Box::new(f(param)), Box::new(f(ret))),
+            Type::OptFunc(param, ret) => Type::OptFunc(Box::new(f(param)), Box::new(f(ret))),
+            Type::Command(kind, params) => Type::Command(
+                *kind,
+                params
+                    .iter()
+                    .map(|param| CommandParam {
+                        ty: f(&param.ty),
+                        optional: param.optional,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&TypePrinter::default().print(self, 0))
+    }
+}
+
+/// 型を文字列にするもの。型変数には現れた順に `'a`, `'b`, ... と名前を付ける。
+#[derive(Default)]
+struct TypePrinter {
+    /// 型変数の名前。
+    names: HashMap<TypeVarId, String>,
+    /// レコードであることの分かっている型変数と、少なくとも持つメンバ。
+    rows: HashMap<TypeVarId, BTreeMap<String, Type>>,
+}
+
+impl TypePrinter {
+    /// ty を文字列にする。prec は周囲の結合の強さで、0 は関数型の右辺、
+    /// 1 は関数型の左辺、2 はタプルの要素や型の引数を表す。
+    fn print(&mut self, ty: &Type, prec: u8) -> String {
+        let (text, own) = match ty {
+            Type::Var(id) if self.rows.contains_key(id) => {
+                // メンバの型に自身が現れても止まるよう、書いている間は取り除く
+                let fields = self.rows.remove(id).unwrap();
+                let text: Vec<_> = fields
+                    .iter()
+                    .map(|(key, ty)| format!("{} : {}; ", key, self.print(ty, 0)))
+                    .collect();
+                self.rows.insert(*id, fields);
+                (format!("(| {}.. |)", text.concat()), 3)
+            }
+            Type::Var(id) => {
+                let len = self.names.len();
+                let name = self.names.entry(*id).or_insert_with(|| {
+                    let letter = (b'a' + (len % 26) as u8) as char;
+                    match len / 26 {
+                        0 => format!("'{}", letter),
+                        n => format!("'{}{}", letter, n),
+                    }
+                });
+                (name.clone(), 3)
+            }
+            Type::Con(name, args) => {
+                let mut words: Vec<_> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        // 型の引数にはコマンドの型を括弧なしで書けない
+                        Type::Command(..) => format!("({})", self.print(arg, 0)),
+                        _ => self.print(arg, 2),
+                    })
+                    .collect();
+                words.push(name.clone());
+                (words.join(" "), if args.is_empty() { 3 } else { 2 })
+            }
+            Type::Tuple(elems) => {
+                let elems: Vec<_> = elems.iter().map(|elem| self.print(elem, 2)).collect();
+                (elems.join(" * "), 1)
+            }
+            Type::Record(fields) if fields.is_empty() => ("(||)".to_owned(), 3),
+            Type::Record(fields) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|(key, ty)| format!("{} : {}", key, self.print(ty, 0)))
+                    .collect();
+                (format!("(| {} |)", fields.join("; ")), 3)
+            }
+            Type::Func(param, ret) => {
+                let text = format!("{} -> {}", self.print(param, 1), self.print(ret, 0));
+                (text, 0)
+            }
+            Type::OptFunc(param, ret) => {
+                let text = format!("{} ?-> {}", self.print(param, 1), self.print(ret, 0));
+                (text, 0)
+            }
+            Type::Command(kind, params) => {
+                let params: Vec<_> = params
+                    .iter()
+                    .map(|param| {
+                        let ty = self.print(&param.ty, if param.optional { 1 } else { 0 });
+                        if param.optional {
+                            format!("{}?", ty)
+                        } else {
+                            ty
+                        }
+                    })
+                    .collect();
+                let kind = match kind {
+                    CommandKind::Inline => "inline-cmd",
+                    CommandKind::Block => "block-cmd",
+                    CommandKind::Math => "math-cmd",
+                };
+                (format!("[{}] {}", params.join("; "), kind), 2)
+            }
+        };
+        if own < prec {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+}
+
+/// 型推論の前提とする、組み込みの型と値、コンストラクタ。
+#[derive(Debug, Default)]
+pub struct Prelude {
+    /// 型の名前と引数の数。
+    types: BTreeMap<String, usize>,
+    /// `val` と `direct` による値の宣言。
+    values: Vec<Ranged<SigStmt>>,
+    /// コンストラクタの名前と型。
+    variants: Vec<(String, Ranged<TypeExpr>)>,
+}
+
+/// SATySFi の標準ライブラリのうち、よく使うもの。
+const STANDARD: &str = r"
+type unit
+type bool
+type int
+type float
+type length
+type string
+type inline-text
+type block-text
+type inline-boxes
+type block-boxes
+type math
+type context
+type 'a list
+type 'a option
+type 'a ref
+type 'a code
+
+val (+) : int -> int -> int
+val (-) : int -> int -> int
+val (*) : int -> int -> int
+val (/) : int -> int -> int
+val (mod) : int -> int -> int
+val (+.) : float -> float -> float
+val (-.) : float -> float -> float
+val (*.) : float -> float -> float
+val (/.) : float -> float -> float
+val (+') : length -> length -> length
+val (-') : length -> length -> length
+val (*') : length -> float -> length
+val (/') : length -> length -> float
+val (==) : int -> int -> bool
+val (<>) : int -> int -> bool
+val (<) : int -> int -> bool
+val (>) : int -> int -> bool
+val (<=) : int -> int -> bool
+val (>=) : int -> int -> bool
+val (<') : length -> length -> bool
+val (>') : length -> length -> bool
+val (&&) : bool -> bool -> bool
+val (||) : bool -> bool -> bool
+val (^) : string -> string -> string
+val (::) : 'a -> 'a list -> 'a list
+val (++) : inline-boxes -> inline-boxes -> inline-boxes
+val (+++) : block-boxes -> block-boxes -> block-boxes
+val (|>) : 'a -> ('a -> 'b) -> 'b
+
+val string-same : string -> string -> bool
+val string-length : string -> int
+val arabic : int -> string
+val show-float : float -> string
+val float : int -> float
+val round : float -> int
+val embed-string : string -> inline-text
+val read-inline : context -> inline-text -> inline-boxes
+val read-block : context -> block-text -> block-boxes
+val inline-skip : length -> inline-boxes
+val inline-fil : inline-boxes
+val line-break : bool -> bool -> context -> inline-boxes -> block-boxes
+val get-font-size : context -> length
+val set-font-size : length -> context -> context
+";
+
+impl Prelude {
+    /// `sig` の中身と同じ書式の text から作る。
+    ///
+    /// `type` で型の名前と引数の数を、`val` や `direct` で値の型を宣言する。
+    pub fn parse(text: &str) -> Result<Self, Diagnostic> {
+        let file = SatysfiParser::parse(Rule::sig_file, text)?.next().unwrap();
+        let mut prelude = Prelude::default();
+        for pair in file.into_inner() {
+            if pair.as_rule() != Rule::sig_inner {
+                continue;
+            }
+            for pair in pair.into_inner() {
+                let stmt = SigStmt::parse_pair_ranged(pair);
+                match &stmt.body {
+                    SigStmt::Type { params, name, .. } => {
+                        prelude.types.insert(name.body.clone(), params.len());
+                    }
+                    _ => prelude.values.push(stmt),
+                }
+            }
+        }
+        // 宣言に知らない型が含まれていないかを確かめる
+        let checker = Checker::new(&prelude);
+        let error = checker.typing.diagnostics.into_iter().next();
+        match error {
+            Some(diagnostic) => Err(diagnostic),
+            None => Ok(prelude),
+        }
+    }
+
+    /// 基本的な型や演算子、`None` と `Some` からなるもの。
+    pub fn standard() -> Self {
+        let mut prelude = Prelude::parse(STANDARD).unwrap();
+        prelude.add_variant("None", "'a option").unwrap();
+        prelude.add_variant("Some", "'a -> 'a option").unwrap();
+        prelude
+    }
+
+    /// 型 ty のコンストラクタ name を加える。
+    ///
+    /// 引数を取るコンストラクタは `'a -> 'a option` のように関数の型で与える。
+    pub fn add_variant(&mut self, name: &str, ty: &str) -> Result<(), Diagnostic> {
+        let pair = SatysfiParser::parse(Rule::type_expr, ty)?.next().unwrap();
+        let rest = ty[pair.as_str().len()..].trim();
+        let ty = TypeExpr::parse_pair_ranged(pair);
+        if !rest.is_empty() {
+            let span = Span {
+                start: ty.end,
+                end: ty.end,
+            };
+            return Err(Diagnostic::error(
+                span,
+                format!("unexpected `{}` after the type", rest),
+            ));
+        }
+        let mut checker = Checker::new(self);
+        checker.declared_type(&ty, &[]);
+        if let Some(diagnostic) = checker.typing.diagnostics.into_iter().next() {
+            return Err(diagnostic);
+        }
+        self.variants.push((name.to_owned(), ty));
+        Ok(())
+    }
+}
+
+/// 型推論の結果。
+#[derive(Debug, Clone, Default)]
+pub struct Typing {
+    /// 定義された名前の範囲と、その型。
+    pub bindings: BTreeMap<Span, Type>,
+    /// 文書本体の式の型。
+    pub expr: Option<Type>,
+    /// 型の誤り。
+    pub diagnostics: Vec<Diagnostic>,
+    /// レコードであることの分かっている型変数と、少なくとも持つメンバ。
+    rows: HashMap<TypeVarId, BTreeMap<String, Type>>,
+}
+
+impl Typing {
+    /// 推論した型を文字列にする。
+    ///
+    /// [`Type`] の `Display` と異なり、レコードであることの分かっている型変数は
+    /// `(| key : t; .. |)` と書く。
+    pub fn show(&self, ty: &Type) -> String {
+        let mut printer = TypePrinter {
+            rows: self.rows.clone(),
+            ..TypePrinter::default()
+        };
+        printer.print(ty, 0)
+    }
+}
+
+/// prelude を前提として program の型を推論する。
+pub fn infer(program: &Program, prelude: &Prelude) -> Typing {
+    let mut checker = Checker::new(prelude);
+    checker.scopes.push(Scope::default());
+    if let Some(preamble) = &program.preamble {
+        for stmt in &preamble.body.0 {
+            checker.statement(stmt);
+        }
+    }
+    if let Some(expr) = &program.expr {
+        let ty = checker.expr(expr);
+        checker.typing.expr = Some(ty);
+    }
+    checker.finish()
+}
+
+/// 一般化された型変数の水準。
+const GENERIC: usize = usize::MAX;
+
+/// 型変数の状態。
+#[derive(Debug, Clone)]
+enum VarState {
+    /// まだ決まっていない。
+    Unbound {
+        /// 導入された let の深さ。一般化する型変数を決めるのに使う。
+        level: usize,
+        /// 少なくとも持つべきレコードのメンバ。レコードに限らない場合は None。
+        fields: Option<BTreeMap<String, Type>>,
+    },
+    /// ty に決まった。
+    Bound(Type),
+}
+
+/// 名前の有効範囲。
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    /// 値やコマンドの型。
+    values: HashMap<(Namespace, String), Type>,
+    /// モジュールが外に見せる名前。
+    modules: HashMap<String, Scope>,
+}
+
+/// 推論の途中の状態。
+struct Checker {
+    /// 型変数の状態。
+    vars: Vec<VarState>,
+    /// 現在の let の深さ。
+    level: usize,
+    /// 型の名前と引数の数。モジュールの抽象型は `M.t` の形で持つ。
+    types: HashMap<String, usize>,
+    /// `sig` の中で、`type` で宣言された名前から `M.t` への対応。
+    aliases: HashMap<String, String>,
+    /// コンストラクタの型。
+    variants: HashMap<String, Type>,
+    /// 名前の有効範囲。最初のものは prelude の値からなる。
+    scopes: Vec<Scope>,
+    /// 推論の結果。
+    typing: Typing,
+}
+
+impl Checker {
+    /// prelude の値を最初の有効範囲に持つものを作る。
+    fn new(prelude: &Prelude) -> Self {
+        let mut checker = Checker {
+            vars: vec![],
+            level: 0,
+            types: prelude.types.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+            aliases: HashMap::new(),
+            variants: HashMap::new(),
+            scopes: vec![],
+            typing: Typing::default(),
+        };
+        let mut scope = Scope::default();
+        for stmt in &prelude.values {
+            if let Some((key, ty)) = checker.declaration(&stmt.body) {
+                scope.values.insert(key, ty);
+            }
+        }
+        for (name, ty) in &prelude.variants {
+            let ty = checker.declared_type(ty, &[]);
+            checker.variants.insert(name.clone(), ty);
+        }
+        checker.scopes.push(scope);
+        checker
+    }
+
+    /// 結果の型から、決まった型変数を取り除いて返す。
+    fn finish(mut self) -> Typing {
+        let bindings = std::mem::take(&mut self.typing.bindings);
+        self.typing.bindings = bindings
+            .into_iter()
+            .map(|(span, ty)| (span, self.zonk(&ty)))
+            .collect();
+        self.typing.expr = self.typing.expr.take().map(|ty| self.zonk(&ty));
+        for (id, var) in self.vars.iter().enumerate() {
+            if let VarState::Unbound {
+                fields: Some(fields),
+                ..
+            } = var
+            {
+                let fields = fields
+                    .iter()
+                    .map(|(key, ty)| (key.clone(), self.zonk(ty)))
+                    .collect();
+                self.typing.rows.insert(id, fields);
+            }
+        }
+        self.typing
+    }
+
+    /// 誤りを報告する。
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.typing
+            .diagnostics
+            .push(Diagnostic::error(span, message));
+    }
+
+    // 型変数 {{{
+
+    /// 現在の深さの新しい型変数。
+    fn fresh(&mut self) -> Type {
+        self.vars.push(VarState::Unbound {
+            level: self.level,
+            fields: None,
+        });
+        Type::Var(self.vars.len() - 1)
+    }
+
+    /// 少なくとも fields を持つレコードを表す新しい型変数。
+    fn fresh_record(&mut self, fields: BTreeMap<String, Type>) -> Type {
+        self.vars.push(VarState::Unbound {
+            level: self.level,
+            fields: Some(fields),
+        });
+        Type::Var(self.vars.len() - 1)
+    }
+
+    /// 一般化された新しい型変数。宣言の型パラメータに使う。
+    fn generic(&mut self) -> Type {
+        self.vars.push(VarState::Unbound {
+            level: GENERIC,
+            fields: None,
+        });
+        Type::Var(self.vars.len() - 1)
+    }
+
+    /// 決まった型変数をたどった先の型。
+    fn shallow(&self, ty: &Type) -> Type {
+        let mut ty = ty;
+        while let Type::Var(id) = ty {
+            match &self.vars[*id] {
+                VarState::Bound(bound) => ty = bound,
+                VarState::Unbound { .. } => break,
+            }
+        }
+        ty.clone()
+    }
+
+    /// 決まった型変数をすべて置き換えた型。
+    fn zonk(&self, ty: &Type) -> Type {
+        match self.shallow(ty) {
+            Type::Var(id) => Type::Var(id),
+            ty => ty.map_children(&mut |child| self.zonk(child)),
+        }
+    }
+
+    /// 決まっていない型変数 id の深さと制約。
+    fn unbound(&self, id: TypeVarId) -> (usize, Option<BTreeMap<String, Type>>) {
+        match &self.vars[id] {
+            VarState::Unbound { level, fields } => (*level, fields.clone()),
+            VarState::Bound(_) => unreachable!("type variable {} is already bound", id),
+        }
+    }
+
+    /// ty に型変数 id が現れるかどうか。
+    fn occurs(&self, id: TypeVarId, ty: &Type) -> bool {
+        match self.shallow(ty) {
+            Type::Var(other) => other == id,
+            ty => ty
+                .children()
+                .into_iter()
+                .any(|child| self.occurs(id, child)),
+        }
+    }
+
+    /// ty に現れる型変数の深さを level 以下にする。
+    fn adjust_levels(&mut self, ty: &Type, level: usize) {
+        match self.shallow(ty) {
+            Type::Var(id) => {
+                let (own, fields) = self.unbound(id);
+                if own > level {
+                    self.vars[id] = VarState::Unbound {
+                        level,
+                        fields: fields.clone(),
+                    };
+                    for field in fields.iter().flat_map(|fields| fields.values()) {
+                        self.adjust_levels(field, level);
+                    }
+                }
+            }
+            ty => {
+                for child in ty.children() {
+                    self.adjust_levels(child, level);
+                }
+            }
+        }
+    }
+
+    /// ty に現れる、現在の深さより深い型変数を一般化する。
+    fn generalize(&mut self, ty: &Type) {
+        match self.shallow(ty) {
+            Type::Var(id) => {
+                let (level, fields) = self.unbound(id);
+                if level > self.level && level != GENERIC {
+                    self.vars[id] = VarState::Unbound {
+                        level: GENERIC,
+                        fields: fields.clone(),
+                    };
+                    for field in fields.iter().flat_map(|fields| fields.values()) {
+                        self.generalize(field);
+                    }
+                }
+            }
+            ty => {
+                for child in ty.children() {
+                    self.generalize(child);
+                }
+            }
+        }
+    }
+
+    /// 一般化された型変数を新しい型変数で置き換える。
+    fn instantiate(&mut self, ty: &Type) -> Type {
+        self.instantiate_with(ty, &mut HashMap::new())
+    }
+
+    /// 一般化された型変数を、map に従って置き換える。
+    fn instantiate_with(&mut self, ty: &Type, map: &mut HashMap<TypeVarId, Type>) -> Type {
+        match self.shallow(ty) {
+            Type::Var(id) => match self.unbound(id) {
+                (GENERIC, fields) => {
+                    if let Some(var) = map.get(&id) {
+                        return var.clone();
+                    }
+                    let var = self.fresh();
+                    map.insert(id, var.clone());
+                    if let (Some(fields), Type::Var(new)) = (fields, &var) {
+                        let fields = fields
+                            .iter()
+                            .map(|(key, ty)| (key.clone(), self.instantiate_with(ty, map)))
+                            .collect();
+                        self.vars[*new] = VarState::Unbound {
+                            level: self.level,
+                            fields: Some(fields),
+                        };
+                    }
+                    var
+                }
+                _ => Type::Var(id),
+            },
+            ty => ty.map_children(&mut |child| self.instantiate_with(child, map)),
+        }
+    }
+
+    // }}}
+
+    // 単一化 {{{
+
+    /// a と b を同じ型にする。できなければ false を返す。
+    fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        match (self.shallow(a), self.shallow(b)) {
+            (Type::Var(x), Type::Var(y)) if x == y => true,
+            (Type::Var(x), Type::Var(y)) => self.merge_vars(x, y),
+            (Type::Var(x), ty) | (ty, Type::Var(x)) => self.bind(x, ty),
+            (Type::Con(n1, a1), Type::Con(n2, a2)) => {
+                n1 == n2 && a1.len() == a2.len() && self.unify_all(&a1, &a2)
+            }
+            (Type::Tuple(e1), Type::Tuple(e2)) => e1.len() == e2.len() && self.unify_all(&e1, &e2),
+            (Type::Record(f1), Type::Record(f2)) => {
+                f1.keys().eq(f2.keys())
+                    && f1
+                        .values()
+                        .zip(f2.values())
+                        .all(|(t1, t2)| self.unify(t1, t2))
+            }
+            (Type::Func(p1, r1), Type::Func(p2, r2))
+            | (Type::OptFunc(p1, r1), Type::OptFunc(p2, r2)) => {
+                self.unify(&p1, &p2) && self.unify(&r1, &r2)
+            }
+            (Type::Command(k1, p1), Type::Command(k2, p2)) => {
+                k1 == k2
+                    && p1.len() == p2.len()
+                    && p1
+                        .iter()
+                        .zip(&p2)
+                        .all(|(x, y)| x.optional == y.optional && self.unify(&x.ty, &y.ty))
+            }
+            _ => false,
+        }
+    }
+
+    /// 型の列を要素ごとに単一化する。
+    fn unify_all(&mut self, a: &[Type], b: &[Type]) -> bool {
+        a.iter().zip(b).all(|(x, y)| self.unify(x, y))
+    }
+
+    /// 決まっていない型変数 x と y を一つにまとめる。
+    fn merge_vars(&mut self, x: TypeVarId, y: TypeVarId) -> bool {
+        let (lx, fx) = self.unbound(x);
+        let (ly, fy) = self.unbound(y);
+        let level = lx.min(ly);
+        let mut pending = vec![];
+        let fields = match (fx, fy) {
+            (None, fields) | (fields, None) => fields,
+            (Some(mut merged), Some(other)) => {
+                for (key, ty) in other {
+                    match merged.get(&key) {
+                        Some(prev) => pending.push((prev.clone(), ty)),
+                        None => {
+                            merged.insert(key, ty);
+                        }
+                    }
+                }
+                Some(merged)
+            }
+        };
+        for field in fields.iter().flat_map(|fields| fields.values()) {
+            self.adjust_levels(field, level);
+        }
+        self.vars[y] = VarState::Unbound { level, fields };
+        self.vars[x] = VarState::Bound(Type::Var(y));
+        pending.iter().all(|(a, b)| self.unify(a, b))
+    }
+
+    /// 決まっていない型変数 id を、型変数でない ty に決める。
+    fn bind(&mut self, id: TypeVarId, ty: Type) -> bool {
+        if self.occurs(id, &ty) {
+            return false;
+        }
+        let (level, required) = self.unbound(id);
+        // 必要なメンバが揃っているかは、型変数を決める前に確かめる
+        let pairs: Vec<_> = match (required, &ty) {
+            (None, _) => vec![],
+            (Some(required), Type::Record(fields)) => {
+                let mut pairs = vec![];
+                for (key, ty) in required {
+                    match fields.get(&key) {
+                        Some(field) => pairs.push((ty, field.clone())),
+                        None => return false,
+                    }
+                }
+                pairs
+            }
+            (Some(_), _) => return false,
+        };
+        self.adjust_levels(&ty, level);
+        self.vars[id] = VarState::Bound(ty);
+        pairs.iter().all(|(a, b)| self.unify(a, b))
+    }
+
+    /// found を expected と単一化し、できなければ span に誤りを報告する。
+    fn expect(&mut self, span: Span, found: &Type, expected: &Type) {
+        if !self.unify(found, expected) {
+            let names = self.describe(&[expected, found]);
+            let (expected, found) = (&names[0], &names[1]);
+            self.error(
+                span,
+                format!("type mismatch: expected `{}`, found `{}`", expected, found),
+            );
+        }
+    }
+
+    /// 型を、共通の型変数の名前を使って文字列にする。
+    ///
+    /// レコードであることの分かっている型変数は `(| key : t; .. |)` と書く。
+    fn describe(&self, types: &[&Type]) -> Vec<String> {
+        /// ty に現れる、レコードであることの分かっている型変数を集める。
+        fn collect(
+            checker: &Checker,
+            ty: &Type,
+            rows: &mut HashMap<TypeVarId, BTreeMap<String, Type>>,
+        ) {
+            match checker.shallow(ty) {
+                Type::Var(id) => {
+                    if let (_, Some(fields)) = checker.unbound(id) {
+                        if let Entry::Vacant(entry) = rows.entry(id) {
+                            let fields: BTreeMap<_, _> = fields
+                                .iter()
+                                .map(|(key, ty)| (key.clone(), checker.zonk(ty)))
+                                .collect();
+                            entry.insert(fields.clone());
+                            for field in fields.values() {
+                                collect(checker, field, rows);
+                            }
+                        }
+                    }
+                }
+                ty => {
+                    for child in ty.children() {
+                        collect(checker, child, rows);
+                    }
+                }
+            }
+        }
+
+        let mut printer = TypePrinter::default();
+        for ty in types {
+            collect(self, ty, &mut printer.rows);
+        }
+        types
+            .iter()
+            .map(|ty| printer.print(&self.zonk(ty), 0))
+            .collect()
+    }
+
+    // }}}
+
+    // 型の宣言 {{{
+
+    /// 宣言された型を、型パラメータを一般化した型に変換する。
+    fn declared_type(&mut self, ty: &Ranged<TypeExpr>, constraints: &[Ranged<Constraint>]) -> Type {
+        let mut params = HashMap::new();
+        let result = self.convert_expr(&ty.body, &mut params);
+        for constraint in constraints {
+            let Constraint { param, record } = &constraint.body;
+            let fields = self.convert_record(&record.body, &mut params);
+            match params.get(&param.body) {
+                Some(Type::Var(id)) => {
+                    self.vars[*id] = VarState::Unbound {
+                        level: GENERIC,
+                        fields: Some(fields),
+                    }
+                }
+                _ => self.error(
+                    param.span(),
+                    format!("unknown type parameter `{}`", param.body),
+                ),
+            }
+        }
+        result
+    }
+
+    /// `t1 ?-> t2 -> t3` を変換する。
+    fn convert_expr(&mut self, ty: &TypeExpr, params: &mut HashMap<String, Type>) -> Type {
+        let mut result = self.convert_prod(&ty.ret.body, params);
+        for arg in ty.args.iter().rev() {
+            let arg = self.convert_prod(&arg.body, params);
+            result = Type::Func(Box::new(arg), Box::new(result));
+        }
+        for arg in ty.optional_args.iter().rev() {
+            let arg = self.convert_prod(&arg.body, params);
+            result = Type::OptFunc(Box::new(arg), Box::new(result));
+        }
+        result
+    }
+
+    /// `t1 * t2` を変換する。
+    fn convert_prod(&mut self, ty: &TypeProd, params: &mut HashMap<String, Type>) -> Type {
+        let mut elems: Vec<_> =
+            ty.0.iter()
+                .map(|unary| self.convert_unary(unary, params))
+                .collect();
+        if elems.len() == 1 {
+            elems.pop().unwrap()
+        } else {
+            Type::Tuple(elems)
+        }
+    }
+
+    /// 単項の型を変換する。
+    fn convert_unary(
+        &mut self,
+        ty: &Ranged<TypeUnary>,
+        params: &mut HashMap<String, Type>,
+    ) -> Type {
+        match &ty.body {
+            TypeUnary::InlineCmd(list) => self.convert_list(CommandKind::Inline, list, params),
+            TypeUnary::BlockCmd(list) => self.convert_list(CommandKind::Block, list, params),
+            TypeUnary::MathCmd(list) => self.convert_list(CommandKind::Math, list, params),
+            TypeUnary::Paren(inner) => self.convert_expr(&inner.body, params),
+            TypeUnary::Record(record) => Type::Record(self.convert_record(record, params)),
+            TypeUnary::Application(units) => {
+                let (last, args) = units.split_last().unwrap();
+                let args = args
+                    .iter()
+                    .map(|arg| self.convert_unary(arg, params))
+                    .collect();
+                match &last.body {
+                    TypeUnary::Name { modname, name } => {
+                        self.named_type(modname.as_ref(), name, args)
+                    }
+                    _ => {
+                        self.error(last.span(), "expected a type name");
+                        self.fresh()
+                    }
+                }
+            }
+            TypeUnary::Param(name) => {
+                if let Some(param) = params.get(name) {
+                    return param.clone();
+                }
+                let param = self.generic();
+                params.insert(name.clone(), param.clone());
+                param
+            }
+            TypeUnary::Name { modname, name } => self.named_type(modname.as_ref(), name, vec![]),
+        }
+    }
+
+    /// `[t1; t2?] inline-cmd` などを変換する。
+    fn convert_list(
+        &mut self,
+        kind: CommandKind,
+        list: &Ranged<TypeList>,
+        params: &mut HashMap<String, Type>,
+    ) -> Type {
+        let list = list
+            .body
+            .0
+            .iter()
+            .map(|unit| match &unit.body {
+                TypeListUnit::Optional(ty) => CommandParam {
+                    ty: self.convert_prod(ty, params),
+                    optional: true,
+                },
+                TypeListUnit::Mandatory(ty) => CommandParam {
+                    ty: self.convert_expr(ty, params),
+                    optional: false,
+                },
+            })
+            .collect();
+        Type::Command(kind, list)
+    }
+
+    /// `(| key : t; ... |)` を変換する。
+    fn convert_record(
+        &mut self,
+        record: &TypeRecord,
+        params: &mut HashMap<String, Type>,
+    ) -> BTreeMap<String, Type> {
+        record
+            .0
+            .iter()
+            .map(|unit| {
+                let ty = self.convert_expr(&unit.body.ty.body, params);
+                (unit.body.key.body.clone(), ty)
+            })
+            .collect()
+    }
+
+    /// 型の名前 `M.t` に args を与えたもの。
+    fn named_type(
+        &mut self,
+        modname: Option<&Ranged<String>>,
+        name: &Ranged<String>,
+        args: Vec<Type>,
+    ) -> Type {
+        let key = match modname {
+            Some(modname) => format!("{}.{}", modname.body, name.body),
+            None => self
+                .aliases
+                .get(&name.body)
+                .cloned()
+                .unwrap_or_else(|| name.body.clone()),
+        };
+        let span = match modname {
+            Some(modname) => Span {
+                start: modname.start,
+                end: name.end,
+            },
+            None => name.span(),
+        };
+        match self.types.get(&key) {
+            Some(&arity) if arity == args.len() => Type::Con(key, args),
+            Some(&arity) => {
+                let message = format!(
+                    "type `{}` expects {} argument(s), but is given {}",
+                    key,
+                    arity,
+                    args.len()
+                );
+                self.error(span, message);
+                self.fresh()
+            }
+            None => {
+                self.error(span, format!("unknown type `{}`", key));
+                self.fresh()
+            }
+        }
+    }
+
+    /// `val` や `direct` の宣言を、名前空間と名前、一般化された型の組にする。
+    fn declaration(&mut self, stmt: &SigStmt) -> Option<((Namespace, String), Type)> {
+        let (name, command, ty, constraints) = match stmt {
+            SigStmt::Type { .. } => return None,
+            SigStmt::Val {
+                name,
+                ty,
+                constraints,
+            } => {
+                let (name, command) = match &name.body {
+                    ValName::Var(name) | ValName::BinOperator(name) => (name, false),
+                    ValName::InlineCmd(name) | ValName::BlockCmd(name) => (name, true),
+                };
+                (name, command, ty, constraints)
+            }
+            SigStmt::Direct {
+                name,
+                ty,
+                constraints,
+            } => (&name.body, true, ty, constraints),
+        };
+        let ty = self.declared_type(ty, constraints);
+        let namespace = match (command, self.shallow(&ty)) {
+            (false, _) => Namespace::Variable,
+            (true, _) if name.starts_with('+') => Namespace::BlockCmd,
+            (true, Type::Command(CommandKind::Math, _)) => Namespace::MathCmd,
+            (true, _) => Namespace::InlineCmd,
+        };
+        Some(((namespace, name.clone()), ty))
+    }
+
+    // }}}
+
+    // 名前 {{{
+
+    /// 現在の有効範囲に名前を定義する。
+    fn define(&mut self, namespace: Namespace, name: &str, span: Span, ty: Type) {
+        self.typing.bindings.insert(span, ty.clone());
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .values
+            .insert((namespace, name.to_owned()), ty);
+    }
+
+    /// モジュール name が外に見せる名前。
+    fn module_scope(&mut self, name: &Ranged<String>) -> Option<Scope> {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.modules.get(&name.body))
+            .cloned();
+        if found.is_none() {
+            self.error(name.span(), format!("unknown module `{}`", name.body));
+        }
+        found
+    }
+
+    /// 名前の型を、一般化された型変数を置き換えて返す。
+    fn lookup(
+        &mut self,
+        namespace: Namespace,
+        module: Option<&Ranged<String>>,
+        name: &str,
+        span: Span,
+    ) -> Type {
+        let key = (namespace, name.to_owned());
+        let found = match module {
+            Some(module) => match self.module_scope(module) {
+                Some(scope) => scope.values.get(&key).cloned(),
+                None => return self.fresh(),
+            },
+            None => self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.values.get(&key))
+                .cloned(),
+        };
+        match found {
+            Some(ty) => self.instantiate(&ty),
+            None => {
+                let what = match namespace {
+                    Namespace::Variable => "variable",
+                    _ => "command",
+                };
+                let name = match (module, name.chars().next()) {
+                    (Some(module), Some(c @ '\\')) | (Some(module), Some(c @ '+')) => {
+                        format!("{}{}.{}", c, module.body, &name[c.len_utf8()..])
+                    }
+                    (Some(module), _) => format!("{}.{}", module.body, name),
+                    (None, _) => name.to_owned(),
+                };
+                self.error(span, format!("unbound {} `{}`", what, name));
+                self.fresh()
+            }
+        }
+    }
+
+    /// `\M.cmd` のようなコマンド名の型。
+    fn lookup_command(&mut self, namespace: Namespace, name: &Ranged<String>) -> Type {
+        let (module, cmd) = split_qualified(&name.body);
+        let module = module.map(|module| Ranged::new(module.to_owned(), name.span()));
+        self.lookup(namespace, module.as_ref(), &cmd, name.span())
+    }
+
+    /// コンストラクタの型。
+    fn variant(&mut self, name: &str, span: Span) -> Type {
+        match self.variants.get(name).cloned() {
+            Some(ty) => self.instantiate(&ty),
+            None => {
+                self.error(span, format!("unknown constructor `{}`", name));
+                self.fresh()
+            }
+        }
+    }
+
+    // }}}
+
+    // 文 {{{
+
+    /// 文の型を推論し、定義された名前を現在の有効範囲に加える。
+    fn statement(&mut self, stmt: &Ranged<Statement>) {
+        match &stmt.body {
+            Statement::Let { ptn, args, expr } if args.is_empty() => {
+                // パターンの型変数も右辺と同じ深さで作り、一般化の対象にする
+                self.level += 1;
+                let ty = self.expr(expr);
+                let mut binds = vec![];
+                let ptn_ty = self.pattern(&ptn.body, ptn.span(), &mut binds);
+                self.expect(expr.span(), &ty, &ptn_ty);
+                self.level -= 1;
+                let value = is_value(&expr.body);
+                for (name, span, ty) in binds {
+                    if value {
+                        self.generalize(&ty);
+                    }
+                    self.define(Namespace::Variable, &name, span, ty);
+                }
+            }
+            Statement::Let { ptn, args, expr } => {
+                let name = match &ptn.body {
+                    Pattern::Variable(var) => Some(&var.name),
+                    _ => {
+                        self.error(ptn.span(), "a function must be defined with a variable");
+                        None
+                    }
+                };
+                let FunctionType { params, ret } = self.function(args, |checker| {
+                    let ty = checker.expr(expr);
+                    Some(ty)
+                });
+                let ty = arrow(params, ret);
+                if let Some(name) = name {
+                    self.define(Namespace::Variable, name, ptn.span(), ty);
+                }
+            }
+            Statement::LetRec(bindings) => {
+                // 右辺の中では、定義する名前は一般化する前の単相の型で見える
+                self.level += 1;
+                let tys: Vec<_> = bindings
+                    .iter()
+                    .map(|binding| {
+                        let var = &binding.body.var;
+                        let ty = self.fresh();
+                        self.define(Namespace::Variable, &var.body.name, var.span(), ty.clone());
+                        ty
+                    })
+                    .collect();
+                for (binding, ty) in bindings.iter().zip(&tys) {
+                    let LetRecBinding { args, expr, .. } = &binding.body;
+                    let ret = self.fresh();
+                    self.scopes.push(Scope::default());
+                    let params = self.parameters(args);
+                    self.expect(binding.span(), &arrow(params, ret.clone()), ty);
+                    let body = self.expr(expr);
+                    self.expect(expr.span(), &body, &ret);
+                    self.scopes.pop();
+                }
+                self.level -= 1;
+                let value = bindings.iter().all(|binding| {
+                    !binding.body.args.is_empty() || is_value(&binding.body.expr.body)
+                });
+                if value {
+                    for ty in &tys {
+                        self.generalize(ty);
+                    }
+                }
+            }
+            Statement::LetInline {
+                ctx,
+                name,
+                args,
+                expr,
+            }
+            | Statement::LetBlock {
+                ctx,
+                name,
+                args,
+                expr,
+            } => {
+                let block = matches!(stmt.body, Statement::LetBlock { .. });
+                let body = match (block, ctx.is_some()) {
+                    (false, true) => "inline-boxes",
+                    (false, false) => "inline-text",
+                    (true, true) => "block-boxes",
+                    (true, false) => "block-text",
+                };
+                let sig = self.function(args, |checker| {
+                    if let Some(ctx) = ctx {
+                        checker.define(
+                            Namespace::Variable,
+                            &ctx.body.name,
+                            ctx.span(),
+                            Type::con("context"),
+                        );
+                    }
+                    let ty = checker.expr(expr);
+                    checker.expect(expr.span(), &ty, &Type::con(body));
+                    None
+                });
+                let (kind, namespace) = match block {
+                    true => (CommandKind::Block, Namespace::BlockCmd),
+                    false => (CommandKind::Inline, Namespace::InlineCmd),
+                };
+                let ty = Type::Command(kind, sig.params);
+                self.define(namespace, &name.body, name.span(), ty);
+            }
+            Statement::LetMath { name, args, expr } => {
+                let sig = self.function(args, |checker| {
+                    let ty = checker.expr(expr);
+                    checker.expect(expr.span(), &ty, &Type::con("math"));
+                    None
+                });
+                let ty = Type::Command(CommandKind::Math, sig.params);
+                self.define(Namespace::MathCmd, &name.body, name.span(), ty);
+            }
+            Statement::LetMutable { var, expr } => {
+                let ty = self.expr(expr);
+                let ty = Type::Con("ref".to_owned(), vec![ty]);
+                self.define(Namespace::Variable, &var.body.name, var.span(), ty);
+            }
+            Statement::Module {
+                name,
+                signature,
+                body,
+            } => {
+                self.scopes.push(Scope::default());
+                for stmt in &body.body.0 {
+                    self.statement(stmt);
+                }
+                let inner = self.scopes.pop().unwrap();
+                let exported = match signature {
+                    Some(signature) => self.seal(&name.body, &signature.body, inner),
+                    None => inner,
+                };
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .modules
+                    .insert(name.body.clone(), exported);
+            }
+        }
+    }
+
+    /// 引数 args を取る定義の型を求め、一般化する。
+    ///
+    /// body は引数を加えた有効範囲で本体の型を推論し、関数であればその型を返す。
+    fn function(
+        &mut self,
+        args: &[Ranged<Argument>],
+        body: impl FnOnce(&mut Self) -> Option<Type>,
+    ) -> FunctionType {
+        self.level += 1;
+        self.scopes.push(Scope::default());
+        let params = self.parameters(args);
+        let ret = body(self).unwrap_or_else(|| Type::con("unit"));
+        self.scopes.pop();
+        self.level -= 1;
+        for param in &params {
+            self.generalize(&param.ty);
+        }
+        self.generalize(&ret);
+        FunctionType { params, ret }
+    }
+
+    /// 引数 args の名前を現在の有効範囲に加え、それぞれの型を返す。
+    fn parameters(&mut self, args: &[Ranged<Argument>]) -> Vec<CommandParam> {
+        let mut params = vec![];
+        for arg in args {
+            match &arg.body {
+                Argument::Pattern(ptn) => {
+                    let mut binds = vec![];
+                    let ty = self.pattern(ptn, arg.span(), &mut binds);
+                    for (name, span, ty) in binds {
+                        self.define(Namespace::Variable, &name, span, ty);
+                    }
+                    params.push(CommandParam {
+                        ty,
+                        optional: false,
+                    });
+                }
+                Argument::Optional(name) => {
+                    let ty = self.fresh();
+                    let option = Type::Con("option".to_owned(), vec![ty.clone()]);
+                    self.define(Namespace::Variable, &name.body, name.span(), option);
+                    params.push(CommandParam { ty, optional: true });
+                }
+            }
+        }
+        params
+    }
+
+    /// sig に従って、モジュール module の中身 inner のうち外に見せるものを選ぶ。
+    fn seal(&mut self, module: &str, sig: &Signature, inner: Scope) -> Scope {
+        let aliases = std::mem::take(&mut self.aliases);
+        let mut exported = Scope::default();
+        let prefix = format!("{}.", module);
+        for stmt in &sig.0 {
+            if let SigStmt::Type { params, name, .. } = &stmt.body {
+                let key = format!("{}{}", prefix, name.body);
+                self.types.insert(key.clone(), params.len());
+                self.aliases.insert(name.body.clone(), key);
+                continue;
+            }
+            let (key, declared) = match self.declaration(&stmt.body) {
+                Some(declaration) => declaration,
+                None => continue,
+            };
+            let name_span = match &stmt.body {
+                SigStmt::Val { name, .. } => name.span(),
+                SigStmt::Direct { name, .. } => name.span(),
+                SigStmt::Type { .. } => unreachable!(),
+            };
+            match inner.values.get(&key) {
+                None => self.error(
+                    name_span,
+                    format!(
+                        "`{}` is declared but not defined in module {}",
+                        key.1, module
+                    ),
+                ),
+                // 抽象型を含む宣言は、中身の型と比べられない
+                Some(_) if mentions_type(&self.zonk(&declared), &prefix) => {}
+                Some(defined) => {
+                    let defined = self.instantiate(defined);
+                    let expected = self.instantiate(&declared);
+                    if !self.unify(&defined, &expected) {
+                        let names = self.describe(&[&expected, &defined]);
+                        let (expected, defined) = (&names[0], &names[1]);
+                        self.error(
+                            name_span,
+                            format!(
+                                "`{}` is declared as `{}`, but defined as `{}`",
+                                key.1, expected, defined
+                            ),
+                        );
+                    }
+                }
+            }
+            if let SigStmt::Direct { .. } = stmt.body {
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .values
+                    .insert(key.clone(), declared.clone());
+            }
+            exported.values.insert(key, declared);
+        }
+        self.aliases = aliases;
+        exported
+    }
+
+    // }}}
+
+    // パターン {{{
+
+    /// パターンの型を推論し、導入される変数とその範囲、型を binds に加える。
+    fn pattern(
+        &mut self,
+        ptn: &Pattern,
+        span: Span,
+        binds: &mut Vec<(String, Span, Type)>,
+    ) -> Type {
+        match ptn {
+            Pattern::List(elems) => {
+                let elem = self.fresh();
+                for ptn in elems {
+                    let ty = self.pattern(&ptn.body, ptn.span(), binds);
+                    self.expect(ptn.span(), &ty, &elem);
+                }
+                Type::Con("list".to_owned(), vec![elem])
+            }
+            Pattern::Tuple(elems) => {
+                let elems = elems
+                    .iter()
+                    .map(|ptn| self.pattern(&ptn.body, ptn.span(), binds))
+                    .collect();
+                Type::Tuple(elems)
+            }
+            Pattern::Paren(ptn) => self.pattern(&ptn.body, ptn.span(), binds),
+            Pattern::Wildcard => self.fresh(),
+            Pattern::Variable(var) => {
+                let ty = self.fresh();
+                binds.push((var.name.clone(), span, ty.clone()));
+                ty
+            }
+            Pattern::Literal(literal) => literal_type(literal),
+            Pattern::Variant { name, arg } => {
+                let ty = self.variant(&name.body, name.span());
+                match (self.shallow(&ty), arg) {
+                    (Type::Func(param, ret), Some(arg)) => {
+                        let arg_ty = self.pattern(&arg.body, arg.span(), binds);
+                        self.expect(arg.span(), &arg_ty, &param);
+                        *ret
+                    }
+                    (Type::Func(_, ret), None) => {
+                        let message = format!("constructor `{}` expects an argument", name.body);
+                        self.error(name.span(), message);
+                        *ret
+                    }
+                    (ty, Some(arg)) => {
+                        let message = format!("constructor `{}` takes no argument", name.body);
+                        self.error(name.span(), message);
+                        self.pattern(&arg.body, arg.span(), binds);
+                        ty
+                    }
+                    (ty, None) => ty,
+                }
+            }
+            Pattern::As { ptn, var } => {
+                let ty = self.pattern(&ptn.body, ptn.span(), binds);
+                binds.push((var.body.name.clone(), var.span(), ty.clone()));
+                ty
+            }
+            Pattern::Cons { head, tail } => {
+                let head_ty = self.pattern(&head.body, head.span(), binds);
+                let tail_ty = self.pattern(&tail.body, tail.span(), binds);
+                let list = Type::Con("list".to_owned(), vec![head_ty]);
+                self.expect(tail.span(), &tail_ty, &list);
+                list
+            }
+        }
+    }
+
+    // }}}
+
+    // 式 {{{
+
+    /// 式の型を推論する。
+    fn expr(&mut self, expr: &Ranged<Expr>) -> Type {
+        match &expr.body {
+            Expr::Match { expr, arms } => {
+                let ty = self.expr(expr);
+                let result = self.fresh();
+                for arm in arms {
+                    self.scopes.push(Scope::default());
+                    let mut binds = vec![];
+                    let ptn = &arm.body.ptn;
+                    let ptn_ty = self.pattern(&ptn.body, ptn.span(), &mut binds);
+                    self.expect(arm.body.ptn.span(), &ptn_ty, &ty);
+                    for (name, span, ty) in binds {
+                        self.define(Namespace::Variable, &name, span, ty);
+                    }
+                    if let Some(guard) = &arm.body.guard {
+                        let guard_ty = self.expr(guard);
+                        self.expect(guard.span(), &guard_ty, &Type::con("bool"));
+                    }
+                    let arm_ty = self.expr(&arm.body.expr);
+                    self.expect(arm.body.expr.span(), &arm_ty, &result);
+                    self.scopes.pop();
+                }
+                result
+            }
+            Expr::Lambda { args, body } => {
+                self.scopes.push(Scope::default());
+                let params = self.parameters(args);
+                let ret = self.expr(body);
+                self.scopes.pop();
+                arrow(params, ret)
+            }
+            Expr::BindStmt { bind, body } => {
+                self.scopes.push(Scope::default());
+                self.statement(bind);
+                let ty = self.expr(body);
+                self.scopes.pop();
+                ty
+            }
+            Expr::CtrlFlowWhile { condition, body } => {
+                let ty = self.expr(condition);
+                self.expect(condition.span(), &ty, &Type::con("bool"));
+                let ty = self.expr(body);
+                self.expect(body.span(), &ty, &Type::con("unit"));
+                Type::con("unit")
+            }
+            Expr::CtrlFlowIf {
+                condition,
+                expr_true,
+                expr_false,
+            } => {
+                let ty = self.expr(condition);
+                self.expect(condition.span(), &ty, &Type::con("bool"));
+                let ty = self.expr(expr_true);
+                let other = self.expr(expr_false);
+                self.expect(expr_false.span(), &other, &ty);
+                ty
+            }
+            Expr::Assign { var, expr } => {
+                let cell = self.lookup(Namespace::Variable, None, &var.body.name, var.span());
+                let content = self.fresh();
+                let expected = Type::Con("ref".to_owned(), vec![content.clone()]);
+                self.expect(var.span(), &cell, &expected);
+                let ty = self.expr(expr);
+                self.expect(expr.span(), &ty, &content);
+                Type::con("unit")
+            }
+            Expr::Dyadic { lhs, rhs, binop } => {
+                let op = self.lookup(Namespace::Variable, None, &binop.body, binop.span());
+                let lhs_ty = self.expr(lhs);
+                let op = self.apply(op, lhs_ty, lhs.span());
+                let rhs_ty = self.expr(rhs);
+                self.apply(op, rhs_ty, rhs.span())
+            }
+            Expr::UnaryOperatorExpr { rhs, unaryop } => {
+                let ty = self.expr(rhs);
+                if unaryop.body == "not" {
+                    self.expect(rhs.span(), &ty, &Type::con("bool"));
+                    return ty;
+                }
+                match self.shallow(&ty) {
+                    Type::Con(name, _) if ["int", "float", "length"].contains(&name.as_str()) => {}
+                    Type::Var(_) => self.expect(rhs.span(), &ty, &Type::con("int")),
+                    other => {
+                        let message = format!(
+                            "type mismatch: expected `int`, `float` or `length`, found `{}`",
+                            self.describe(&[&other])[0]
+                        );
+                        self.error(rhs.span(), message);
+                    }
+                }
+                ty
+            }
+            Expr::VariantConstructor { variant, arg } => {
+                let ty = self.variant(&variant.body, variant.span());
+                match (self.shallow(&ty), arg) {
+                    (Type::Func(..), Some(arg)) => {
+                        let arg_ty = self.unary(&arg.body, arg.span());
+                        self.apply(ty, arg_ty, arg.span())
+                    }
+                    (Type::Func(_, ret), None) => {
+                        let message = format!("constructor `{}` expects an argument", variant.body);
+                        self.error(variant.span(), message);
+                        *ret
+                    }
+                    (ty, Some(arg)) => {
+                        let message = format!("constructor `{}` takes no argument", variant.body);
+                        self.error(variant.span(), message);
+                        self.unary(&arg.body, arg.span());
+                        ty
+                    }
+                    (ty, None) => ty,
+                }
+            }
+            Expr::Application {
+                modname,
+                func,
+                args,
+            } => {
+                let mut ty = self.lookup(
+                    Namespace::Variable,
+                    modname.as_ref(),
+                    &func.body.name,
+                    func.span(),
+                );
+                for arg in args {
+                    let span = arg.span();
+                    ty = match &arg.body {
+                        ApplicationArg::Unary(unary) => {
+                            let arg_ty = self.unary(unary, span);
+                            self.apply(ty, arg_ty, span)
+                        }
+                        ApplicationArg::Variant(name) => {
+                            let arg_ty = self.variant(name, span);
+                            self.apply(ty, arg_ty, span)
+                        }
+                        ApplicationArg::Optional(unary) => {
+                            let arg_ty = self.unary(&unary.body, unary.span());
+                            self.apply_optional(ty, Some(arg_ty), span)
+                        }
+                        ApplicationArg::OptionalOmitted => self.apply_optional(ty, None, span),
+                    };
+                }
+                ty
+            }
+            Expr::Command(name) => self.lookup_command(Namespace::InlineCmd, name),
+            Expr::RecordMember { record, member } => {
+                let ty = self.unary(&record.body, record.span());
+                let field = self.fresh();
+                let mut fields = BTreeMap::new();
+                fields.insert(member.body.name.clone(), field.clone());
+                let expected = self.fresh_record(fields);
+                self.expect(record.span(), &ty, &expected);
+                field
+            }
+            Expr::Unary(unary) => self.unary(&unary.body, unary.span()),
+        }
+    }
+
+    /// 関数の型 func に、span にある型 arg の引数を与えた結果の型。
+    /// 前にある `?->` の引数は省略されたものとする。
+    fn apply(&mut self, func: Type, arg: Type, span: Span) -> Type {
+        match self.shallow(&func) {
+            Type::Func(param, ret) => {
+                self.expect(span, &arg, &param);
+                *ret
+            }
+            Type::OptFunc(_, ret) => self.apply(*ret, arg, span),
+            Type::Var(_) => {
+                let ret = self.fresh();
+                let expected = Type::Func(Box::new(arg.clone()), Box::new(ret.clone()));
+                self.expect(span, &func, &expected);
+                ret
+            }
+            other => {
+                let message = format!(
+                    "`{}` is not a function, but is applied to an argument",
+                    self.describe(&[&other])[0]
+                );
+                self.error(span, message);
+                self.fresh()
+            }
+        }
+    }
+
+    /// 関数の型 func に、`?:arg` や `?*` を与えた結果の型。
+    fn apply_optional(&mut self, func: Type, arg: Option<Type>, span: Span) -> Type {
+        match self.shallow(&func) {
+            Type::OptFunc(param, ret) => {
+                if let Some(arg) = arg {
+                    self.expect(span, &arg, &param);
+                }
+                *ret
+            }
+            Type::Var(_) => {
+                let param = arg.unwrap_or_else(|| self.fresh());
+                let ret = self.fresh();
+                let expected = Type::OptFunc(Box::new(param), Box::new(ret.clone()));
+                self.expect(span, &func, &expected);
+                ret
+            }
+            other => {
+                let message = format!(
+                    "`{}` takes no optional argument here",
+                    self.describe(&[&other])[0]
+                );
+                self.error(span, message);
+                func
+            }
+        }
+    }
+
+    /// 単項式の型を推論する。
+    fn unary(&mut self, unary: &Unary, span: Span) -> Type {
+        match unary {
+            Unary::BlockText(vertical) => {
+                self.vertical(vertical);
+                Type::con("block-text")
+            }
+            Unary::HorizontalText(horizontal) => {
+                self.horizontal(horizontal);
+                Type::con("inline-text")
+            }
+            Unary::MathText(math) => {
+                self.math(math);
+                Type::con("math")
+            }
+            Unary::Record(Record::Map(units)) => {
+                let mut fields = BTreeMap::new();
+                for unit in units {
+                    let ty = self.expr(&unit.body.val);
+                    let key = &unit.body.key;
+                    if fields.insert(key.body.clone(), ty).is_some() {
+                        self.error(key.span(), format!("duplicate field `{}`", key.body));
+                    }
+                }
+                Type::Record(fields)
+            }
+            Unary::Record(Record::MapWithDefault { map, default }) => {
+                let ty = self.unary(&default.body, default.span());
+                let mut fields = BTreeMap::new();
+                for unit in map {
+                    let field = self.expr(&unit.body.val);
+                    fields.insert(unit.body.key.body.clone(), field);
+                }
+                let expected = self.fresh_record(fields);
+                self.expect(span, &ty, &expected);
+                ty
+            }
+            Unary::List(list) => {
+                let elem = self.fresh();
+                for expr in &list.0 {
+                    let ty = self.expr(expr);
+                    self.expect(expr.span(), &ty, &elem);
+                }
+                Type::Con("list".to_owned(), vec![elem])
+            }
+            Unary::Tuple(tuple) => {
+                Type::Tuple(tuple.0.iter().map(|expr| self.expr(expr)).collect())
+            }
+            Unary::BinOperator(op) => self.lookup(Namespace::Variable, None, op, span),
+            Unary::Literal(literal) => literal_type(literal),
+            Unary::Expr {
+                modname: Some(modname),
+                expr,
+            } => {
+                let scope = self.module_scope(modname).unwrap_or_default();
+                self.scopes.push(scope);
+                let ty = self.expr(expr);
+                self.scopes.pop();
+                ty
+            }
+            Unary::Expr {
+                modname: None,
+                expr,
+            } => self.expr(expr),
+            Unary::Variable { modname, var } => self.lookup(
+                Namespace::Variable,
+                modname.as_ref(),
+                &var.body.name,
+                var.span(),
+            ),
+            Unary::Deref(inner) => {
+                let ty = self.unary(&inner.body, inner.span());
+                let result = self.fresh();
+                let cell = Type::Con("ref".to_owned(), vec![result.clone()]);
+                self.expect(inner.span(), &ty, &cell);
+                result
+            }
+            Unary::Next(inner) => {
+                let ty = self.unary(&inner.body, inner.span());
+                Type::Con("code".to_owned(), vec![ty])
+            }
+            Unary::Prev(inner) => {
+                let ty = self.unary(&inner.body, inner.span());
+                let result = self.fresh();
+                let code = Type::Con("code".to_owned(), vec![result.clone()]);
+                self.expect(inner.span(), &ty, &code);
+                result
+            }
+        }
+    }
+
+    // }}}
+
+    // テキスト {{{
+
+    /// インラインテキスト中のコマンドや埋め込みを検査する。
+    fn horizontal(&mut self, horizontal: &Horizontal) {
+        match horizontal {
+            Horizontal::Single(single) => self.horizontal_single(single),
+            Horizontal::List(singles) => {
+                for single in singles {
+                    self.horizontal_single(&single.body);
+                }
+            }
+            Horizontal::BulletList(bullets) => {
+                for bullet in bullets {
+                    self.horizontal_single(&bullet.body.body.body);
+                }
+            }
+        }
+    }
+
+    /// インラインテキストの一つの要素を検査する。
+    fn horizontal_single(&mut self, single: &HorizontalSingle) {
+        for token in &single.0 {
+            match &token.body {
+                HorizontalToken::InlineCmd { name, args } => {
+                    let args = self.command_args(args);
+                    self.command(CommandKind::Inline, name, args);
+                }
+                HorizontalToken::HorizontalTextEmbedding { mod_name, name } => {
+                    let ty = self.lookup(
+                        Namespace::Variable,
+                        mod_name.as_ref(),
+                        &name.body,
+                        name.span(),
+                    );
+                    self.expect(token.span(), &ty, &Type::con("inline-text"));
+                }
+                HorizontalToken::Math(math) => self.math(&math.body),
+                _ => {}
+            }
+        }
+    }
+
+    /// ブロックテキスト中のコマンドや埋め込みを検査する。
+    fn vertical(&mut self, vertical: &Vertical) {
+        for element in &vertical.0 {
+            match &element.body {
+                VerticalElement::BlockCmd { name, args } => {
+                    let args = self.command_args(args);
+                    self.command(CommandKind::Block, name, args);
+                }
+                VerticalElement::BlockTextEmbedding { mod_name, name } => {
+                    let ty = self.lookup(
+                        Namespace::Variable,
+                        mod_name.as_ref(),
+                        &name.body,
+                        name.span(),
+                    );
+                    self.expect(element.span(), &ty, &Type::con("block-text"));
+                }
+            }
+        }
+    }
+
+    /// 数式中のコマンドを検査する。
+    fn math(&mut self, math: &Math) {
+        match math {
+            Math::Single(single) => self.math_single(single),
+            Math::List(singles) => {
+                for single in singles {
+                    self.math_single(&single.body);
+                }
+            }
+        }
+    }
+
+    /// 数式の一つの要素を検査する。
+    fn math_single(&mut self, single: &MathSingle) {
+        for token in &single.0 {
+            self.math_unary(&token.body.base.body);
+            for group in token.body.sup.iter().chain(&token.body.sub) {
+                match &group.body {
+                    MathGroup::Group(single) => self.math_single(single),
+                    MathGroup::Unary(unary) => self.math_unary(unary),
+                }
+            }
+        }
+    }
+
+    /// 数式コマンドを検査する。
+    fn math_unary(&mut self, unary: &MathUnary) {
+        if let MathUnary::Cmd { name, args } = unary {
+            let args = args
+                .iter()
+                .map(|arg| match &arg.body {
+                    MathCmdArg::Optional(inner) => {
+                        let ty = self.math_arg(&inner.body, inner.span());
+                        (arg.span(), Some(ty), true)
+                    }
+                    other => (arg.span(), Some(self.math_arg(other, arg.span())), false),
+                })
+                .collect();
+            self.command(CommandKind::Math, name, args);
+        }
+    }
+
+    /// 数式コマンドの引数の型。
+    fn math_arg(&mut self, arg: &MathCmdArg, span: Span) -> Type {
+        match arg {
+            MathCmdArg::Math(math) => {
+                self.math(math);
+                Type::con("math")
+            }
+            MathCmdArg::Horizontal(horizontal) => {
+                self.horizontal(horizontal);
+                Type::con("inline-text")
+            }
+            MathCmdArg::Vertical(vertical) => {
+                self.vertical(vertical);
+                Type::con("block-text")
+            }
+            MathCmdArg::Expr(unary) => self.unary(unary, span),
+            MathCmdArg::Optional(inner) => self.math_arg(&inner.body, inner.span()),
+        }
+    }
+
+    /// コマンドの引数の範囲と型、省略可能な引数かどうか。`?*` の型は None とする。
+    fn command_args(&mut self, args: &[Ranged<CommandArg>]) -> Vec<(Span, Option<Type>, bool)> {
+        args.iter()
+            .map(|arg| {
+                let span = arg.span();
+                match &arg.body {
+                    CommandArg::Expr(unary) => (span, Some(self.unary(unary, span)), false),
+                    CommandArg::Optional(unary) => {
+                        (span, Some(self.unary(&unary.body, unary.span())), true)
+                    }
+                    CommandArg::OptionalOmitted => (span, None, true),
+                    CommandArg::Vertical(vertical) => {
+                        self.vertical(vertical);
+                        (span, Some(Type::con("block-text")), false)
+                    }
+                    CommandArg::Horizontal(horizontal) => {
+                        self.horizontal(horizontal);
+                        (span, Some(Type::con("inline-text")), false)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// kind のコマンド name を args で呼び出せるかを検査する。
+    fn command(
+        &mut self,
+        kind: CommandKind,
+        name: &Ranged<String>,
+        args: Vec<(Span, Option<Type>, bool)>,
+    ) {
+        let namespace = match kind {
+            CommandKind::Inline => Namespace::InlineCmd,
+            CommandKind::Block => Namespace::BlockCmd,
+            CommandKind::Math => Namespace::MathCmd,
+        };
+        let ty = self.lookup_command(namespace, name);
+        let params = match self.shallow(&ty) {
+            Type::Command(k, params) if k == kind => params,
+            Type::Var(_) => {
+                let params = args
+                    .into_iter()
+                    .map(|(_, ty, optional)| CommandParam {
+                        ty: ty.unwrap_or_else(|| self.fresh()),
+                        optional,
+                    })
+                    .collect();
+                self.expect(name.span(), &Type::Command(kind, params), &ty);
+                return;
+            }
+            other => {
+                let message = format!(
+                    "`{}` is not a command of this kind, but has type `{}`",
+                    name.body,
+                    self.describe(&[&other])[0]
+                );
+                self.error(name.span(), message);
+                return;
+            }
+        };
+        let mut next = 0;
+        for (span, ty, optional) in args {
+            if optional {
+                match params.get(next) {
+                    Some(param) if param.optional => {
+                        if let Some(ty) = ty {
+                            self.expect(span, &ty, &param.ty);
+                        }
+                        next += 1;
+                    }
+                    _ => {
+                        let message = format!("unexpected optional argument for `{}`", name.body);
+                        self.error(span, message);
+                    }
+                }
+                continue;
+            }
+            while params.get(next).is_some_and(|param| param.optional) {
+                next += 1;
+            }
+            match params.get(next) {
+                Some(param) => {
+                    self.expect(span, ty.as_ref().unwrap(), &param.ty);
+                    next += 1;
+                }
+                None => {
+                    let message = format!("too many arguments for `{}`", name.body);
+                    self.error(span, message);
+                }
+            }
+        }
+        let missing = params[next.min(params.len())..]
+            .iter()
+            .filter(|param| !param.optional)
+            .count();
+        if missing > 0 {
+            let message = format!("`{}` expects {} more argument(s)", name.body, missing);
+            self.error(name.span(), message);
+        }
+    }
+
+    // }}}
+}
+
+/// 引数を取る定義の型。
+struct FunctionType {
+    /// 引数の型。
+    params: Vec<CommandParam>,
+    /// 本体の型。
+    ret: Type,
+}
+
+/// 引数 params を順に取り ret を返す関数の型。params が空であれば ret そのもの。
+fn arrow(params: Vec<CommandParam>, ret: Type) -> Type {
+    params.into_iter().rev().fold(ret, |ret, param| {
+        if param.optional {
+            Type::OptFunc(Box::new(param.ty), Box::new(ret))
+        } else {
+            Type::Func(Box::new(param.ty), Box::new(ret))
+        }
+    })
+}
+
+/// リテラルの型。
+fn literal_type(literal: &Literal) -> Type {
+    Type::con(match literal {
+        Literal::Unit => "unit",
+        Literal::Bool(_) => "bool",
+        Literal::String(_) => "string",
+        Literal::Length(_) => "length",
+        Literal::Float(_) => "float",
+        Literal::Int(_) => "int",
+    })
+}
+
+/// ty に、名前が prefix で始まる型が現れるかどうか。
+fn mentions_type(ty: &Type, prefix: &str) -> bool {
+    match ty {
+        Type::Con(name, _) if name.starts_with(prefix) => true,
+        ty => ty
+            .children()
+            .into_iter()
+            .any(|child| mentions_type(child, prefix)),
+    }
+}
+
+/// 評価しても副作用のない式かどうか。値である式の型のみを一般化する。
+fn is_value(expr: &Expr) -> bool {
+    match expr {
+        Expr::Unary(unary) => is_value_unary(&unary.body),
+        Expr::VariantConstructor { arg, .. } => {
+            arg.as_ref().is_none_or(|arg| is_value_unary(&arg.body))
+        }
+        Expr::Command(_) | Expr::Lambda { .. } => true,
+        _ => false,
+    }
+}
+
+/// 評価しても副作用のない単項式かどうか。
+fn is_value_unary(unary: &Unary) -> bool {
+    match unary {
+        Unary::BlockText(_)
+        | Unary::HorizontalText(_)
+        | Unary::MathText(_)
+        | Unary::BinOperator(_)
+        | Unary::Literal(_)
+        | Unary::Variable { .. } => true,
+        Unary::Record(Record::Map(units)) => units.iter().all(|unit| is_value(&unit.body.val.body)),
+        Unary::List(list) => list.0.iter().all(|expr| is_value(&expr.body)),
+        Unary::Tuple(tuple) => tuple.0.iter().all(|expr| is_value(&expr.body)),
+        Unary::Expr { expr, .. } => is_value(&expr.body),
+        _ => false,
+    }
+}
@@ -0,0 +1,440 @@
+use super::*;
+use crate::grammar::common::Grammar;
+
+/// 標準の prelude で text の型を推論する。
+fn infer_text(text: &str) -> Typing {
+    infer(&Program::parse(text).unwrap(), &Prelude::standard())
+}
+
+/// text の型の誤りの、位置とメッセージ。
+fn errors(text: &str) -> Vec<(usize, usize, String)> {
+    infer_text(text)
+        .diagnostics
+        .into_iter()
+        .map(|d| (d.span.start.row, d.span.start.col, d.message))
+        .collect()
+}
+
+/// row 行 col 列から始まる名前の型。
+fn binding(typing: &Typing, row: usize, col: usize) -> String {
+    typing
+        .bindings
+        .iter()
+        .find(|(span, _)| span.start.row == row && span.start.col == col)
+        .map(|(_, ty)| typing.show(ty))
+        .unwrap()
+}
+
+#[test]
+fn display() {
+    let int = Type::con("int");
+    let list = |ty| Type::Con("list".to_owned(), vec![ty]);
+    let func = |a, b| Type::Func(Box::new(a), Box::new(b));
+    assert_eq!(list(list(Type::Var(3))).to_string(), "'a list list");
+    let ty = func(func(Type::Var(1), Type::Var(2)), list(Type::Var(1)));
+    assert_eq!(ty.to_string(), "('a -> 'b) -> 'a list");
+    let ty = Type::Tuple(vec![
+        int.clone(),
+        Type::Tuple(vec![int.clone(), int.clone()]),
+    ]);
+    assert_eq!(ty.to_string(), "int * (int * int)");
+    let ty = Type::OptFunc(
+        Box::new(int.clone()),
+        Box::new(func(int.clone(), int.clone())),
+    );
+    assert_eq!(ty.to_string(), "int ?-> int -> int");
+    let ty = Type::Command(
+        CommandKind::Inline,
+        vec![
+            CommandParam {
+                ty: list(int.clone()),
+                optional: true,
+            },
+            CommandParam {
+                ty: Type::con("inline-text"),
+                optional: false,
+            },
+        ],
+    );
+    assert_eq!(ty.to_string(), "[int list?; inline-text] inline-cmd");
+}
+
+#[test]
+fn let_polymorphism() {
+    let typing = infer_text("let id x = x\nlet a = id 1\nlet b = id `s`\nin (a, b)");
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(binding(&typing, 1, 5), "'a -> 'a");
+    assert_eq!(typing.expr.unwrap().to_string(), "int * string");
+
+    // 値でない式の型は一般化しない
+    let text = "let id x = x\nlet f = id id\nlet a = f 1\nlet b = f `s`\nin a";
+    assert_eq!(
+        errors(text),
+        vec![(
+            4,
+            11,
+            "type mismatch: expected `int`, found `string`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn recursive_functions() {
+    let text = "\
+let-rec fact n = if n <= 0 then 1 else n * fact (n - 1)
+let-rec length xs = match xs with | [] -> 0 | _ :: ys -> 1 + length ys
+let-rec even n = if n == 0 then true else odd (n - 1)
+and odd n = if n == 0 then false else even (n - 1)
+in (fact 5, length [1], length [`s`], odd 3)";
+    let typing = infer_text(text);
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(binding(&typing, 1, 9), "int -> int");
+    assert_eq!(binding(&typing, 2, 9), "'a list -> int");
+    assert_eq!(binding(&typing, 4, 5), "int -> bool");
+    assert_eq!(typing.expr.unwrap().to_string(), "int * int * int * bool");
+
+    // 右辺の中では単相の型で見える
+    assert_eq!(
+        errors("let-rec f x = let a = f 1 in f `s`\nin f"),
+        vec![(
+            1,
+            32,
+            "type mismatch: expected `int`, found `string`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn lambdas() {
+    let text = "\
+let twice f x = f (f x)
+let succ = twice (fun n -> n + 1)
+let id = fun x -> x
+in (succ 1, twice (fun s -> s ^ `!`) `a`, id true, fun ?:x y -> y)";
+    let typing = infer_text(text);
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(binding(&typing, 1, 5), "('a -> 'a) -> 'a -> 'a");
+    assert_eq!(binding(&typing, 2, 5), "int -> int");
+    assert_eq!(binding(&typing, 3, 5), "'a -> 'a");
+    assert_eq!(
+        typing.expr.unwrap().to_string(),
+        "int * string * bool * ('a ?-> 'b -> 'b)"
+    );
+    assert_eq!(
+        errors("let apply f x = f x in apply (fun n -> n + 1) `a`"),
+        vec![(
+            1,
+            47,
+            "type mismatch: expected `int`, found `string`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn mismatch() {
+    assert_eq!(
+        errors("let x = 1 + `a`\nin x"),
+        vec![(
+            1,
+            13,
+            "type mismatch: expected `int`, found `string`".to_owned()
+        )]
+    );
+    assert_eq!(
+        errors("if 1 then 2 else 3.0"),
+        vec![
+            (
+                1,
+                4,
+                "type mismatch: expected `bool`, found `int`".to_owned()
+            ),
+            (
+                1,
+                18,
+                "type mismatch: expected `int`, found `float`".to_owned()
+            ),
+        ]
+    );
+    assert_eq!(
+        errors("[1; `a`]"),
+        vec![(
+            1,
+            5,
+            "type mismatch: expected `int`, found `string`".to_owned()
+        )]
+    );
+    assert_eq!(
+        errors("f 1"),
+        vec![(1, 1, "unbound variable `f`".to_owned())]
+    );
+}
+
+#[test]
+fn records() {
+    let text = "let get r = r#name\nlet s = get (| name = `a`; age = 1 |)\nlet n = get (| age = 1 |)\nin s";
+    let typing = infer_text(text);
+    assert_eq!(binding(&typing, 1, 5), "(| name : 'a; .. |) -> 'a");
+    assert_eq!(typing.expr.unwrap().to_string(), "string");
+    assert_eq!(
+        typing
+            .diagnostics
+            .into_iter()
+            .map(|d| (d.span.start.row, d.span.start.col, d.message))
+            .collect::<Vec<_>>(),
+        vec![(
+            3,
+            13,
+            "type mismatch: expected `(| name : 'a; .. |)`, found `(| age : int |)`".to_owned()
+        )]
+    );
+    let typing = infer_text("let r = (| x = 1; y = `a` |)\nin (| r with x = 2 |)");
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(
+        typing.expr.unwrap().to_string(),
+        "(| x : int; y : string |)"
+    );
+    assert_eq!(
+        errors("(| x = 1; x = 2 |)"),
+        vec![(1, 11, "duplicate field `x`".to_owned())]
+    );
+}
+
+#[test]
+fn optional_arguments() {
+    let text = "let f ?:x y = y\nlet a = f 1\nlet b = f ?:`s` 2\nlet c = f ?* 3\nin a";
+    let typing = infer_text(text);
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(binding(&typing, 1, 5), "'a ?-> 'b -> 'b");
+    assert_eq!(binding(&typing, 1, 9), "'a option");
+    assert_eq!(
+        errors("let f x = x\nin f ?:1 2"),
+        vec![(
+            2,
+            6,
+            "`'a -> 'a` takes no optional argument here".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn match_and_variants() {
+    let text = "let f o = match o with\n| None -> 0\n| Some(x) -> x\nin f (Some(1))";
+    let typing = infer_text(text);
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(binding(&typing, 1, 5), "int option -> int");
+    assert_eq!(
+        errors("match 1 with\n| x :: [] -> x\n| _ -> 0"),
+        vec![(
+            2,
+            3,
+            "type mismatch: expected `int`, found `'a list`".to_owned()
+        )]
+    );
+    assert_eq!(
+        errors("Foo"),
+        vec![(1, 1, "unknown constructor `Foo`".to_owned())]
+    );
+    assert_eq!(
+        errors("None(1)"),
+        vec![(1, 1, "constructor `None` takes no argument".to_owned())]
+    );
+}
+
+#[test]
+fn commands() {
+    let text = "let-inline ctx \\bold it = read-inline ctx it\nlet-block ctx +p ?:indent it = line-break true true ctx (read-inline ctx it)\nin '<+p{a \\bold{b}}>";
+    let typing = infer_text(text);
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(binding(&typing, 1, 16), "[inline-text] inline-cmd");
+    assert_eq!(binding(&typing, 2, 15), "['a?; inline-text] block-cmd");
+    assert_eq!(typing.expr.unwrap().to_string(), "block-text");
+
+    let text =
+        "let-inline ctx \\bold it = read-inline ctx it\nin {\\bold(1); \\bold; \\bold{a}{b}}";
+    assert_eq!(
+        errors(text),
+        vec![
+            (
+                2,
+                10,
+                "type mismatch: expected `inline-text`, found `int`".to_owned()
+            ),
+            (2, 15, "`\\bold` expects 1 more argument(s)".to_owned()),
+            (2, 30, "too many arguments for `\\bold`".to_owned()),
+        ]
+    );
+
+    // 本体の型
+    assert_eq!(
+        errors("let-inline ctx \\a = 1\nin {}"),
+        vec![(
+            1,
+            21,
+            "type mismatch: expected `inline-boxes`, found `int`".to_owned()
+        )]
+    );
+    assert_eq!(
+        errors("let x = 1\nin {#x;}"),
+        vec![(
+            2,
+            5,
+            "type mismatch: expected `inline-text`, found `int`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn math_commands() {
+    let text = "let-math \\frac a b = a\nin ${\\frac{x}{y} + \\frac!(1)}";
+    assert_eq!(
+        errors(text),
+        vec![
+            (
+                2,
+                25,
+                "type mismatch: expected `math`, found `int`".to_owned()
+            ),
+            (2, 20, "`\\frac` expects 1 more argument(s)".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn modules() {
+    let text = "module M : sig
+  type t
+  val make : int -> t
+  val get : t -> int
+end = struct
+  let make x = x
+  let get x = x
+  let hidden = 1
+end
+let id x = x
+let a = id M.(get (make 1))
+let b = id M.(make 1) + 1
+let c = id M.hidden
+in a";
+    assert_eq!(
+        errors(text),
+        vec![
+            (
+                12,
+                9,
+                "type mismatch: expected `int`, found `M.t`".to_owned()
+            ),
+            (13, 14, "unbound variable `M.hidden`".to_owned()),
+        ]
+    );
+
+    let text = "module N : sig
+  val x : string
+  val y : int
+  direct \\cmd : [inline-text] inline-cmd
+end = struct
+  let x = 1
+  let-inline ctx \\cmd it = read-inline ctx it
+end
+in {\\cmd{a}}";
+    assert_eq!(
+        errors(text),
+        vec![
+            (
+                2,
+                7,
+                "`x` is declared as `string`, but defined as `int`".to_owned()
+            ),
+            (
+                3,
+                7,
+                "`y` is declared but not defined in module N".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn staging() {
+    let typing = infer_text("@stage: 0\nlet c = &(1 + 1)\nlet d = &(~c + 1)\nin d");
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(typing.expr.unwrap().to_string(), "int code");
+    assert_eq!(
+        errors("@stage: 0\n&(~1)"),
+        vec![(
+            2,
+            4,
+            "type mismatch: expected `'a code`, found `int`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn assignment() {
+    let typing = infer_text("let-mutable a <- 0\nin a <- 1 + 2");
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(typing.expr.unwrap().to_string(), "unit");
+    assert_eq!(
+        errors("let-mutable a <- 0\nin a <- `s`"),
+        vec![(
+            2,
+            9,
+            "type mismatch: expected `int`, found `string`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn dereference() {
+    let typing = infer_text("let-mutable a <- 0 in !a + 1");
+    assert_eq!(typing.diagnostics, vec![]);
+    assert_eq!(typing.expr.unwrap().to_string(), "int");
+    assert_eq!(
+        errors("let a = 0 in !a"),
+        vec![(
+            1,
+            15,
+            "type mismatch: expected `'a ref`, found `int`".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn prelude() {
+    let prelude = Prelude::parse(
+        "% 型と値
+type int
+type 'a box
+type elem
+val wrap : 'a -> 'a box
+val \\show : [elem box] inline-cmd
+val get-x : 'r -> int constraint 'r :: (| x : int |)
+",
+    )
+    .unwrap();
+    let program =
+        Program::parse("let a = get-x (| x = 1; y = 2 |)\nlet b = get-x (| y = 2 |)\nin a")
+            .unwrap();
+    let typing = infer(&program, &prelude);
+    assert_eq!(typing.expr.unwrap().to_string(), "int");
+    assert_eq!(typing.diagnostics.len(), 1);
+
+    let e = Prelude::parse("type foo\nval x : bar").unwrap_err();
+    assert_eq!(e.message, "unknown type `bar`");
+    assert_eq!((e.span.start.row, e.span.start.col), (2, 9));
+    let e = Prelude::parse("type 'a box\nval x : box").unwrap_err();
+    assert_eq!(
+        e.message,
+        "type `box` expects 1 argument(s), but is given 0"
+    );
+    assert!(Prelude::parse("val x :").is_err());
+
+    let mut prelude = Prelude::standard();
+    prelude
+        .add_variant("Pair", "int * int -> int option")
+        .unwrap();
+    let program = Program::parse("Pair(1, 2)").unwrap();
+    let typing = infer(&program, &prelude);
+    assert_eq!(typing.expr.unwrap().to_string(), "int option");
+    assert!(prelude.add_variant("Bad", "int foo").is_err());
+    assert!(prelude.add_variant("Bad", "int ->").is_err());
+}
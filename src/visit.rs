@@ -0,0 +1,606 @@
+//! Read-only traversal of the AST.
+//!
+//! [`Visitor`] の各メソッドは既定で対応する `walk_*` を呼び、子要素を順に訪れる。
+//! 必要な構文要素のメソッドだけを上書きし、子要素も訪れたい場合はその中で `walk_*` を呼ぶ。
+//!
+//! 各メソッドには構文要素の範囲が渡される。
+//! 構文要素自体が範囲を持たない場合（[`Ranged`](crate::grammar::common::Ranged) で包まれていない場合）は、
+//! それを囲む最も内側の構文要素の範囲が渡される。
+
+#![allow(clippy::missing_docs_in_private_items)]
+#![allow(missing_docs)]
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::Span;
+use crate::grammar::{
+    ApplicationArg, Argument, CommandArg, Constraint, Expr, Header, Horizontal, HorizontalBullet,
+    HorizontalSingle, HorizontalToken, LetRecBinding, List, Literal, MatchArm, Math, MathCmdArg,
+    MathGroup, MathSingle, MathToken, MathUnary, Pattern, Preamble, Program, Record, RecordUnit,
+    SigStmt, Signature, Stage, Statement, Struct, Tuple, TypeExpr, TypeList, TypeListUnit,
+    TypeProd, TypeRecord, TypeRecordUnit, TypeUnary, Unary, ValName, Variable, Vertical,
+    VerticalElement,
+};
+
+/// AST を読み取りながら辿るトレイト。
+#[allow(unused_variables)]
+pub trait Visitor {
+    fn visit_program(&mut self, node: &Program) {
+        walk_program(self, node)
+    }
+    fn visit_stage(&mut self, node: &Stage, span: Span) {}
+    fn visit_header(&mut self, node: &Header, span: Span) {}
+    fn visit_preamble(&mut self, node: &Preamble, span: Span) {
+        walk_preamble(self, node, span)
+    }
+    fn visit_statement(&mut self, node: &Statement, span: Span) {
+        walk_statement(self, node, span)
+    }
+    fn visit_let_rec_binding(&mut self, node: &LetRecBinding, span: Span) {
+        walk_let_rec_binding(self, node, span)
+    }
+    fn visit_argument(&mut self, node: &Argument, span: Span) {
+        walk_argument(self, node, span)
+    }
+    fn visit_signature(&mut self, node: &Signature, span: Span) {
+        walk_signature(self, node, span)
+    }
+    fn visit_sig_stmt(&mut self, node: &SigStmt, span: Span) {
+        walk_sig_stmt(self, node, span)
+    }
+    fn visit_val_name(&mut self, node: &ValName, span: Span) {}
+    fn visit_struct(&mut self, node: &Struct, span: Span) {
+        walk_struct(self, node, span)
+    }
+    fn visit_constraint(&mut self, node: &Constraint, span: Span) {
+        walk_constraint(self, node, span)
+    }
+    fn visit_type_expr(&mut self, node: &TypeExpr, span: Span) {
+        walk_type_expr(self, node, span)
+    }
+    fn visit_type_prod(&mut self, node: &TypeProd, span: Span) {
+        walk_type_prod(self, node, span)
+    }
+    fn visit_type_unary(&mut self, node: &TypeUnary, span: Span) {
+        walk_type_unary(self, node, span)
+    }
+    fn visit_type_list(&mut self, node: &TypeList, span: Span) {
+        walk_type_list(self, node, span)
+    }
+    fn visit_type_list_unit(&mut self, node: &TypeListUnit, span: Span) {
+        walk_type_list_unit(self, node, span)
+    }
+    fn visit_type_record(&mut self, node: &TypeRecord, span: Span) {
+        walk_type_record(self, node, span)
+    }
+    fn visit_type_record_unit(&mut self, node: &TypeRecordUnit, span: Span) {
+        walk_type_record_unit(self, node, span)
+    }
+    fn visit_pattern(&mut self, node: &Pattern, span: Span) {
+        walk_pattern(self, node, span)
+    }
+    fn visit_expr(&mut self, node: &Expr, span: Span) {
+        walk_expr(self, node, span)
+    }
+    fn visit_match_arm(&mut self, node: &MatchArm, span: Span) {
+        walk_match_arm(self, node, span)
+    }
+    fn visit_application_arg(&mut self, node: &ApplicationArg, span: Span) {
+        walk_application_arg(self, node, span)
+    }
+    fn visit_unary(&mut self, node: &Unary, span: Span) {
+        walk_unary(self, node, span)
+    }
+    fn visit_record(&mut self, node: &Record, span: Span) {
+        walk_record(self, node, span)
+    }
+    fn visit_record_unit(&mut self, node: &RecordUnit, span: Span) {
+        walk_record_unit(self, node, span)
+    }
+    fn visit_list(&mut self, node: &List, span: Span) {
+        walk_list(self, node, span)
+    }
+    fn visit_tuple(&mut self, node: &Tuple, span: Span) {
+        walk_tuple(self, node, span)
+    }
+    fn visit_variable(&mut self, node: &Variable, span: Span) {}
+    fn visit_literal(&mut self, node: &Literal, span: Span) {}
+    fn visit_command_arg(&mut self, node: &CommandArg, span: Span) {
+        walk_command_arg(self, node, span)
+    }
+    fn visit_vertical(&mut self, node: &Vertical, span: Span) {
+        walk_vertical(self, node, span)
+    }
+    fn visit_vertical_element(&mut self, node: &VerticalElement, span: Span) {
+        walk_vertical_element(self, node, span)
+    }
+    fn visit_horizontal(&mut self, node: &Horizontal, span: Span) {
+        walk_horizontal(self, node, span)
+    }
+    fn visit_horizontal_bullet(&mut self, node: &HorizontalBullet, span: Span) {
+        walk_horizontal_bullet(self, node, span)
+    }
+    fn visit_horizontal_single(&mut self, node: &HorizontalSingle, span: Span) {
+        walk_horizontal_single(self, node, span)
+    }
+    fn visit_horizontal_token(&mut self, node: &HorizontalToken, span: Span) {
+        walk_horizontal_token(self, node, span)
+    }
+    fn visit_math(&mut self, node: &Math, span: Span) {
+        walk_math(self, node, span)
+    }
+    fn visit_math_single(&mut self, node: &MathSingle, span: Span) {
+        walk_math_single(self, node, span)
+    }
+    fn visit_math_token(&mut self, node: &MathToken, span: Span) {
+        walk_math_token(self, node, span)
+    }
+    fn visit_math_group(&mut self, node: &MathGroup, span: Span) {
+        walk_math_group(self, node, span)
+    }
+    fn visit_math_unary(&mut self, node: &MathUnary, span: Span) {
+        walk_math_unary(self, node, span)
+    }
+    fn visit_math_cmd_arg(&mut self, node: &MathCmdArg, span: Span) {
+        walk_math_cmd_arg(self, node, span)
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(v: &mut V, node: &Program) {
+    if let Some(stage) = &node.stage {
+        v.visit_stage(&stage.body, stage.span());
+    }
+    for header in &node.header {
+        v.visit_header(&header.body, header.span());
+    }
+    if let Some(preamble) = &node.preamble {
+        v.visit_preamble(&preamble.body, preamble.span());
+    }
+    if let Some(expr) = &node.expr {
+        v.visit_expr(&expr.body, expr.span());
+    }
+}
+
+pub fn walk_preamble<V: Visitor + ?Sized>(v: &mut V, node: &Preamble, _span: Span) {
+    for stmt in &node.0 {
+        v.visit_statement(&stmt.body, stmt.span());
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(v: &mut V, node: &Statement, _span: Span) {
+    match node {
+        Statement::Let { ptn, args, expr } => {
+            v.visit_pattern(&ptn.body, ptn.span());
+            for arg in args {
+                v.visit_argument(&arg.body, arg.span());
+            }
+            v.visit_expr(&expr.body, expr.span());
+        }
+        Statement::LetRec(bindings) => {
+            for binding in bindings {
+                v.visit_let_rec_binding(&binding.body, binding.span());
+            }
+        }
+        Statement::LetInline {
+            ctx, args, expr, ..
+        }
+        | Statement::LetBlock {
+            ctx, args, expr, ..
+        } => {
+            if let Some(ctx) = ctx {
+                v.visit_variable(&ctx.body, ctx.span());
+            }
+            for arg in args {
+                v.visit_argument(&arg.body, arg.span());
+            }
+            v.visit_expr(&expr.body, expr.span());
+        }
+        Statement::LetMath { args, expr, .. } => {
+            for arg in args {
+                v.visit_argument(&arg.body, arg.span());
+            }
+            v.visit_expr(&expr.body, expr.span());
+        }
+        Statement::LetMutable { var, expr } => {
+            v.visit_variable(&var.body, var.span());
+            v.visit_expr(&expr.body, expr.span());
+        }
+        Statement::Module {
+            signature, body, ..
+        } => {
+            if let Some(signature) = signature {
+                v.visit_signature(&signature.body, signature.span());
+            }
+            v.visit_struct(&body.body, body.span());
+        }
+    }
+}
+
+pub fn walk_let_rec_binding<V: Visitor + ?Sized>(v: &mut V, node: &LetRecBinding, _span: Span) {
+    v.visit_variable(&node.var.body, node.var.span());
+    for arg in &node.args {
+        v.visit_argument(&arg.body, arg.span());
+    }
+    v.visit_expr(&node.expr.body, node.expr.span());
+}
+
+pub fn walk_argument<V: Visitor + ?Sized>(v: &mut V, node: &Argument, span: Span) {
+    match node {
+        Argument::Pattern(ptn) => v.visit_pattern(ptn, span),
+        Argument::Optional(_) => {}
+    }
+}
+
+pub fn walk_signature<V: Visitor + ?Sized>(v: &mut V, node: &Signature, _span: Span) {
+    for stmt in &node.0 {
+        v.visit_sig_stmt(&stmt.body, stmt.span());
+    }
+}
+
+pub fn walk_sig_stmt<V: Visitor + ?Sized>(v: &mut V, node: &SigStmt, _span: Span) {
+    let constraints = match node {
+        SigStmt::Type { constraints, .. } => constraints,
+        SigStmt::Val {
+            name,
+            ty,
+            constraints,
+        } => {
+            v.visit_val_name(&name.body, name.span());
+            v.visit_type_expr(&ty.body, ty.span());
+            constraints
+        }
+        SigStmt::Direct {
+            ty, constraints, ..
+        } => {
+            v.visit_type_expr(&ty.body, ty.span());
+            constraints
+        }
+    };
+    for constraint in constraints {
+        v.visit_constraint(&constraint.body, constraint.span());
+    }
+}
+
+pub fn walk_struct<V: Visitor + ?Sized>(v: &mut V, node: &Struct, _span: Span) {
+    for stmt in &node.0 {
+        v.visit_statement(&stmt.body, stmt.span());
+    }
+}
+
+pub fn walk_constraint<V: Visitor + ?Sized>(v: &mut V, node: &Constraint, _span: Span) {
+    v.visit_type_record(&node.record.body, node.record.span());
+}
+
+pub fn walk_type_expr<V: Visitor + ?Sized>(v: &mut V, node: &TypeExpr, _span: Span) {
+    for arg in node.optional_args.iter().chain(&node.args) {
+        v.visit_type_prod(&arg.body, arg.span());
+    }
+    v.visit_type_prod(&node.ret.body, node.ret.span());
+}
+
+pub fn walk_type_prod<V: Visitor + ?Sized>(v: &mut V, node: &TypeProd, _span: Span) {
+    for unit in &node.0 {
+        v.visit_type_unary(&unit.body, unit.span());
+    }
+}
+
+pub fn walk_type_unary<V: Visitor + ?Sized>(v: &mut V, node: &TypeUnary, span: Span) {
+    match node {
+        TypeUnary::InlineCmd(list) | TypeUnary::BlockCmd(list) | TypeUnary::MathCmd(list) => {
+            v.visit_type_list(&list.body, list.span())
+        }
+        TypeUnary::Paren(ty) => v.visit_type_expr(&ty.body, ty.span()),
+        TypeUnary::Record(record) => v.visit_type_record(record, span),
+        TypeUnary::Application(units) => {
+            for unit in units {
+                v.visit_type_unary(&unit.body, unit.span());
+            }
+        }
+        TypeUnary::Param(_) | TypeUnary::Name { .. } => {}
+    }
+}
+
+pub fn walk_type_list<V: Visitor + ?Sized>(v: &mut V, node: &TypeList, _span: Span) {
+    for unit in &node.0 {
+        v.visit_type_list_unit(&unit.body, unit.span());
+    }
+}
+
+pub fn walk_type_list_unit<V: Visitor + ?Sized>(v: &mut V, node: &TypeListUnit, span: Span) {
+    match node {
+        TypeListUnit::Optional(ty) => v.visit_type_prod(ty, span),
+        TypeListUnit::Mandatory(ty) => v.visit_type_expr(ty, span),
+    }
+}
+
+pub fn walk_type_record<V: Visitor + ?Sized>(v: &mut V, node: &TypeRecord, _span: Span) {
+    for unit in &node.0 {
+        v.visit_type_record_unit(&unit.body, unit.span());
+    }
+}
+
+pub fn walk_type_record_unit<V: Visitor + ?Sized>(v: &mut V, node: &TypeRecordUnit, _span: Span) {
+    v.visit_type_expr(&node.ty.body, node.ty.span());
+}
+
+pub fn walk_pattern<V: Visitor + ?Sized>(v: &mut V, node: &Pattern, span: Span) {
+    match node {
+        Pattern::List(ptns) | Pattern::Tuple(ptns) => {
+            for ptn in ptns {
+                v.visit_pattern(&ptn.body, ptn.span());
+            }
+        }
+        Pattern::Paren(ptn) => v.visit_pattern(&ptn.body, ptn.span()),
+        Pattern::Wildcard => {}
+        Pattern::Variable(var) => v.visit_variable(var, span),
+        Pattern::Literal(lit) => v.visit_literal(lit, span),
+        Pattern::Variant { arg, .. } => {
+            if let Some(arg) = arg {
+                v.visit_pattern(&arg.body, arg.span());
+            }
+        }
+        Pattern::As { ptn, var } => {
+            v.visit_pattern(&ptn.body, ptn.span());
+            v.visit_variable(&var.body, var.span());
+        }
+        Pattern::Cons { head, tail } => {
+            v.visit_pattern(&head.body, head.span());
+            v.visit_pattern(&tail.body, tail.span());
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, node: &Expr, _span: Span) {
+    match node {
+        Expr::Match { expr, arms } => {
+            v.visit_expr(&expr.body, expr.span());
+            for arm in arms {
+                v.visit_match_arm(&arm.body, arm.span());
+            }
+        }
+        Expr::BindStmt { bind, body } => {
+            v.visit_statement(&bind.body, bind.span());
+            v.visit_expr(&body.body, body.span());
+        }
+        Expr::Lambda { args, body } => {
+            for arg in args {
+                v.visit_argument(&arg.body, arg.span());
+            }
+            v.visit_expr(&body.body, body.span());
+        }
+        Expr::CtrlFlowWhile { condition, body } => {
+            v.visit_expr(&condition.body, condition.span());
+            v.visit_expr(&body.body, body.span());
+        }
+        Expr::CtrlFlowIf {
+            condition,
+            expr_true,
+            expr_false,
+        } => {
+            v.visit_expr(&condition.body, condition.span());
+            v.visit_expr(&expr_true.body, expr_true.span());
+            v.visit_expr(&expr_false.body, expr_false.span());
+        }
+        Expr::Assign { var, expr } => {
+            v.visit_variable(&var.body, var.span());
+            v.visit_expr(&expr.body, expr.span());
+        }
+        Expr::Dyadic { lhs, rhs, .. } => {
+            v.visit_expr(&lhs.body, lhs.span());
+            v.visit_expr(&rhs.body, rhs.span());
+        }
+        Expr::UnaryOperatorExpr { rhs, .. } => v.visit_expr(&rhs.body, rhs.span()),
+        Expr::VariantConstructor { arg, .. } => {
+            if let Some(arg) = arg {
+                v.visit_unary(&arg.body, arg.span());
+            }
+        }
+        Expr::Application { func, args, .. } => {
+            v.visit_variable(&func.body, func.span());
+            for arg in args {
+                v.visit_application_arg(&arg.body, arg.span());
+            }
+        }
+        Expr::Command(_) => {}
+        Expr::RecordMember { record, member } => {
+            v.visit_unary(&record.body, record.span());
+            v.visit_variable(&member.body, member.span());
+        }
+        Expr::Unary(unary) => v.visit_unary(&unary.body, unary.span()),
+    }
+}
+
+pub fn walk_match_arm<V: Visitor + ?Sized>(v: &mut V, node: &MatchArm, _span: Span) {
+    v.visit_pattern(&node.ptn.body, node.ptn.span());
+    if let Some(guard) = &node.guard {
+        v.visit_expr(&guard.body, guard.span());
+    }
+    v.visit_expr(&node.expr.body, node.expr.span());
+}
+
+pub fn walk_application_arg<V: Visitor + ?Sized>(v: &mut V, node: &ApplicationArg, span: Span) {
+    match node {
+        ApplicationArg::Unary(unary) => v.visit_unary(unary, span),
+        ApplicationArg::Optional(unary) => v.visit_unary(&unary.body, unary.span()),
+        ApplicationArg::Variant(_) | ApplicationArg::OptionalOmitted => {}
+    }
+}
+
+pub fn walk_unary<V: Visitor + ?Sized>(v: &mut V, node: &Unary, span: Span) {
+    match node {
+        Unary::BlockText(vertical) => v.visit_vertical(vertical, span),
+        Unary::HorizontalText(horizontal) => v.visit_horizontal(horizontal, span),
+        Unary::MathText(math) => v.visit_math(math, span),
+        Unary::Record(record) => v.visit_record(record, span),
+        Unary::List(list) => v.visit_list(list, span),
+        Unary::Tuple(tuple) => v.visit_tuple(tuple, span),
+        Unary::BinOperator(_) => {}
+        Unary::Literal(lit) => v.visit_literal(lit, span),
+        Unary::Expr { expr, .. } => v.visit_expr(&expr.body, expr.span()),
+        Unary::Variable { var, .. } => v.visit_variable(&var.body, var.span()),
+        Unary::Deref(unary) | Unary::Next(unary) | Unary::Prev(unary) => {
+            v.visit_unary(&unary.body, unary.span())
+        }
+    }
+}
+
+pub fn walk_record<V: Visitor + ?Sized>(v: &mut V, node: &Record, _span: Span) {
+    let map = match node {
+        Record::Map(map) => map,
+        Record::MapWithDefault { map, default } => {
+            v.visit_unary(&default.body, default.span());
+            map
+        }
+    };
+    for unit in map {
+        v.visit_record_unit(&unit.body, unit.span());
+    }
+}
+
+pub fn walk_record_unit<V: Visitor + ?Sized>(v: &mut V, node: &RecordUnit, _span: Span) {
+    v.visit_expr(&node.val.body, node.val.span());
+}
+
+pub fn walk_list<V: Visitor + ?Sized>(v: &mut V, node: &List, _span: Span) {
+    for expr in &node.0 {
+        v.visit_expr(&expr.body, expr.span());
+    }
+}
+
+pub fn walk_tuple<V: Visitor + ?Sized>(v: &mut V, node: &Tuple, _span: Span) {
+    for expr in &node.0 {
+        v.visit_expr(&expr.body, expr.span());
+    }
+}
+
+pub fn walk_command_arg<V: Visitor + ?Sized>(v: &mut V, node: &CommandArg, span: Span) {
+    match node {
+        CommandArg::Expr(unary) => v.visit_unary(unary, span),
+        CommandArg::Optional(unary) => v.visit_unary(&unary.body, unary.span()),
+        CommandArg::OptionalOmitted => {}
+        CommandArg::Vertical(vertical) => v.visit_vertical(vertical, span),
+        CommandArg::Horizontal(horizontal) => v.visit_horizontal(horizontal, span),
+    }
+}
+
+pub fn walk_vertical<V: Visitor + ?Sized>(v: &mut V, node: &Vertical, _span: Span) {
+    for elem in &node.0 {
+        v.visit_vertical_element(&elem.body, elem.span());
+    }
+}
+
+pub fn walk_vertical_element<V: Visitor + ?Sized>(v: &mut V, node: &VerticalElement, _span: Span) {
+    match node {
+        VerticalElement::BlockCmd { args, .. } => {
+            for arg in args {
+                v.visit_command_arg(&arg.body, arg.span());
+            }
+        }
+        VerticalElement::BlockTextEmbedding { .. } => {}
+    }
+}
+
+pub fn walk_horizontal<V: Visitor + ?Sized>(v: &mut V, node: &Horizontal, span: Span) {
+    match node {
+        Horizontal::Single(single) => v.visit_horizontal_single(single, span),
+        Horizontal::List(singles) => {
+            for single in singles {
+                v.visit_horizontal_single(&single.body, single.span());
+            }
+        }
+        Horizontal::BulletList(bullets) => {
+            for bullet in bullets {
+                v.visit_horizontal_bullet(&bullet.body, bullet.span());
+            }
+        }
+    }
+}
+
+pub fn walk_horizontal_bullet<V: Visitor + ?Sized>(
+    v: &mut V,
+    node: &HorizontalBullet,
+    _span: Span,
+) {
+    v.visit_horizontal_single(&node.body.body, node.body.span());
+}
+
+pub fn walk_horizontal_single<V: Visitor + ?Sized>(
+    v: &mut V,
+    node: &HorizontalSingle,
+    _span: Span,
+) {
+    for token in &node.0 {
+        v.visit_horizontal_token(&token.body, token.span());
+    }
+}
+
+pub fn walk_horizontal_token<V: Visitor + ?Sized>(v: &mut V, node: &HorizontalToken, _span: Span) {
+    match node {
+        HorizontalToken::InlineCmd { args, .. } => {
+            for arg in args {
+                v.visit_command_arg(&arg.body, arg.span());
+            }
+        }
+        HorizontalToken::Math(math) => v.visit_math(&math.body, math.span()),
+        HorizontalToken::StringLiteral(lit) => v.visit_literal(&lit.body, lit.span()),
+        HorizontalToken::Text(_)
+        | HorizontalToken::SpecialChar(_)
+        | HorizontalToken::HorizontalTextEmbedding { .. } => {}
+    }
+}
+
+pub fn walk_math<V: Visitor + ?Sized>(v: &mut V, node: &Math, span: Span) {
+    match node {
+        Math::Single(single) => v.visit_math_single(single, span),
+        Math::List(singles) => {
+            for single in singles {
+                v.visit_math_single(&single.body, single.span());
+            }
+        }
+    }
+}
+
+pub fn walk_math_single<V: Visitor + ?Sized>(v: &mut V, node: &MathSingle, _span: Span) {
+    for token in &node.0 {
+        v.visit_math_token(&token.body, token.span());
+    }
+}
+
+pub fn walk_math_token<V: Visitor + ?Sized>(v: &mut V, node: &MathToken, _span: Span) {
+    v.visit_math_unary(&node.base.body, node.base.span());
+    if let Some(sub) = &node.sub {
+        v.visit_math_group(&sub.body, sub.span());
+    }
+    if let Some(sup) = &node.sup {
+        v.visit_math_group(&sup.body, sup.span());
+    }
+}
+
+pub fn walk_math_group<V: Visitor + ?Sized>(v: &mut V, node: &MathGroup, span: Span) {
+    match node {
+        MathGroup::Group(single) => v.visit_math_single(single, span),
+        MathGroup::Unary(unary) => v.visit_math_unary(unary, span),
+    }
+}
+
+pub fn walk_math_unary<V: Visitor + ?Sized>(v: &mut V, node: &MathUnary, _span: Span) {
+    match node {
+        MathUnary::Cmd { args, .. } => {
+            for arg in args {
+                v.visit_math_cmd_arg(&arg.body, arg.span());
+            }
+        }
+        MathUnary::Char(_) | MathUnary::SpecialChar(_) | MathUnary::Symbol(_) => {}
+    }
+}
+
+pub fn walk_math_cmd_arg<V: Visitor + ?Sized>(v: &mut V, node: &MathCmdArg, span: Span) {
+    match node {
+        MathCmdArg::Math(math) => v.visit_math(math, span),
+        MathCmdArg::Horizontal(horizontal) => v.visit_horizontal(horizontal, span),
+        MathCmdArg::Vertical(vertical) => v.visit_vertical(vertical, span),
+        MathCmdArg::Expr(unary) => v.visit_unary(unary, span),
+        MathCmdArg::Optional(arg) => v.visit_math_cmd_arg(&arg.body, arg.span()),
+    }
+}
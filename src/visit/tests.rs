@@ -0,0 +1,64 @@
+use super::*;
+use crate::grammar::common::{Grammar, Location};
+
+/// テスト用の Visitor。変数とインラインコマンドを出現順に集める。
+#[derive(Default)]
+struct Collector {
+    variables: Vec<(String, Span)>,
+    inline_cmds: Vec<String>,
+}
+
+impl Visitor for Collector {
+    fn visit_variable(&mut self, node: &Variable, span: Span) {
+        self.variables.push((node.name.clone(), span));
+    }
+
+    fn visit_horizontal_token(&mut self, node: &HorizontalToken, span: Span) {
+        if let HorizontalToken::InlineCmd { name, .. } = node {
+            self.inline_cmds.push(name.body.clone());
+        }
+        walk_horizontal_token(self, node, span)
+    }
+}
+
+/// テスト用の関数。1 行目の col1 列目から col2 列目までの範囲を作る。
+fn span(col1: usize, col2: usize) -> Span {
+    Span {
+        start: Location { row: 1, col: col1 },
+        end: Location { row: 1, col: col2 },
+    }
+}
+
+#[test]
+fn collect_variables() {
+    let program = Program::parse("let f x = g x {a \\b(y){c \\d;}} in f").unwrap();
+    let mut collector = Collector::default();
+    collector.visit_program(&program);
+    let names: Vec<_> = collector
+        .variables
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(names, vec!["f", "x", "g", "x", "y", "f"]);
+    assert_eq!(collector.variables[1].1, span(7, 8));
+    assert_eq!(collector.variables[4].1, span(21, 22));
+    assert_eq!(collector.inline_cmds, vec!["\\b", "\\d"]);
+}
+
+#[test]
+fn skip_children() {
+    /// 式の中には入らない Visitor。
+    #[derive(Default)]
+    struct StatementCounter(usize);
+
+    impl Visitor for StatementCounter {
+        fn visit_statement(&mut self, _node: &Statement, _span: Span) {
+            self.0 += 1;
+        }
+    }
+
+    let program = Program::parse("let a = 1 let b = let c = 2 in c in a").unwrap();
+    let mut counter = StatementCounter::default();
+    counter.visit_program(&program);
+    assert_eq!(counter.0, 2);
+}
@@ -0,0 +1,639 @@
+//! Mutable traversal of the AST.
+//!
+//! [`VisitorMut`] の各メソッドは既定で対応する `walk_*_mut` を呼び、子要素を順に訪れる。
+//! 必要な構文要素のメソッドだけを上書きし、子要素も訪れたい場合はその中で `walk_*_mut` を呼ぶ。
+//!
+//! 各メソッドには構文要素の範囲が渡される。
+//! 構文要素自体が範囲を持たない場合（[`Ranged`](crate::grammar::common::Ranged) で包まれていない場合）は、
+//! それを囲む最も内側の構文要素の範囲が渡される。
+
+#![allow(clippy::missing_docs_in_private_items)]
+#![allow(missing_docs)]
+
+#[cfg(test)]
+mod tests;
+
+use crate::grammar::common::Span;
+use crate::grammar::{
+    ApplicationArg, Argument, CommandArg, Constraint, Expr, Header, Horizontal, HorizontalBullet,
+    HorizontalSingle, HorizontalToken, LetRecBinding, List, Literal, MatchArm, Math, MathCmdArg,
+    MathGroup, MathSingle, MathToken, MathUnary, Pattern, Preamble, Program, Record, RecordUnit,
+    SigStmt, Signature, Stage, Statement, Struct, Tuple, TypeExpr, TypeList, TypeListUnit,
+    TypeProd, TypeRecord, TypeRecordUnit, TypeUnary, Unary, ValName, Variable, Vertical,
+    VerticalElement,
+};
+
+/// Ranged で包まれた子要素を、その範囲とともに訪れる。
+macro_rules! visit_ranged {
+    ($v:ident, $method:ident, $node:expr) => {{
+        let node = $node;
+        let span = node.span();
+        $v.$method(&mut node.body, span)
+    }};
+}
+
+/// AST を書き換えながら辿るトレイト。
+#[allow(unused_variables)]
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, node: &mut Program) {
+        walk_program_mut(self, node)
+    }
+    fn visit_stage_mut(&mut self, node: &mut Stage, span: Span) {}
+    fn visit_header_mut(&mut self, node: &mut Header, span: Span) {}
+    fn visit_preamble_mut(&mut self, node: &mut Preamble, span: Span) {
+        walk_preamble_mut(self, node, span)
+    }
+    fn visit_statement_mut(&mut self, node: &mut Statement, span: Span) {
+        walk_statement_mut(self, node, span)
+    }
+    fn visit_let_rec_binding_mut(&mut self, node: &mut LetRecBinding, span: Span) {
+        walk_let_rec_binding_mut(self, node, span)
+    }
+    fn visit_argument_mut(&mut self, node: &mut Argument, span: Span) {
+        walk_argument_mut(self, node, span)
+    }
+    fn visit_signature_mut(&mut self, node: &mut Signature, span: Span) {
+        walk_signature_mut(self, node, span)
+    }
+    fn visit_sig_stmt_mut(&mut self, node: &mut SigStmt, span: Span) {
+        walk_sig_stmt_mut(self, node, span)
+    }
+    fn visit_val_name_mut(&mut self, node: &mut ValName, span: Span) {}
+    fn visit_struct_mut(&mut self, node: &mut Struct, span: Span) {
+        walk_struct_mut(self, node, span)
+    }
+    fn visit_constraint_mut(&mut self, node: &mut Constraint, span: Span) {
+        walk_constraint_mut(self, node, span)
+    }
+    fn visit_type_expr_mut(&mut self, node: &mut TypeExpr, span: Span) {
+        walk_type_expr_mut(self, node, span)
+    }
+    fn visit_type_prod_mut(&mut self, node: &mut TypeProd, span: Span) {
+        walk_type_prod_mut(self, node, span)
+    }
+    fn visit_type_unary_mut(&mut self, node: &mut TypeUnary, span: Span) {
+        walk_type_unary_mut(self, node, span)
+    }
+    fn visit_type_list_mut(&mut self, node: &mut TypeList, span: Span) {
+        walk_type_list_mut(self, node, span)
+    }
+    fn visit_type_list_unit_mut(&mut self, node: &mut TypeListUnit, span: Span) {
+        walk_type_list_unit_mut(self, node, span)
+    }
+    fn visit_type_record_mut(&mut self, node: &mut TypeRecord, span: Span) {
+        walk_type_record_mut(self, node, span)
+    }
+    fn visit_type_record_unit_mut(&mut self, node: &mut TypeRecordUnit, span: Span) {
+        walk_type_record_unit_mut(self, node, span)
+    }
+    fn visit_pattern_mut(&mut self, node: &mut Pattern, span: Span) {
+        walk_pattern_mut(self, node, span)
+    }
+    fn visit_expr_mut(&mut self, node: &mut Expr, span: Span) {
+        walk_expr_mut(self, node, span)
+    }
+    fn visit_match_arm_mut(&mut self, node: &mut MatchArm, span: Span) {
+        walk_match_arm_mut(self, node, span)
+    }
+    fn visit_application_arg_mut(&mut self, node: &mut ApplicationArg, span: Span) {
+        walk_application_arg_mut(self, node, span)
+    }
+    fn visit_unary_mut(&mut self, node: &mut Unary, span: Span) {
+        walk_unary_mut(self, node, span)
+    }
+    fn visit_record_mut(&mut self, node: &mut Record, span: Span) {
+        walk_record_mut(self, node, span)
+    }
+    fn visit_record_unit_mut(&mut self, node: &mut RecordUnit, span: Span) {
+        walk_record_unit_mut(self, node, span)
+    }
+    fn visit_list_mut(&mut self, node: &mut List, span: Span) {
+        walk_list_mut(self, node, span)
+    }
+    fn visit_tuple_mut(&mut self, node: &mut Tuple, span: Span) {
+        walk_tuple_mut(self, node, span)
+    }
+    fn visit_variable_mut(&mut self, node: &mut Variable, span: Span) {}
+    fn visit_literal_mut(&mut self, node: &mut Literal, span: Span) {}
+    fn visit_command_arg_mut(&mut self, node: &mut CommandArg, span: Span) {
+        walk_command_arg_mut(self, node, span)
+    }
+    fn visit_vertical_mut(&mut self, node: &mut Vertical, span: Span) {
+        walk_vertical_mut(self, node, span)
+    }
+    fn visit_vertical_element_mut(&mut self, node: &mut VerticalElement, span: Span) {
+        walk_vertical_element_mut(self, node, span)
+    }
+    fn visit_horizontal_mut(&mut self, node: &mut Horizontal, span: Span) {
+        walk_horizontal_mut(self, node, span)
+    }
+    fn visit_horizontal_bullet_mut(&mut self, node: &mut HorizontalBullet, span: Span) {
+        walk_horizontal_bullet_mut(self, node, span)
+    }
+    fn visit_horizontal_single_mut(&mut self, node: &mut HorizontalSingle, span: Span) {
+        walk_horizontal_single_mut(self, node, span)
+    }
+    fn visit_horizontal_token_mut(&mut self, node: &mut HorizontalToken, span: Span) {
+        walk_horizontal_token_mut(self, node, span)
+    }
+    fn visit_math_mut(&mut self, node: &mut Math, span: Span) {
+        walk_math_mut(self, node, span)
+    }
+    fn visit_math_single_mut(&mut self, node: &mut MathSingle, span: Span) {
+        walk_math_single_mut(self, node, span)
+    }
+    fn visit_math_token_mut(&mut self, node: &mut MathToken, span: Span) {
+        walk_math_token_mut(self, node, span)
+    }
+    fn visit_math_group_mut(&mut self, node: &mut MathGroup, span: Span) {
+        walk_math_group_mut(self, node, span)
+    }
+    fn visit_math_unary_mut(&mut self, node: &mut MathUnary, span: Span) {
+        walk_math_unary_mut(self, node, span)
+    }
+    fn visit_math_cmd_arg_mut(&mut self, node: &mut MathCmdArg, span: Span) {
+        walk_math_cmd_arg_mut(self, node, span)
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Program) {
+    if let Some(stage) = &mut node.stage {
+        visit_ranged!(v, visit_stage_mut, stage);
+    }
+    for header in &mut node.header {
+        visit_ranged!(v, visit_header_mut, header);
+    }
+    if let Some(preamble) = &mut node.preamble {
+        visit_ranged!(v, visit_preamble_mut, preamble);
+    }
+    if let Some(expr) = &mut node.expr {
+        visit_ranged!(v, visit_expr_mut, expr);
+    }
+}
+
+pub fn walk_preamble_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Preamble, _span: Span) {
+    for stmt in &mut node.0 {
+        visit_ranged!(v, visit_statement_mut, stmt);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Statement, _span: Span) {
+    match node {
+        Statement::Let { ptn, args, expr } => {
+            visit_ranged!(v, visit_pattern_mut, ptn);
+            for arg in args {
+                visit_ranged!(v, visit_argument_mut, arg);
+            }
+            visit_ranged!(v, visit_expr_mut, expr);
+        }
+        Statement::LetRec(bindings) => {
+            for binding in bindings {
+                visit_ranged!(v, visit_let_rec_binding_mut, binding);
+            }
+        }
+        Statement::LetInline {
+            ctx, args, expr, ..
+        }
+        | Statement::LetBlock {
+            ctx, args, expr, ..
+        } => {
+            if let Some(ctx) = ctx {
+                visit_ranged!(v, visit_variable_mut, ctx);
+            }
+            for arg in args {
+                visit_ranged!(v, visit_argument_mut, arg);
+            }
+            visit_ranged!(v, visit_expr_mut, expr);
+        }
+        Statement::LetMath { args, expr, .. } => {
+            for arg in args {
+                visit_ranged!(v, visit_argument_mut, arg);
+            }
+            visit_ranged!(v, visit_expr_mut, expr);
+        }
+        Statement::LetMutable { var, expr } => {
+            visit_ranged!(v, visit_variable_mut, var);
+            visit_ranged!(v, visit_expr_mut, expr);
+        }
+        Statement::Module {
+            signature, body, ..
+        } => {
+            if let Some(signature) = signature {
+                visit_ranged!(v, visit_signature_mut, signature);
+            }
+            visit_ranged!(v, visit_struct_mut, body);
+        }
+    }
+}
+
+pub fn walk_let_rec_binding_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    node: &mut LetRecBinding,
+    _span: Span,
+) {
+    visit_ranged!(v, visit_variable_mut, &mut node.var);
+    for arg in &mut node.args {
+        visit_ranged!(v, visit_argument_mut, arg);
+    }
+    visit_ranged!(v, visit_expr_mut, &mut node.expr);
+}
+
+pub fn walk_argument_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Argument, span: Span) {
+    match node {
+        Argument::Pattern(ptn) => v.visit_pattern_mut(ptn, span),
+        Argument::Optional(_) => {}
+    }
+}
+
+pub fn walk_signature_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Signature, _span: Span) {
+    for stmt in &mut node.0 {
+        visit_ranged!(v, visit_sig_stmt_mut, stmt);
+    }
+}
+
+pub fn walk_sig_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut SigStmt, _span: Span) {
+    let constraints = match node {
+        SigStmt::Type { constraints, .. } => constraints,
+        SigStmt::Val {
+            name,
+            ty,
+            constraints,
+        } => {
+            visit_ranged!(v, visit_val_name_mut, name);
+            visit_ranged!(v, visit_type_expr_mut, ty);
+            constraints
+        }
+        SigStmt::Direct {
+            ty, constraints, ..
+        } => {
+            visit_ranged!(v, visit_type_expr_mut, ty);
+            constraints
+        }
+    };
+    for constraint in constraints {
+        visit_ranged!(v, visit_constraint_mut, constraint);
+    }
+}
+
+pub fn walk_struct_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Struct, _span: Span) {
+    for stmt in &mut node.0 {
+        visit_ranged!(v, visit_statement_mut, stmt);
+    }
+}
+
+pub fn walk_constraint_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Constraint, _span: Span) {
+    visit_ranged!(v, visit_type_record_mut, &mut node.record);
+}
+
+pub fn walk_type_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut TypeExpr, _span: Span) {
+    for arg in node.optional_args.iter_mut().chain(&mut node.args) {
+        visit_ranged!(v, visit_type_prod_mut, arg);
+    }
+    visit_ranged!(v, visit_type_prod_mut, &mut node.ret);
+}
+
+pub fn walk_type_prod_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut TypeProd, _span: Span) {
+    for unit in &mut node.0 {
+        visit_ranged!(v, visit_type_unary_mut, unit);
+    }
+}
+
+pub fn walk_type_unary_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut TypeUnary, span: Span) {
+    match node {
+        TypeUnary::InlineCmd(list) | TypeUnary::BlockCmd(list) | TypeUnary::MathCmd(list) => {
+            visit_ranged!(v, visit_type_list_mut, list)
+        }
+        TypeUnary::Paren(ty) => visit_ranged!(v, visit_type_expr_mut, ty),
+        TypeUnary::Record(record) => v.visit_type_record_mut(record, span),
+        TypeUnary::Application(units) => {
+            for unit in units {
+                visit_ranged!(v, visit_type_unary_mut, unit);
+            }
+        }
+        TypeUnary::Param(_) | TypeUnary::Name { .. } => {}
+    }
+}
+
+pub fn walk_type_list_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut TypeList, _span: Span) {
+    for unit in &mut node.0 {
+        visit_ranged!(v, visit_type_list_unit_mut, unit);
+    }
+}
+
+pub fn walk_type_list_unit_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    node: &mut TypeListUnit,
+    span: Span,
+) {
+    match node {
+        TypeListUnit::Optional(ty) => v.visit_type_prod_mut(ty, span),
+        TypeListUnit::Mandatory(ty) => v.visit_type_expr_mut(ty, span),
+    }
+}
+
+pub fn walk_type_record_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut TypeRecord, _span: Span) {
+    for unit in &mut node.0 {
+        visit_ranged!(v, visit_type_record_unit_mut, unit);
+    }
+}
+
+pub fn walk_type_record_unit_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    node: &mut TypeRecordUnit,
+    _span: Span,
+) {
+    visit_ranged!(v, visit_type_expr_mut, &mut node.ty);
+}
+
+pub fn walk_pattern_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Pattern, span: Span) {
+    match node {
+        Pattern::List(ptns) | Pattern::Tuple(ptns) => {
+            for ptn in ptns {
+                visit_ranged!(v, visit_pattern_mut, ptn);
+            }
+        }
+        Pattern::Paren(ptn) => visit_ranged!(v, visit_pattern_mut, ptn),
+        Pattern::Wildcard => {}
+        Pattern::Variable(var) => v.visit_variable_mut(var, span),
+        Pattern::Literal(lit) => v.visit_literal_mut(lit, span),
+        Pattern::Variant { arg, .. } => {
+            if let Some(arg) = arg {
+                visit_ranged!(v, visit_pattern_mut, arg);
+            }
+        }
+        Pattern::As { ptn, var } => {
+            visit_ranged!(v, visit_pattern_mut, ptn);
+            visit_ranged!(v, visit_variable_mut, var);
+        }
+        Pattern::Cons { head, tail } => {
+            visit_ranged!(v, visit_pattern_mut, head);
+            visit_ranged!(v, visit_pattern_mut, tail);
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Expr, _span: Span) {
+    match node {
+        Expr::Match { expr, arms } => {
+            visit_ranged!(v, visit_expr_mut, expr);
+            for arm in arms {
+                visit_ranged!(v, visit_match_arm_mut, arm);
+            }
+        }
+        Expr::BindStmt { bind, body } => {
+            visit_ranged!(v, visit_statement_mut, bind);
+            visit_ranged!(v, visit_expr_mut, body);
+        }
+        Expr::Lambda { args, body } => {
+            for arg in args {
+                visit_ranged!(v, visit_argument_mut, arg);
+            }
+            visit_ranged!(v, visit_expr_mut, body);
+        }
+        Expr::CtrlFlowWhile { condition, body } => {
+            visit_ranged!(v, visit_expr_mut, condition);
+            visit_ranged!(v, visit_expr_mut, body);
+        }
+        Expr::CtrlFlowIf {
+            condition,
+            expr_true,
+            expr_false,
+        } => {
+            visit_ranged!(v, visit_expr_mut, condition);
+            visit_ranged!(v, visit_expr_mut, expr_true);
+            visit_ranged!(v, visit_expr_mut, expr_false);
+        }
+        Expr::Assign { var, expr } => {
+            visit_ranged!(v, visit_variable_mut, var);
+            visit_ranged!(v, visit_expr_mut, expr);
+        }
+        Expr::Dyadic { lhs, rhs, .. } => {
+            visit_ranged!(v, visit_expr_mut, lhs);
+            visit_ranged!(v, visit_expr_mut, rhs);
+        }
+        Expr::UnaryOperatorExpr { rhs, .. } => visit_ranged!(v, visit_expr_mut, rhs),
+        Expr::VariantConstructor { arg, .. } => {
+            if let Some(arg) = arg {
+                visit_ranged!(v, visit_unary_mut, arg);
+            }
+        }
+        Expr::Application { func, args, .. } => {
+            visit_ranged!(v, visit_variable_mut, func);
+            for arg in args {
+                visit_ranged!(v, visit_application_arg_mut, arg);
+            }
+        }
+        Expr::Command(_) => {}
+        Expr::RecordMember { record, member } => {
+            visit_ranged!(v, visit_unary_mut, record);
+            visit_ranged!(v, visit_variable_mut, member);
+        }
+        Expr::Unary(unary) => visit_ranged!(v, visit_unary_mut, unary),
+    }
+}
+
+pub fn walk_match_arm_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut MatchArm, _span: Span) {
+    visit_ranged!(v, visit_pattern_mut, &mut node.ptn);
+    if let Some(guard) = &mut node.guard {
+        visit_ranged!(v, visit_expr_mut, guard);
+    }
+    visit_ranged!(v, visit_expr_mut, &mut node.expr);
+}
+
+pub fn walk_application_arg_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    node: &mut ApplicationArg,
+    span: Span,
+) {
+    match node {
+        ApplicationArg::Unary(unary) => v.visit_unary_mut(unary, span),
+        ApplicationArg::Optional(unary) => visit_ranged!(v, visit_unary_mut, unary),
+        ApplicationArg::Variant(_) | ApplicationArg::OptionalOmitted => {}
+    }
+}
+
+pub fn walk_unary_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Unary, span: Span) {
+    match node {
+        Unary::BlockText(vertical) => v.visit_vertical_mut(vertical, span),
+        Unary::HorizontalText(horizontal) => v.visit_horizontal_mut(horizontal, span),
+        Unary::MathText(math) => v.visit_math_mut(math, span),
+        Unary::Record(record) => v.visit_record_mut(record, span),
+        Unary::List(list) => v.visit_list_mut(list, span),
+        Unary::Tuple(tuple) => v.visit_tuple_mut(tuple, span),
+        Unary::BinOperator(_) => {}
+        Unary::Literal(lit) => v.visit_literal_mut(lit, span),
+        Unary::Expr { expr, .. } => visit_ranged!(v, visit_expr_mut, expr),
+        Unary::Variable { var, .. } => visit_ranged!(v, visit_variable_mut, var),
+        Unary::Deref(unary) | Unary::Next(unary) | Unary::Prev(unary) => {
+            visit_ranged!(v, visit_unary_mut, unary)
+        }
+    }
+}
+
+pub fn walk_record_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Record, _span: Span) {
+    let map = match node {
+        Record::Map(map) => map,
+        Record::MapWithDefault { map, default } => {
+            visit_ranged!(v, visit_unary_mut, default);
+            map
+        }
+    };
+    for unit in map {
+        visit_ranged!(v, visit_record_unit_mut, unit);
+    }
+}
+
+pub fn walk_record_unit_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut RecordUnit, _span: Span) {
+    visit_ranged!(v, visit_expr_mut, &mut node.val);
+}
+
+pub fn walk_list_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut List, _span: Span) {
+    for expr in &mut node.0 {
+        visit_ranged!(v, visit_expr_mut, expr);
+    }
+}
+
+pub fn walk_tuple_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Tuple, _span: Span) {
+    for expr in &mut node.0 {
+        visit_ranged!(v, visit_expr_mut, expr);
+    }
+}
+
+pub fn walk_command_arg_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut CommandArg, span: Span) {
+    match node {
+        CommandArg::Expr(unary) => v.visit_unary_mut(unary, span),
+        CommandArg::Optional(unary) => visit_ranged!(v, visit_unary_mut, unary),
+        CommandArg::OptionalOmitted => {}
+        CommandArg::Vertical(vertical) => v.visit_vertical_mut(vertical, span),
+        CommandArg::Horizontal(horizontal) => v.visit_horizontal_mut(horizontal, span),
+    }
+}
+
+pub fn walk_vertical_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Vertical, _span: Span) {
+    for elem in &mut node.0 {
+        visit_ranged!(v, visit_vertical_element_mut, elem);
+    }
+}
+
+pub fn walk_vertical_element_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    node: &mut VerticalElement,
+    _span: Span,
+) {
+    match node {
+        VerticalElement::BlockCmd { args, .. } => {
+            for arg in args {
+                visit_ranged!(v, visit_command_arg_mut, arg);
+            }
+        }
+        VerticalElement::BlockTextEmbedding { .. } => {}
+    }
+}
+
+pub fn walk_horizontal_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Horizontal, span: Span) {
+    match node {
+        Horizontal::Single(single) => v.visit_horizontal_single_mut(single, span),
+        Horizontal::List(singles) => {
+            for single in singles {
+                visit_ranged!(v, visit_horizontal_single_mut, single);
+            }
+        }
+        Horizontal::BulletList(bullets) => {
+            for bullet in bullets {
+                visit_ranged!(v, visit_horizontal_bullet_mut, bullet);
+            }
+        }
+    }
+}
+
+pub fn walk_horizontal_bullet_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    node: &mut HorizontalBullet,
+    _span: Span,
+) {
+    visit_ranged!(v, visit_horizontal_single_mut, &mut node.body);
+}
+
+pub fn walk_horizontal_single_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    node: &mut HorizontalSingle,
+    _span: Span,
+) {
+    for token in &mut node.0 {
+        visit_ranged!(v, visit_horizontal_token_mut, token);
+    }
+}
+
+pub fn walk_horizontal_token_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    node: &mut HorizontalToken,
+    _span: Span,
+) {
+    match node {
+        HorizontalToken::InlineCmd { args, .. } => {
+            for arg in args {
+                visit_ranged!(v, visit_command_arg_mut, arg);
+            }
+        }
+        HorizontalToken::Math(math) => visit_ranged!(v, visit_math_mut, math),
+        HorizontalToken::StringLiteral(lit) => visit_ranged!(v, visit_literal_mut, lit),
+        HorizontalToken::Text(_)
+        | HorizontalToken::SpecialChar(_)
+        | HorizontalToken::HorizontalTextEmbedding { .. } => {}
+    }
+}
+
+pub fn walk_math_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Math, span: Span) {
+    match node {
+        Math::Single(single) => v.visit_math_single_mut(single, span),
+        Math::List(singles) => {
+            for single in singles {
+                visit_ranged!(v, visit_math_single_mut, single);
+            }
+        }
+    }
+}
+
+pub fn walk_math_single_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut MathSingle, _span: Span) {
+    for token in &mut node.0 {
+        visit_ranged!(v, visit_math_token_mut, token);
+    }
+}
+
+pub fn walk_math_token_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut MathToken, _span: Span) {
+    visit_ranged!(v, visit_math_unary_mut, &mut node.base);
+    if let Some(sub) = &mut node.sub {
+        visit_ranged!(v, visit_math_group_mut, sub);
+    }
+    if let Some(sup) = &mut node.sup {
+        visit_ranged!(v, visit_math_group_mut, sup);
+    }
+}
+
+pub fn walk_math_group_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut MathGroup, span: Span) {
+    match node {
+        MathGroup::Group(single) => v.visit_math_single_mut(single, span),
+        MathGroup::Unary(unary) => v.visit_math_unary_mut(unary, span),
+    }
+}
+
+pub fn walk_math_unary_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut MathUnary, _span: Span) {
+    match node {
+        MathUnary::Cmd { args, .. } => {
+            for arg in args {
+                visit_ranged!(v, visit_math_cmd_arg_mut, arg);
+            }
+        }
+        MathUnary::Char(_) | MathUnary::SpecialChar(_) | MathUnary::Symbol(_) => {}
+    }
+}
+
+pub fn walk_math_cmd_arg_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut MathCmdArg, span: Span) {
+    match node {
+        MathCmdArg::Math(math) => v.visit_math_mut(math, span),
+        MathCmdArg::Horizontal(horizontal) => v.visit_horizontal_mut(horizontal, span),
+        MathCmdArg::Vertical(vertical) => v.visit_vertical_mut(vertical, span),
+        MathCmdArg::Expr(unary) => v.visit_unary_mut(unary, span),
+        MathCmdArg::Optional(arg) => visit_ranged!(v, visit_math_cmd_arg_mut, arg),
+    }
+}
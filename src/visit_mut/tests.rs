@@ -0,0 +1,48 @@
+use super::*;
+use crate::grammar::common::Grammar;
+use crate::printer::print;
+
+/// テスト用の VisitorMut。変数名を置き換える。
+struct Renamer<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+impl VisitorMut for Renamer<'_> {
+    fn visit_variable_mut(&mut self, node: &mut Variable, _span: Span) {
+        if node.name == self.from {
+            node.name = self.to.to_owned();
+        }
+    }
+}
+
+#[test]
+fn rename_variables() {
+    let mut program = Program::parse("let x = 1 in f x {a \\b(x);} [x; y]").unwrap();
+    Renamer { from: "x", to: "z" }.visit_program_mut(&mut program);
+    assert_eq!(
+        print(&program),
+        "let z = 1\n\nin\n\nf z {a \\b(z);} [z; y]\n"
+    );
+}
+
+#[test]
+fn rewrite_literals() {
+    /// 整数リテラルを 2 倍にする VisitorMut。
+    struct Doubler;
+
+    impl VisitorMut for Doubler {
+        fn visit_literal_mut(&mut self, node: &mut Literal, _span: Span) {
+            if let Literal::Int(i) = node {
+                *i *= 2;
+            }
+        }
+    }
+
+    let mut program = Program::parse("match 1 with | 2 -> (3, ${x^{\\f!(4)}})").unwrap();
+    Doubler.visit_program_mut(&mut program);
+    assert_eq!(
+        print(&program),
+        "match 2 with\n| 4 -> (6, ${x^{\\f!(8)}})\n"
+    );
+}
@@ -0,0 +1,428 @@
+//! Workspace of multiple source files.
+//!
+//! 文書と、それが `@require:` や `@import:` で読み込むファイルをまとめてパースして保持し、
+//! ファイルをまたいだ問い合わせ（`\textbf` はどこで定義されているか、
+//! `+section` を公開しているモジュールはどれか）に答える。
+//!
+//! ファイルの中身が変わったときは、そのファイルだけをパースし直す。
+
+#[cfg(test)]
+mod tests;
+
+use crate::arity;
+use crate::diagnostic::Diagnostic;
+use crate::grammar::common::{Grammar, Location, Ranged, Span};
+use crate::grammar::{Header, Program, SigStmt, Signature, Statement, ValName};
+use crate::package::{Dependency, DependencyGraph, PackageResolver};
+use crate::resolve::{resolve, BindingKind, Namespace, Resolution};
+use crate::stage;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// ファイルのトップレベルやモジュールの直下での定義。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    /// 名前。コマンドの場合は `\` や `+` を含み、モジュール名は含まない。
+    pub name: String,
+    /// 名前空間。
+    pub namespace: Namespace,
+    /// 名前の範囲。
+    pub span: Span,
+    /// 定義を囲むモジュールの名前。外側のものから順に並ぶ。トップレベルでは空。
+    pub module: Vec<String>,
+    /// モジュールの外から見えるかどうか。トップレベルの定義では常に true。
+    pub exported: bool,
+    /// `direct` で宣言され、モジュール名を付けずに参照できるかどうか。
+    pub direct: bool,
+}
+
+/// `@require:` や `@import:` による読み込み。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// パッケージ名。
+    pub name: String,
+    /// パッケージ名の範囲。
+    pub span: Span,
+    /// 読み込まれるファイル。見つからない場合は None。
+    pub path: Option<PathBuf>,
+}
+
+/// ワークスペース中のファイル。
+#[derive(Debug)]
+struct File {
+    /// ファイルの中身。
+    text: String,
+    /// パースの結果。
+    program: Result<Program, Diagnostic>,
+    /// 読み込むファイル。
+    imports: Vec<Import>,
+    /// トップレベルやモジュールの直下での定義。
+    definitions: Vec<Definition>,
+}
+
+/// 複数のファイルをまとめて保持するもの。
+#[derive(Debug, Default)]
+pub struct Workspace {
+    /// `@require:` や `@import:` の指すファイルを探すもの。
+    resolver: PackageResolver,
+    /// パスからファイルへの対応。
+    files: BTreeMap<PathBuf, File>,
+}
+
+impl Workspace {
+    /// resolver で読み込むファイルを探す、空のワークスペースを作る。
+    pub fn new(resolver: PackageResolver) -> Self {
+        Workspace {
+            resolver,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// path の中身を text とし、まだワークスペースにないファイルで、読み込まれるものをディスクから読む。
+    ///
+    /// パースしたファイルのパスを返す。中身が変わっていなければ path はパースし直さない。
+    pub fn update(&mut self, path: impl Into<PathBuf>, text: String) -> Vec<PathBuf> {
+        let path = normalize(path.into());
+        let mut parsed = vec![];
+        if self.files.get(&path).map(|file| &file.text) != Some(&text) {
+            self.insert(path.clone(), text);
+            parsed.push(path);
+        }
+        // 新しく読み込まれるようになったファイルを読む
+        let mut next = 0;
+        while next < parsed.len() {
+            let imports: Vec<_> = self.files[&parsed[next]]
+                .imports
+                .iter()
+                .filter_map(|import| import.path.clone())
+                .collect();
+            for path in imports {
+                if self.files.contains_key(&path) {
+                    continue;
+                }
+                match std::fs::read_to_string(&path) {
+                    Ok(text) => self.insert(path.clone(), text),
+                    Err(e) => self.insert_unreadable(path.clone(), &e),
+                }
+                parsed.push(path);
+            }
+            next += 1;
+        }
+        parsed
+    }
+
+    /// path をディスクから読み、[`Workspace::update`] する。
+    pub fn load(&mut self, path: impl Into<PathBuf>) -> io::Result<Vec<PathBuf>> {
+        let path = path.into();
+        let text = std::fs::read_to_string(&path)?;
+        Ok(self.update(path, text))
+    }
+
+    /// path をワークスペースから取り除く。path が読み込んでいたファイルは残す。
+    pub fn remove(&mut self, path: &Path) -> bool {
+        self.files.remove(&normalize(path.to_owned())).is_some()
+    }
+
+    /// ワークスペース中のファイルのパス。
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// path の中身。
+    pub fn text(&self, path: &Path) -> Option<&str> {
+        self.file(path).map(|file| file.text.as_str())
+    }
+
+    /// path のパースの結果。パースに失敗している場合は None。
+    pub fn program(&self, path: &Path) -> Option<&Program> {
+        self.file(path)?.program.as_ref().ok()
+    }
+
+    /// path が読み込むファイル。
+    pub fn imports(&self, path: &Path) -> &[Import] {
+        self.file(path).map_or(&[], |file| &file.imports)
+    }
+
+    /// path を直接読み込んでいるファイル。
+    pub fn importers(&self, path: &Path) -> Vec<&Path> {
+        let path = normalize(path.to_owned());
+        self.files
+            .iter()
+            .filter(|(_, file)| {
+                file.imports
+                    .iter()
+                    .any(|import| import.path.as_ref() == Some(&path))
+            })
+            .map(|(importer, _)| importer.as_path())
+            .collect()
+    }
+
+    /// path のトップレベルやモジュールの直下での定義。
+    pub fn definitions(&self, path: &Path) -> &[Definition] {
+        self.file(path).map_or(&[], |file| &file.definitions)
+    }
+
+    /// name を定義しているファイルと定義。
+    ///
+    /// `\M.cmd` や `M.x` のようにモジュール名を付けた場合は、そのモジュールが公開しているものを探す。
+    /// 付けない場合は、トップレベルの定義と `direct` で宣言されたものを探す。
+    pub fn definitions_of(&self, name: &str) -> Vec<(&Path, &Definition)> {
+        let (module, name) = split_qualified(name);
+        self.all_definitions()
+            .filter(|(_, def)| def.name == name)
+            .filter(|(_, def)| match module {
+                Some(module) => {
+                    def.exported && def.module.last().map(String::as_str) == Some(module)
+                }
+                None => def.module.is_empty() || (def.exported && def.direct),
+            })
+            .collect()
+    }
+
+    /// name を公開しているモジュールの定義。モジュール名は [`Definition::module`] にある。
+    pub fn exporters_of(&self, name: &str) -> Vec<(&Path, &Definition)> {
+        self.all_definitions()
+            .filter(|(_, def)| def.name == name && def.exported && !def.module.is_empty())
+            .collect()
+    }
+
+    /// ワークスペース中のファイルの依存関係のグラフ。
+    ///
+    /// ファイルの番号は [`Workspace::paths`] の順である。
+    /// 診断には、パースの失敗、見つからないパッケージ、循環を含む。
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        let indices: BTreeMap<_, _> = self
+            .files
+            .keys()
+            .enumerate()
+            .map(|(i, path)| (path, i))
+            .collect();
+        let mut graph = DependencyGraph {
+            files: self.files.keys().cloned().collect(),
+            ..Default::default()
+        };
+        for (from, file) in self.files.values().enumerate() {
+            if let Err(diagnostic) = &file.program {
+                graph.diagnostics.push((from, diagnostic.clone()));
+            }
+            for import in &file.imports {
+                match import.path.as_ref().and_then(|path| indices.get(path)) {
+                    Some(&to) => graph.dependencies.push(Dependency {
+                        from,
+                        to,
+                        span: import.span,
+                    }),
+                    None => {
+                        let message = format!("package not found: {}", import.name);
+                        graph
+                            .diagnostics
+                            .push((from, Diagnostic::error(import.span, message)));
+                    }
+                }
+            }
+        }
+        graph.find_cycles();
+        graph
+    }
+
+    /// ファイルごとの診断。診断のないファイルも含む。
+    ///
+    /// [`Workspace::dependency_graph`] の診断に加えて、ステージの誤りと、
+    /// `sig` での宣言と合わないコマンドの引数も含む。
+    pub fn diagnostics(&self) -> BTreeMap<&Path, Vec<Diagnostic>> {
+        let graph = self.dependency_graph();
+        let mut diagnostics: BTreeMap<_, _> = self.paths().map(|path| (path, vec![])).collect();
+        for (file, diagnostic) in graph.diagnostics {
+            let path = self.files.keys().nth(file).unwrap();
+            diagnostics
+                .get_mut(path.as_path())
+                .unwrap()
+                .push(diagnostic);
+        }
+        for (path, diagnostics) in &mut diagnostics {
+            diagnostics.extend(stage::check_imports(self, path));
+            if let Some(program) = self.program(path) {
+                diagnostics.extend(stage::check_stages(program));
+            }
+            diagnostics.extend(arity::check_workspace(self, path));
+        }
+        diagnostics
+    }
+
+    /// path のファイル。
+    fn file(&self, path: &Path) -> Option<&File> {
+        self.files
+            .get(path)
+            .or_else(|| self.files.get(&normalize(path.to_owned())))
+    }
+
+    /// すべてのファイルの定義。
+    fn all_definitions(&self) -> impl Iterator<Item = (&Path, &Definition)> {
+        self.files.iter().flat_map(|(path, file)| {
+            file.definitions
+                .iter()
+                .map(move |def| (path.as_path(), def))
+        })
+    }
+
+    /// text をパースして path に置く。
+    fn insert(&mut self, path: PathBuf, text: String) {
+        let dir = path.parent().map(Path::to_owned).unwrap_or_default();
+        let program = Program::parse(&text).map_err(Diagnostic::from);
+        let mut imports = vec![];
+        let mut definitions = vec![];
+        if let Ok(program) = &program {
+            for header in &program.header {
+                let name = match &header.body {
+                    Header::Require(name) | Header::Import(name) => name,
+                };
+                imports.push(Import {
+                    name: name.body.trim().to_owned(),
+                    span: name.span(),
+                    path: self.resolver.locate(&header.body, &dir).map(normalize),
+                });
+            }
+            if let Some(preamble) = &program.preamble {
+                let resolution = resolve(program);
+                collect_definitions(&preamble.body.0, None, &[], &resolution, &mut definitions);
+            }
+        }
+        let file = File {
+            text,
+            program,
+            imports,
+            definitions,
+        };
+        self.files.insert(path, file);
+    }
+
+    /// 読めなかったファイルを path に置く。
+    fn insert_unreadable(&mut self, path: PathBuf, e: &io::Error) {
+        let start = Location { row: 1, col: 1 };
+        let message = format!("cannot read {}: {}", path.display(), e);
+        let file = File {
+            text: String::new(),
+            program: Err(Diagnostic::error(Span { start, end: start }, message)),
+            imports: vec![],
+            definitions: vec![],
+        };
+        self.files.insert(path, file);
+    }
+}
+
+/// 同じファイルを別のパスで指すことがあるため、できれば正規化する。
+fn normalize(path: PathBuf) -> PathBuf {
+    path.canonicalize().unwrap_or(path)
+}
+
+/// `\M.cmd` や `M.x` を、モジュール名と `\cmd` や `x` に分ける。
+pub(crate) fn split_qualified(name: &str) -> (Option<&str>, String) {
+    let (prefix, rest) = match name.chars().next() {
+        Some(c @ '\\') | Some(c @ '+') => name.split_at(c.len_utf8()),
+        _ => ("", name),
+    };
+    match rest.rsplit_once('.') {
+        Some((module, name)) => (Some(module), format!("{}{}", prefix, name)),
+        None => (None, name.to_owned()),
+    }
+}
+
+/// inner が outer の中にあるかどうか。
+fn within(inner: Span, outer: Span) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// stmts で定義される名前を集める。signature はそれらを囲むモジュールのシグネチャ。
+fn collect_definitions(
+    stmts: &[Ranged<Statement>],
+    signature: Option<&Signature>,
+    module: &[String],
+    resolution: &Resolution,
+    definitions: &mut Vec<Definition>,
+) {
+    let mut define = |name: &str, namespace: Namespace, span: Span| {
+        let declaration = signature.map(|signature| declaration(signature, namespace, name));
+        let (exported, direct) = match declaration {
+            // シグネチャがなければすべて公開される
+            None => (true, false),
+            Some(None) => (false, false),
+            Some(Some(direct)) => (true, direct),
+        };
+        definitions.push(Definition {
+            name: name.to_owned(),
+            namespace,
+            span,
+            module: module.to_vec(),
+            exported,
+            direct,
+        });
+    };
+    let mut submodules = vec![];
+    for stmt in stmts {
+        match &stmt.body {
+            Statement::Let { ptn, .. } => {
+                for binding in &resolution.bindings {
+                    if binding.kind == BindingKind::Let && within(binding.span, ptn.span()) {
+                        define(&binding.name, Namespace::Variable, binding.span);
+                    }
+                }
+            }
+            Statement::LetRec(bindings) => {
+                for binding in bindings {
+                    let var = &binding.body.var;
+                    define(&var.body.name, Namespace::Variable, var.span());
+                }
+            }
+            Statement::LetMutable { var, .. } => {
+                define(&var.body.name, Namespace::Variable, var.span())
+            }
+            Statement::LetInline { name, .. } => {
+                define(&name.body, Namespace::InlineCmd, name.span())
+            }
+            Statement::LetBlock { name, .. } => {
+                define(&name.body, Namespace::BlockCmd, name.span())
+            }
+            Statement::LetMath { name, .. } => define(&name.body, Namespace::MathCmd, name.span()),
+            Statement::Module {
+                name,
+                signature,
+                body,
+            } => {
+                define(&name.body, Namespace::Module, name.span());
+                submodules.push((name, signature, body));
+            }
+        }
+    }
+    for (name, signature, body) in submodules {
+        let mut module = module.to_vec();
+        module.push(name.body.clone());
+        let signature = signature.as_ref().map(|signature| &signature.body);
+        collect_definitions(&body.body.0, signature, &module, resolution, definitions);
+    }
+}
+
+/// signature での name の宣言。宣言されていなければ None、`direct` であれば Some(true) を返す。
+fn declaration(signature: &Signature, namespace: Namespace, name: &str) -> Option<bool> {
+    signature.0.iter().find_map(|stmt| match &stmt.body {
+        SigStmt::Val { name: val, .. } => {
+            let declared = match &val.body {
+                ValName::Var(var) => namespace == Namespace::Variable && var == name,
+                ValName::InlineCmd(cmd) => {
+                    matches!(namespace, Namespace::InlineCmd | Namespace::MathCmd) && cmd == name
+                }
+                ValName::BlockCmd(cmd) => namespace == Namespace::BlockCmd && cmd == name,
+                ValName::BinOperator(_) => false,
+            };
+            declared.then_some(false)
+        }
+        SigStmt::Direct { name: cmd, .. } => {
+            let command = matches!(
+                namespace,
+                Namespace::InlineCmd | Namespace::BlockCmd | Namespace::MathCmd
+            );
+            (command && cmd.body == name).then_some(true)
+        }
+        SigStmt::Type { .. } => None,
+    })
+}
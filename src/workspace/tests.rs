@@ -0,0 +1,143 @@
+use super::*;
+use std::fs;
+
+/// テストごとの一時ディレクトリに files を書き出し、そのディレクトリを返す。
+fn setup(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "satysfi-peg-parser-workspace-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, text) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+    dir.canonicalize().unwrap()
+}
+
+const STDJA: &str = "\
+let-inline ctx \\textbf inner = read-inline ctx inner
+module Section : sig
+  val +section : [inline-text; block-text] block-cmd
+  direct \\ref : [string] inline-cmd
+end = struct
+  let-block ctx +section title inner = '<>
+  let-inline ctx \\ref key = {}
+  let-block ctx +hidden = '<>
+end
+module Util = struct
+  let (width, height) = (1, 2)
+end
+";
+
+#[test]
+fn incremental_update() {
+    let dir = setup(
+        "incremental",
+        &[
+            ("packages/stdja.satyh", STDJA),
+            ("local.satyh", "@require: stdja\nlet x = 1\n"),
+        ],
+    );
+    let doc = dir.join("doc.saty");
+    let mut workspace = Workspace::new(PackageResolver::new(vec![dir.join("packages")]));
+    let text = "@require: stdja\n@import: local\n'<>".to_owned();
+    assert_eq!(
+        workspace.update(&doc, text.clone()),
+        vec![
+            doc.clone(),
+            dir.join("packages/stdja.satyh"),
+            dir.join("local.satyh")
+        ]
+    );
+    assert_eq!(workspace.paths().count(), 3);
+    // 中身が変わらなければパースし直さない
+    assert!(workspace.update(&doc, text).is_empty());
+    // 読み込み済みのファイルは読み直さない
+    assert_eq!(
+        workspace.update(&doc, "@import: local\n'<>".to_owned()),
+        vec![doc.clone()]
+    );
+    let local = dir.join("local.satyh");
+    assert_eq!(
+        workspace.update(&local, "let x = 2\n".to_owned()),
+        vec![local.clone()]
+    );
+    assert_eq!(workspace.text(&local), Some("let x = 2\n"));
+    assert_eq!(workspace.importers(&local), vec![doc.as_path()]);
+    assert_eq!(workspace.imports(&doc)[0].path, Some(local.clone()));
+    assert!(workspace.remove(&doc));
+    assert!(workspace.importers(&local).is_empty());
+}
+
+#[test]
+fn cross_file_queries() {
+    let dir = setup("queries", &[("packages/stdja.satyh", STDJA)]);
+    let stdja = dir.join("packages/stdja.satyh");
+    let mut workspace = Workspace::new(PackageResolver::new(vec![dir.join("packages")]));
+    workspace.update(dir.join("doc.saty"), "@require: stdja\n'<>".to_owned());
+
+    let found = |name| -> Vec<_> {
+        workspace
+            .definitions_of(name)
+            .into_iter()
+            .map(|(path, def)| (path.to_owned(), def.span.start.row, def.module.clone()))
+            .collect()
+    };
+    assert_eq!(found("\\textbf"), vec![(stdja.clone(), 1, vec![])]);
+    assert_eq!(
+        found("\\ref"),
+        vec![(stdja.clone(), 7, vec!["Section".to_owned()])]
+    );
+    assert!(found("+section").is_empty());
+    assert_eq!(
+        found("+Section.section"),
+        vec![(stdja.clone(), 6, vec!["Section".to_owned()])]
+    );
+    // sig で宣言されていないものは公開されない
+    assert!(found("+Section.hidden").is_empty());
+    assert_eq!(found("Util.height").len(), 1);
+
+    let exporters: Vec<_> = workspace
+        .exporters_of("+section")
+        .into_iter()
+        .map(|(_, def)| def.module.join("."))
+        .collect();
+    assert_eq!(exporters, vec!["Section"]);
+    assert!(workspace.exporters_of("+hidden").is_empty());
+}
+
+#[test]
+fn diagnostics_per_file() {
+    let dir = setup(
+        "diagnostics",
+        &[
+            ("a.satyh", "@import: b\nlet a = 1\n"),
+            ("b.satyh", "@import: a\nlet = 1\n"),
+        ],
+    );
+    let doc = dir.join("doc.saty");
+    let mut workspace = Workspace::default();
+    workspace.update(&doc, "@import: a\n@require: nothing\n'<>".to_owned());
+    let diagnostics = workspace.diagnostics();
+    assert_eq!(diagnostics.len(), 3);
+    let messages = |path: &Path| -> Vec<_> {
+        diagnostics[path]
+            .iter()
+            .map(|d| d.message.clone())
+            .collect()
+    };
+    assert_eq!(messages(&doc), vec!["package not found: nothing"]);
+    // b はパースできないため a を読み込まず、循環はない
+    assert!(messages(&dir.join("a.satyh")).is_empty());
+    assert_eq!(messages(&dir.join("b.satyh")).len(), 1);
+
+    workspace.update(dir.join("b.satyh"), "@import: a\nlet b = 1\n".to_owned());
+    let diagnostics = workspace.diagnostics();
+    assert_eq!(diagnostics[dir.join("b.satyh").as_path()].len(), 1);
+    assert!(diagnostics[dir.join("b.satyh").as_path()][0]
+        .message
+        .starts_with("import cycle: "));
+}
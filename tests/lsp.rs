@@ -0,0 +1,333 @@
+//! satysfi-lsp を起動し、標準入出力で JSON-RPC のメッセージをやり取りするテスト。
+#![cfg(feature = "lsp")]
+
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+/// メッセージを Content-Length のヘッダを付けて書き出す。
+fn frame(message: &Value) -> String {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+/// ヘッダ付きのメッセージの並びを読む。
+fn unframe(mut output: &str) -> Vec<Value> {
+    let mut messages = vec![];
+    while let Some((header, rest)) = output.split_once("\r\n\r\n") {
+        let len: usize = header
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        messages.push(serde_json::from_str(&rest[..len]).unwrap());
+        output = &rest[len..];
+    }
+    messages
+}
+
+/// messages を順に送り、exit 通知の後に返ってきたメッセージをすべて返す。
+fn session(messages: &[Value]) -> Vec<Value> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_satysfi-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for message in messages {
+        stdin.write_all(frame(message).as_bytes()).unwrap();
+    }
+    drop(stdin);
+    let mut output = String::new();
+    child
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+    unframe(&output)
+}
+
+/// id に対するレスポンス。
+fn response(messages: &[Value], id: i64) -> &Value {
+    messages
+        .iter()
+        .find(|message| message["id"] == id && message.get("method").is_none())
+        .unwrap_or_else(|| panic!("no response to request {}", id))
+}
+
+#[test]
+fn invalid_params() {
+    let uri = "file:///test.saty";
+    let position = json!({ "line": 1, "character": 0 });
+    let messages = session(&[
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "capabilities": {} } }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": { "uri": uri, "languageId": "satysfi", "version": 1, "text": "let x = 1\nin x\n" }
+            }
+        }),
+        // 引数の正しくない通知は無視する
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didChange", "params": {} }),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/definition",
+            "params": { "position": position }
+        }),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "textDocument/definition",
+            "params": { "textDocument": { "uri": uri }, "position": { "line": 1, "character": 3 } }
+        }),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]);
+
+    let error = &response(&messages, 2)["error"];
+    assert_eq!(error["code"], -32602);
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .contains("missing field `textDocument`"));
+
+    // 誤ったリクエストの後も処理を続ける
+    let definition = &response(&messages, 3)["result"];
+    assert_eq!(definition["uri"], uri);
+    assert_eq!(
+        definition["range"],
+        json!({ "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 5 } })
+    );
+    assert!(response(&messages, 4)["error"].is_null());
+}
+
+/// initialize から text を開くまでのメッセージ、requests、shutdown と exit を順に送る。
+fn open_session(uri: &str, text: &str, requests: &[Value]) -> Vec<Value> {
+    let mut messages = vec![
+        json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": { "capabilities": {} } }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": { "uri": uri, "languageId": "satysfi", "version": 1, "text": text }
+            }
+        }),
+    ];
+    messages.extend_from_slice(requests);
+    messages.push(json!({ "jsonrpc": "2.0", "id": 1000, "method": "shutdown" }));
+    messages.push(json!({ "jsonrpc": "2.0", "method": "exit" }));
+    session(&messages)
+}
+
+/// uri のファイルについてのリクエスト。
+fn request(id: i64, method: &str, uri: &str, params: Value) -> Value {
+    let mut params = params;
+    params["textDocument"] = json!({ "uri": uri });
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+/// 範囲を表す JSON。
+fn range((l1, c1): (u32, u32), (l2, c2): (u32, u32)) -> Value {
+    json!({ "start": { "line": l1, "character": c1 }, "end": { "line": l2, "character": c2 } })
+}
+
+/// 通知された診断を順に返す。
+fn published_diagnostics(messages: &[Value]) -> Vec<&Value> {
+    messages
+        .iter()
+        .filter(|message| message["method"] == "textDocument/publishDiagnostics")
+        .map(|message| &message["params"]["diagnostics"])
+        .collect()
+}
+
+#[test]
+fn diagnostics_on_open_and_change() {
+    let uri = "file:///test.saty";
+    let messages = open_session(
+        uri,
+        "let x = 1\nin (x\n",
+        &[json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": "let x = 1\nin x\n" }]
+            }
+        })],
+    );
+    let published = published_diagnostics(&messages);
+    assert_eq!(published.len(), 2);
+    let opened = published[0].as_array().unwrap();
+    assert_eq!(opened.len(), 1);
+    assert_eq!(opened[0]["severity"], 1);
+    assert_eq!(opened[0]["source"], "satysfi");
+    assert_eq!(opened[0]["range"], range((2, 0), (2, 0)));
+    // 直したら診断は空になる
+    assert_eq!(published[1], &json!([]));
+}
+
+#[test]
+fn document_symbols() {
+    let uri = "file:///test.saty";
+    let text = "\
+let x = 1
+let-inline ctx \\foo = read-inline ctx {foo}
+let-block ctx +bar = read-block ctx '<>
+module M = struct
+  let y = 2
+end
+in x
+";
+    let messages = open_session(
+        uri,
+        text,
+        &[request(1, "textDocument/documentSymbol", uri, json!({}))],
+    );
+    let symbols = response(&messages, 1)["result"].as_array().unwrap();
+    let summary: Vec<_> = symbols
+        .iter()
+        .map(|symbol| {
+            (
+                symbol["name"].as_str().unwrap(),
+                symbol["detail"].as_str().unwrap(),
+                symbol["kind"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("x", "let", 13),
+            ("\\foo", "let-inline", 12),
+            ("+bar", "let-block", 12),
+            ("M", "module", 2),
+        ]
+    );
+    assert_eq!(symbols[0]["selectionRange"], range((0, 4), (0, 5)));
+    assert_eq!(symbols[3]["range"], range((3, 0), (5, 3)));
+    let children = symbols[3]["children"].as_array().unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0]["name"], "y");
+    assert_eq!(children[0]["selectionRange"], range((4, 6), (4, 7)));
+}
+
+#[test]
+fn folding_ranges() {
+    let uri = "file:///test.saty";
+    let text = "\
+module M : sig
+  val x : int
+end = struct
+  let x = 1
+end
+in
+'<
+  +p{
+    hello
+  }
+>
+";
+    let messages = open_session(
+        uri,
+        text,
+        &[request(1, "textDocument/foldingRange", uri, json!({}))],
+    );
+    let ranges: Vec<_> = response(&messages, 1)["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|range| {
+            (
+                range["startLine"].as_u64().unwrap(),
+                range["endLine"].as_u64().unwrap(),
+                range["collapsedText"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            (0, 2, "sig ... end"),
+            (2, 4, "struct ... end"),
+            (6, 10, "< ... >"),
+            (7, 9, "{ ... }"),
+        ]
+    );
+}
+
+#[test]
+fn selection_ranges() {
+    let uri = "file:///test.saty";
+    let messages = open_session(
+        uri,
+        "let f x = x + 1 in f 2\n",
+        &[request(
+            1,
+            "textDocument/selectionRange",
+            uri,
+            json!({ "positions": [{ "line": 0, "character": 10 }] }),
+        )],
+    );
+    let mut selection = &response(&messages, 1)["result"][0];
+    let mut chain = vec![];
+    while !selection.is_null() {
+        chain.push(selection["range"].clone());
+        selection = &selection["parent"];
+    }
+    // 内側から順に、外側の範囲が内側の範囲を含む
+    assert_eq!(chain[0], range((0, 10), (0, 11)));
+    assert_eq!(chain[1], range((0, 10), (0, 15)));
+    assert_eq!(chain[2], range((0, 0), (0, 15)));
+    assert_eq!(chain.len(), 3);
+    for pair in chain.windows(2) {
+        let (inner, outer) = (&pair[0], &pair[1]);
+        assert!(outer["start"]["character"].as_u64() <= inner["start"]["character"].as_u64());
+        assert!(outer["end"]["character"].as_u64() >= inner["end"]["character"].as_u64());
+        assert_ne!(inner, outer);
+    }
+}
+
+#[test]
+fn utf16_positions() {
+    let uri = "file:///test.saty";
+    // `𝒜` は UTF-16 で 2 単位になる
+    let messages = open_session(
+        uri,
+        "let s = {あ𝒜} in s\n",
+        &[
+            request(
+                1,
+                "textDocument/definition",
+                uri,
+                json!({ "position": { "line": 0, "character": 17 } }),
+            ),
+            request(
+                2,
+                "textDocument/references",
+                uri,
+                json!({
+                    "position": { "line": 0, "character": 4 },
+                    "context": { "includeDeclaration": false }
+                }),
+            ),
+        ],
+    );
+    assert_eq!(
+        response(&messages, 1)["result"]["range"],
+        range((0, 4), (0, 5))
+    );
+    let references = response(&messages, 2)["result"].as_array().unwrap();
+    assert_eq!(references.len(), 1);
+    assert_eq!(references[0]["range"], range((0, 17), (0, 18)));
+
+    let messages = open_session(uri, "let s = {あ𝒜} in )\n", &[]);
+    let published = published_diagnostics(&messages);
+    assert_eq!(published[0][0]["range"]["start"]["character"], 17);
+}